@@ -0,0 +1,71 @@
+//! Wireframe drawing benchmark on bundled models.
+//! Run with `cargo bench --bench draw`.
+
+use s3d::{math::*, model, render, window};
+
+struct Surface {
+    data: Vec<u32>,
+    extent: Vec2<usize>,
+}
+
+impl<'a> window::Surface<'a> for Surface {
+    fn get_data(&self) -> &[u32] {
+        self.data.as_slice()
+    }
+
+    fn get_data_mut(&mut self) -> &mut [u32] {
+        self.data.as_mut_slice()
+    }
+
+    fn get_extent(&self) -> Vec2<usize> {
+        self.extent
+    }
+}
+
+const FRAME_COUNT: usize = 360;
+
+/// Frame sequence rendering function
+/// * `camera` - camera location and target by orbit angle getting function
+/// * Returns average frame time in milliseconds
+fn bench_frames(render: &mut render::Render, surface: &mut Surface, model: &model::Model, camera: impl Fn(f32) -> (Vec3f, Vec3f)) -> f64 {
+    let start = std::time::Instant::now();
+
+    for frame in 0..FRAME_COUNT {
+        let (location, at) = camera(frame as f32 / FRAME_COUNT as f32 * std::f32::consts::PI * 2.0);
+        render.get_camera_mut().set(&location, &at, &Vec3f::new(0.0, 1.0, 0.0));
+
+        let mut context = render.start(surface);
+        model.draw(&mut context);
+        context.finish();
+    }
+
+    start.elapsed().as_secs_f64() * 1000.0 / FRAME_COUNT as f64
+}
+
+fn main() {
+    let mut render = render::Render::new();
+    let mut surface = Surface {
+        data: vec![0; 800 * 600],
+        extent: Vec2::<usize>::new(800, 600),
+    };
+
+    for path in ["models/cow.obj", "models/cow++.obj", "models/e1m1.obj", "models/quaddamage.obj"] {
+        let model = model::load_obj(path).unwrap();
+
+        let bounds = model.get_bounds();
+        let center = bounds.center();
+        let radius = bounds.size().length() / 2.0;
+
+        // Orbit around model, keeping all of it in view
+        let duration = bench_frames(&mut render, &mut surface, &model, |angle| {
+            (center + Vec3f::new(angle.cos(), 0.3, angle.sin()) * radius * 1.5, center)
+        });
+        println!("{:<24} orbit  {:>8.3} ms/frame", path, duration);
+
+        // Look around from model center, keeping most of it out of view
+        let duration = bench_frames(&mut render, &mut surface, &model, |angle| {
+            (center, center + Vec3f::new(angle.cos(), 0.0, angle.sin()))
+        });
+        println!("{:<24} inside {:>8.3} ms/frame", path, duration);
+    }
+}
@@ -0,0 +1,521 @@
+//! OBJ parser as it was before byte-based and parallel parsing, kept to compare loading speed against.
+//! Parsing is unchanged, only primitive construction is adapted to current face and line strip storage.
+
+use s3d::math::*;
+use s3d::model::{LoadError, Model, Position, SubMesh};
+use s3d::render;
+
+/// Sub-mesh, that is being read
+struct SubMeshData {
+    name: String,
+    groups: Vec<String>,
+    smoothing_group: u32,
+    material: Option<String>,
+    /// Number of first sub-mesh face in file
+    first_face: usize,
+    face_normals: Vec<Vec3f>,
+    /// Faces in primitive format, referencing file positions
+    indices: Vec<u32>,
+    /// Texture coordinate index of every face vertex in indices order
+    vertex_tex_coords: Vec<Option<u32>>,
+    /// Line strips in primitive format, referencing file positions
+    line_strips: Vec<u32>,
+    /// Points, referencing file positions
+    points: Vec<u32>,
+}
+
+/// Loaded OBJ file contents
+struct ObjData {
+    positions: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+    tex_coords: Vec<Vec2f>,
+    face_count: usize,
+
+    current: SubMeshData,
+    sub_meshes: Vec<SubMesh>,
+    material_libraries: Vec<String>,
+
+    /// Number of last sub-mesh, that used position, by position
+    position_sub_meshes: Vec<u32>,
+    /// Index of position in last sub-mesh, that used it
+    position_remap: Vec<u32>,
+}
+
+impl ObjData {
+    /// Current sub-mesh finishing function, starts new sub-mesh with same object, groups, smoothing group and material
+    fn finish_sub_mesh(&mut self) -> Result<(), LoadError> {
+        if self.current.face_normals.is_empty() && self.current.line_strips.is_empty() && self.current.points.is_empty() {
+            self.current.first_face = self.face_count;
+            return Ok(());
+        }
+
+        let next = SubMeshData {
+            name: self.current.name.clone(),
+            groups: self.current.groups.clone(),
+            smoothing_group: self.current.smoothing_group,
+            material: self.current.material.clone(),
+            first_face: self.face_count,
+            face_normals: Vec::new(),
+            indices: Vec::new(),
+            vertex_tex_coords: Vec::new(),
+            line_strips: Vec::new(),
+            points: Vec::new(),
+        };
+        let mut current = std::mem::replace(&mut self.current, next);
+
+        // Sub-mesh gets only positions it uses
+        let sub_mesh_number = self.sub_meshes.len() as u32 + 1;
+        self.position_sub_meshes.resize(self.positions.len(), 0);
+        self.position_remap.resize(self.positions.len(), 0);
+
+        let mut positions = Vec::new();
+        let mut tex_coords = Vec::new();
+        let has_tex_coords = current.vertex_tex_coords.iter().any(Option::is_some);
+
+        let mut remap = |index: &mut u32, tex_coord: Option<u32>| {
+            let position = *index as usize;
+
+            if self.position_sub_meshes[position] != sub_mesh_number {
+                self.position_sub_meshes[position] = sub_mesh_number;
+                self.position_remap[position] = positions.len() as u32;
+                positions.push(self.positions[position]);
+                if has_tex_coords {
+                    tex_coords.push(Vec2f::new(0.0, 0.0));
+                }
+            }
+            *index = self.position_remap[position];
+
+            if let Some(tex_coord) = tex_coord {
+                tex_coords[*index as usize] = self.tex_coords[tex_coord as usize];
+            }
+        };
+
+        let mut vertex_tex_coords = current.vertex_tex_coords.iter();
+        let mut offset = 0;
+        while offset < current.indices.len() {
+            let face_end = offset + current.indices[offset] as usize + 2;
+
+            for index in &mut current.indices[offset + 2..face_end] {
+                remap(index, vertex_tex_coords.next().copied().flatten());
+            }
+            offset = face_end;
+        }
+
+        let mut offset = 0;
+        while offset < current.line_strips.len() {
+            let strip_end = offset + current.line_strips[offset] as usize + 1;
+
+            for index in &mut current.line_strips[offset + 1..strip_end] {
+                remap(index, None);
+            }
+            offset = strip_end;
+        }
+
+        for index in &mut current.points {
+            remap(index, None);
+        }
+
+        let mut faces = render::Faces::with_capacity(current.face_normals.len(), current.indices.len());
+        let mut offset = 0;
+        while offset < current.indices.len() {
+            let face_end = offset + current.indices[offset] as usize + 2;
+            faces.push(current.indices[offset + 1], current.indices[offset + 2..face_end].iter().copied());
+            offset = face_end;
+        }
+
+        let mut line_strips = render::LineStrips::new();
+        let mut offset = 0;
+        while offset < current.line_strips.len() {
+            let strip_end = offset + current.line_strips[offset] as usize + 1;
+            line_strips.push(current.line_strips[offset + 1..strip_end].iter().copied());
+            offset = strip_end;
+        }
+
+        // Data is validated during parsing, so primitive construction can't fail
+        let primitive_error = |err: render::PrimitiveError| LoadError::syntax(Position::Unknown, err.to_string());
+        let mut primitive = render::Primitive::new(positions, current.face_normals, faces, 0x00FF00).map_err(primitive_error)?;
+        primitive.set_tex_coords(tex_coords).map_err(primitive_error)?;
+        primitive.set_line_strips(line_strips).map_err(primitive_error)?;
+        primitive.set_points(current.points).map_err(primitive_error)?;
+
+        self.sub_meshes.push(SubMesh {
+            name: current.name,
+            groups: current.groups,
+            smoothing_group: current.smoothing_group,
+            material: current.material,
+            faces: current.first_face..self.face_count,
+            primitive,
+            visible: true,
+            transform: Mat4x4f::identity(),
+        });
+
+        Ok(())
+    }
+}
+
+/// Single face vertex, indices are resolved and zero-based
+#[derive(Copy, Clone)]
+struct FaceVertex {
+    position: u32,
+    tex_coord: Option<u32>,
+    normal: Option<u32>,
+}
+
+/// Statement token
+#[derive(Copy, Clone)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    /// Token position getting function
+    /// * `offset` - byte offset in token
+    fn position(&self, offset: usize) -> Position {
+        Position::Text { line: self.line, column: self.column + offset }
+    }
+
+    /// Position right after token getting function
+    fn end_position(&self) -> Position {
+        self.position(self.text.len())
+    }
+}
+
+/// OBJ reading function.
+///
+/// Supported statements are `v`, `vt`, `vn`, `f`, `l`, `p`, `o`, `g`, `s`, `usemtl` and `mtllib`, all other statements are ignored:
+/// * Tokens are separated by any amount of spaces and tabs, lines ending with `\` continue on next line.
+/// * `#` starts comment, that lasts until end of line.
+/// * `v x y z [w]` - position, `w` is ignored, same as `r g b` vertex color extension.
+/// * `vt u [v [w]]` - texture coordinate, `v` is 0 if missing, `w` is ignored.
+/// * `vn x y z` - normal.
+/// * `f` - face of 3 or more vertices, each written as `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+/// * `l` - line strip of 2 or more vertices, each written as `v` or `v/vt`, texture coordinates are ignored.
+/// * `p` - points, each written as `v`.
+/// * `o name`, `g [names]`, `s group|off`, `usemtl name` - set object name, group names (`default` if none),
+///   smoothing group and material of following faces.
+/// * `mtllib names` - add material libraries to model.
+///
+/// Faces are split into sub-meshes, new sub-mesh starts at every `o`, `g`, `s` and `usemtl` statement,
+/// sub-meshes without faces are skipped. Each sub-mesh gets its own primitive, that contains only positions it uses.
+///
+/// Indices are 1-based if positive and relative to the last defined element if negative (`-1` is the last one),
+/// so every index must reference an element, defined before face. Zero, unparsable and out of range indices are errors,
+/// same as unparsable or non-finite numbers and missing components.
+///
+/// Face normal is the normalized sum of its vertex normals. Faces without vertex normals (or with ones,
+/// that sum up to zero) get geometric normal, computed from vertex positions.
+/// If any sub-mesh face references texture coordinates, sub-mesh primitive gets per-position texture coordinates,
+/// position, that is used with different texture coordinates, gets the last ones.
+/// * `data` - OBJ source
+/// * Returns model, or error with line and column
+pub fn read_obj(data: &[u8]) -> Result<Model, LoadError> {
+    let text = std::str::from_utf8(data).map_err(|_| LoadError::syntax(Position::Unknown, "source is not valid UTF-8"))?;
+
+    let mut data = ObjData {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        tex_coords: Vec::new(),
+        face_count: 0,
+
+        current: SubMeshData {
+            name: String::new(),
+            groups: vec!["default".to_string()],
+            smoothing_group: 0,
+            material: None,
+            first_face: 0,
+            face_normals: Vec::new(),
+            indices: Vec::new(),
+            vertex_tex_coords: Vec::new(),
+            line_strips: Vec::new(),
+            points: Vec::new(),
+        },
+        sub_meshes: Vec::new(),
+        material_libraries: Vec::new(),
+
+        position_sub_meshes: Vec::new(),
+        position_remap: Vec::new(),
+    };
+    let mut face = Vec::<FaceVertex>::new();
+    let mut tokens = Vec::<Token>::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        tokens.clear();
+
+        // Gather tokens of continued lines
+        let mut continued = tokenize_line(line, index + 1, &mut tokens);
+        while continued {
+            let Some((index, line)) = lines.next() else {
+                break;
+            };
+            continued = tokenize_line(line, index + 1, &mut tokens);
+        }
+
+        let Some((statement, arguments)) = tokens.split_first() else {
+            continue;
+        };
+
+        match statement.text {
+            "v" => {
+                // Weight or vertex color is ignored
+                let (numbers, _) = parse_numbers(statement, arguments, 3, 3, "position")?;
+                data.positions.push(Vec3f::new(numbers[0], numbers[1], numbers[2]));
+            }
+            "vt" => {
+                let (numbers, extra) = parse_numbers(statement, arguments, 1, 2, "texture coordinate")?;
+                data.tex_coords.push(Vec2f::new(numbers[0], extra.first().copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let (numbers, _) = parse_numbers(statement, arguments, 3, 0, "normal")?;
+                data.normals.push(Vec3f::new(numbers[0], numbers[1], numbers[2]));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    let end = arguments.last().unwrap_or(statement);
+                    return Err(LoadError::syntax(end.end_position(), format!("face must have at least 3 vertices, got {}", arguments.len())));
+                }
+
+                face.clear();
+                for token in arguments {
+                    face.push(parse_face_vertex(token, &data)?);
+                }
+                add_face(&mut data, &face);
+            }
+            "l" => {
+                if arguments.len() < 2 {
+                    let end = arguments.last().unwrap_or(statement);
+                    return Err(LoadError::syntax(end.end_position(), format!("line must have at least 2 vertices, got {}", arguments.len())));
+                }
+
+                data.current.line_strips.push(arguments.len() as u32);
+                for token in arguments {
+                    let vertex = parse_face_vertex(token, &data)?;
+                    if vertex.normal.is_some() {
+                        return Err(LoadError::syntax(token.position(0), format!("invalid line vertex '{}', expected v or v/vt", token.text)));
+                    }
+                    data.current.line_strips.push(vertex.position);
+                }
+            }
+            "p" => {
+                if arguments.is_empty() {
+                    return Err(LoadError::syntax(statement.end_position(), "expected point vertices"));
+                }
+
+                for token in arguments {
+                    let vertex = parse_face_vertex(token, &data)?;
+                    if vertex.normal.is_some() || vertex.tex_coord.is_some() {
+                        return Err(LoadError::syntax(token.position(0), format!("invalid point vertex '{}', expected v", token.text)));
+                    }
+                    data.current.points.push(vertex.position);
+                }
+            }
+            "o" => {
+                data.finish_sub_mesh()?;
+                data.current.name = join_names(arguments);
+            }
+            "g" => {
+                data.finish_sub_mesh()?;
+                data.current.groups = if arguments.is_empty() {
+                    vec!["default".to_string()]
+                } else {
+                    arguments.iter().map(|token| token.text.to_string()).collect()
+                };
+            }
+            "s" => {
+                let [group] = arguments else {
+                    return Err(LoadError::syntax(statement.end_position(), "expected single smoothing group"));
+                };
+                data.finish_sub_mesh()?;
+                data.current.smoothing_group = match group.text {
+                    "off" => 0,
+                    text => text.parse::<u32>().map_err(|_| LoadError::syntax(group.position(0), format!("expected smoothing group, got '{text}'")))?,
+                };
+            }
+            "usemtl" => {
+                if arguments.is_empty() {
+                    return Err(LoadError::syntax(statement.end_position(), "expected material name"));
+                }
+                data.finish_sub_mesh()?;
+                data.current.material = Some(join_names(arguments));
+            }
+            "mtllib" => data.material_libraries.extend(arguments.iter().map(|token| token.text.to_string())),
+            _ => {}
+        }
+    }
+
+    data.finish_sub_mesh()?;
+
+    Ok(Model {
+        sub_meshes: data.sub_meshes,
+        material_libraries: data.material_libraries,
+        materials: Vec::new(),
+        textures: Vec::new(),
+    })
+}
+
+/// Name, that may contain spaces, joining function
+fn join_names(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| token.text).collect::<Vec<_>>().join(" ")
+}
+
+/// Single line splitting function
+/// * `line` - line to split
+/// * `line_number` - 1-based line number
+/// * `tokens` - vector to push line tokens to
+/// * Returns true if line ends with `\` and continues on next line
+fn tokenize_line<'a>(line: &'a str, line_number: usize, tokens: &mut Vec<Token<'a>>) -> bool {
+    let line = &line[..line.find('#').unwrap_or(line.len())];
+    let line = line.trim_end();
+    let (line, continued) = match line.strip_suffix('\\') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+
+    let mut rest = line;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if rest.is_empty() {
+            break;
+        }
+
+        let length = rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len());
+        tokens.push(Token {
+            text: &rest[..length],
+            line: line_number,
+            column: line.len() - rest.len() + 1,
+        });
+        rest = &rest[length..];
+    }
+
+    continued
+}
+
+/// Statement numbers parsing function
+/// * `statement` - statement token
+/// * `arguments` - statement arguments
+/// * `count` - number of required numbers
+/// * `max_extra` - maximal number of optional numbers after required ones
+/// * `element` - parsed element name, used in error messages
+/// * Returns required and optional numbers
+fn parse_numbers(statement: &Token, arguments: &[Token], count: usize, max_extra: usize, element: &str) -> Result<(Vec<f32>, Vec<f32>), LoadError> {
+    if arguments.len() < count {
+        let end = arguments.last().unwrap_or(statement);
+        return Err(LoadError::syntax(end.end_position(), format!("{element} must have at least {count} components, got {}", arguments.len())));
+    }
+    if arguments.len() > count + max_extra {
+        let extra = &arguments[count + max_extra];
+        return Err(LoadError::syntax(extra.position(0), format!("unexpected {element} component '{}'", extra.text)));
+    }
+
+    let numbers = arguments
+        .iter()
+        .map(|token| match token.text.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            Ok(_) => Err(LoadError::syntax(token.position(0), format!("number '{}' is not finite", token.text))),
+            Err(_) => Err(LoadError::syntax(token.position(0), format!("expected number, got '{}'", token.text))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (required, extra) = numbers.split_at(count);
+    Ok((required.to_vec(), extra.to_vec()))
+}
+
+/// Face vertex parsing function
+/// * `token` - vertex token in `v`, `v/vt`, `v//vn` or `v/vt/vn` form
+/// * `data` - already loaded data, used to resolve indices
+/// * Returns vertex with resolved indices
+fn parse_face_vertex(token: &Token, data: &ObjData) -> Result<FaceVertex, LoadError> {
+    let mut components = token.text.split('/');
+
+    let position = components.next().unwrap_or("");
+    let tex_coord = components.next();
+    let normal = components.next();
+
+    if components.next().is_some() || normal == Some("") || (normal.is_none() && token.text.ends_with('/')) {
+        return Err(LoadError::syntax(
+            token.position(0),
+            format!("invalid face vertex '{}', expected v, v/vt, v//vn or v/vt/vn", token.text),
+        ));
+    }
+
+    // Components are subslices of token, so their offsets give columns
+    let resolve = |component: &str, count: usize, element: &str| {
+        let offset = component.as_ptr() as usize - token.text.as_ptr() as usize;
+        resolve_index(component, count, element).map_err(|(is_range, message)| if is_range {
+            LoadError::index_range(token.position(offset), message)
+        } else {
+            LoadError::syntax(token.position(offset), message)
+        })
+    };
+
+    Ok(FaceVertex {
+        position: resolve(position, data.positions.len(), "position")?,
+        tex_coord: tex_coord
+            .filter(|component| !component.is_empty())
+            .map(|index| resolve(index, data.tex_coords.len(), "texture coordinate"))
+            .transpose()?,
+        normal: normal.map(|index| resolve(index, data.normals.len(), "normal")).transpose()?,
+    })
+}
+
+/// OBJ index resolving function
+/// * `token` - 1-based positive or relative negative index
+/// * `count` - number of already defined elements
+/// * `element` - indexed element name, used in error message
+/// * Returns zero-based index, or error message with flag, that is set if index is out of range
+fn resolve_index(token: &str, count: usize, element: &str) -> Result<u32, (bool, String)> {
+    let index = token.parse::<i64>().map_err(|_| (false, format!("expected {element} index, got '{token}'")))?;
+
+    let resolved = match index {
+        1.. => index - 1,
+        ..0 => count as i64 + index,
+        0 => return Err((false, format!("{element} index must not be 0"))),
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err((true, format!("{element} index {index} is out of range, {count} {element}s defined")));
+    }
+
+    Ok(resolved as u32)
+}
+
+/// Face adding function
+/// * `data` - data to add face to
+/// * `face` - face vertices, at least 3
+fn add_face(data: &mut ObjData, face: &[FaceVertex]) {
+    let normal_sum = face.iter()
+        .filter_map(|vertex| vertex.normal)
+        .fold(Vec3f::new(0.0, 0.0, 0.0), |sum, index| sum + data.normals[index as usize]);
+
+    let normal = if face.iter().all(|vertex| vertex.normal.is_some()) && normal_sum.length2() > 0.0 {
+        normal_sum.normalized()
+    } else {
+        // Newell's method, works for non-planar and concave polygons
+        let mut normal = Vec3f::new(0.0, 0.0, 0.0);
+        for (index, vertex) in face.iter().enumerate() {
+            let current = data.positions[vertex.position as usize];
+            let next = data.positions[face[(index + 1) % face.len()].position as usize];
+
+            normal.x += (current.y - next.y) * (current.z + next.z);
+            normal.y += (current.z - next.z) * (current.x + next.x);
+            normal.z += (current.x - next.x) * (current.y + next.y);
+        }
+
+        if normal.length2() > 0.0 {
+            normal.normalized()
+        } else {
+            Vec3f::new(0.0, 1.0, 0.0)
+        }
+    };
+
+    let current = &mut data.current;
+    current.indices.push(face.len() as u32);
+    current.indices.push(current.face_normals.len() as u32);
+    current.indices.extend(face.iter().map(|vertex| vertex.position));
+    current.vertex_tex_coords.extend(face.iter().map(|vertex| vertex.tex_coord));
+    current.face_normals.push(normal);
+
+    data.face_count += 1;
+}
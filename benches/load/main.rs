@@ -0,0 +1,107 @@
+//! OBJ loading benchmark on bundled models and on a generated large mesh, both sequential and parallel,
+//! compared with the baseline string-based parser and with reading the same model from binary cache.
+//! Run with `cargo bench --bench load`.
+
+mod baseline;
+
+use s3d::model;
+
+const LOAD_COUNT: usize = 10;
+
+/// Grid mesh OBJ text generating function
+/// * `size` - number of grid vertices along each side
+/// * Returns OBJ text with positions, normals, texture coordinates and quad faces
+fn generate_grid(size: usize) -> String {
+    let mut text = String::new();
+
+    for y in 0..size {
+        for x in 0..size {
+            let (u, v) = (x as f32 / (size - 1) as f32, y as f32 / (size - 1) as f32);
+            text += &format!("v {:.6} {:.6} {:.6}\n", u * 100.0, (u * 20.0).sin() * (v * 20.0).cos(), v * 100.0);
+            text += &format!("vt {u:.6} {v:.6}\n");
+            text += "vn 0.000000 1.000000 0.000000\n";
+        }
+    }
+
+    for y in 0..size - 1 {
+        for x in 0..size - 1 {
+            let i = y * size + x + 1;
+            text += &format!("f {}/{}/{} {}/{}/{} {}/{}/{} {}/{}/{}\n", i, i, i, i + 1, i + 1, i + 1, i + size + 1, i + size + 1, i + size + 1, i + size, i + size, i + size);
+        }
+    }
+
+    text
+}
+
+/// Repeated loading function
+/// * `data` - OBJ file contents
+/// * `options` - loading options
+/// * Returns average loading time in milliseconds
+fn bench_load(data: &[u8], options: &model::ObjOptions) -> f64 {
+    let start = std::time::Instant::now();
+
+    for _ in 0..LOAD_COUNT {
+        std::hint::black_box(model::read_obj_with(data, options).unwrap());
+    }
+
+    start.elapsed().as_secs_f64() * 1000.0 / LOAD_COUNT as f64
+}
+
+/// Repeated baseline parser loading function
+/// * `data` - OBJ file contents
+/// * Returns average loading time in milliseconds
+fn bench_baseline(data: &[u8]) -> f64 {
+    let start = std::time::Instant::now();
+
+    for _ in 0..LOAD_COUNT {
+        std::hint::black_box(baseline::read_obj(data).unwrap());
+    }
+
+    start.elapsed().as_secs_f64() * 1000.0 / LOAD_COUNT as f64
+}
+
+/// Repeated cache reading function
+/// * `data` - cache file contents
+/// * Returns average reading time in milliseconds
+fn bench_cache(data: &[u8]) -> f64 {
+    let start = std::time::Instant::now();
+
+    for _ in 0..LOAD_COUNT {
+        std::hint::black_box(model::read_cache(data).unwrap());
+    }
+
+    start.elapsed().as_secs_f64() * 1000.0 / LOAD_COUNT as f64
+}
+
+fn main() {
+    let mut sources = ["models/cow.obj", "models/cow++.obj", "models/e1m1.obj", "models/quaddamage.obj"]
+        .into_iter()
+        .map(|path| (path.to_string(), std::fs::read(path).unwrap()))
+        .collect::<Vec<_>>();
+    sources.push(("grid 500x500".to_string(), generate_grid(500).into_bytes()));
+
+    let sequential = model::ObjOptions { threads: 1, ..Default::default() };
+    // Small chunks make bundled models parsed in parallel too
+    let parallel = model::ObjOptions { threads: 0, min_chunk_size: 64 << 10, ..Default::default() };
+
+    for (name, data) in &sources {
+        let duration = bench_baseline(data);
+        let speed = data.len() as f64 / 1048576.0 / (duration / 1000.0);
+
+        println!("{:<24} {:<10} {:>9.3} ms/load {:>8.1} MiB/s", name, "baseline", duration, speed);
+
+        for (mode, options) in [("sequential", &sequential), ("parallel", &parallel)] {
+            let duration = bench_load(data, options);
+            let speed = data.len() as f64 / 1048576.0 / (duration / 1000.0);
+
+            println!("{:<24} {:<10} {:>9.3} ms/load {:>8.1} MiB/s", name, mode, duration, speed);
+        }
+
+        let mut cache = Vec::new();
+        model::write_cache(&mut cache, &model::read_obj_with(data.as_slice(), &sequential).unwrap(), None).unwrap();
+        let duration = bench_cache(&cache);
+        let speed = cache.len() as f64 / 1048576.0 / (duration / 1000.0);
+
+        println!("{:<24} {:<10} {:>9.3} ms/load {:>8.1} MiB/s", name, "cache", duration, speed);
+    }
+}
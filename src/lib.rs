@@ -0,0 +1,6 @@
+pub mod render;
+pub mod math;
+pub mod window;
+pub mod model;
+pub mod spatial;
+pub mod mesh;
@@ -1,17 +1,7 @@
-pub mod render;
-pub mod math;
-pub mod window;
-
-use std::{arch::x86_64::_mm_crc32_u64, io::Read};
+use s3d::{math, mesh, model, render, window};
 
 use math::*;
 
-impl std::fmt::Display for Vec3f {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return f.write_fmt(format_args!("<{}, {}, {}>", self.x, self.y, self.z));
-    }
-}
-
 pub struct Surface {
     data: Vec<u32>,
     extent: math::Vec2<usize>
@@ -122,95 +112,139 @@ impl Timer {
     }
 }
 
-pub fn load_obj(path: &str) -> Result<render::Primitive, String> {
-    let text = {
-        let mut file = std::fs::File::open(path).map_err(|err| err.to_string())?;
-        let mut buf = String::new();
-
-        _ = file.read_to_string(&mut buf);
+/// Quake unit size in viewer units for .bsp, .map, .mdl and .md2 files, matches scale of bundled e1m1.obj
+const BSP_UNIT_SCALE: f32 = 1.0 / 64.0;
 
-        buf
-    };
-
-    let mut positions = Vec::<Vec3f>::new();
-    let mut normals = Vec::<Vec3f>::new();
-
-    positions.push(Vec3f {x: 0.0, y: 0.0, z: 0.0});
-    normals.push(Vec3f {x: 0.0, y: 1.0, z: 0.0});
+/// Frame rate of .mdl and .md2 animations, Quake plays them at 10 frames per second
+const ANIMATION_FRAME_RATE: f32 = 10.0;
 
-    let mut primitive_idx = Vec::<u32>::new();
-    let mut primitive_ns = Vec::<Vec3f>::new();
+/// Displayed model
+enum Scene {
+    Primitive(render::Primitive),
+    Model(model::Model),
+    Animated(model::AnimatedModel),
+    Bsp(model::bsp::BspMap),
+}
 
-    for (line_number, line) in text.split('\n').enumerate() {
-        let line = line.trim();
-        let elems: Vec<&str> = line.split(' ').collect();
+/// Procedural shape showcase building function
+/// * Returns model with every generated shape in row along X axis and axis arrows at origin
+fn build_shapes() -> model::Model {
+    let shapes = [
+        ("cube", mesh::cube(1.0)),
+        ("uv_sphere", mesh::uv_sphere(0.5, 24, 12)),
+        ("icosphere", mesh::icosphere(0.5, 2)),
+        ("cylinder", mesh::cylinder(0.5, 1.0, 24)),
+        ("cone", mesh::cone(0.5, 1.0, 24)),
+        ("torus", mesh::torus(0.4, 0.15, 24, 12)),
+        ("grid", mesh::grid(1.0, 1.0, 4, 4)),
+    ];
+
+    let mut sub_meshes = shapes
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, primitive))| {
+            let mut sub_mesh = model::SubMesh::new(name, primitive);
+            sub_mesh.transform = Mat4x4f::translate(2.0 + index as f32 * 1.5, 0.0, 0.0);
+            sub_mesh
+        })
+        .collect::<Vec<_>>();
+
+    // Arrows point along +Y, so they are rotated to X and Z
+    let axes = [
+        ("axis_x", 0xFF0000, Mat4x4f::rotate_z(-std::f32::consts::FRAC_PI_2)),
+        ("axis_y", 0x00FF00, Mat4x4f::identity()),
+        ("axis_z", 0x0000FF, Mat4x4f::rotate_x(std::f32::consts::FRAC_PI_2)),
+    ];
+    for (name, color, transform) in axes {
+        let mut arrow = mesh::arrow(1.0, 0.03, 0.2, 0.08, 12);
+        arrow.color = color;
+
+        let mut sub_mesh = model::SubMesh::new(name, arrow);
+        sub_mesh.transform = transform;
+        sub_meshes.push(sub_mesh);
+    }
 
-        if elems.len() < 1 {
-            continue;
-        }
+    model::Model {
+        sub_meshes,
+        material_libraries: Vec::new(),
+        materials: Vec::new(),
+        textures: Vec::new(),
+    }
+}
 
-        match *unsafe { elems.get_unchecked(0) } {
-            "v" => {
-                if elems.len() >= 4 {
-                    unsafe {
-                        positions.push(Vec3f {
-                            x: elems.get_unchecked(1).parse::<f32>().unwrap_or(0.0),
-                            y: elems.get_unchecked(2).parse::<f32>().unwrap_or(0.0),
-                            z: elems.get_unchecked(3).parse::<f32>().unwrap_or(0.0),
-                        });
-                    }
-                }
+/// Scene loading function
+/// * `path` - model file path, or `shapes` for procedural shape showcase
+/// * Returns scene and player start location, if scene defines it
+fn load_scene(path: &str) -> Result<(Scene, Option<Vec3f>), Box<dyn std::error::Error>> {
+    Ok(if path == "shapes" {
+        (Scene::Model(build_shapes()), None)
+    } else if path.ends_with(".bsp") {
+        let map = model::bsp::BspMap::load(path, BSP_UNIT_SCALE)?;
+        let start = map.get_player_start();
+
+        (Scene::Bsp(map), start)
+    } else if path.ends_with(".map") {
+        let map = model::map::MapFile::load(path)?;
+        let primitive = map.build_primitive(&model::map::MapOptions {
+            scale: BSP_UNIT_SCALE,
+            merge_coplanar: true,
+            remove_hidden: true,
+            ..Default::default()
+        })?;
+
+        (Scene::Primitive(primitive), map.get_player_start(BSP_UNIT_SCALE))
+    } else if path.ends_with(".mdl") {
+        (Scene::Animated(model::load_mdl(path, BSP_UNIT_SCALE)?), None)
+    } else if path.ends_with(".md2") {
+        (Scene::Animated(model::load_md2(path, BSP_UNIT_SCALE)?), None)
+    } else if path.ends_with(".gltf") || path.ends_with(".glb") {
+        (Scene::Model(model::load_cached(path, |path| model::load_gltf(path))?), None)
+    } else if path.ends_with(model::cache::CACHE_EXTENSION) {
+        (Scene::Model(model::load_cache(path)?), None)
+    } else if path.ends_with(".ply") {
+        (Scene::Primitive(model::load_ply(path)?), None)
+    } else if path.ends_with(".stl") {
+        (Scene::Primitive(model::load_stl(path)?), None)
+    } else {
+        (Scene::Model(model::load_cached(path, |path| model::load_obj(path))?), None)
+    })
+}
 
-            },
-            "vn" => {
-                if elems.len() >= 4 {
-                    unsafe {
-                        normals.push(Vec3f {
-                            x: elems.get_unchecked(1).parse::<f32>().unwrap_or(0.0),
-                            y: elems.get_unchecked(2).parse::<f32>().unwrap_or(0.0),
-                            z: elems.get_unchecked(3).parse::<f32>().unwrap_or(0.0),
-                        });
-                    }
-                }
-            },
-            "f" => if elems.len() >= 3 {
-                let mut vertex_count: usize = 0;
-                let mut normal = Vec3f::new(0.0, 0.0, 0.0);
-                primitive_idx.push(0); // new vertex
-                primitive_idx.push(primitive_ns.len() as u32); // new normal
-                for vertex in &elems[1..] {
-                    vertex_count += 1;
-
-                    let components: Vec<&str> = vertex.split('/').collect();
-
-                    if components.len() != 3 {
-                        return Err(format!("OBJ Parsing error({path}, {line_number}): incorrect number of vertex components"));
-                    }
-                    unsafe {
-                        normal += *normals.get_unchecked(components.get_unchecked(2).parse::<u32>().unwrap_or(0) as usize);
-                        primitive_idx.push(components.get_unchecked(0).parse::<u32>().unwrap_or(0));
-                    }
-                }
+/// Scene geometry analysis function
+/// * `scene` - scene to analyse
+/// * Returns analysis of all visible scene geometry, animated models are analysed at first frame
+fn analyze_scene(scene: &Scene) -> mesh::MeshAnalysis {
+    let options = mesh::AnalysisOptions::default();
+
+    match scene {
+        Scene::Primitive(primitive) => mesh::analyze(primitive, &options),
+        Scene::Model(model) => mesh::analyze_parts(
+            model.sub_meshes.iter().filter(|sub_mesh| sub_mesh.visible).map(|sub_mesh| (&sub_mesh.primitive, sub_mesh.transform)),
+            &options,
+        ),
+        Scene::Animated(model) => mesh::analyze(&model.sample(0.0), &options),
+        Scene::Bsp(map) => mesh::analyze_parts(map.get_models().iter().map(|primitive| (primitive, Mat4x4f::identity())), &options),
+    }
+}
 
-                unsafe {
-                    let len = primitive_idx.len();
-                    *primitive_idx.get_unchecked_mut(len - vertex_count - 2) = vertex_count as u32;
+fn main() {
+    // `analyze <path>...` prints analysis of every model instead of displaying it
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("analyze") {
+        let mut is_failed = false;
+
+        for path in &args[2..] {
+            match load_scene(path) {
+                Ok((scene, _)) => println!("{path}:\n{}\n", analyze_scene(&scene)),
+                Err(err) => {
+                    eprintln!("{err}");
+                    is_failed = true;
                 }
-                primitive_ns.push(normal.normalized());
             }
-            _ => {},
         }
+        std::process::exit(is_failed as i32);
     }
 
-    Ok(render::Primitive {
-        color: 0x00FF00,
-        indices: primitive_idx,
-        positions,
-        normals: primitive_ns,
-    })
-}
-
-fn main() {
     let sdl = sdl2::init().unwrap();
     let video = sdl.video().unwrap();
     let mut event_pump = sdl.event_pump().unwrap();
@@ -224,16 +258,38 @@ fn main() {
     let mut timer = Timer::new();
     let mut frame = 0;
 
-    let cow = load_obj("models/e1m1.obj").unwrap();
-    let triangle = render::Primitive {
-        color: 0x00FF00,
-        indices: vec![3, 0, 0, 1, 2],
-        normals: vec![Vec3f::new(0.0, 0.0, 1.0)],
-        positions: vec![
-            Vec3f::new( 0.000,  1.000, 0.000),
-            Vec3f::new(-0.866, -0.500, 0.000),
-            Vec3f::new( 0.866, -0.500, 0.000),
-        ],
+    let model_path = std::env::args().nth(1).unwrap_or("models/e1m1.obj".to_string());
+    let (scene, start) = load_scene(&model_path).unwrap_or_else(|err| panic!("{err}"));
+    if let Some(start) = start {
+        render.get_camera_mut().set(&start, &(start + Vec3f::new(1.0, 0.0, 0.0)), &Vec3f::new(0.0, 1.0, 0.0));
+    }
+
+    // Optional second argument is subdivision level of displayed model
+    let subdivision = mesh::SubdivisionOptions {
+        level: std::env::args().nth(2).and_then(|level| level.parse().ok()).unwrap_or(0),
+        ..Default::default()
+    };
+    let scene = match scene {
+        _ if subdivision.level == 0 => scene,
+        Scene::Primitive(primitive) => Scene::Primitive(mesh::subdivide(&primitive, &subdivision)),
+        Scene::Model(mut model) => {
+            for sub_mesh in &mut model.sub_meshes {
+                sub_mesh.primitive = mesh::subdivide(&sub_mesh.primitive, &subdivision);
+            }
+            Scene::Model(model)
+        }
+        scene => scene,
+    };
+
+    let triangle = {
+        let mut builder = mesh::MeshBuilder::with_capacity(3, 1);
+        let vertices = [
+            builder.add_vertex(Vec3f::new( 0.000,  1.000, 0.000)),
+            builder.add_vertex(Vec3f::new(-0.866, -0.500, 0.000)),
+            builder.add_vertex(Vec3f::new( 0.866, -0.500, 0.000)),
+        ];
+        builder.add_face(vertices);
+        builder.build().unwrap()
     };
 
     // render.get_camera_mut().set(&Vec3f::new(0.0, 0.0, -50.0), &Vec3f::new(0.0, 0.0, 0.0), &Vec3f::new(0.0, 1.0, 0.0));
@@ -317,11 +373,17 @@ fn main() {
 
         timer.response();
 
+        let camera_location = render.get_camera_mut().get_location().location;
         let mut context = render.start(&mut surface);
 
         // rendering
         context.draw(&triangle);
-        context.draw(&cow);
+        match &scene {
+            Scene::Primitive(primitive) => context.draw(primitive),
+            Scene::Model(model) => model.draw(&mut context),
+            Scene::Animated(model) => context.draw(&model.sample(timer.get_time() * ANIMATION_FRAME_RATE)),
+            Scene::Bsp(map) => map.draw(&mut context, camera_location),
+        }
 
         context.finish();
 
@@ -0,0 +1,295 @@
+use super::vec::*;
+use super::mat::*;
+
+/// Axis-aligned bounding box
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    pub min: Vec3<f32>,
+    pub max: Vec3<f32>,
+} // struct Aabb
+
+impl Aabb {
+    /// Empty box getting function
+    /// * Returns box that contains no points at all
+    pub fn empty() -> Self {
+        Self {
+            min: Vec3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Vec3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    } // fn empty
+
+    /// Point set bounding box getting function
+    /// * `points` - points to build box around
+    /// * Returns minimal box containing all of points
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3<f32>>) -> Self {
+        let mut aabb = Self::empty();
+        for point in points {
+            aabb.add_point(point);
+        }
+        aabb
+    } // fn from_points
+
+    /// Box emptiness checking function
+    /// * Returns true if box contains no points
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    } // fn is_empty
+
+    /// Box extending to contain point function
+    /// * `point` - point to add to box
+    pub fn add_point(&mut self, point: &Vec3<f32>) {
+        self.min = Vec3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z));
+        self.max = Vec3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z));
+    } // fn add_point
+
+    /// Box union getting function
+    /// * `rhs` - box to unite this with
+    /// * Returns minimal box containing both boxes
+    pub fn union(&self, rhs: &Aabb) -> Self {
+        Self {
+            min: Vec3::new(self.min.x.min(rhs.min.x), self.min.y.min(rhs.min.y), self.min.z.min(rhs.min.z)),
+            max: Vec3::new(self.max.x.max(rhs.max.x), self.max.y.max(rhs.max.y), self.max.z.max(rhs.max.z)),
+        }
+    } // fn union
+
+    /// Box center getting function
+    /// * Returns center of box
+    pub fn center(&self) -> Vec3<f32> {
+        (self.min + self.max) * 0.5
+    } // fn center
+
+    /// Box size getting function
+    /// * Returns box size by every axis
+    pub fn size(&self) -> Vec3<f32> {
+        self.max - self.min
+    } // fn size
+} // impl Aabb
+
+/// Bounding sphere
+#[derive(Copy, Clone)]
+pub struct Sphere {
+    pub center: Vec3<f32>,
+    pub radius: f32,
+} // struct Sphere
+
+impl Sphere {
+    /// Point set bounding sphere getting function
+    /// * `points` - points to build sphere around
+    /// * Returns sphere centered in points bounding box center and containing all points
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3<f32>> + Clone) -> Self {
+        let aabb = Aabb::from_points(points.clone());
+
+        if aabb.is_empty() {
+            return Self { center: Vec3::new(0.0, 0.0, 0.0), radius: 0.0 };
+        }
+
+        let center = aabb.center();
+        let radius2 = points
+            .into_iter()
+            .fold(0.0f32, |radius2, point| radius2.max((*point - center).length2()));
+
+        Self { center, radius: radius2.sqrt() }
+    } // fn from_points
+} // impl Sphere
+
+/// Plane, represented as normal and distance from origin. Point `p` is in front of plane if `normal ^ p + distance > 0`
+#[derive(Copy, Clone)]
+pub struct Plane {
+    pub normal: Vec3<f32>,
+    pub distance: f32,
+} // struct Plane
+
+impl Plane {
+    /// Signed distance from plane to point getting function
+    /// * `point` - point to get distance to
+    /// * Returns signed distance, positive if point is in front of plane
+    pub fn distance_to(&self, point: &Vec3<f32>) -> f32 {
+        (self.normal ^ *point) + self.distance
+    } // fn distance_to
+} // impl Plane
+
+/// Result of bounding volume against frustum test
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Containment {
+    /// Volume is completely outside
+    Outside,
+    /// Volume is partially inside
+    Intersects,
+    /// Volume is completely inside
+    Inside,
+} // enum Containment
+
+/// View frustum, represented by six inward-looking planes
+#[derive(Copy, Clone)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes
+    pub planes: [Plane; 6],
+} // struct Frustum
+
+impl Frustum {
+    /// Frustum from view-projection matrix extraction function
+    /// * `matrix` - matrix, that transforms row vectors to clip space
+    /// * Returns frustum, that contains all points with -w <= x, y, z <= w after transformation
+    pub fn from_matrix(matrix: &Mat4x4<f32>) -> Self {
+        let column = |i: usize| [matrix.data[0][i], matrix.data[1][i], matrix.data[2][i], matrix.data[3][i]];
+        let (x, y, z, w) = (column(0), column(1), column(2), column(3));
+
+        let plane = |sign: f32, c: [f32; 4]| {
+            let normal = Vec3::new(w[0] + sign * c[0], w[1] + sign * c[1], w[2] + sign * c[2]);
+            let length = normal.length();
+
+            Plane {
+                normal: normal / length,
+                distance: (w[3] + sign * c[3]) / length,
+            }
+        };
+
+        Self {
+            planes: [
+                plane( 1.0, x),
+                plane(-1.0, x),
+                plane( 1.0, y),
+                plane(-1.0, y),
+                plane( 1.0, z),
+                plane(-1.0, z),
+            ]
+        }
+    } // fn from_matrix
+
+    /// Sphere testing function
+    /// * `sphere` - sphere to test
+    /// * Returns sphere location relative to frustum
+    pub fn test_sphere(&self, sphere: &Sphere) -> Containment {
+        let mut result = Containment::Inside;
+
+        for plane in &self.planes {
+            let distance = plane.distance_to(&sphere.center);
+
+            if distance < -sphere.radius {
+                return Containment::Outside;
+            }
+            if distance < sphere.radius {
+                result = Containment::Intersects;
+            }
+        }
+
+        result
+    } // fn test_sphere
+
+    /// Axis-aligned box testing function
+    /// * `aabb` - box to test
+    /// * Returns box location relative to frustum. Boxes near frustum corners may be reported as intersecting while being outside.
+    pub fn test_aabb(&self, aabb: &Aabb) -> Containment {
+        let mut result = Containment::Inside;
+
+        for plane in &self.planes {
+            // Box corners furthest in front of and behind plane
+            let positive = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            let negative = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.min.x } else { aabb.max.x },
+                if plane.normal.y >= 0.0 { aabb.min.y } else { aabb.max.y },
+                if plane.normal.z >= 0.0 { aabb.min.z } else { aabb.max.z },
+            );
+
+            if plane.distance_to(&positive) < 0.0 {
+                return Containment::Outside;
+            }
+            if plane.distance_to(&negative) < 0.0 {
+                result = Containment::Intersects;
+            }
+        }
+
+        result
+    } // fn test_aabb
+} // impl Frustum
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Orthographic frustum of box [-1, 1] x [-1, 1] x [-10, -1] getting function
+    fn box_frustum() -> Frustum {
+        Frustum::from_matrix(&Mat4x4::projection_ortho(-1.0, 1.0, -1.0, 1.0, 1.0, 10.0))
+    } // fn box_frustum
+
+    /// Box by center and half size getting function
+    fn aabb(center: Vec3<f32>, half_size: f32) -> Aabb {
+        let half_size = Vec3::new(half_size, half_size, half_size);
+        Aabb { min: center - half_size, max: center + half_size }
+    } // fn aabb
+
+    #[test]
+    fn extracts_normalized_inward_planes() {
+        let frustum = box_frustum();
+        let expected = [
+            (Vec3::new( 1.0,  0.0,  0.0), 1.0),
+            (Vec3::new(-1.0,  0.0,  0.0), 1.0),
+            (Vec3::new( 0.0,  1.0,  0.0), 1.0),
+            (Vec3::new( 0.0, -1.0,  0.0), 1.0),
+            (Vec3::new( 0.0,  0.0, -1.0), -1.0),
+            (Vec3::new( 0.0,  0.0,  1.0), 10.0),
+        ];
+
+        for (plane, (normal, distance)) in frustum.planes.iter().zip(expected) {
+            assert!((plane.normal - normal).length2() < 1e-10);
+            assert!((plane.distance - distance).abs() < 1e-5);
+        }
+    } // fn extracts_normalized_inward_planes
+
+    #[test]
+    fn extracts_perspective_view_frustum() {
+        // Camera at Z = 5 looks at origin with 90 degree field of view
+        let view = Mat4x4::view(&Vec3::new(0.0, 0.0, 5.0), &Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+        let frustum = Frustum::from_matrix(&(view * Mat4x4::projection_frustum(-1.0, 1.0, -1.0, 1.0, 1.0, 100.0)));
+        let point = |x: f32, y: f32, z: f32| Sphere { center: Vec3::new(x, y, z), radius: 0.0 };
+
+        assert_eq!(frustum.test_sphere(&point(0.0, 0.0, 0.0)), Containment::Inside);
+        assert_eq!(frustum.test_sphere(&point(4.9, 0.0, 0.0)), Containment::Inside);
+        assert_eq!(frustum.test_sphere(&point(0.0, -5.1, 0.0)), Containment::Outside);
+        // Behind camera, closer than near plane and further than far one
+        assert_eq!(frustum.test_sphere(&point(0.0, 0.0, 6.0)), Containment::Outside);
+        assert_eq!(frustum.test_sphere(&point(0.0, 0.0, 4.5)), Containment::Outside);
+        assert_eq!(frustum.test_sphere(&point(0.0, 0.0, -96.0)), Containment::Outside);
+        assert_eq!(frustum.test_sphere(&point(0.0, 0.0, -94.0)), Containment::Inside);
+    } // fn extracts_perspective_view_frustum
+
+    #[test]
+    fn tests_spheres() {
+        let frustum = box_frustum();
+        let sphere = |x: f32, radius: f32| Sphere { center: Vec3::new(x, 0.0, -5.0), radius };
+
+        assert_eq!(frustum.test_sphere(&sphere(0.0, 0.5)), Containment::Inside);
+        assert_eq!(frustum.test_sphere(&sphere(0.0, 2.0)), Containment::Intersects);
+        assert_eq!(frustum.test_sphere(&sphere(1.4, 0.5)), Containment::Intersects);
+        assert_eq!(frustum.test_sphere(&sphere(1.6, 0.5)), Containment::Outside);
+        assert_eq!(frustum.test_sphere(&sphere(-1.6, 0.5)), Containment::Outside);
+    } // fn tests_spheres
+
+    #[test]
+    fn tests_boxes() {
+        let frustum = box_frustum();
+
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 0.0, -5.0), 0.5)), Containment::Inside);
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 0.0, -5.0), 20.0)), Containment::Intersects);
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 1.2, -5.0), 0.5)), Containment::Intersects);
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 0.0, -0.8), 0.5)), Containment::Intersects);
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 1.6, -5.0), 0.5)), Containment::Outside);
+        assert_eq!(frustum.test_aabb(&aabb(Vec3::new(0.0, 0.0, -11.0), 0.5)), Containment::Outside);
+        assert_eq!(frustum.test_aabb(&Aabb::empty()), Containment::Outside);
+    } // fn tests_boxes
+
+    #[test]
+    fn reports_boxes_near_corners_as_intersecting() {
+        // Box crosses right and far planes, but not their intersection at X = 100, Z = -100
+        let frustum = Frustum::from_matrix(&Mat4x4::projection_frustum(-1.0, 1.0, -1.0, 1.0, 1.0, 100.0));
+        let corner = Aabb { min: Vec3::new(100.5, -0.5, -101.0), max: Vec3::new(101.0, 0.5, -99.0) };
+
+        assert_eq!(frustum.test_aabb(&corner), Containment::Intersects);
+    } // fn reports_boxes_near_corners_as_intersecting
+} // mod tests
+
+// file bounds.rs
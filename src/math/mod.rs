@@ -6,9 +6,11 @@
 
 pub mod vec;
 pub mod mat;
+pub mod bounds;
 
 pub use vec::{Vec2, Vec3, Vec4};
 pub use mat::Mat4x4;
+pub use bounds::{Aabb, Sphere, Plane, Frustum, Containment};
 
 pub type Mat4x4f = Mat4x4<f32>;
 
@@ -181,4 +181,11 @@ impl<T: Copy + core::ops::Mul<T, Output = T> + core::ops::Sub<T, Output = T>> co
     } // fn rem
 } // impl<T: Copy + core::ops::Mul<T, Output = T> + core::ops::Sub<T, Output = T>> core::ops::Rem<Vec3<T>> for Vec3<T>
 
+/// 3-component vector displaying implementation
+impl<T: std::fmt::Display> std::fmt::Display for Vec3<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("<{}, {}, {}>", self.x, self.y, self.z))
+    } // fn fmt
+} // impl<T: std::fmt::Display> std::fmt::Display for Vec3<T>
+
 // file vec.rs
\ No newline at end of file
@@ -0,0 +1,233 @@
+use crate::math::*;
+use crate::render;
+use super::builder::MeshBuilder;
+use super::cleanup::{weld_vertices, WeldOptions};
+use super::half_edge::{HalfEdgeMesh, TopologyReport};
+use super::normals::newell_vector;
+
+/// Mesh analysis options
+#[derive(Copy, Clone, Debug)]
+pub struct AnalysisOptions {
+    /// Maximal distance between positions, that are the same vertex for topology, zero for exactly equal positions only
+    pub weld_epsilon: f32,
+    /// Maximal distance from polygon vertex to polygon plane relative to polygon radius, that is planar
+    pub planarity_tolerance: f32,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            weld_epsilon: WeldOptions::default().epsilon,
+            planarity_tolerance: 1e-3,
+        }
+    }
+}
+
+/// Mesh analysis result
+#[derive(Copy, Clone)]
+pub struct MeshAnalysis {
+    /// Number of source vertices
+    pub vertex_count: usize,
+    pub face_count: usize,
+    /// Number of faces with more than 3 vertices
+    pub polygon_count: usize,
+    /// Number of polygons, vertices of which don't lie in the same plane
+    pub non_planar_count: usize,
+    pub bounds: Aabb,
+    pub surface_area: f32,
+    /// Volume, enclosed by faces, positive if faces are counter-clockwise from outside, meaningful for closed meshes only
+    pub volume: f32,
+    /// Number of face groups, connected through shared vertices
+    pub component_count: usize,
+    /// Topology of faces with welded vertices
+    pub topology: TopologyReport,
+}
+
+impl MeshAnalysis {
+    /// Genus getting function
+    /// * Returns total number of handles of all components, None if mesh is non-manifold, isn't consistently oriented
+    ///   or has degenerate edges
+    pub fn genus(&self) -> Option<usize> {
+        if !self.topology.is_manifold() || !self.topology.is_oriented() || self.topology.degenerate_edge_count != 0 {
+            return None;
+        }
+
+        // Every component has Euler characteristic 2 - 2 * genus - boundary loop count
+        let double_genus = 2 * self.component_count as isize - self.topology.boundary_loop_count as isize - self.topology.euler_characteristic();
+        (double_genus >= 0 && double_genus % 2 == 0).then_some(double_genus as usize / 2)
+    }
+}
+
+impl std::fmt::Display for MeshAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "vertices: {} ({} welded)", self.vertex_count, self.topology.vertex_count)?;
+        writeln!(f, "faces: {} ({} polygons, {} non-planar)", self.face_count, self.polygon_count, self.non_planar_count)?;
+        writeln!(f, "edges: {}", self.topology.edge_count)?;
+        if self.bounds.is_empty() {
+            writeln!(f, "bounds: empty")?;
+        } else {
+            writeln!(f, "bounds: {} - {}, size {}", self.bounds.min, self.bounds.max, self.bounds.size())?;
+        }
+        writeln!(f, "surface area: {}", self.surface_area)?;
+        writeln!(f, "signed volume: {}", self.volume)?;
+        writeln!(f, "components: {}", self.component_count)?;
+        writeln!(f, "boundary loops: {}", self.topology.boundary_loop_count)?;
+        writeln!(f, "manifold: {}, oriented: {}, closed: {}", self.topology.is_manifold(), self.topology.is_oriented(), self.topology.is_closed())?;
+        write!(f, "euler characteristic: {}, genus: ", self.topology.euler_characteristic())?;
+        match self.genus() {
+            Some(genus) => write!(f, "{genus}"),
+            None => write!(f, "undefined"),
+        }
+    }
+}
+
+/// Several primitive analysis function.
+///
+/// Primitives are analysed together, as single mesh, so parts, that share positions (e.g. sub-meshes of different
+/// materials), are connected.
+/// * `parts` - primitives with their transforms
+/// * `options` - analysis options
+/// * Returns analysis of all parts
+pub fn analyze_parts<'a>(parts: impl IntoIterator<Item = (&'a render::Primitive, Mat4x4f)>, options: &AnalysisOptions) -> MeshAnalysis {
+    let mut builder = MeshBuilder::new();
+    for (primitive, transform) in parts {
+        let base = builder.get_vertex_count() as u32;
+
+        for position in primitive.get_positions() {
+            builder.add_vertex(transform.transform_point(*position));
+        }
+        for face in primitive.faces() {
+            builder.add_face(face.vertices.iter().map(|vertex| base + vertex));
+        }
+    }
+    let primitive = builder.build().expect("merged primitives are valid");
+
+    analyze(&primitive, options)
+}
+
+/// Primitive analysis function
+/// * `primitive` - primitive to analyse
+/// * `options` - analysis options
+/// * Returns primitive analysis
+pub fn analyze(primitive: &render::Primitive, options: &AnalysisOptions) -> MeshAnalysis {
+    let positions = primitive.get_positions();
+
+    let mut surface_area = 0.0;
+    let mut volume = 0.0;
+    let mut polygon_count = 0;
+    let mut non_planar_count = 0;
+    for face in primitive.faces() {
+        let vertex = |index: usize| positions[face.vertices[index] as usize];
+        let count = face.vertices.len();
+
+        let normal = newell_vector(count, vertex);
+        surface_area += normal.length() / 2.0;
+
+        // Fan tetrahedra with origin, so non-planar faces are measured by their triangulation
+        for index in 1..count.saturating_sub(1) {
+            volume += (vertex(0) ^ (vertex(index) % vertex(index + 1))) / 6.0;
+        }
+
+        if count <= 3 {
+            continue;
+        }
+        polygon_count += 1;
+
+        let center = face.vertices.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, vertex| sum + positions[*vertex as usize]) * (1.0 / count as f32);
+        let radius = (0..count).map(|index| (vertex(index) - center).length()).fold(0.0, f32::max);
+        let unit_normal = if normal.length2() > 0.0 { normal.normalized() } else { normal };
+        let distance = (0..count).map(|index| ((vertex(index) - center) ^ unit_normal).abs()).fold(0.0, f32::max);
+
+        if distance > options.planarity_tolerance * radius {
+            non_planar_count += 1;
+        }
+    }
+
+    let (welded, _) = weld_vertices(primitive, &WeldOptions { epsilon: options.weld_epsilon, keep_seams: false });
+    let topology = HalfEdgeMesh::new(&welded).report();
+
+    // Components are found by disjoint set forest of welded vertices
+    let mut parents = (0..welded.get_positions().len()).collect::<Vec<_>>();
+    let find = |parents: &mut Vec<usize>, mut vertex: usize| {
+        while parents[vertex] != vertex {
+            parents[vertex] = parents[parents[vertex]];
+            vertex = parents[vertex];
+        }
+        vertex
+    };
+    let mut is_referenced = vec![false; parents.len()];
+    for face in welded.faces() {
+        let first = face.vertices[0] as usize;
+        for vertex in face.vertices {
+            is_referenced[*vertex as usize] = true;
+
+            let (root, first_root) = (find(&mut parents, *vertex as usize), find(&mut parents, first));
+            parents[root] = first_root;
+        }
+    }
+    let component_count = (0..parents.len()).filter(|vertex| is_referenced[*vertex] && find(&mut parents, *vertex) == *vertex).count();
+
+    MeshAnalysis {
+        vertex_count: positions.len(),
+        face_count: primitive.get_faces().len(),
+        polygon_count,
+        non_planar_count,
+        bounds: *primitive.get_bounds(),
+        surface_area,
+        volume,
+        component_count,
+        topology,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::shapes::{cube, grid, torus};
+
+    #[test]
+    fn cube_measures() {
+        let analysis = analyze(&cube(2.0), &AnalysisOptions::default());
+
+        assert_eq!((analysis.vertex_count, analysis.face_count, analysis.polygon_count, analysis.non_planar_count), (24, 6, 6, 0));
+        assert!((analysis.surface_area - 24.0).abs() < 1e-4);
+        assert!((analysis.volume - 8.0).abs() < 1e-4);
+        assert!((analysis.bounds.size() - Vec3f::new(2.0, 2.0, 2.0)).length2() < 1e-10);
+        assert_eq!(analysis.component_count, 1);
+        assert!(analysis.topology.is_closed());
+        assert_eq!(analysis.topology.vertex_count, 8);
+        assert_eq!(analysis.genus(), Some(0));
+    }
+
+    #[test]
+    fn torus_has_single_handle() {
+        let analysis = analyze(&torus(2.0, 0.5, 16, 8), &AnalysisOptions::default());
+
+        assert!(analysis.topology.is_closed());
+        assert_eq!(analysis.topology.euler_characteristic(), 0);
+        assert_eq!(analysis.genus(), Some(1));
+        assert!(analysis.volume > 0.0);
+    }
+
+    #[test]
+    fn open_grid_has_boundary() {
+        let analysis = analyze(&grid(2.0, 2.0, 4, 4), &AnalysisOptions::default());
+
+        assert!(!analysis.topology.is_closed());
+        assert_eq!(analysis.topology.boundary_loop_count, 1);
+        assert_eq!(analysis.genus(), Some(0));
+        assert!((analysis.surface_area - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parts_are_analysed_together() {
+        let cube = cube(2.0);
+        let analysis = analyze_parts([(&cube, Mat4x4f::identity()), (&cube, Mat4x4f::translate(3.0, 0.0, 0.0))], &AnalysisOptions::default());
+
+        assert_eq!(analysis.component_count, 2);
+        assert!((analysis.volume - 16.0).abs() < 1e-3);
+        assert_eq!(analysis.topology.euler_characteristic(), 4);
+        assert_eq!(analysis.genus(), Some(0));
+        assert!((analysis.bounds.size().x - 5.0).abs() < 1e-5);
+    }
+}
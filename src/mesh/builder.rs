@@ -0,0 +1,260 @@
+use crate::math::*;
+use super::polygon_normal;
+use crate::render;
+
+/// Primitive builder.
+///
+/// Vertices are added with positions only, other vertex attributes are set separately and are stored only if they
+/// are set for at least one vertex. Face normals are computed from face vertex positions unless given explicitly.
+pub struct MeshBuilder {
+    color: u32,
+    positions: Vec<Vec3f>,
+    /// Texture coordinates, empty if none is set
+    tex_coords: Vec<Vec2f>,
+    /// Vertex colors, empty if none is set
+    colors: Vec<u32>,
+    /// Vertex normals, empty if none is set, zero for vertices without normal
+    vertex_normals: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+    faces: render::Faces,
+    line_strips: render::LineStrips,
+    points: Vec<u32>,
+}
+
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeshBuilder {
+    /// Empty builder create function
+    pub fn new() -> Self {
+        Self::with_capacity(0, 0)
+    }
+
+    /// Empty builder with preallocated storage create function
+    /// * `vertex_count` - expected number of vertices
+    /// * `face_count` - expected number of faces
+    pub fn with_capacity(vertex_count: usize, face_count: usize) -> Self {
+        Self {
+            color: 0x00FF00,
+            positions: Vec::with_capacity(vertex_count),
+            tex_coords: Vec::new(),
+            colors: Vec::new(),
+            vertex_normals: Vec::new(),
+            normals: Vec::with_capacity(face_count),
+            faces: render::Faces::with_capacity(face_count, face_count * 4),
+            line_strips: render::LineStrips::new(),
+            points: Vec::new(),
+        }
+    }
+
+    /// Primitive color setting function
+    /// * `color` - primitive color, also used for vertices without color, if any vertex color is set
+    pub fn set_color(&mut self, color: u32) {
+        self.color = color;
+    }
+
+    /// Vertex adding function
+    /// * `position` - vertex position
+    /// * Returns vertex index
+    pub fn add_vertex(&mut self, position: Vec3f) -> u32 {
+        self.positions.push(position);
+
+        if !self.tex_coords.is_empty() {
+            self.tex_coords.push(Vec2f::new(0.0, 0.0));
+        }
+        if !self.colors.is_empty() {
+            self.colors.push(self.color);
+        }
+        if !self.vertex_normals.is_empty() {
+            self.vertex_normals.push(Vec3f::new(0.0, 0.0, 0.0));
+        }
+
+        self.positions.len() as u32 - 1
+    }
+
+    /// Vertex with normal and texture coordinate adding function
+    /// * `position` - vertex position
+    /// * `normal` - vertex normal
+    /// * `tex_coord` - vertex texture coordinate
+    /// * Returns vertex index
+    pub fn add_vertex_with(&mut self, position: Vec3f, normal: Vec3f, tex_coord: Vec2f) -> u32 {
+        let vertex = self.add_vertex(position);
+        self.set_vertex_normal(vertex, normal);
+        self.set_tex_coord(vertex, tex_coord);
+
+        vertex
+    }
+
+    /// Vertex texture coordinate setting function, vertices without texture coordinate get zero one
+    /// * `vertex` - index of added vertex, panics if vertex doesn't exist
+    /// * `tex_coord` - texture coordinate
+    pub fn set_tex_coord(&mut self, vertex: u32, tex_coord: Vec2f) {
+        self.tex_coords.resize(self.positions.len(), Vec2f::new(0.0, 0.0));
+        self.tex_coords[vertex as usize] = tex_coord;
+    }
+
+    /// Vertex color setting function, vertices without color get primitive color
+    /// * `vertex` - index of added vertex, panics if vertex doesn't exist
+    /// * `color` - vertex color in primitive color format
+    pub fn set_vertex_color(&mut self, vertex: u32, color: u32) {
+        self.colors.resize(self.positions.len(), self.color);
+        self.colors[vertex as usize] = color;
+    }
+
+    /// Vertex normal setting function, vertices without normal get average normal of their faces
+    /// * `vertex` - index of added vertex, panics if vertex doesn't exist
+    /// * `normal` - vertex normal, normalized on set
+    pub fn set_vertex_normal(&mut self, vertex: u32, normal: Vec3f) {
+        self.vertex_normals.resize(self.positions.len(), Vec3f::new(0.0, 0.0, 0.0));
+        self.vertex_normals[vertex as usize] = if normal.length2() > 0.0 { normal.normalized() } else { normal };
+    }
+
+    /// Face adding function
+    /// * `vertices` - face vertex indices in counter-clockwise order
+    /// * Returns face index, that is index of face in built primitive too. Face normal is computed from vertex positions
+    pub fn add_face(&mut self, vertices: impl IntoIterator<Item = u32>) -> usize {
+        let face = self.faces.push(self.normals.len() as u32, vertices);
+        let vertices = self.faces.get(face).expect("face is just added").vertices;
+
+        // Missing vertices are reported on build, so they are just replaced here
+        let normal = polygon_normal(vertices.len(), |index| self.positions.get(vertices[index] as usize).copied().unwrap_or(Vec3f::new(0.0, 0.0, 0.0)));
+        self.normals.push(normal);
+
+        face
+    }
+
+    /// Face with explicit normal adding function
+    /// * `normal` - face normal, normalized on add
+    /// * `vertices` - face vertex indices
+    /// * Returns face index, that is index of face in built primitive too
+    pub fn add_face_with_normal(&mut self, normal: Vec3f, vertices: impl IntoIterator<Item = u32>) -> usize {
+        self.normals.push(if normal.length2() > 0.0 { normal.normalized() } else { Vec3f::new(0.0, 1.0, 0.0) });
+        self.faces.push(self.normals.len() as u32 - 1, vertices)
+    }
+
+    /// Line strip adding function
+    /// * `vertices` - strip vertex indices, at least two
+    pub fn add_line_strip(&mut self, vertices: impl IntoIterator<Item = u32>) {
+        self.line_strips.push(vertices);
+    }
+
+    /// Point adding function
+    /// * `vertex` - point vertex index
+    pub fn add_point(&mut self, vertex: u32) {
+        self.points.push(vertex);
+    }
+
+    /// Vertex count getting function
+    pub fn get_vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Face count getting function
+    pub fn get_face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// Vertex positions getting function
+    pub fn get_positions(&self) -> &[Vec3f] {
+        &self.positions
+    }
+
+    /// Primitive building function
+    /// * Returns primitive with faces in add order, or error if any face, line strip or point references missing vertex
+    ///   or position isn't finite
+    pub fn build(mut self) -> Result<render::Primitive, render::PrimitiveError> {
+        // Vertices without normal get average normal of faces, that contain them
+        if self.vertex_normals.iter().any(|normal| normal.length2() == 0.0) {
+            let mut sums = vec![Vec3f::new(0.0, 0.0, 0.0); self.positions.len()];
+            for face in &self.faces {
+                for vertex in face.vertices {
+                    if let (Some(sum), Some(normal)) = (sums.get_mut(*vertex as usize), self.normals.get(face.normal as usize)) {
+                        *sum += *normal;
+                    }
+                }
+            }
+
+            for (normal, sum) in self.vertex_normals.iter_mut().zip(sums) {
+                if normal.length2() == 0.0 {
+                    *normal = if sum.length2() > 0.0 { sum.normalized() } else { Vec3f::new(0.0, 1.0, 0.0) };
+                }
+            }
+        }
+
+        let mut primitive = render::Primitive::new(self.positions, self.normals, self.faces, self.color)?;
+        primitive.set_tex_coords(self.tex_coords)?;
+        primitive.set_colors(self.colors)?;
+        primitive.set_vertex_normals(self.vertex_normals)?;
+        // Element setters recompute bounding volumes, so they are called only if needed
+        if !self.line_strips.is_empty() {
+            primitive.set_line_strips(self.line_strips)?;
+        }
+        if !self.points.is_empty() {
+            primitive.set_points(self.points)?;
+        }
+
+        Ok(primitive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of quads building function
+    /// * `size` - number of quads along each side
+    /// * Returns builder and vertices of every added face in add order
+    fn grid(size: u32) -> (MeshBuilder, Vec<[u32; 4]>) {
+        let mut builder = MeshBuilder::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                builder.add_vertex(Vec3f::new(x as f32, 0.0, y as f32));
+            }
+        }
+
+        // Faces are added in scattered order, so clustering has to move them
+        let mut faces = Vec::new();
+        for index in 0..size * size {
+            let index = (index * 7919) % (size * size);
+            let (x, y) = (index % size, index / size);
+            let first = y * (size + 1) + x;
+            let face = [first, first + size + 1, first + size + 2, first + 1];
+
+            assert_eq!(builder.add_face(face), faces.len());
+            faces.push(face);
+        }
+
+        (builder, faces)
+    }
+
+    #[test]
+    fn clustered_primitive_keeps_face_order() {
+        let (builder, faces) = grid(40);
+        let primitive = builder.build().unwrap();
+
+        assert!(faces.len() > 256 && primitive.get_clusters().len() > 1);
+        assert_eq!(primitive.get_faces().len(), faces.len());
+        assert!(primitive.faces().zip(&faces).all(|(face, vertices)| face.vertices == vertices));
+
+        // Clusters list faces out of add order, every face once
+        let cluster_faces = primitive.get_cluster_faces();
+        let mut is_listed = vec![false; faces.len()];
+        for face in cluster_faces {
+            assert!(!std::mem::replace(&mut is_listed[*face as usize], true), "face {face} is listed twice");
+        }
+        assert!(cluster_faces.iter().enumerate().any(|(index, face)| index != *face as usize));
+    }
+
+    #[test]
+    fn small_primitive_has_single_cluster() {
+        let (builder, faces) = grid(16);
+        let primitive = builder.build().unwrap();
+
+        assert_eq!(faces.len(), 256);
+        assert_eq!(primitive.get_clusters().len(), 1);
+        assert!(primitive.faces().zip(&faces).all(|(face, vertices)| face.vertices == vertices));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::math::*;
+use crate::render;
+use super::data::MeshData;
+use super::normals::newell_vector;
+
+/// Vertex welding options
+#[derive(Copy, Clone, Debug)]
+pub struct WeldOptions {
+    /// Maximal distance between welded positions, zero to weld exactly equal positions only
+    pub epsilon: f32,
+    /// Weld only vertices with equal texture coordinates, colors and vertex normals, so texture seams and hard edges stay
+    pub keep_seams: bool,
+}
+
+impl Default for WeldOptions {
+    fn default() -> Self {
+        Self {
+            epsilon: 1e-5,
+            keep_seams: true,
+        }
+    }
+}
+
+/// Vertex welding result
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WeldReport {
+    /// Number of vertices before welding
+    pub vertex_count: usize,
+    /// Number of vertices, merged into other ones
+    pub welded_count: usize,
+}
+
+impl std::fmt::Display for WeldReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "welded {} of {} vertices", self.welded_count, self.vertex_count)
+    }
+}
+
+/// Face cleanup result
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaceCleanupReport {
+    /// Number of faces before cleanup
+    pub face_count: usize,
+    /// Number of removed face vertices, that repeated previous vertex of the same face
+    pub repeated_vertex_count: usize,
+    /// Number of removed faces with less than 3 distinct vertices or too small area
+    pub degenerate_count: usize,
+    /// Number of removed faces, that repeated earlier face with the same vertices in the same cyclic order
+    pub duplicate_count: usize,
+}
+
+impl std::fmt::Display for FaceCleanupReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "removed {} degenerate and {} duplicate of {} faces, {} repeated face vertices",
+            self.degenerate_count, self.duplicate_count, self.face_count, self.repeated_vertex_count,
+        )
+    }
+}
+
+/// Triangulation result
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TriangulationReport {
+    /// Number of faces with more than 3 vertices, that are split into triangles
+    pub polygon_count: usize,
+    /// Number of triangles, these faces are split into
+    pub triangle_count: usize,
+    /// Number of polygons, that had no valid ear at some step (because they are self-intersecting or degenerate),
+    /// so some of their triangles may overlap or be flipped
+    pub forced_count: usize,
+}
+
+impl std::fmt::Display for TriangulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "split {} polygons into {} triangles", self.polygon_count, self.triangle_count)?;
+        if self.forced_count != 0 {
+            write!(f, ", {} polygons are invalid", self.forced_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Vertex welding function.
+///
+/// Vertices are visited in order, every vertex is merged into first earlier kept vertex within `epsilon`, found through
+/// spatial hash with `epsilon` sized cells, so chains of close vertices don't collapse into one. Kept vertices keep their
+/// order and attributes. Faces may get repeated vertices, [`clean_faces`] removes them.
+/// * `primitive` - primitive to weld vertices of
+/// * `options` - welding options
+/// * Returns primitive with welded vertices and welding report
+pub fn weld_vertices(primitive: &render::Primitive, options: &WeldOptions) -> (render::Primitive, WeldReport) {
+    let positions = primitive.get_positions();
+    let tex_coords = primitive.get_tex_coords();
+    let colors = primitive.get_colors();
+    let vertex_normals = primitive.get_vertex_normals();
+
+    // Zero epsilon keys cells by exact coordinates, zero sum makes negative zero equal to positive one
+    let epsilon = options.epsilon.max(0.0);
+    let reach = if epsilon > 0.0 { 1 } else { 0 };
+    let cell = |position: Vec3f| -> (i64, i64, i64) {
+        if epsilon > 0.0 {
+            ((position.x / epsilon).floor() as i64, (position.y / epsilon).floor() as i64, (position.z / epsilon).floor() as i64)
+        } else {
+            ((position.x + 0.0).to_bits() as i64, (position.y + 0.0).to_bits() as i64, (position.z + 0.0).to_bits() as i64)
+        }
+    };
+    let is_same = |vertex: usize, other: usize| {
+        (positions[vertex] - positions[other]).length2() <= epsilon * epsilon
+            && (!options.keep_seams || (
+                tex_coords.get(vertex).zip(tex_coords.get(other)).is_none_or(|(first, second)| (*first - *second).length2() == 0.0)
+                    && colors.get(vertex) == colors.get(other)
+                    && vertex_normals.get(vertex).zip(vertex_normals.get(other)).is_none_or(|(first, second)| (*first - *second).length2() == 0.0)
+            ))
+    };
+
+    // Kept vertices by cell
+    let mut cells = HashMap::<(i64, i64, i64), Vec<u32>>::with_capacity(positions.len());
+    let mut remap = Vec::with_capacity(positions.len());
+    let mut kept_count = 0;
+
+    for (vertex, position) in positions.iter().enumerate() {
+        let (x, y, z) = cell(*position);
+
+        let mut found = None;
+        'search: for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    let Some(kept) = cells.get(&(x + dx, y + dy, z + dz)) else {
+                        continue;
+                    };
+                    if let Some(other) = kept.iter().find(|other| is_same(vertex, **other as usize)) {
+                        found = Some(*other);
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        match found {
+            Some(other) => remap.push(remap[other as usize]),
+            None => {
+                cells.entry((x, y, z)).or_default().push(vertex as u32);
+                remap.push(kept_count);
+                kept_count += 1;
+            }
+        }
+    }
+
+    let report = WeldReport { vertex_count: positions.len(), welded_count: positions.len() - kept_count as usize };
+
+    let mut data = MeshData::new(primitive);
+    data.remap_vertices(&remap);
+
+    (data.build(), report)
+}
+
+/// Degenerate and duplicate face removing function.
+///
+/// Face vertices, equal to previous ones, are removed first, then faces with less than 3 vertices or area not
+/// greater than `min_area` are removed, then faces, that repeat earlier ones up to starting vertex, are removed.
+/// Faces with reverse order of the same vertices are kept, as they are back sides of two-sided geometry.
+/// * `primitive` - primitive to clean faces of
+/// * `min_area` - maximal area of degenerate face
+/// * Returns primitive with clean faces and cleanup report
+pub fn clean_faces(primitive: &render::Primitive, min_area: f32) -> (render::Primitive, FaceCleanupReport) {
+    let positions = primitive.get_positions();
+    let mut report = FaceCleanupReport { face_count: primitive.get_faces().len(), ..Default::default() };
+
+    let mut faces = render::Faces::with_capacity(primitive.get_faces().len(), primitive.get_faces().get_vertices().len());
+    let mut face_keys = HashSet::with_capacity(primitive.get_faces().len());
+    let mut vertices = Vec::new();
+
+    for face in primitive.faces() {
+        vertices.clear();
+        for (index, vertex) in face.vertices.iter().enumerate() {
+            if *vertex != face.vertices[(index + face.vertices.len() - 1) % face.vertices.len()] || face.vertices.len() == 1 {
+                vertices.push(*vertex);
+            }
+        }
+        report.repeated_vertex_count += face.vertices.len() - vertices.len();
+
+        let area = newell_vector(vertices.len(), |index| positions[vertices[index] as usize]).length() / 2.0;
+        if vertices.len() < 3 || area <= min_area {
+            report.degenerate_count += 1;
+            continue;
+        }
+
+        // Duplicate key is vertex list, rotated to start with the smallest vertex
+        let start = (0..vertices.len()).min_by_key(|index| vertices[*index]).unwrap_or(0);
+        let key = vertices[start..].iter().chain(&vertices[..start]).copied().collect::<Vec<_>>();
+        if !face_keys.insert(key) {
+            report.duplicate_count += 1;
+            continue;
+        }
+
+        faces.push(face.normal, vertices.iter().copied());
+    }
+
+    let mut data = MeshData::new(primitive);
+    data.faces = faces;
+
+    (data.build(), report)
+}
+
+/// Triangulation function.
+///
+/// Faces with more than 3 vertices are projected to plane, orthogonal to their Newell normal, and split by ear clipping,
+/// so concave polygons are split correctly. Resulting triangles keep face normal.
+/// * `primitive` - primitive to triangulate
+/// * Returns primitive with triangle faces only and triangulation report
+pub fn triangulate(primitive: &render::Primitive) -> (render::Primitive, TriangulationReport) {
+    let positions = primitive.get_positions();
+    let mut report = TriangulationReport::default();
+
+    let mut faces = render::Faces::with_capacity(primitive.get_faces().len(), primitive.get_faces().get_vertices().len());
+    let mut points = Vec::new();
+    let mut triangles = Vec::new();
+
+    for face in primitive.faces() {
+        if face.vertices.len() <= 3 {
+            faces.push(face.normal, face.vertices.iter().copied());
+            continue;
+        }
+
+        let normal = newell_vector(face.vertices.len(), |index| positions[face.vertices[index] as usize]);
+        let (u, v) = plane_axes(normal);
+        points.clear();
+        points.extend(face.vertices.iter().map(|vertex| {
+            let position = positions[*vertex as usize];
+            Vec2f::new(position ^ u, position ^ v)
+        }));
+
+        triangles.clear();
+        if !clip_ears(&points, &mut triangles) {
+            report.forced_count += 1;
+        }
+
+        for triangle in &triangles {
+            faces.push(face.normal, triangle.map(|corner| face.vertices[corner]));
+        }
+        report.polygon_count += 1;
+        report.triangle_count += triangles.len();
+    }
+
+    let mut data = MeshData::new(primitive);
+    data.faces = faces;
+
+    (data.build(), report)
+}
+
+/// Polygon plane axes computing function
+/// * `normal` - polygon normal, may be not normalized or zero
+/// * Returns axes, cross product of which is directed along normal, so counter-clockwise polygon stays counter-clockwise
+fn plane_axes(normal: Vec3f) -> (Vec3f, Vec3f) {
+    if normal.length2() == 0.0 {
+        return (Vec3f::new(1.0, 0.0, 0.0), Vec3f::new(0.0, 1.0, 0.0));
+    }
+
+    let normal = normal.normalized();
+    let helper = if normal.x.abs() < 0.9 { Vec3f::new(1.0, 0.0, 0.0) } else { Vec3f::new(0.0, 1.0, 0.0) };
+    let u = (helper % normal).normalized();
+
+    (u, normal % u)
+}
+
+/// Two-dimensional cross product getting function
+fn cross(a: Vec2f, b: Vec2f) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Ear clipping function
+/// * `points` - counter-clockwise polygon in plane, at least 3 points
+/// * `triangles` - triangle corner numbers output
+/// * Returns true if valid ear is found at every step, otherwise polygon is invalid and some triangles were forced
+fn clip_ears(points: &[Vec2f], triangles: &mut Vec<[usize; 3]>) -> bool {
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut is_valid = true;
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corners = |index: usize| [remaining[(index + count - 1) % count], remaining[index], remaining[(index + 1) % count]];
+
+        let is_ear = |index: usize| {
+            let [a, b, c] = corners(index).map(|corner| points[corner]);
+            if cross(b - a, c - b) <= 0.0 {
+                return false;
+            }
+
+            // Vertices, that coincide with ear corners, touch ear from outside
+            !remaining.iter().map(|corner| points[*corner]).any(|point| {
+                [a, b, c].iter().all(|corner| (point - *corner).length2() > 0.0)
+                    && cross(b - a, point - a) >= 0.0 && cross(c - b, point - b) >= 0.0 && cross(a - c, point - c) >= 0.0
+            })
+        };
+
+        let ear = match (0..count).find(|index| is_ear(*index)) {
+            Some(ear) => ear,
+            None => {
+                is_valid = false;
+                // Most convex corner gives the least broken result
+                (0..count)
+                    .max_by(|first, second| {
+                        let turn = |index: usize| {
+                            let [a, b, c] = corners(index).map(|corner| points[corner]);
+                            cross(b - a, c - b)
+                        };
+                        turn(*first).total_cmp(&turn(*second))
+                    })
+                    .unwrap_or(0)
+            }
+        };
+
+        triangles.push(corners(ear));
+        remaining.remove(ear);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    is_valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshBuilder;
+
+    /// Two triangle quad with separate vertices per triangle building function.
+    /// Vertices on shared diagonal are 0-4 and 2-3, texture coordinates differ on 2-3, 4 is moved by `offset`.
+    fn split_quad(offset: f32) -> render::Primitive {
+        let mut builder = MeshBuilder::new();
+        let normal = Vec3f::new(0.0, 0.0, 1.0);
+
+        builder.add_vertex_with(Vec3f::new(0.0, 0.0, 0.0), normal, Vec2f::new(0.0, 0.0));
+        builder.add_vertex_with(Vec3f::new(1.0, 0.0, 0.0), normal, Vec2f::new(1.0, 0.0));
+        builder.add_vertex_with(Vec3f::new(1.0, 1.0, 0.0), normal, Vec2f::new(1.0, 1.0));
+        builder.add_vertex_with(Vec3f::new(1.0, 1.0, 0.0), normal, Vec2f::new(2.0, 1.0));
+        builder.add_vertex_with(Vec3f::new(offset, 0.0, 0.0), normal, Vec2f::new(0.0, 0.0));
+        builder.add_vertex_with(Vec3f::new(0.0, 1.0, 0.0), normal, Vec2f::new(0.0, 1.0));
+        builder.add_face([0, 1, 2]);
+        builder.add_face([4, 3, 5]);
+
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn weld_keeps_texture_seams() {
+        let (welded, report) = weld_vertices(&split_quad(1e-6), &WeldOptions::default());
+
+        assert_eq!(report, WeldReport { vertex_count: 6, welded_count: 1 });
+        assert_eq!(welded.get_positions().len(), 5);
+        assert_eq!(welded.faces().nth(1).unwrap().vertices, &[0, 3, 4]);
+    }
+
+    #[test]
+    fn weld_without_seams_merges_all_close_vertices() {
+        let (welded, report) = weld_vertices(&split_quad(1e-6), &WeldOptions { keep_seams: false, ..Default::default() });
+
+        assert_eq!(report, WeldReport { vertex_count: 6, welded_count: 2 });
+        assert_eq!(welded.faces().nth(1).unwrap().vertices, &[0, 2, 3]);
+        // Kept vertices keep their attributes
+        assert_eq!(welded.get_tex_coords()[2].x, 1.0);
+
+        // Vertices further than epsilon and exactly equal ones with zero epsilon
+        let (_, report) = weld_vertices(&split_quad(1e-3), &WeldOptions { keep_seams: false, ..Default::default() });
+        assert_eq!(report.welded_count, 1);
+        let (_, report) = weld_vertices(&split_quad(1e-6), &WeldOptions { epsilon: 0.0, keep_seams: false });
+        assert_eq!(report.welded_count, 1);
+    }
+
+    #[test]
+    fn weld_doesnt_chain_close_vertices() {
+        let mut builder = MeshBuilder::new();
+        for x in [0.0, 0.6, 1.2, 1.8] {
+            builder.add_vertex(Vec3f::new(x, 0.0, 0.0));
+        }
+        builder.add_face([0, 1, 2]);
+        builder.add_face([1, 2, 3]);
+
+        // Vertex 1 is merged into 0, 2 is too far from 0, 3 is merged into 2
+        let (welded, report) = weld_vertices(&builder.build().unwrap(), &WeldOptions { epsilon: 1.0, keep_seams: false });
+        assert_eq!(report.welded_count, 2);
+        assert_eq!(welded.faces().map(|face| face.vertices.to_vec()).collect::<Vec<_>>(), vec![vec![0, 0, 1], vec![0, 1, 1]]);
+    }
+
+    /// Polygon triangulation function
+    /// * `points` - counter-clockwise polygon in XY plane
+    /// * Returns triangles and triangulation report
+    fn triangulate_polygon(points: &[[f32; 2]]) -> (render::Primitive, TriangulationReport) {
+        let mut builder = MeshBuilder::new();
+        for [x, y] in points {
+            builder.add_vertex(Vec3f::new(*x, *y, 0.0));
+        }
+        builder.add_face(0..points.len() as u32);
+
+        triangulate(&builder.build().unwrap())
+    }
+
+    /// Triangulation checking function, every triangle must be counter-clockwise and triangles must cover polygon area
+    fn check_triangulation(points: &[[f32; 2]]) {
+        let (triangles, report) = triangulate_polygon(points);
+        let area = newell_vector(points.len(), |index| Vec3f::new(points[index][0], points[index][1], 0.0)).z / 2.0;
+
+        assert_eq!(report, TriangulationReport { polygon_count: 1, triangle_count: points.len() - 2, forced_count: 0 });
+
+        let mut triangle_area = 0.0;
+        for face in triangles.faces() {
+            let normal = newell_vector(3, |index| triangles.get_positions()[face.vertices[index] as usize]);
+            assert!(normal.z >= 0.0, "triangle {:?} is flipped", face.vertices);
+            triangle_area += normal.z / 2.0;
+        }
+        assert!((triangle_area - area).abs() < 1e-4, "triangles cover {triangle_area} of {area}");
+    }
+
+    #[test]
+    fn ear_clipping_splits_concave_polygons() {
+        // U shape
+        check_triangulation(&[[0.0, 0.0], [3.0, 0.0], [3.0, 3.0], [2.0, 3.0], [2.0, 1.0], [1.0, 1.0], [1.0, 3.0], [0.0, 3.0]]);
+        // Arrow, reflex vertex lies on diagonals of both convex corners
+        check_triangulation(&[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [2.0, 2.0], [0.0, 4.0]]);
+    }
+
+    #[test]
+    fn ear_clipping_handles_collinear_vertices() {
+        // U shape with extra vertices in the middle of outer and inner edges
+        check_triangulation(&[
+            [0.0, 0.0], [1.5, 0.0], [3.0, 0.0], [3.0, 1.5], [3.0, 3.0], [2.0, 3.0], [2.0, 2.0], [2.0, 1.0],
+            [1.5, 1.0], [1.0, 1.0], [1.0, 2.0], [1.0, 3.0], [0.0, 3.0], [0.0, 1.5],
+        ]);
+        // Triangle with collinear vertices along every side
+        check_triangulation(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0], [2.0, 1.0], [1.0, 2.0], [0.0, 3.0], [0.0, 2.0], [0.0, 1.0]]);
+        // Reflex vertex, collinear with an edge
+        check_triangulation(&[[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]]);
+    }
+}
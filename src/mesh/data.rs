@@ -0,0 +1,234 @@
+use crate::math::*;
+use crate::render;
+
+/// Editable primitive contents, that mesh operations work on
+#[derive(Clone)]
+pub(super) struct MeshData {
+    pub color: u32,
+    pub positions: Vec<Vec3f>,
+    pub normals: Vec<Vec3f>,
+    pub faces: render::Faces,
+    /// Texture coordinates, one per position or empty
+    pub tex_coords: Vec<Vec2f>,
+    /// Vertex colors, one per position or empty
+    pub colors: Vec<u32>,
+    /// Vertex normals, one per position or empty
+    pub vertex_normals: Vec<Vec3f>,
+    pub line_strips: render::LineStrips,
+    pub points: Vec<u32>,
+}
+
+impl MeshData {
+    /// Primitive contents copying function
+    /// * `primitive` - primitive to copy
+    pub fn new(primitive: &render::Primitive) -> Self {
+        Self {
+            color: primitive.color,
+            positions: primitive.get_positions().to_vec(),
+            normals: primitive.get_normals().to_vec(),
+            faces: primitive.get_faces().clone(),
+            tex_coords: primitive.get_tex_coords().to_vec(),
+            colors: primitive.get_colors().to_vec(),
+            vertex_normals: primitive.get_vertex_normals().to_vec(),
+            line_strips: primitive.get_line_strips().clone(),
+            points: primitive.get_points().to_vec(),
+        }
+    }
+
+    /// Vertex copy adding function
+    /// * `vertex` - index of vertex to copy with all its attributes
+    /// * Returns index of copy
+    pub fn duplicate_vertex(&mut self, vertex: u32) -> u32 {
+        let vertex = vertex as usize;
+
+        self.positions.push(self.positions[vertex]);
+        if !self.tex_coords.is_empty() {
+            self.tex_coords.push(self.tex_coords[vertex]);
+        }
+        if !self.colors.is_empty() {
+            self.colors.push(self.colors[vertex]);
+        }
+        if !self.vertex_normals.is_empty() {
+            self.vertex_normals.push(self.vertex_normals[vertex]);
+        }
+
+        self.positions.len() as u32 - 1
+    }
+
+    /// Averaged vertex adding function
+    /// * `position` - new vertex position
+    /// * `sources` - non-empty set of vertices, texture coordinates, colors and vertex normals of which are averaged
+    /// * Returns index of new vertex
+    pub fn add_average_vertex(&mut self, position: Vec3f, sources: &[u32]) -> u32 {
+        let weight = 1.0 / sources.len() as f32;
+
+        self.positions.push(position);
+        if !self.tex_coords.is_empty() {
+            let sum = sources.iter().fold(Vec2f::new(0.0, 0.0), |sum, source| sum + self.tex_coords[*source as usize]);
+            self.tex_coords.push(sum * weight);
+        }
+        if !self.colors.is_empty() {
+            let color = [0, 8, 16].iter().fold(0, |color, shift| {
+                let sum = sources.iter().map(|source| (self.colors[*source as usize] >> shift) & 0xFF).sum::<u32>();
+                color | (((sum as f32 * weight).round() as u32) << shift)
+            });
+            self.colors.push(color);
+        }
+        if !self.vertex_normals.is_empty() {
+            let sum = sources.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, source| sum + self.vertex_normals[*source as usize]);
+            self.vertex_normals.push(if sum.length2() > 0.0 { sum.normalized() } else { Vec3f::new(0.0, 1.0, 0.0) });
+        }
+
+        self.positions.len() as u32 - 1
+    }
+
+    /// Vertex remapping function
+    /// * `remap` - new index of every vertex, vertices with the same new index are merged into first of them,
+    ///   vertices with `u32::MAX` index are removed and must not be referenced. New indices must cover range from zero
+    ///   to their maximum
+    pub fn remap_vertices(&mut self, remap: &[u32]) {
+        let count = remap.iter().filter(|index| **index != u32::MAX).map(|index| *index as usize + 1).max().unwrap_or(0);
+
+        // First old vertex of every new vertex
+        let mut sources = vec![u32::MAX; count];
+        for (vertex, index) in remap.iter().enumerate().filter(|(_, index)| **index != u32::MAX) {
+            let source = &mut sources[*index as usize];
+            if *source == u32::MAX {
+                *source = vertex as u32;
+            }
+        }
+
+        fn gather<T: Copy>(values: &mut Vec<T>, sources: &[u32]) {
+            if !values.is_empty() {
+                *values = sources.iter().map(|source| values[*source as usize]).collect();
+            }
+        }
+        gather(&mut self.positions, &sources);
+        gather(&mut self.tex_coords, &sources);
+        gather(&mut self.colors, &sources);
+        gather(&mut self.vertex_normals, &sources);
+
+        for index in self.faces.get_vertices_mut() {
+            *index = remap[*index as usize];
+        }
+
+        for index in self.line_strips.get_vertices_mut() {
+            *index = remap[*index as usize];
+        }
+
+        for index in &mut self.points {
+            *index = remap[*index as usize];
+        }
+    }
+
+    /// Primitive building function
+    /// * Returns primitive, panics if contents are invalid, that mesh operations must not allow
+    pub fn build(self) -> render::Primitive {
+        let mut primitive = render::Primitive::new(self.positions, self.normals, self.faces, self.color).expect("mesh operation result is valid");
+        primitive.set_tex_coords(self.tex_coords).expect("mesh operation result is valid");
+        primitive.set_colors(self.colors).expect("mesh operation result is valid");
+        primitive.set_vertex_normals(self.vertex_normals).expect("mesh operation result is valid");
+        // Element setters recompute bounding volumes, so they are called only if needed
+        if !self.line_strips.is_empty() {
+            primitive.set_line_strips(self.line_strips).expect("mesh operation result is valid");
+        }
+        if !self.points.is_empty() {
+            primitive.set_points(self.points).expect("mesh operation result is valid");
+        }
+
+        primitive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshBuilder;
+
+    /// Square with every vertex attribute, line strip and point building function
+    fn square() -> render::Primitive {
+        let mut builder = MeshBuilder::new();
+        for (index, (x, y)) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].into_iter().enumerate() {
+            let normal = Vec3f::new(x - 0.5, y - 0.5, 1.0).normalized();
+            let vertex = builder.add_vertex_with(Vec3f::new(x, y, 0.0), normal, Vec2f::new(x, y));
+            builder.set_vertex_color(vertex, 0x102030 * index as u32);
+        }
+        builder.add_face([0, 1, 2, 3]);
+        builder.add_line_strip([0, 2]);
+        builder.add_point(3);
+
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn copies_and_builds_primitive() {
+        let source = square();
+        let primitive = MeshData::new(&source).build();
+
+        assert_eq!(primitive.get_positions().len(), 4);
+        assert_eq!(primitive.faces().next().unwrap().vertices, [0, 1, 2, 3]);
+        assert_eq!(primitive.get_colors(), source.get_colors());
+        assert_eq!(primitive.get_tex_coords().len(), 4);
+        assert_eq!(primitive.get_vertex_normals().len(), 4);
+        assert_eq!(primitive.line_strips().collect::<Vec<_>>(), vec![&[0, 2]]);
+        assert_eq!(primitive.get_points(), [3]);
+    }
+
+    #[test]
+    fn duplicates_vertex_with_attributes() {
+        let mut data = MeshData::new(&square());
+        let copy = data.duplicate_vertex(2);
+
+        assert_eq!(copy, 4);
+        assert_eq!((data.positions.len(), data.tex_coords.len(), data.colors.len(), data.vertex_normals.len()), (5, 5, 5, 5));
+        assert!((data.positions[4] - data.positions[2]).length2() == 0.0);
+        assert!((data.tex_coords[4] - data.tex_coords[2]).length2() == 0.0);
+        assert!((data.vertex_normals[4] - data.vertex_normals[2]).length2() == 0.0);
+        assert_eq!(data.colors[4], data.colors[2]);
+
+        // Missing attributes stay missing
+        let mut data = MeshData::new(&square());
+        data.tex_coords.clear();
+        data.colors.clear();
+        data.duplicate_vertex(0);
+        assert!(data.tex_coords.is_empty() && data.colors.is_empty());
+        assert_eq!(data.vertex_normals.len(), 5);
+    }
+
+    #[test]
+    fn averages_vertex_attributes() {
+        let mut data = MeshData::new(&square());
+        let vertex = data.add_average_vertex(Vec3f::new(0.5, 0.5, 0.0), &[0, 2]) as usize;
+
+        assert_eq!(vertex, 4);
+        assert!((data.tex_coords[vertex] - Vec2f::new(0.5, 0.5)).length2() < 1e-12);
+        // Channels are averaged separately and rounded
+        assert_eq!(data.colors[vertex], 0x102030);
+        assert!((data.vertex_normals[vertex] - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12);
+
+        // Opposite normals give Y axis
+        data.vertex_normals[1] = -data.vertex_normals[0];
+        let vertex = data.add_average_vertex(Vec3f::new(0.5, 0.0, 0.0), &[0, 1]) as usize;
+        assert!((data.vertex_normals[vertex] - Vec3f::new(0.0, 1.0, 0.0)).length2() < 1e-12);
+    }
+
+    #[test]
+    fn remaps_vertices() {
+        let mut data = MeshData::new(&square());
+        data.faces = [(0, [0, 1, 2])].into_iter().collect();
+        data.line_strips = render::LineStrips::new();
+        data.line_strips.push([0, 2]);
+        data.points = vec![2];
+
+        // Vertex 2 merges into 0, vertex 3 is removed
+        data.remap_vertices(&[0, 1, 0, u32::MAX]);
+
+        assert_eq!(data.positions.len(), 2);
+        assert_eq!((data.tex_coords.len(), data.colors.len(), data.vertex_normals.len()), (2, 2, 2));
+        assert!((data.positions[1] - Vec3f::new(1.0, 0.0, 0.0)).length2() == 0.0);
+        assert_eq!(data.colors, [0, 0x102030]);
+        assert_eq!(data.faces.get_vertices(), [0, 1, 0]);
+        assert_eq!(data.line_strips.get_vertices(), [0, 0]);
+        assert_eq!(data.points, [0]);
+    }
+}
@@ -0,0 +1,716 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::math::*;
+use crate::render;
+use super::cleanup::triangulate;
+use super::data::MeshData;
+use super::normals::polygon_normal;
+
+/// Weight of boundary constraint plane relative to squared boundary edge length
+const BOUNDARY_WEIGHT: f64 = 10.0;
+
+/// Decimation options
+#[derive(Copy, Clone, Debug)]
+pub struct DecimateOptions {
+    /// Triangle count, decimation stops at, polygons are counted as triangles they are split into
+    pub target_face_count: usize,
+    /// Maximal error in primitive space units, decimation stops before collapse with larger error
+    pub max_error: f32,
+    /// Keep boundary vertices in place, otherwise boundaries are simplified along themselves
+    pub lock_boundaries: bool,
+}
+
+impl Default for DecimateOptions {
+    fn default() -> Self {
+        Self {
+            target_face_count: 0,
+            max_error: f32::INFINITY,
+            lock_boundaries: true,
+        }
+    }
+}
+
+/// Decimation result
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DecimateReport {
+    /// Number of triangles before decimation, polygons are counted as triangles they are split into
+    pub triangle_count: usize,
+    /// Number of vertices before decimation
+    pub vertex_count: usize,
+    /// Number of triangles after decimation, result consists of triangles only
+    pub result_triangle_count: usize,
+    /// Number of vertices after decimation
+    pub result_vertex_count: usize,
+    /// Maximum over performed collapses of square root of area-weighted mean squared distance from collapse target
+    /// to planes of original faces around collapsed edge, in primitive space units, zero if nothing is collapsed
+    pub error: f32,
+}
+
+impl std::fmt::Display for DecimateReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "decimated {} to {} triangles, {} to {} vertices, error {}",
+            self.triangle_count, self.result_triangle_count, self.vertex_count, self.result_vertex_count, self.error,
+        )
+    }
+}
+
+/// Weighted sum of squared distances to planes, stored as upper triangle of symmetric 4x4 matrix and total weight
+#[derive(Copy, Clone, Default)]
+struct Quadric {
+    values: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+    /// Single plane quadric create function
+    /// * `normal` - unit plane normal
+    /// * `point` - point on plane
+    /// * `weight` - quadric weight
+    fn from_plane(normal: Vec3f, point: Vec3f, weight: f64) -> Self {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -(a * point.x as f64 + b * point.y as f64 + c * point.z as f64);
+
+        Self {
+            values: [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|value| value * weight),
+            weight,
+        }
+    }
+
+    /// Quadric adding function
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.values.iter_mut().zip(other.values) {
+            *value += other;
+        }
+        self.weight += other.weight;
+    }
+
+    /// Quadric sum getting function
+    fn sum(&self, other: &Quadric) -> Quadric {
+        let mut sum = *self;
+        sum.add(other);
+        sum
+    }
+
+    /// Error getting function
+    /// * `point` - point to get error at
+    /// * Returns weighted mean of squared distances from point to quadric planes
+    fn error(&self, point: Vec3f) -> f64 {
+        if self.weight <= 0.0 {
+            return 0.0;
+        }
+
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, d2] = self.values;
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+
+        let error = a2 * x * x + 2.0 * ab * x * y + 2.0 * ac * x * z + 2.0 * ad * x
+            + b2 * y * y + 2.0 * bc * y * z + 2.0 * bd * y
+            + c2 * z * z + 2.0 * cd * z
+            + d2;
+
+        (error / self.weight).max(0.0)
+    }
+
+    /// Minimal error point getting function
+    /// * Returns point, or None if quadric planes don't define single point
+    fn optimum(&self) -> Option<Vec3f> {
+        if self.weight <= 0.0 {
+            return None;
+        }
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, _] = self.values.map(|value| value / self.weight);
+
+        let det = a2 * (b2 * c2 - bc * bc) - ab * (ab * c2 - bc * ac) + ac * (ab * bc - b2 * ac);
+        if det.abs() < 1e-9 {
+            return None;
+        }
+
+        // Cramer's rule for gradient being zero
+        let (r0, r1, r2) = (-ad, -bd, -cd);
+        let x = (r0 * (b2 * c2 - bc * bc) - ab * (r1 * c2 - bc * r2) + ac * (r1 * bc - b2 * r2)) / det;
+        let y = (a2 * (r1 * c2 - bc * r2) - r0 * (ab * c2 - bc * ac) + ac * (ab * r2 - r1 * ac)) / det;
+        let z = (a2 * (b2 * r2 - r1 * bc) - ab * (ab * r2 - r1 * ac) + r0 * (ab * bc - b2 * ac)) / det;
+
+        Some(Vec3f::new(x as f32, y as f32, z as f32))
+    }
+}
+
+/// Vertex role in decimation
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum VertexKind {
+    /// Vertex may be moved
+    Interior,
+    /// Vertex may be moved along boundary only
+    Boundary,
+    /// Vertex may not be moved or removed
+    Locked,
+}
+
+/// Edge collapse candidate
+struct Collapse {
+    /// Quadric error of collapse
+    error: f64,
+    /// Vertex, that is moved to target
+    keep: u32,
+    /// Vertex, that is merged into kept one
+    remove: u32,
+    target: Vec3f,
+    /// Versions of vertices, collapse is computed for
+    keep_version: u32,
+    remove_version: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// Reverse error order, so binary heap pops the cheapest collapse
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.error.total_cmp(&self.error)
+            .then_with(|| other.keep.cmp(&self.keep))
+            .then_with(|| other.remove.cmp(&self.remove))
+    }
+}
+
+/// Edge key getting function
+fn edge_key(first: u32, second: u32) -> (u32, u32) {
+    (first.min(second), first.max(second))
+}
+
+/// Edge collapse decimation state
+struct Decimator {
+    data: MeshData,
+    triangles: Vec<[u32; 3]>,
+    is_triangle_removed: Vec<bool>,
+    face_count: usize,
+    /// Triangles, that use vertex, may contain removed ones
+    vertex_triangles: Vec<Vec<u32>>,
+    quadrics: Vec<Quadric>,
+    kinds: Vec<VertexKind>,
+    /// Vertex version, increased on every change of vertex, so older collapse candidates are skipped
+    versions: Vec<u32>,
+    is_vertex_removed: Vec<bool>,
+    is_on_boundary: Vec<bool>,
+    boundary_edges: HashSet<(u32, u32)>,
+}
+
+impl Decimator {
+    /// Decimation state create function
+    /// * `data` - primitive with triangle faces only
+    /// * `lock_boundaries` - lock boundary vertices
+    fn new(data: MeshData, lock_boundaries: bool) -> Self {
+        let vertex_count = data.positions.len();
+        let triangles = data.faces.iter().map(|face| [face.vertices[0], face.vertices[1], face.vertices[2]]).collect::<Vec<_>>();
+
+        let mut vertex_triangles = vec![Vec::new(); vertex_count];
+        let mut quadrics = vec![Quadric::default(); vertex_count];
+        let mut edges = HashMap::<(u32, u32), (u32, u32)>::with_capacity(triangles.len() * 3 / 2);
+
+        for (index, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|vertex| data.positions[vertex as usize]);
+            let normal = (b - a) % (c - a);
+            let quadric = if normal.length2() > 0.0 {
+                Quadric::from_plane(normal.normalized(), a, normal.length() as f64 / 2.0)
+            } else {
+                Quadric::default()
+            };
+
+            for (corner, vertex) in triangle.iter().enumerate() {
+                vertex_triangles[*vertex as usize].push(index as u32);
+                quadrics[*vertex as usize].add(&quadric);
+
+                // Face count and first face of every edge
+                let edge = edges.entry(edge_key(*vertex, triangle[(corner + 1) % 3])).or_insert((0, index as u32));
+                edge.0 += 1;
+            }
+        }
+
+        let mut kinds = vec![VertexKind::Interior; vertex_count];
+        let mut boundary_edge_counts = vec![0u32; vertex_count];
+        let mut boundary_edges = HashSet::new();
+        let mut boundary_triangles = Vec::new();
+
+        for (&(first, second), &(count, triangle)) in &edges {
+            match count {
+                1 => {
+                    boundary_edges.insert((first, second));
+                    boundary_triangles.push((first, second, triangle));
+                    for vertex in [first, second] {
+                        boundary_edge_counts[vertex as usize] += 1;
+                    }
+                }
+                2 => {}
+                // Non-manifold edges stay as they are
+                _ => {
+                    kinds[first as usize] = VertexKind::Locked;
+                    kinds[second as usize] = VertexKind::Locked;
+                }
+            }
+        }
+
+        for (kind, count) in kinds.iter_mut().zip(&boundary_edge_counts) {
+            if *count != 0 && *kind == VertexKind::Interior {
+                // Boundary vertex, that isn't on single boundary loop, may only be locked
+                *kind = if lock_boundaries || *count != 2 { VertexKind::Locked } else { VertexKind::Boundary };
+            }
+        }
+
+        // Vertices, that share position with other ones, are on texture seam or hard edge, so they are locked not to open cracks
+        let mut position_counts = HashMap::<(u32, u32, u32), u32>::with_capacity(vertex_count);
+        for position in &data.positions {
+            *position_counts.entry((position.x.to_bits(), position.y.to_bits(), position.z.to_bits())).or_default() += 1;
+        }
+        for (kind, position) in kinds.iter_mut().zip(&data.positions) {
+            if position_counts[&(position.x.to_bits(), position.y.to_bits(), position.z.to_bits())] > 1 {
+                *kind = VertexKind::Locked;
+            }
+        }
+
+        for vertex in data.line_strips.get_vertices().iter().chain(&data.points) {
+            kinds[*vertex as usize] = VertexKind::Locked;
+        }
+
+        // Planes through boundary edges, orthogonal to their faces, keep movable boundaries in place
+        boundary_triangles.sort_unstable();
+        for (first, second, triangle) in boundary_triangles {
+            if kinds[first as usize] != VertexKind::Boundary && kinds[second as usize] != VertexKind::Boundary {
+                continue;
+            }
+
+            let [a, b, c] = triangles[triangle as usize].map(|vertex| data.positions[vertex as usize]);
+            let (start, end) = (data.positions[first as usize], data.positions[second as usize]);
+            let normal = (end - start) % ((b - a) % (c - a));
+            if normal.length2() > 0.0 {
+                let quadric = Quadric::from_plane(normal.normalized(), start, BOUNDARY_WEIGHT * (end - start).length2() as f64);
+                quadrics[first as usize].add(&quadric);
+                quadrics[second as usize].add(&quadric);
+            }
+        }
+
+        Self {
+            face_count: triangles.len(),
+            is_triangle_removed: vec![false; triangles.len()],
+            triangles,
+            vertex_triangles,
+            quadrics,
+            kinds,
+            versions: vec![0; vertex_count],
+            is_vertex_removed: vec![false; vertex_count],
+            is_on_boundary: boundary_edge_counts.iter().map(|count| *count != 0).collect(),
+            boundary_edges,
+            data,
+        }
+    }
+
+    /// Vertex neighbours getting function
+    /// * `vertex` - vertex to get neighbours of
+    /// * Returns vertices, that share triangle with vertex, without duplicates
+    fn neighbours(&self, vertex: u32) -> Vec<u32> {
+        let mut neighbours = Vec::new();
+
+        for triangle in &self.vertex_triangles[vertex as usize] {
+            if self.is_triangle_removed[*triangle as usize] {
+                continue;
+            }
+            for other in self.triangles[*triangle as usize] {
+                if other != vertex && !neighbours.contains(&other) {
+                    neighbours.push(other);
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    /// Edge collapse computing function
+    /// * `first`, `second` - edge vertices
+    /// * Returns cheapest collapse, allowed by vertex kinds, if any
+    fn compute_collapse(&self, first: u32, second: u32) -> Option<Collapse> {
+        use VertexKind::*;
+
+        let (keep, remove, is_fixed) = match (self.kinds[first as usize], self.kinds[second as usize]) {
+            (Locked, Locked) => return None,
+            (Locked, _) | (Boundary, Interior) => (first, second, true),
+            (_, Locked) | (Interior, Boundary) => (second, first, true),
+            (Boundary, Boundary) if !self.boundary_edges.contains(&edge_key(first, second)) => return None,
+            (Boundary, Boundary) | (Interior, Interior) => (first, second, false),
+        };
+
+        let quadric = self.quadrics[keep as usize].sum(&self.quadrics[remove as usize]);
+        let (keep_position, remove_position) = (self.data.positions[keep as usize], self.data.positions[remove as usize]);
+
+        let target = if is_fixed {
+            keep_position
+        } else {
+            // Optimum far from edge is result of nearly singular quadric
+            let middle = (keep_position + remove_position) * 0.5;
+            let limit = (remove_position - keep_position).length2() * 4.0;
+            let optimum = quadric.optimum().filter(|optimum| (*optimum - middle).length2() <= limit);
+
+            optimum.into_iter()
+                .chain([keep_position, remove_position, middle])
+                .min_by(|first, second| quadric.error(*first).total_cmp(&quadric.error(*second)))
+                .unwrap_or(middle)
+        };
+
+        Some(Collapse {
+            error: quadric.error(target),
+            keep,
+            remove,
+            target,
+            keep_version: self.versions[keep as usize],
+            remove_version: self.versions[remove as usize],
+        })
+    }
+
+    /// Collapse validity checking function
+    /// * `collapse` - collapse to check
+    /// * Returns true if collapse keeps mesh manifold and flips no triangles
+    fn is_valid(&self, collapse: &Collapse) -> bool {
+        let (keep, remove) = (collapse.keep, collapse.remove);
+
+        // Inner edge between boundary vertices pinches mesh
+        if self.is_on_boundary[keep as usize] && self.is_on_boundary[remove as usize] && !self.boundary_edges.contains(&edge_key(keep, remove)) {
+            return false;
+        }
+
+        // Link condition: vertices may share only neighbours, opposite to their common edge
+        let keep_neighbours = self.neighbours(keep);
+        let opposite_count = self.vertex_triangles[keep as usize]
+            .iter()
+            .filter(|triangle| !self.is_triangle_removed[**triangle as usize] && self.triangles[**triangle as usize].contains(&remove))
+            .count();
+        let common_count = self.neighbours(remove).iter().filter(|vertex| keep_neighbours.contains(vertex)).count();
+        if common_count != opposite_count {
+            return false;
+        }
+
+        for vertex in [keep, remove] {
+            for index in &self.vertex_triangles[vertex as usize] {
+                let triangle = self.triangles[*index as usize];
+                if self.is_triangle_removed[*index as usize] || (triangle.contains(&keep) && triangle.contains(&remove)) {
+                    continue;
+                }
+
+                let [a, b, c] = triangle.map(|vertex| self.data.positions[vertex as usize]);
+                let [new_a, new_b, new_c] = triangle.map(|corner| {
+                    if corner == keep || corner == remove { collapse.target } else { self.data.positions[corner as usize] }
+                });
+
+                let normal = (b - a) % (c - a);
+                let new_normal = (new_b - new_a) % (new_c - new_a);
+                if normal.length2() > 0.0 && (normal ^ new_normal) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Collapse applying function
+    /// * `collapse` - valid collapse
+    fn apply(&mut self, collapse: &Collapse) {
+        let (keep, remove) = (collapse.keep as usize, collapse.remove as usize);
+
+        // Attributes are interpolated by target projection on edge
+        let edge = self.data.positions[remove] - self.data.positions[keep];
+        let t = if edge.length2() > 0.0 { ((collapse.target - self.data.positions[keep]) ^ edge) / edge.length2() } else { 0.0 };
+        let t = t.clamp(0.0, 1.0);
+
+        if !self.data.tex_coords.is_empty() {
+            self.data.tex_coords[keep] = self.data.tex_coords[keep] + (self.data.tex_coords[remove] - self.data.tex_coords[keep]) * t;
+        }
+        if !self.data.colors.is_empty() {
+            let (first, second) = (self.data.colors[keep], self.data.colors[remove]);
+            self.data.colors[keep] = [0, 8, 16].iter().fold(0, |color, shift| {
+                let (first, second) = (((first >> shift) & 0xFF) as f32, ((second >> shift) & 0xFF) as f32);
+                color | (((first + (second - first) * t).round() as u32) << shift)
+            });
+        }
+        if !self.data.vertex_normals.is_empty() {
+            let normal = self.data.vertex_normals[keep] + (self.data.vertex_normals[remove] - self.data.vertex_normals[keep]) * t;
+            self.data.vertex_normals[keep] = if normal.length2() > 0.0 { normal.normalized() } else { self.data.vertex_normals[keep] };
+        }
+
+        // Boundary edges of removed vertex move to kept one
+        if self.is_on_boundary[remove] {
+            self.is_on_boundary[keep] = true;
+            for neighbour in self.neighbours(collapse.remove) {
+                if self.boundary_edges.remove(&edge_key(collapse.remove, neighbour)) && neighbour != collapse.keep {
+                    self.boundary_edges.insert(edge_key(collapse.keep, neighbour));
+                }
+            }
+        }
+
+        let remove_triangles = std::mem::take(&mut self.vertex_triangles[remove]);
+        for triangle in remove_triangles {
+            if self.is_triangle_removed[triangle as usize] {
+                continue;
+            }
+
+            let corners = &mut self.triangles[triangle as usize];
+            if corners.contains(&collapse.keep) {
+                self.is_triangle_removed[triangle as usize] = true;
+                self.face_count -= 1;
+            } else {
+                for corner in corners.iter_mut().filter(|corner| **corner == collapse.remove) {
+                    *corner = collapse.keep;
+                }
+                self.vertex_triangles[keep].push(triangle);
+            }
+        }
+        let is_triangle_removed = &self.is_triangle_removed;
+        self.vertex_triangles[keep].retain(|triangle| !is_triangle_removed[*triangle as usize]);
+
+        self.data.positions[keep] = collapse.target;
+        let remove_quadric = self.quadrics[remove];
+        self.quadrics[keep].add(&remove_quadric);
+        self.versions[keep] += 1;
+        self.is_vertex_removed[remove] = true;
+    }
+}
+
+/// Quadric error metric edge collapse decimation function.
+///
+/// Faces are triangulated first. Edges are collapsed in order of quadric error (mean of squared distances
+/// to planes of original faces, merged into edge vertices, weighted by face area) until face count reaches target, error exceeds maximal one,
+/// or no valid collapse remains. Collapses, that make mesh non-manifold or flip triangles, are skipped.
+///
+/// Vertices, that share position with other vertices (so are on texture seam or hard normal edge),
+/// vertices of non-manifold edges and vertices of line strips and points are never moved, so seams don't crack.
+/// Boundary vertices are locked or move along boundary only. Texture coordinates, colors and vertex normals
+/// of moved vertices are interpolated along collapsed edge. Result faces get geometric normals.
+/// * `primitive` - primitive to decimate
+/// * `options` - decimation options
+/// * Returns decimated primitive and decimation report
+pub fn decimate(primitive: &render::Primitive, options: &DecimateOptions) -> (render::Primitive, DecimateReport) {
+    let data = if primitive.faces().all(|face| face.vertices.len() == 3) {
+        MeshData::new(primitive)
+    } else {
+        MeshData::new(&triangulate(primitive).0)
+    };
+
+    let mut decimator = Decimator::new(data, options.lock_boundaries);
+    let mut report = DecimateReport {
+        triangle_count: decimator.face_count,
+        vertex_count: primitive.get_positions().len(),
+        ..Default::default()
+    };
+
+    let mut heap = BinaryHeap::new();
+    for triangle in &decimator.triangles {
+        for corner in 0..3 {
+            let (first, second) = (triangle[corner], triangle[(corner + 1) % 3]);
+            // Every inner edge is met twice, but only one of them is ordered
+            if first < second || decimator.boundary_edges.contains(&edge_key(first, second)) {
+                heap.extend(decimator.compute_collapse(first, second));
+            }
+        }
+    }
+
+    let max_error = options.max_error as f64 * options.max_error as f64;
+    let mut error = 0.0f64;
+
+    while decimator.face_count > options.target_face_count {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+
+        let (keep, remove) = (collapse.keep as usize, collapse.remove as usize);
+        if decimator.is_vertex_removed[keep] || decimator.is_vertex_removed[remove]
+            || decimator.versions[keep] != collapse.keep_version || decimator.versions[remove] != collapse.remove_version
+        {
+            continue;
+        }
+        if collapse.error > max_error {
+            break;
+        }
+        if !decimator.is_valid(&collapse) {
+            continue;
+        }
+
+        decimator.apply(&collapse);
+        error = error.max(collapse.error);
+
+        for neighbour in decimator.neighbours(collapse.keep) {
+            heap.extend(decimator.compute_collapse(collapse.keep, neighbour));
+        }
+    }
+
+    // Result has remaining triangles with own normals and vertices, used by any element
+    let Decimator { mut data, triangles, is_triangle_removed, .. } = decimator;
+    let triangles = triangles.into_iter().zip(is_triangle_removed).filter(|(_, is_removed)| !is_removed).map(|(triangle, _)| triangle);
+
+    data.faces = triangles.enumerate().map(|(index, triangle)| (index as u32, triangle)).collect();
+    data.normals = data.faces.iter()
+        .map(|face| polygon_normal(3, |corner| data.positions[face.vertices[corner] as usize]))
+        .collect();
+
+    let mut is_used = vec![false; data.positions.len()];
+    for vertex in data.faces.get_vertices().iter().chain(data.line_strips.get_vertices()).chain(&data.points) {
+        is_used[*vertex as usize] = true;
+    }
+
+    let mut used_count = 0;
+    let remap = is_used.iter().map(|is_used| if *is_used { used_count += 1; used_count - 1 } else { u32::MAX }).collect::<Vec<_>>();
+    data.remap_vertices(&remap);
+
+    report.result_triangle_count = data.faces.len();
+    report.result_vertex_count = data.positions.len();
+    report.error = error.sqrt() as f32;
+
+    (data.build(), report)
+}
+
+/// Level of detail chain building options
+#[derive(Copy, Clone, Debug)]
+pub struct LodOptions {
+    /// Maximal number of levels, including source primitive
+    pub level_count: usize,
+    /// Target face count of every level relative to previous level
+    pub reduction: f32,
+    /// Projected decimation error in pixels, level is used up to
+    pub pixel_error: f32,
+    /// Keep boundary vertices of all levels in place
+    pub lock_boundaries: bool,
+}
+
+impl Default for LodOptions {
+    fn default() -> Self {
+        Self {
+            level_count: 4,
+            reduction: 0.5,
+            pixel_error: 1.0,
+            lock_boundaries: true,
+        }
+    }
+}
+
+/// Level of detail chain building function.
+///
+/// Every level is decimated from source primitive, so errors don't accumulate. Level is used while its decimation
+/// error, projected to screen, doesn't exceed `options.pixel_error`. Building stops early, if decimation can't
+/// reduce triangle count anymore.
+/// * `primitive` - the finest level of detail
+/// * `options` - chain building options
+/// * Returns chain with source primitive and its decimated levels
+pub fn build_lod_chain(primitive: render::Primitive, options: &LodOptions) -> render::LodChain {
+    let diameter = primitive.get_bounding_sphere().radius * 2.0;
+    let mut triangle_count = primitive.faces().map(|face| face.vertices.len().saturating_sub(2)).sum::<usize>();
+
+    let mut levels = Vec::new();
+    for _ in 1..options.level_count {
+        let target_face_count = (triangle_count as f32 * options.reduction) as usize;
+        if target_face_count == 0 {
+            break;
+        }
+
+        let (level, report) = decimate(&primitive, &DecimateOptions {
+            target_face_count,
+            lock_boundaries: options.lock_boundaries,
+            ..Default::default()
+        });
+        if report.result_triangle_count >= triangle_count {
+            break;
+        }
+
+        let max_screen_size = if report.error > 0.0 { options.pixel_error * diameter / report.error } else { f32::INFINITY };
+        triangle_count = report.result_triangle_count;
+        levels.push((level, max_screen_size));
+    }
+
+    let mut chain = render::LodChain::new(primitive);
+    for (level, max_screen_size) in levels {
+        chain.push(level, max_screen_size);
+    }
+
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::shapes::{grid, icosphere};
+
+    /// Grid boundary position checking function
+    fn is_on_boundary(position: Vec3f) -> bool {
+        position.x.abs() > 0.999 || position.z.abs() > 0.999
+    }
+
+    #[test]
+    fn grid_reaches_target_with_locked_boundary() {
+        let source = grid(2.0, 2.0, 10, 10);
+        let (result, report) = decimate(&source, &DecimateOptions { target_face_count: 60, ..Default::default() });
+
+        assert_eq!(report.triangle_count, 200);
+        assert_eq!(report.vertex_count, 121);
+        assert!(report.result_triangle_count <= 60, "{report}");
+        assert_eq!(report.result_triangle_count, result.faces().count());
+        assert_eq!(report.result_vertex_count, result.get_positions().len());
+        // Grid is flat, so collapses don't move surface
+        assert!(report.error < 1e-4, "{report}");
+
+        // Boundary vertices are kept at their places
+        let positions = result.get_positions();
+        for source_position in source.get_positions().iter().filter(|position| is_on_boundary(**position)) {
+            assert!(positions.iter().any(|position| (*position - *source_position).length2() < 1e-10));
+        }
+
+        // Triangles cover the whole grid and none of them is flipped
+        let mut area = 0.0;
+        for face in result.faces() {
+            let [a, b, c] = [0, 1, 2].map(|corner| positions[face.vertices[corner] as usize]);
+            let normal = (b - a) % (c - a);
+            assert!(normal.y > 0.0);
+            area += normal.y / 2.0;
+        }
+        assert!((area - 4.0).abs() < 1e-3, "area is {area}");
+    }
+
+    #[test]
+    fn sphere_error_grows_with_reduction() {
+        let source = icosphere(1.0, 3);
+        let (_, coarse) = decimate(&source, &DecimateOptions { target_face_count: 100, ..Default::default() });
+        let (_, fine) = decimate(&source, &DecimateOptions { target_face_count: 800, ..Default::default() });
+
+        assert_eq!(coarse.triangle_count, 1280);
+        assert!(coarse.result_triangle_count <= 100, "{coarse}");
+        assert!(fine.result_triangle_count <= 800, "{fine}");
+        assert!(fine.error > 0.0 && fine.error < coarse.error, "{fine}, {coarse}");
+        // Deviation can't exceed sphere size
+        assert!(coarse.error < 1.0, "{coarse}");
+
+        // Error limit stops decimation before target
+        let (_, limited) = decimate(&source, &DecimateOptions { target_face_count: 100, max_error: fine.error, ..Default::default() });
+        assert!(limited.result_triangle_count > 100 && limited.error <= fine.error, "{limited}");
+    }
+
+    #[test]
+    fn lod_levels_get_coarser() {
+        let chain = build_lod_chain(icosphere(1.0, 3), &LodOptions::default());
+        let levels = chain.get_levels();
+
+        assert_eq!(levels.len(), 4);
+        for pair in levels.windows(2) {
+            assert!(pair[1].primitive.faces().count() < pair[0].primitive.faces().count());
+            assert!(pair[1].max_screen_size < pair[0].max_screen_size);
+        }
+
+        // The finest level is used at large size, the coarsest one at small size
+        assert_eq!(chain.select(1e6), 0);
+        assert_eq!(chain.select(levels[2].max_screen_size), 2);
+        assert_eq!(chain.select(0.0), 3);
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+
+use crate::math::*;
+use crate::render;
+use super::data::MeshData;
+
+/// Half-edge connectivity kind
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Edge of two consistently oriented faces, half-edge has twin
+    Interior,
+    /// Edge of single face
+    Boundary,
+    /// Edge of more than two faces
+    NonManifold,
+    /// Edge of two faces, that pass it in the same direction, so they have opposite orientation
+    Misoriented,
+    /// Edge from vertex to itself
+    Degenerate,
+}
+
+/// Directed edge of face
+#[derive(Copy, Clone, Debug)]
+pub struct HalfEdge {
+    /// Vertex, half-edge starts at
+    pub origin: u32,
+    /// Face, half-edge belongs to
+    pub face: u32,
+    /// Next half-edge of face
+    pub next: u32,
+    /// Previous half-edge of face
+    pub prev: u32,
+    /// Opposite half-edge of adjacent face, exists for interior edges only
+    pub twin: Option<u32>,
+    pub kind: EdgeKind,
+}
+
+/// Mesh topology summary
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopologyReport {
+    /// Number of vertices, referenced by faces
+    pub vertex_count: usize,
+    /// Number of distinct undirected edges
+    pub edge_count: usize,
+    pub face_count: usize,
+    pub boundary_edge_count: usize,
+    pub boundary_loop_count: usize,
+    pub non_manifold_edge_count: usize,
+    /// Number of vertices, faces around which form several fans, connected through vertex only
+    pub non_manifold_vertex_count: usize,
+    pub misoriented_edge_count: usize,
+    pub degenerate_edge_count: usize,
+}
+
+impl TopologyReport {
+    /// Manifoldness checking function
+    /// * Returns true if every edge has one or two faces and faces around every vertex form single fan
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edge_count == 0 && self.non_manifold_vertex_count == 0
+    }
+
+    /// Orientation consistency checking function
+    /// * Returns true if every pair of adjacent faces passes shared edge in opposite directions
+    pub fn is_oriented(&self) -> bool {
+        self.misoriented_edge_count == 0
+    }
+
+    /// Closedness checking function
+    /// * Returns true if mesh is manifold and has no boundary
+    pub fn is_closed(&self) -> bool {
+        self.is_manifold() && self.boundary_edge_count == 0
+    }
+
+    /// Euler characteristic getting function
+    /// * Returns vertex count minus edge count plus face count
+    pub fn euler_characteristic(&self) -> isize {
+        self.vertex_count as isize - self.edge_count as isize + self.face_count as isize
+    }
+}
+
+impl std::fmt::Display for TopologyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} edges, {} faces, {} boundary edges in {} loops",
+            self.vertex_count, self.edge_count, self.face_count, self.boundary_edge_count, self.boundary_loop_count,
+        )?;
+        if !self.is_manifold() {
+            write!(f, ", {} non-manifold edges and {} vertices", self.non_manifold_edge_count, self.non_manifold_vertex_count)?;
+        }
+        if !self.is_oriented() {
+            write!(f, ", {} misoriented edges", self.misoriented_edge_count)?;
+        }
+        if self.degenerate_edge_count != 0 {
+            write!(f, ", {} degenerate edges", self.degenerate_edge_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Half-edge mesh representation.
+///
+/// Half-edges of every face are stored in face order, so half-edge index is index of its face corner in primitive face
+/// vertex stream. Connectivity is built on vertex indices, so vertices with equal positions and different attributes
+/// (e.g. on texture seams) are different vertices, [`super::weld_vertices`] may merge them first.
+/// Non-manifold, misoriented and degenerate edges don't panic, their half-edges have no twins and are reported.
+pub struct HalfEdgeMesh {
+    data: MeshData,
+    half_edges: Vec<HalfEdge>,
+    /// First half-edge of every face
+    face_half_edges: Vec<u32>,
+    /// Outgoing half-edge of every vertex, that starts its fan, None if vertex has no faces
+    vertex_half_edges: Vec<Option<u32>>,
+    non_manifold_vertices: Vec<u32>,
+}
+
+impl HalfEdgeMesh {
+    /// Half-edge mesh building function
+    /// * `primitive` - primitive to build connectivity of
+    pub fn new(primitive: &render::Primitive) -> Self {
+        let data = MeshData::new(primitive);
+        let faces = &data.faces;
+        let offsets = faces.get_offsets();
+
+        let mut half_edges = Vec::with_capacity(faces.get_vertices().len());
+        let mut face_half_edges = Vec::with_capacity(faces.len());
+        for face in faces.iter() {
+            let first = offsets[face.index];
+            let count = face.vertices.len() as u32;
+            face_half_edges.push(first);
+
+            for index in 0..count {
+                half_edges.push(HalfEdge {
+                    origin: face.vertices[index as usize],
+                    face: face.index as u32,
+                    next: first + (index + 1) % count,
+                    prev: first + (index + count - 1) % count,
+                    twin: None,
+                    kind: EdgeKind::Boundary,
+                });
+            }
+        }
+
+        // Half-edges of every undirected edge
+        let mut edges = HashMap::<(u32, u32), Vec<u32>>::with_capacity(half_edges.len());
+        for (index, half_edge) in half_edges.iter().enumerate() {
+            let (origin, target) = (half_edge.origin, half_edges[half_edge.next as usize].origin);
+            edges.entry((origin.min(target), origin.max(target))).or_default().push(index as u32);
+        }
+
+        for (&(first, second), edge) in &edges {
+            let kind = match edge.as_slice() {
+                _ if first == second => EdgeKind::Degenerate,
+                [_] => EdgeKind::Boundary,
+                [half_edge, other] if half_edges[*half_edge as usize].origin != half_edges[*other as usize].origin => {
+                    half_edges[*half_edge as usize].twin = Some(*other);
+                    half_edges[*other as usize].twin = Some(*half_edge);
+                    EdgeKind::Interior
+                }
+                [_, _] => EdgeKind::Misoriented,
+                _ => EdgeKind::NonManifold,
+            };
+            for half_edge in edge {
+                half_edges[*half_edge as usize].kind = kind;
+            }
+        }
+
+        // Fans are started at boundary half-edges, so rotation through twins covers them
+        let mut vertex_half_edges = vec![None; data.positions.len()];
+        for (index, half_edge) in half_edges.iter().enumerate() {
+            let start = &mut vertex_half_edges[half_edge.origin as usize];
+            if start.is_none() || half_edge.twin.is_none() {
+                *start = Some(index as u32);
+            }
+        }
+
+        // Face corners of every vertex are joined through non-degenerate edges, vertex with several groups is non-manifold
+        let mut parents = (0..half_edges.len()).collect::<Vec<_>>();
+        let find = |parents: &mut Vec<usize>, mut corner: usize| {
+            while parents[corner] != corner {
+                parents[corner] = parents[parents[corner]];
+                corner = parents[corner];
+            }
+            corner
+        };
+        for edge in edges.values() {
+            if half_edges[edge[0] as usize].kind == EdgeKind::Degenerate {
+                continue;
+            }
+
+            // Half-edge corner is at its origin and next half-edge corner is at its target, corner at smaller vertex goes first
+            let corners = |half_edge: u32| {
+                let next = half_edges[half_edge as usize].next;
+                if half_edges[half_edge as usize].origin < half_edges[next as usize].origin {
+                    (half_edge as usize, next as usize)
+                } else {
+                    (next as usize, half_edge as usize)
+                }
+            };
+            let first = corners(edge[0]);
+
+            for half_edge in &edge[1..] {
+                let other = corners(*half_edge);
+                for (corner, other) in [(first.0, other.0), (first.1, other.1)] {
+                    let (root, other_root) = (find(&mut parents, corner), find(&mut parents, other));
+                    parents[root] = other_root;
+                }
+            }
+        }
+
+        let mut vertex_roots = vec![usize::MAX; data.positions.len()];
+        let mut is_non_manifold = vec![false; data.positions.len()];
+        for (corner, half_edge) in half_edges.iter().enumerate() {
+            let (vertex, root) = (half_edge.origin as usize, find(&mut parents, corner));
+
+            if vertex_roots[vertex] == usize::MAX {
+                vertex_roots[vertex] = root;
+            } else if vertex_roots[vertex] != root {
+                is_non_manifold[vertex] = true;
+            }
+        }
+        let non_manifold_vertices = (0..data.positions.len() as u32).filter(|vertex| is_non_manifold[*vertex as usize]).collect();
+
+        Self { data, half_edges, face_half_edges, vertex_half_edges, non_manifold_vertices }
+    }
+
+    /// Primitive building function
+    /// * Returns primitive with faces, collected from half-edge loops in mesh face order, and source primitive attributes
+    pub fn to_primitive(&self) -> render::Primitive {
+        let mut data = MeshData {
+            faces: render::Faces::with_capacity(self.face_half_edges.len(), self.half_edges.len()),
+            ..self.data.clone()
+        };
+        let normals = self.data.faces.get_normals();
+
+        for (face, normal) in normals.iter().enumerate() {
+            data.faces.push(*normal, self.face_vertices(face as u32));
+        }
+
+        data.build()
+    }
+
+    /// Vertex positions getting function
+    pub fn get_positions(&self) -> &[Vec3f] {
+        &self.data.positions
+    }
+
+    /// Half-edges getting function
+    /// * Returns half-edges in face corner order
+    pub fn get_half_edges(&self) -> &[HalfEdge] {
+        &self.half_edges
+    }
+
+    /// Half-edge getting function
+    /// * `half_edge` - half-edge index
+    pub fn get_half_edge(&self, half_edge: u32) -> &HalfEdge {
+        &self.half_edges[half_edge as usize]
+    }
+
+    /// Vertex count getting function
+    /// * Returns number of vertices, including ones without faces
+    pub fn vertex_count(&self) -> usize {
+        self.data.positions.len()
+    }
+
+    /// Face count getting function
+    pub fn face_count(&self) -> usize {
+        self.face_half_edges.len()
+    }
+
+    /// Half-edge target vertex getting function
+    /// * `half_edge` - half-edge index
+    /// * Returns vertex, half-edge ends at
+    pub fn target(&self, half_edge: u32) -> u32 {
+        self.half_edges[self.half_edges[half_edge as usize].next as usize].origin
+    }
+
+    /// Face half-edges iterating function
+    /// * `face` - face index
+    /// * Returns iterator on face half-edges in face order
+    pub fn face_half_edges(&self, face: u32) -> impl Iterator<Item = u32> + '_ {
+        let first = self.face_half_edges[face as usize];
+        let mut current = Some(first);
+
+        std::iter::from_fn(move || {
+            let half_edge = current?;
+            let next = self.half_edges[half_edge as usize].next;
+            current = (next != first).then_some(next);
+            Some(half_edge)
+        })
+    }
+
+    /// Face vertices iterating function
+    /// * `face` - face index
+    /// * Returns iterator on face vertices in face order
+    pub fn face_vertices(&self, face: u32) -> impl Iterator<Item = u32> + '_ {
+        self.face_half_edges(face).map(|half_edge| self.half_edges[half_edge as usize].origin)
+    }
+
+    /// Adjacent faces iterating function
+    /// * `face` - face index
+    /// * Returns iterator on faces, that share interior edges with face
+    pub fn face_neighbours(&self, face: u32) -> impl Iterator<Item = u32> + '_ {
+        self.face_half_edges(face)
+            .filter_map(|half_edge| self.half_edges[half_edge as usize].twin)
+            .map(|twin| self.half_edges[twin as usize].face)
+    }
+
+    /// Vertex outgoing half-edges iterating function.
+    ///
+    /// Half-edges are visited by rotation through interior edges, starting from boundary half-edge if vertex has one,
+    /// so iteration covers single fan only, that is all faces of vertex, if it is manifold
+    /// * `vertex` - vertex index
+    /// * Returns iterator on half-edges, that start at vertex
+    pub fn vertex_half_edges(&self, vertex: u32) -> impl Iterator<Item = u32> + '_ {
+        let first = self.vertex_half_edges[vertex as usize];
+        let mut current = first;
+
+        std::iter::from_fn(move || {
+            let half_edge = current?;
+            current = self.half_edges[self.half_edges[half_edge as usize].prev as usize].twin.filter(|next| Some(*next) != first);
+            Some(half_edge)
+        })
+    }
+
+    /// Vertex faces iterating function
+    /// * `vertex` - vertex index
+    /// * Returns iterator on faces of vertex fan
+    pub fn vertex_faces(&self, vertex: u32) -> impl Iterator<Item = u32> + '_ {
+        self.vertex_half_edges(vertex).map(|half_edge| self.half_edges[half_edge as usize].face)
+    }
+
+    /// Adjacent vertices getting function
+    /// * `vertex` - vertex index
+    /// * Returns vertices, connected with vertex by edges of its fan, in rotation order
+    pub fn vertex_neighbours(&self, vertex: u32) -> Vec<u32> {
+        let mut neighbours = Vec::new();
+        let mut last = None;
+
+        for half_edge in self.vertex_half_edges(vertex) {
+            neighbours.push(self.target(half_edge));
+            last = Some(half_edge);
+        }
+
+        // Open fan ends with incoming half-edge without twin
+        if let Some(last) = last {
+            let prev = &self.half_edges[self.half_edges[last as usize].prev as usize];
+            if prev.twin.is_none() {
+                neighbours.push(prev.origin);
+            }
+        }
+
+        neighbours
+    }
+
+    /// Edges iterating function
+    /// * Returns iterator on one half-edge of every interior edge and every half-edge without twin
+    pub fn edges(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.half_edges.len() as u32).filter(|half_edge| self.half_edges[*half_edge as usize].twin.is_none_or(|twin| *half_edge < twin))
+    }
+
+    /// Boundary vertex checking function
+    /// * `vertex` - vertex index
+    /// * Returns true if vertex has outgoing boundary half-edge
+    pub fn is_boundary_vertex(&self, vertex: u32) -> bool {
+        self.vertex_half_edges[vertex as usize].is_some_and(|half_edge| self.half_edges[half_edge as usize].kind == EdgeKind::Boundary)
+    }
+
+    /// Boundary loops finding function.
+    ///
+    /// Loop is followed from boundary half-edge to next one, that starts at its target, by rotation around target,
+    /// so loops, that pass non-manifold vertices, non-manifold or misoriented edges, may be split into open chains.
+    /// * Returns boundary half-edge sequences in face direction
+    pub fn boundary_loops(&self) -> Vec<Vec<u32>> {
+        let mut is_visited = vec![false; self.half_edges.len()];
+        let mut loops = Vec::new();
+
+        for start in 0..self.half_edges.len() {
+            if is_visited[start] || self.half_edges[start].kind != EdgeKind::Boundary {
+                continue;
+            }
+
+            let mut boundary_loop = Vec::new();
+            let mut current = Some(start as u32);
+            while let Some(half_edge) = current.filter(|half_edge| !is_visited[*half_edge as usize]) {
+                is_visited[half_edge as usize] = true;
+                boundary_loop.push(half_edge);
+
+                // Clockwise rotation around target stops at outgoing half-edge without twin
+                let mut next = self.half_edges[half_edge as usize].next;
+                for _ in 0..self.half_edges.len() {
+                    match self.half_edges[next as usize].twin {
+                        Some(twin) => next = self.half_edges[twin as usize].next,
+                        None => break,
+                    }
+                }
+                current = (self.half_edges[next as usize].kind == EdgeKind::Boundary).then_some(next);
+            }
+
+            loops.push(boundary_loop);
+        }
+
+        loops
+    }
+
+    /// Non-manifold vertices getting function
+    /// * Returns vertices, faces around which form several fans, connected through vertex only
+    pub fn get_non_manifold_vertices(&self) -> &[u32] {
+        &self.non_manifold_vertices
+    }
+
+    /// Topology summary computing function
+    pub fn report(&self) -> TopologyReport {
+        let mut report = TopologyReport {
+            vertex_count: self.vertex_half_edges.iter().filter(|half_edge| half_edge.is_some()).count(),
+            face_count: self.face_half_edges.len(),
+            boundary_loop_count: self.boundary_loops().len(),
+            non_manifold_vertex_count: self.non_manifold_vertices.len(),
+            ..Default::default()
+        };
+
+        // Edges without twins are counted once per edge, not per half-edge
+        let mut counted = std::collections::HashSet::new();
+        for half_edge in self.edges() {
+            let (origin, target) = (self.half_edges[half_edge as usize].origin, self.target(half_edge));
+            if !counted.insert((origin.min(target), origin.max(target))) {
+                continue;
+            }
+
+            report.edge_count += 1;
+            match self.half_edges[half_edge as usize].kind {
+                EdgeKind::Interior => {}
+                EdgeKind::Boundary => report.boundary_edge_count += 1,
+                EdgeKind::NonManifold => report.non_manifold_edge_count += 1,
+                EdgeKind::Misoriented => report.misoriented_edge_count += 1,
+                EdgeKind::Degenerate => report.degenerate_edge_count += 1,
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{cube, grid, weld_vertices, MeshBuilder, WeldOptions};
+
+    /// Half-edge mesh of triangles with distinct vertex positions building function
+    fn triangles(faces: &[[u32; 3]]) -> HalfEdgeMesh {
+        let mut builder = MeshBuilder::new();
+        let vertex_count = faces.iter().flatten().max().map_or(0, |vertex| vertex + 1);
+        for vertex in 0..vertex_count {
+            let angle = vertex as f32;
+            builder.add_vertex(Vec3f::new(angle.cos(), angle.sin(), vertex as f32 * 0.1));
+        }
+        for face in faces {
+            builder.add_face(*face);
+        }
+
+        HalfEdgeMesh::new(&builder.build().unwrap())
+    }
+
+    #[test]
+    fn fin_edge_is_non_manifold() {
+        let mesh = triangles(&[[0, 1, 2], [1, 0, 3], [0, 1, 4]]);
+        let report = mesh.report();
+
+        assert_eq!(report.edge_count, 7);
+        assert_eq!(report.non_manifold_edge_count, 1);
+        assert_eq!(report.boundary_edge_count, 6);
+        // Faces around edge vertices are connected through the edge
+        assert_eq!(report.non_manifold_vertex_count, 0);
+        assert!(!report.is_manifold() && !report.is_closed());
+        assert!(mesh.get_half_edges().iter().filter(|half_edge| half_edge.kind == EdgeKind::NonManifold).all(|half_edge| half_edge.twin.is_none()));
+    }
+
+    #[test]
+    fn bowtie_vertex_is_non_manifold() {
+        let mesh = triangles(&[[0, 1, 2], [0, 3, 4]]);
+        let report = mesh.report();
+
+        assert_eq!(mesh.get_non_manifold_vertices(), &[0]);
+        assert_eq!(report.non_manifold_edge_count, 0);
+        assert_eq!(report.boundary_loop_count, 2);
+        assert!(!report.is_manifold());
+    }
+
+    #[test]
+    fn opposite_faces_are_misoriented() {
+        let report = triangles(&[[0, 1, 2], [0, 1, 3]]).report();
+
+        assert_eq!(report.misoriented_edge_count, 1);
+        assert!(report.is_manifold() && !report.is_oriented());
+    }
+
+    #[test]
+    fn grid_has_single_boundary_loop() {
+        let mesh = HalfEdgeMesh::new(&grid(3.0, 3.0, 3, 3));
+        let report = mesh.report();
+
+        assert_eq!(report, TopologyReport {
+            vertex_count: 16,
+            edge_count: 24,
+            face_count: 9,
+            boundary_edge_count: 12,
+            boundary_loop_count: 1,
+            ..Default::default()
+        });
+        assert_eq!(report.euler_characteristic(), 1);
+
+        // Loop is continuous and passes every boundary vertex once
+        let loops = mesh.boundary_loops();
+        let boundary_loop = &loops[0];
+        assert_eq!(boundary_loop.len(), 12);
+        for (index, half_edge) in boundary_loop.iter().enumerate() {
+            let next = boundary_loop[(index + 1) % boundary_loop.len()];
+            assert_eq!(mesh.target(*half_edge), mesh.get_half_edge(next).origin);
+        }
+        assert_eq!((0..16).filter(|vertex| mesh.is_boundary_vertex(*vertex)).count(), 12);
+    }
+
+    #[test]
+    fn welded_cube_is_closed() {
+        // Sides have own vertices, so they are separate squares
+        let report = HalfEdgeMesh::new(&cube(2.0)).report();
+        assert_eq!((report.vertex_count, report.boundary_loop_count), (24, 6));
+
+        let (welded, _) = weld_vertices(&cube(2.0), &WeldOptions { keep_seams: false, ..Default::default() });
+        let mesh = HalfEdgeMesh::new(&welded);
+        let report = mesh.report();
+
+        assert_eq!((report.vertex_count, report.edge_count, report.face_count), (8, 12, 6));
+        assert!(report.is_closed() && report.is_oriented());
+        assert_eq!(report.euler_characteristic(), 2);
+        assert!(mesh.boundary_loops().is_empty());
+        assert!((0..8).all(|vertex| mesh.vertex_neighbours(vertex).len() == 3 && mesh.vertex_faces(vertex).count() == 3));
+    }
+}
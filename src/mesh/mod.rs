@@ -0,0 +1,19 @@
+mod analysis;
+mod builder;
+mod cleanup;
+mod data;
+mod decimate;
+mod half_edge;
+mod normals;
+mod subdivide;
+
+pub mod shapes;
+
+pub use analysis::*;
+pub use builder::MeshBuilder;
+pub use cleanup::*;
+pub use decimate::*;
+pub use half_edge::*;
+pub use normals::*;
+pub use subdivide::*;
+pub use shapes::{arrow, cone, cube, cylinder, grid, icosphere, plane, torus, uv_sphere};
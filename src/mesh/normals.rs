@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use crate::math::*;
+use crate::render;
+use super::data::MeshData;
+
+/// Weight of face normal in vertex normal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Every face has the same weight
+    Uniform,
+    /// Face weight is face area, so large faces dominate
+    Area,
+    /// Face weight is face angle at vertex, so result doesn't depend on face tessellation
+    Angle,
+}
+
+/// Smooth vertex normal computation options
+#[derive(Copy, Clone, Debug)]
+pub struct NormalOptions {
+    /// Maximal angle between adjacent face normals in radians, that is smoothed over, edges with larger angle are hard.
+    /// PI or more smoothes over every edge
+    pub crease_angle: f32,
+    /// Face normal weighting
+    pub weighting: NormalWeighting,
+}
+
+impl Default for NormalOptions {
+    fn default() -> Self {
+        Self {
+            crease_angle: 60.0f32.to_radians(),
+            weighting: NormalWeighting::Angle,
+        }
+    }
+}
+
+/// Polygon area vector computing function
+/// * `vertex_count` - number of polygon vertices
+/// * `vertex` - polygon vertex position by vertex number
+/// * Returns polygon normal, scaled by double polygon area, computed by Newell's method
+pub(super) fn newell_vector(vertex_count: usize, vertex: impl Fn(usize) -> Vec3f) -> Vec3f {
+    let mut normal = Vec3f::new(0.0, 0.0, 0.0);
+
+    for index in 0..vertex_count {
+        let current = vertex(index);
+        let next = vertex((index + 1) % vertex_count);
+
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+
+    normal
+}
+
+/// Polygon normal computing function
+/// * `vertex_count` - number of polygon vertices
+/// * `vertex` - polygon vertex position by vertex number
+/// * Returns normalized normal, computed by Newell's method, that works for non-planar and concave polygons,
+///   or Y axis if polygon is degenerate
+pub fn polygon_normal(vertex_count: usize, vertex: impl Fn(usize) -> Vec3f) -> Vec3f {
+    let normal = newell_vector(vertex_count, vertex);
+
+    if normal.length2() > 0.0 {
+        normal.normalized()
+    } else {
+        Vec3f::new(0.0, 1.0, 0.0)
+    }
+}
+
+/// Geometric face normals computing function
+/// * `primitive` - primitive to compute normals of
+/// * Returns normal of every face in face order, computed from face vertex positions
+pub fn face_normals(primitive: &render::Primitive) -> Vec<Vec3f> {
+    let positions = primitive.get_positions();
+
+    primitive.faces()
+        .map(|face| polygon_normal(face.vertices.len(), |index| positions[face.vertices[index] as usize]))
+        .collect()
+}
+
+/// Flat shaded primitive building function
+/// * `primitive` - source primitive
+/// * Returns copy of primitive with own geometric normal for every face and without vertex normals
+pub fn flat_normals(primitive: &render::Primitive) -> render::Primitive {
+    let mut data = MeshData::new(primitive);
+    data.normals = face_normals(primitive);
+    data.faces = primitive.faces().map(|face| (face.index as u32, face.vertices.iter().copied())).collect();
+    data.vertex_normals.clear();
+
+    data.build()
+}
+
+/// Smooth shaded primitive building function.
+///
+/// Face corners, that share position, are smoothed together if they are connected through edges, angle between
+/// geometric normals of faces at which doesn't exceed crease angle. Edges are matched by positions, so vertices,
+/// split at texture seams, are smoothed together. Vertex, that has several such corner groups, is split, every extra
+/// group gets copy of vertex with its texture coordinate and color, so hard edges stay hard.
+/// First group of vertex keeps its index, so line strips and points are unchanged. Face normals are kept.
+/// * `primitive` - source primitive
+/// * `options` - crease angle and face weighting
+/// * Returns copy of primitive with vertex normals, vertices without faces get Y axis as normal
+pub fn smooth_normals(primitive: &render::Primitive, options: &NormalOptions) -> render::Primitive {
+    let positions = primitive.get_positions();
+    let faces = primitive.get_faces();
+    let corner_vertices = faces.get_vertices();
+    let offsets = faces.get_offsets();
+
+    // Unnormalized face normals, zero for degenerate faces
+    let area_normals = faces.iter()
+        .map(|face| newell_vector(face.vertices.len(), |index| positions[face.vertices[index] as usize]))
+        .collect::<Vec<_>>();
+    let unit_normals = area_normals.iter()
+        .map(|normal| if normal.length2() > 0.0 { normal.normalized() } else { *normal })
+        .collect::<Vec<_>>();
+
+    // Corners are indices in face vertex stream, smooth corner groups are kept in disjoint set forest
+    let mut parents = (0..corner_vertices.len()).collect::<Vec<_>>();
+    let find = |parents: &mut Vec<usize>, mut corner: usize| {
+        while parents[corner] != corner {
+            parents[corner] = parents[parents[corner]];
+            corner = parents[corner];
+        }
+        corner
+    };
+
+    // Vertices with equal positions are the same point, so texture seams don't split smooth groups
+    let mut point_indices = HashMap::<(u32, u32, u32), u32>::with_capacity(positions.len());
+    let vertex_points = positions
+        .iter()
+        .map(|position| {
+            // Zero sum makes negative zero equal to positive one
+            let key = ((position.x + 0.0).to_bits(), (position.y + 0.0).to_bits(), (position.z + 0.0).to_bits());
+            let point_count = point_indices.len() as u32;
+            *point_indices.entry(key).or_insert(point_count)
+        })
+        .collect::<Vec<_>>();
+
+    let min_cos = if options.crease_angle >= std::f32::consts::PI { -2.0 } else { options.crease_angle.cos() };
+    // First face, that used edge, and its corners at smaller and larger edge point
+    let mut edges = HashMap::<(u32, u32), (usize, usize, usize)>::with_capacity(corner_vertices.len());
+
+    for face in faces.iter() {
+        let first = offsets[face.index] as usize;
+        let count = face.vertices.len();
+
+        for index in 0..count {
+            let (corner, next_corner) = (first + index, first + (index + 1) % count);
+            let (point, next_point) = (vertex_points[corner_vertices[corner] as usize], vertex_points[corner_vertices[next_corner] as usize]);
+            if point == next_point {
+                continue;
+            }
+
+            let (key, corners) = if point < next_point {
+                ((point, next_point), (corner, next_corner))
+            } else {
+                ((next_point, point), (next_corner, corner))
+            };
+
+            let Some(&(other_face, other_min, other_max)) = edges.get(&key) else {
+                edges.insert(key, (face.index, corners.0, corners.1));
+                continue;
+            };
+
+            // Degenerate faces have zero normal, so they are smoothed with any neighbour
+            let (normal, other_normal) = (unit_normals[face.index], unit_normals[other_face]);
+            let is_smooth = normal.length2() == 0.0 || other_normal.length2() == 0.0 || (normal ^ other_normal) >= min_cos;
+            if is_smooth {
+                for (corner, other) in [(corners.0, other_min), (corners.1, other_max)] {
+                    let (root, other_root) = (find(&mut parents, corner), find(&mut parents, other));
+                    parents[root] = other_root;
+                }
+            }
+        }
+    }
+
+    // Weighted face normal sums of corner groups
+    let mut sums = vec![Vec3f::new(0.0, 0.0, 0.0); corner_vertices.len()];
+    for face in faces.iter() {
+        let first = offsets[face.index] as usize;
+        let count = face.vertices.len();
+
+        for index in 0..count {
+            let weight = match options.weighting {
+                NormalWeighting::Uniform => 1.0,
+                NormalWeighting::Area => area_normals[face.index].length() / 2.0,
+                NormalWeighting::Angle => {
+                    let position = positions[face.vertices[index] as usize];
+                    let previous = positions[face.vertices[(index + count - 1) % count] as usize] - position;
+                    let next = positions[face.vertices[(index + 1) % count] as usize] - position;
+
+                    if previous.length2() > 0.0 && next.length2() > 0.0 {
+                        (previous.normalized() ^ next.normalized()).clamp(-1.0, 1.0).acos()
+                    } else {
+                        0.0
+                    }
+                }
+            };
+
+            let root = find(&mut parents, first + index);
+            sums[root] += unit_normals[face.index] * weight;
+        }
+    }
+
+    // Every vertex of corner group gets its copy, first group of vertex takes vertex itself
+    let mut data = MeshData::new(primitive);
+    data.vertex_normals = vec![Vec3f::new(0.0, 1.0, 0.0); positions.len()];
+    let mut is_taken = vec![false; positions.len()];
+    let mut group_vertices = HashMap::<(usize, u32), u32>::with_capacity(corner_vertices.len());
+    let mut new_corner_vertices = Vec::with_capacity(corner_vertices.len());
+
+    for (corner, vertex) in corner_vertices.iter().enumerate() {
+        let root = find(&mut parents, corner);
+
+        let group_vertex = *group_vertices.entry((root, *vertex)).or_insert_with(|| {
+            let sum = sums[root];
+            let normal = if sum.length2() > 0.0 { sum.normalized() } else { Vec3f::new(0.0, 1.0, 0.0) };
+
+            if is_taken[*vertex as usize] {
+                let duplicate = data.duplicate_vertex(*vertex);
+                data.vertex_normals[duplicate as usize] = normal;
+                duplicate
+            } else {
+                is_taken[*vertex as usize] = true;
+                data.vertex_normals[*vertex as usize] = normal;
+                *vertex
+            }
+        });
+        new_corner_vertices.push(group_vertex);
+    }
+
+    data.faces.get_vertices_mut().copy_from_slice(&new_corner_vertices);
+
+    data.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{cube, uv_sphere, weld_vertices, WeldOptions};
+
+    /// Cube with 8 shared vertices building function
+    fn welded_cube() -> render::Primitive {
+        let (primitive, report) = weld_vertices(&cube(2.0), &WeldOptions { epsilon: 0.0, keep_seams: false });
+        assert_eq!(report.vertex_count - report.welded_count, 8);
+        primitive
+    }
+
+    /// Vertex normal of every face corner getting function
+    fn corner_normals(primitive: &render::Primitive) -> impl Iterator<Item = (usize, Vec3f)> + '_ {
+        primitive.faces().flat_map(move |face| face.vertices.iter().map(move |vertex| (face.index, primitive.get_vertex_normals()[*vertex as usize])))
+    }
+
+    #[test]
+    fn computes_newell_normal_of_non_planar_polygon() {
+        // Saddle hexagon around Z axis, alternating above and below XY plane
+        let vertices = (0..6)
+            .map(|index| {
+                let angle = index as f32 / 6.0 * std::f32::consts::TAU;
+                Vec3f::new(angle.cos(), angle.sin(), if index % 2 == 0 { 0.3 } else { -0.3 })
+            })
+            .collect::<Vec<_>>();
+
+        let normal = polygon_normal(vertices.len(), |index| vertices[index]);
+        assert!((normal - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-10);
+
+        // Reversed order flips normal, area vector length is doubled area of projection to XY plane
+        let reversed = polygon_normal(vertices.len(), |index| vertices[vertices.len() - 1 - index]);
+        assert!((reversed - Vec3f::new(0.0, 0.0, -1.0)).length2() < 1e-10);
+        let area = 6.0 * 3.0f32.sqrt() / 4.0;
+        assert!((newell_vector(vertices.len(), |index| vertices[index]).length() - 2.0 * area).abs() < 1e-5);
+    }
+
+    #[test]
+    fn computes_newell_normal_of_concave_polygon() {
+        // L-shaped polygon in XZ plane, counter-clockwise seen from above
+        let vertices = [(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0), (1.0, 0.0)]
+            .map(|(x, z)| Vec3f::new(x, 0.0, z));
+
+        let normal = polygon_normal(vertices.len(), |index| vertices[index]);
+        assert!((normal - Vec3f::new(0.0, 1.0, 0.0)).length2() < 1e-10);
+        assert!((newell_vector(vertices.len(), |index| vertices[index]).length() - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn returns_y_axis_for_degenerate_polygon() {
+        let vertices = [Vec3f::new(0.0, 0.0, 0.0), Vec3f::new(1.0, 1.0, 1.0), Vec3f::new(2.0, 2.0, 2.0)];
+        let normal = polygon_normal(vertices.len(), |index| vertices[index]);
+
+        assert!((normal - Vec3f::new(0.0, 1.0, 0.0)).length2() < 1e-10);
+    }
+
+    #[test]
+    fn splits_cube_along_hard_edges() {
+        let primitive = smooth_normals(&welded_cube(), &NormalOptions::default());
+
+        assert_eq!(primitive.get_positions().len(), 24);
+        assert_eq!(primitive.get_vertex_normals().len(), 24);
+
+        // Every corner gets normal of its side
+        let face_normals = face_normals(&primitive);
+        assert!(corner_normals(&primitive).all(|(face, normal)| (normal - face_normals[face]).length2() < 1e-10));
+    }
+
+    #[test]
+    fn smoothes_cube_over_every_edge_without_crease() {
+        let options = NormalOptions { crease_angle: std::f32::consts::PI, ..Default::default() };
+        let primitive = smooth_normals(&welded_cube(), &options);
+
+        assert_eq!(primitive.get_positions().len(), 8);
+
+        // Angle weighting gives diagonal normals
+        let positions = primitive.get_positions();
+        assert!(primitive.get_vertex_normals().iter().zip(positions).all(|(normal, position)| (*normal - position.normalized()).length2() < 1e-10));
+    }
+
+    #[test]
+    fn keeps_sphere_welded() {
+        let sphere = uv_sphere(1.0, 16, 8);
+        let primitive = smooth_normals(&sphere, &NormalOptions::default());
+
+        // Seam and pole vertices are smoothed together with neighbours, so nothing is split
+        assert_eq!(primitive.get_positions().len(), sphere.get_positions().len());
+        for (normal, position) in primitive.get_vertex_normals().iter().zip(primitive.get_positions()) {
+            assert!((normal.length() - 1.0).abs() < 1e-5);
+            assert!((*normal ^ position.normalized()) > 0.99);
+        }
+    }
+}
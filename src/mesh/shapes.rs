@@ -0,0 +1,495 @@
+//! Procedural primitive generators.
+//!
+//! All shapes have counter-clockwise outward faces, vertex normals and texture coordinates. Round shapes have
+//! their axis along Y, texture seams are split, so texture coordinates don't wrap inside of any face.
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use crate::math::*;
+use crate::render;
+use super::MeshBuilder;
+
+/// Built shape unwrapping function, generators reference only vertices they add, so building can't fail
+fn finish(builder: MeshBuilder) -> render::Primitive {
+    builder.build().expect("generated shape is valid")
+}
+
+/// Direction in XZ plane by angle getting function, angle grows counter-clockwise around Y axis starting from Z axis
+fn radial(angle: f32) -> Vec3f {
+    Vec3f::new(angle.sin(), 0.0, angle.cos())
+}
+
+/// Angle of segment boundary around full turn getting function
+/// * `segment` - boundary number, `segments` is the same boundary as zero
+/// * `segments` - number of segments in full turn
+/// * Returns angle in radians, equal for both seam boundaries, so seam vertices get bitwise equal positions
+fn segment_angle(segment: u32, segments: u32) -> f32 {
+    (segment % segments) as f32 / segments as f32 * TAU
+}
+
+/// Surface of revolution side adding function
+/// * `builder` - builder to add side to
+/// * `segments` - number of side segments around Y axis
+/// * `bottom`, `top` - radius and height of side ends, zero top radius makes apex with per-segment normals
+fn add_side(builder: &mut MeshBuilder, segments: u32, bottom: (f32, f32), top: (f32, f32)) {
+    let ((bottom_radius, bottom_y), (top_radius, top_y)) = (bottom, top);
+    // Side slope normal in (radial, Y) plane
+    let slope = Vec2f::new(top_y - bottom_y, bottom_radius - top_radius);
+    let slope = if slope.length2() > 0.0 { slope.normalized() } else { Vec2f::new(1.0, 0.0) };
+    let normal = |angle: f32| radial(angle) * slope.x + Vec3f::new(0.0, slope.y, 0.0);
+
+    let first = builder.get_vertex_count() as u32;
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let angle = segment_angle(segment, segments);
+        builder.add_vertex_with(radial(angle) * bottom_radius + Vec3f::new(0.0, bottom_y, 0.0), normal(angle), Vec2f::new(u, 0.0));
+    }
+
+    if top_radius == 0.0 {
+        for segment in 0..segments {
+            let u = (segment as f32 + 0.5) / segments as f32;
+            let apex = builder.add_vertex_with(Vec3f::new(0.0, top_y, 0.0), normal(u * TAU), Vec2f::new(u, 1.0));
+            builder.add_face([first + segment, first + segment + 1, apex]);
+        }
+        return;
+    }
+
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let angle = segment_angle(segment, segments);
+        builder.add_vertex_with(radial(angle) * top_radius + Vec3f::new(0.0, top_y, 0.0), normal(angle), Vec2f::new(u, 1.0));
+    }
+
+    let top_first = first + segments + 1;
+    for segment in 0..segments {
+        builder.add_face([first + segment, first + segment + 1, top_first + segment + 1, top_first + segment]);
+    }
+}
+
+/// Disc, perpendicular to Y axis, adding function
+/// * `builder` - builder to add disc to
+/// * `segments` - number of disc polygon vertices
+/// * `radius` - disc radius
+/// * `y` - disc height
+/// * `up` - does disc face Y axis direction, or the opposite one
+fn add_disc(builder: &mut MeshBuilder, segments: u32, radius: f32, y: f32, up: bool) {
+    let normal = Vec3f::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+    let first = builder.get_vertex_count() as u32;
+
+    for segment in 0..segments {
+        let direction = radial(segment as f32 / segments as f32 * TAU);
+        // Texture is upright, if disc is seen from outside with Z axis pointing to bottom for upper disc and to top for lower one
+        let tex_coord = Vec2f::new(0.5 + 0.5 * direction.x, 0.5 + if up { -0.5 } else { 0.5 } * direction.z);
+        builder.add_vertex_with(direction * radius + Vec3f::new(0.0, y, 0.0), normal, tex_coord);
+    }
+
+    if up {
+        builder.add_face(first..first + segments);
+    } else {
+        builder.add_face((first..first + segments).rev());
+    }
+}
+
+/// Cube generating function
+/// * `size` - cube edge length, cube is centered at origin
+/// * Returns cube with separate vertices per side, every side is textured by whole texture
+pub fn cube(size: f32) -> render::Primitive {
+    let mut builder = MeshBuilder::with_capacity(24, 6);
+    let half = size / 2.0;
+
+    // Side normal and texture U and V axes, U x V = normal, so corner order below is counter-clockwise
+    let sides = [
+        (Vec3f::new( 1.0,  0.0,  0.0), Vec3f::new( 0.0, 0.0, -1.0), Vec3f::new(0.0, 1.0,  0.0)),
+        (Vec3f::new(-1.0,  0.0,  0.0), Vec3f::new( 0.0, 0.0,  1.0), Vec3f::new(0.0, 1.0,  0.0)),
+        (Vec3f::new( 0.0,  1.0,  0.0), Vec3f::new( 1.0, 0.0,  0.0), Vec3f::new(0.0, 0.0, -1.0)),
+        (Vec3f::new( 0.0, -1.0,  0.0), Vec3f::new( 1.0, 0.0,  0.0), Vec3f::new(0.0, 0.0,  1.0)),
+        (Vec3f::new( 0.0,  0.0,  1.0), Vec3f::new( 1.0, 0.0,  0.0), Vec3f::new(0.0, 1.0,  0.0)),
+        (Vec3f::new( 0.0,  0.0, -1.0), Vec3f::new(-1.0, 0.0,  0.0), Vec3f::new(0.0, 1.0,  0.0)),
+    ];
+
+    for (normal, u_axis, v_axis) in sides {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(u, v)| {
+            let position = (normal + u_axis * (u * 2.0 - 1.0) + v_axis * (v * 2.0 - 1.0)) * half;
+            builder.add_vertex_with(position, normal, Vec2f::new(u, v))
+        });
+        builder.add_face(corners);
+    }
+
+    finish(builder)
+}
+
+/// Latitude-longitude sphere generating function
+/// * `radius` - sphere radius, sphere is centered at origin
+/// * `segments` - number of segments around Y axis, at least 3
+/// * `rings` - number of rings from pole to pole, at least 2
+/// * Returns sphere with quad faces and triangles at poles, texture is wrapped around Y axis
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> render::Primitive {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut builder = MeshBuilder::with_capacity(((segments + 1) * (rings + 1)) as usize, (segments * rings) as usize);
+
+    // Every inner ring has seam vertex twice
+    for ring in 1..rings {
+        let polar = ring as f32 / rings as f32 * PI;
+
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let normal = radial(segment_angle(segment, segments)) * polar.sin() + Vec3f::new(0.0, polar.cos(), 0.0);
+            builder.add_vertex_with(normal * radius, normal, Vec2f::new(u, 1.0 - ring as f32 / rings as f32));
+        }
+    }
+
+    // Poles have vertex per segment to have different texture coordinates
+    let poles = [1.0f32, -1.0].map(|y| {
+        let first = builder.get_vertex_count() as u32;
+        for segment in 0..segments {
+            let u = (segment as f32 + 0.5) / segments as f32;
+            builder.add_vertex_with(Vec3f::new(0.0, y * radius, 0.0), Vec3f::new(0.0, y, 0.0), Vec2f::new(u, (y + 1.0) / 2.0));
+        }
+        first
+    });
+
+    let row = segments + 1;
+    let last_row = (rings - 2) * row;
+    for segment in 0..segments {
+        builder.add_face([segment, segment + 1, poles[0] + segment]);
+    }
+    for ring in 1..rings - 1 {
+        for segment in 0..segments {
+            let upper = (ring - 1) * row + segment;
+            let lower = upper + row;
+            builder.add_face([lower, lower + 1, upper + 1, upper]);
+        }
+    }
+    for segment in 0..segments {
+        builder.add_face([poles[1] + segment, last_row + segment + 1, last_row + segment]);
+    }
+
+    finish(builder)
+}
+
+/// Subdivided icosahedron sphere generating function
+/// * `radius` - sphere radius, sphere is centered at origin
+/// * `subdivisions` - number of times every triangle is split in four, 0 gives icosahedron
+/// * Returns sphere of equal triangles, textured as latitude-longitude sphere
+pub fn icosphere(radius: f32, subdivisions: u32) -> render::Primitive {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut positions = [
+        (-1.0,  t, 0.0), ( 1.0,  t, 0.0), (-1.0, -t, 0.0), ( 1.0, -t, 0.0),
+        (0.0, -1.0,  t), (0.0,  1.0,  t), (0.0, -1.0, -t), (0.0,  1.0, -t),
+        ( t, 0.0, -1.0), ( t, 0.0,  1.0), (-t, 0.0, -1.0), (-t, 0.0,  1.0),
+    ].map(|(x, y, z)| Vec3f::new(x, y, z).normalized()).to_vec();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut middles = HashMap::new();
+        let mut middle = |a: u32, b: u32| *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            positions.push((positions[a as usize] + positions[b as usize]).normalized());
+            positions.len() as u32 - 1
+        });
+
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    // Vertices are split by texture U, so triangles crossing texture seam get U over 1 and pole vertices get U of their triangle
+    let mut builder = MeshBuilder::with_capacity(positions.len(), triangles.len());
+    let mut vertices = HashMap::new();
+
+    for triangle in triangles {
+        let direction = triangle.map(|vertex| positions[vertex as usize]);
+        let is_pole = direction.map(|direction| direction.x.abs() < 1e-6 && direction.z.abs() < 1e-6);
+        // Absolute value turns negative zero to zero, so vertices on seam have single key
+        let mut u = direction.map(|direction| (direction.x.atan2(direction.z).rem_euclid(TAU) / TAU).abs());
+
+        let (min, max) = (0..3).filter(|corner| !is_pole[*corner]).fold((1.0f32, 0.0f32), |(min, max), corner| (min.min(u[corner]), max.max(u[corner])));
+        for corner in 0..3 {
+            if !is_pole[corner] && max - min > 0.5 && u[corner] < 0.5 {
+                u[corner] += 1.0;
+            }
+        }
+        let pole_u = (0..3).filter(|corner| !is_pole[*corner]).map(|corner| u[corner]).sum::<f32>() / 2.0;
+
+        let corners = [0, 1, 2].map(|corner| {
+            let u = if is_pole[corner] { pole_u } else { u[corner] };
+            *vertices.entry((triangle[corner], u.to_bits())).or_insert_with(|| {
+                let tex_coord = Vec2f::new(u, 0.5 + direction[corner].y.clamp(-1.0, 1.0).asin() / PI);
+                builder.add_vertex_with(direction[corner] * radius, direction[corner], tex_coord)
+            })
+        });
+        builder.add_face(corners);
+    }
+
+    finish(builder)
+}
+
+/// Cylinder generating function
+/// * `radius` - cylinder radius
+/// * `height` - cylinder height, cylinder is centered at origin
+/// * `segments` - number of side segments, at least 3
+/// * Returns cylinder with quad side faces and polygon caps
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> render::Primitive {
+    let segments = segments.max(3);
+    let mut builder = MeshBuilder::with_capacity((segments * 4 + 2) as usize, (segments + 2) as usize);
+
+    add_side(&mut builder, segments, (radius, -height / 2.0), (radius, height / 2.0));
+    add_disc(&mut builder, segments, radius, height / 2.0, true);
+    add_disc(&mut builder, segments, radius, -height / 2.0, false);
+
+    finish(builder)
+}
+
+/// Cone generating function
+/// * `radius` - base radius
+/// * `height` - cone height, cone is centered at origin, apex is up
+/// * `segments` - number of side segments, at least 3
+/// * Returns cone with triangle side faces and polygon base
+pub fn cone(radius: f32, height: f32, segments: u32) -> render::Primitive {
+    let segments = segments.max(3);
+    let mut builder = MeshBuilder::with_capacity((segments * 3 + 1) as usize, (segments + 1) as usize);
+
+    add_side(&mut builder, segments, (radius, -height / 2.0), (0.0, height / 2.0));
+    add_disc(&mut builder, segments, radius, -height / 2.0, false);
+
+    finish(builder)
+}
+
+/// Torus generating function
+/// * `major_radius` - distance from origin to tube center
+/// * `minor_radius` - tube radius
+/// * `major_segments` - number of segments around Y axis, at least 3
+/// * `minor_segments` - number of segments around tube, at least 3
+/// * Returns torus in XZ plane, centered at origin, texture U goes around Y axis and V goes around tube
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> render::Primitive {
+    let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+    let mut builder = MeshBuilder::with_capacity(((major_segments + 1) * (minor_segments + 1)) as usize, (major_segments * minor_segments) as usize);
+
+    for major in 0..=major_segments {
+        let u = major as f32 / major_segments as f32;
+        let direction = radial(segment_angle(major, major_segments));
+
+        for minor in 0..=minor_segments {
+            let v = minor as f32 / minor_segments as f32;
+            let angle = segment_angle(minor, minor_segments);
+            let normal = direction * angle.cos() + Vec3f::new(0.0, angle.sin(), 0.0);
+            builder.add_vertex_with(direction * major_radius + normal * minor_radius, normal, Vec2f::new(u, v));
+        }
+    }
+
+    let row = minor_segments + 1;
+    for major in 0..major_segments {
+        for minor in 0..minor_segments {
+            let vertex = major * row + minor;
+            builder.add_face([vertex, vertex + row, vertex + row + 1, vertex + 1]);
+        }
+    }
+
+    finish(builder)
+}
+
+/// Grid generating function
+/// * `width` - grid size along X axis
+/// * `depth` - grid size along Z axis
+/// * `width_segments` - number of cells along X axis, at least 1
+/// * `depth_segments` - number of cells along Z axis, at least 1
+/// * Returns grid of quads in XZ plane, centered at origin and facing Y axis, texture is upright, if seen from above
+///   with Z axis pointing to bottom
+pub fn grid(width: f32, depth: f32, width_segments: u32, depth_segments: u32) -> render::Primitive {
+    let (width_segments, depth_segments) = (width_segments.max(1), depth_segments.max(1));
+    let mut builder = MeshBuilder::with_capacity(((width_segments + 1) * (depth_segments + 1)) as usize, (width_segments * depth_segments) as usize);
+
+    for z in 0..=depth_segments {
+        let v = z as f32 / depth_segments as f32;
+        for x in 0..=width_segments {
+            let u = x as f32 / width_segments as f32;
+            let position = Vec3f::new((u - 0.5) * width, 0.0, (v - 0.5) * depth);
+            builder.add_vertex_with(position, Vec3f::new(0.0, 1.0, 0.0), Vec2f::new(u, 1.0 - v));
+        }
+    }
+
+    let row = width_segments + 1;
+    for z in 0..depth_segments {
+        for x in 0..width_segments {
+            let vertex = z * row + x;
+            builder.add_face([vertex, vertex + row, vertex + row + 1, vertex + 1]);
+        }
+    }
+
+    finish(builder)
+}
+
+/// Plane generating function
+/// * `width` - plane size along X axis
+/// * `depth` - plane size along Z axis
+/// * Returns single quad grid
+pub fn plane(width: f32, depth: f32) -> render::Primitive {
+    grid(width, depth, 1, 1)
+}
+
+/// Arrow generating function
+/// * `length` - arrow length, arrow starts at origin and points along Y axis
+/// * `shaft_radius` - shaft cylinder radius
+/// * `head_length` - head cone height, clamped to arrow length
+/// * `head_radius` - head cone base radius
+/// * `segments` - number of shaft and head segments, at least 3
+/// * Returns arrow of shaft cylinder and head cone
+pub fn arrow(length: f32, shaft_radius: f32, head_length: f32, head_radius: f32, segments: u32) -> render::Primitive {
+    let segments = segments.max(3);
+    let head_start = length - head_length.clamp(0.0, length);
+    let mut builder = MeshBuilder::with_capacity((segments * 7 + 2) as usize, (segments * 2 + 2) as usize);
+
+    add_side(&mut builder, segments, (shaft_radius, 0.0), (shaft_radius, head_start));
+    add_disc(&mut builder, segments, shaft_radius, 0.0, false);
+    add_side(&mut builder, segments, (head_radius, head_start), (0.0, length));
+    add_disc(&mut builder, segments, head_radius, head_start, false);
+
+    finish(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{analyze, AnalysisOptions, MeshAnalysis};
+
+    /// Shape attributes and face orientation checking function
+    /// * `primitive` - generated shape
+    /// * `vertex_count`, `face_count` - expected shape vertex and face counts
+    /// * `welded_vertex_count` - expected number of distinct vertex positions
+    /// * Returns shape analysis
+    fn check_shape(primitive: &render::Primitive, vertex_count: usize, face_count: usize, welded_vertex_count: usize) -> MeshAnalysis {
+        assert_eq!(primitive.get_positions().len(), vertex_count);
+        assert_eq!(primitive.get_faces().len(), face_count);
+        assert_eq!(primitive.get_tex_coords().len(), vertex_count);
+        assert_eq!(primitive.get_vertex_normals().len(), vertex_count);
+
+        // Counter-clockwise faces have normals, that agree with vertex normals
+        for face in primitive.faces() {
+            let normal = crate::mesh::polygon_normal(face.vertices.len(), |index| primitive.get_positions()[face.vertices[index] as usize]);
+            assert!((primitive.get_normals()[face.normal as usize] - normal).length2() < 1e-8);
+            for vertex in face.vertices {
+                let vertex_normal = primitive.get_vertex_normals()[*vertex as usize];
+                assert!((vertex_normal.length() - 1.0).abs() < 1e-5);
+                assert!((vertex_normal ^ normal) > 0.0, "face {} has inward vertex normal", face.index);
+            }
+        }
+
+        let analysis = analyze(primitive, &AnalysisOptions::default());
+        assert_eq!(analysis.topology.vertex_count, welded_vertex_count);
+        assert!(analysis.topology.is_oriented());
+        assert_eq!(analysis.non_planar_count, 0);
+        analysis
+    }
+
+    /// Closed shape checking function
+    /// * `analysis` - shape analysis
+    /// * `volume` - expected shape volume
+    /// * `tolerance` - maximal relative volume error
+    fn check_closed(analysis: &MeshAnalysis, volume: f32, tolerance: f32) {
+        assert!(analysis.topology.is_closed());
+        assert_eq!(analysis.component_count, 1);
+        // Positive volume means outward faces
+        assert!((analysis.volume - volume).abs() <= volume * tolerance, "volume {} isn't {volume}", analysis.volume);
+    }
+
+    /// Regular polygon area getting function
+    fn polygon_area(radius: f32, segments: u32) -> f32 {
+        segments as f32 / 2.0 * (TAU / segments as f32).sin() * radius * radius
+    }
+
+    #[test]
+    fn cube_is_closed() {
+        let analysis = check_shape(&cube(2.0), 24, 6, 8);
+
+        check_closed(&analysis, 8.0, 1e-5);
+        assert!((analysis.bounds.size() - Vec3f::new(2.0, 2.0, 2.0)).length2() < 1e-10);
+    }
+
+    #[test]
+    fn uv_sphere_is_closed() {
+        let analysis = check_shape(&uv_sphere(1.0, 8, 4), 3 * 9 + 2 * 8, 32, 3 * 8 + 2);
+        check_closed(&analysis, 4.0 / 3.0 * PI, 0.4);
+
+        let analysis = check_shape(&uv_sphere(1.0, 32, 16), 15 * 33 + 2 * 32, 32 * 16, 15 * 32 + 2);
+        check_closed(&analysis, 4.0 / 3.0 * PI, 0.05);
+        assert_eq!(analysis.genus(), Some(0));
+
+        // Segment and ring counts are clamped
+        check_shape(&uv_sphere(1.0, 0, 0), 4 + 2 * 3, 6, 5);
+    }
+
+    #[test]
+    fn icosphere_is_closed() {
+        for subdivisions in 0..3 {
+            let face_count = 20 << (2 * subdivisions);
+            let primitive = icosphere(1.0, subdivisions);
+            let vertex_count = primitive.get_positions().len();
+
+            let analysis = check_shape(&primitive, vertex_count, face_count, face_count / 2 + 2);
+            check_closed(&analysis, 4.0 / 3.0 * PI, 0.4);
+            assert!(primitive.get_positions().iter().all(|position| (position.length() - 1.0).abs() < 1e-5));
+        }
+    }
+
+    #[test]
+    fn cylinder_is_closed() {
+        let analysis = check_shape(&cylinder(1.0, 2.0, 16), 2 * 17 + 2 * 16, 18, 32);
+
+        check_closed(&analysis, polygon_area(1.0, 16) * 2.0, 1e-4);
+        assert!((analysis.bounds.size() - Vec3f::new(2.0, 2.0, 2.0)).length2() < 1e-10);
+    }
+
+    #[test]
+    fn cone_is_closed() {
+        let analysis = check_shape(&cone(1.0, 2.0, 16), 17 + 16 + 16, 17, 17);
+
+        check_closed(&analysis, polygon_area(1.0, 16) * 2.0 / 3.0, 1e-4);
+    }
+
+    #[test]
+    fn torus_is_closed() {
+        let analysis = check_shape(&torus(2.0, 0.5, 32, 16), 33 * 17, 32 * 16, 32 * 16);
+
+        check_closed(&analysis, 2.0 * PI * PI * 2.0 * 0.5 * 0.5, 0.05);
+        assert_eq!(analysis.genus(), Some(1));
+    }
+
+    #[test]
+    fn grid_is_open() {
+        let primitive = grid(4.0, 2.0, 4, 3);
+        let analysis = check_shape(&primitive, 5 * 4, 12, 5 * 4);
+
+        assert_eq!(analysis.topology.boundary_loop_count, 1);
+        assert!((analysis.surface_area - 8.0).abs() < 1e-4);
+        assert!(primitive.get_normals().iter().all(|normal| (*normal - Vec3f::new(0.0, 1.0, 0.0)).length2() < 1e-10));
+
+        let analysis = check_shape(&plane(4.0, 2.0), 4, 1, 4);
+        assert!((analysis.surface_area - 8.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn arrow_faces_outside() {
+        let primitive = arrow(2.0, 0.1, 0.5, 0.3, 8);
+        let analysis = check_shape(&primitive, 2 * 9 + 8 + 9 + 8 + 8, 8 + 1 + 8 + 1, 8 + 8 + 8 + 1);
+
+        // Shaft top is covered by head base, so only positive volume is meaningful
+        assert!(analysis.volume > 0.0);
+        assert!((analysis.bounds.max.y - 2.0).abs() < 1e-5 && analysis.bounds.min.y.abs() < 1e-5);
+
+        // Side faces look away from axis, bases look down
+        for face in primitive.faces() {
+            let center = face.vertices.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, vertex| sum + primitive.get_positions()[*vertex as usize]);
+            let normal = primitive.get_normals()[face.normal as usize];
+            assert!((normal ^ Vec3f::new(center.x, 0.0, center.z)) > 0.0 || normal.y < -0.999, "face {} faces inside", face.index);
+        }
+    }
+}
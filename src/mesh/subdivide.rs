@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use crate::math::*;
+use crate::render;
+use super::cleanup::triangulate;
+use super::data::MeshData;
+use super::normals::{newell_vector, polygon_normal, smooth_normals, NormalOptions};
+
+/// Subdivision scheme
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    /// Loop for triangle meshes and Catmull-Clark for other ones
+    Auto,
+    /// Loop scheme, every triangle is split into 4 ones, non-triangle faces are triangulated first
+    Loop,
+    /// Catmull-Clark scheme, every face with N vertices is split into N quads
+    CatmullClark,
+}
+
+/// Subdivision options
+#[derive(Copy, Clone, Debug)]
+pub struct SubdivisionOptions {
+    /// Subdivision scheme
+    pub scheme: SubdivisionScheme,
+    /// Number of subdivision steps, face count grows about 4 times with every step
+    pub level: usize,
+    /// Minimal angle between adjacent face normals in radians, that makes edge crease. Creases and boundaries
+    /// are subdivided as curves, vertices with single or more than two of them and boundary vertices of single face
+    /// are corners and stay in place. PI or more makes boundaries the only creases
+    pub crease_angle: f32,
+}
+
+impl Default for SubdivisionOptions {
+    fn default() -> Self {
+        Self {
+            scheme: SubdivisionScheme::Auto,
+            level: 1,
+            crease_angle: std::f32::consts::PI,
+        }
+    }
+}
+
+/// Edge between two points
+struct Edge {
+    /// Number of faces, that contain edge
+    face_count: u32,
+    /// First two faces, that contain edge
+    faces: [usize; 2],
+    /// Opposite points of edge in first two faces, used by Loop scheme
+    opposites: [u32; 2],
+    is_crease: bool,
+}
+
+/// Point ordered pair getting function
+fn edge_key(first: u32, second: u32) -> (u32, u32) {
+    (first.min(second), first.max(second))
+}
+
+/// Single subdivision step function.
+///
+/// Topology is built on points, vertices with equal positions are the same point, so texture and normal seams don't
+/// tear surface apart. Vertices keep their indices, edge and face vertices are added after them, their attributes are
+/// averages of vertex ones.
+/// * `data` - primitive contents without vertex normals
+/// * `is_loop` - true for Loop scheme, that requires triangle faces, false for Catmull-Clark one
+/// * `min_cos` - minimal cosine of angle between smooth edge face normals
+/// * Returns subdivided primitive contents
+fn subdivide_step(mut data: MeshData, is_loop: bool, min_cos: f32) -> MeshData {
+    // Zero sum makes negative zero equal to positive one
+    let mut point_indices = HashMap::<(u32, u32, u32), u32>::with_capacity(data.positions.len());
+    let mut points = Vec::new();
+    let vertex_points = data.positions
+        .iter()
+        .map(|position| {
+            let key = ((position.x + 0.0).to_bits(), (position.y + 0.0).to_bits(), (position.z + 0.0).to_bits());
+            *point_indices.entry(key).or_insert_with(|| {
+                points.push(*position);
+                points.len() as u32 - 1
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let faces = std::mem::take(&mut data.faces);
+    let face_normals = faces.iter()
+        .map(|face| newell_vector(face.vertices.len(), |index| data.positions[face.vertices[index] as usize]))
+        .map(|normal| if normal.length2() > 0.0 { normal.normalized() } else { normal })
+        .collect::<Vec<_>>();
+    let face_points = faces.iter()
+        .map(|face| face.vertices.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, vertex| sum + data.positions[*vertex as usize]) * (1.0 / face.vertices.len() as f32))
+        .collect::<Vec<_>>();
+
+    let mut edges = HashMap::<(u32, u32), Edge>::with_capacity(faces.get_vertices().len());
+    for face in faces.iter() {
+        let count = face.vertices.len();
+
+        for index in 0..count {
+            let (point, next_point) = (vertex_points[face.vertices[index] as usize], vertex_points[face.vertices[(index + 1) % count] as usize]);
+            let opposite = vertex_points[face.vertices[(index + 2) % count] as usize];
+
+            let edge = edges.entry(edge_key(point, next_point)).or_insert(Edge { face_count: 0, faces: [face.index; 2], opposites: [opposite; 2], is_crease: false });
+            if edge.face_count == 1 {
+                edge.faces[1] = face.index;
+                edge.opposites[1] = opposite;
+            }
+            edge.face_count += 1;
+        }
+    }
+
+    // Boundary, non-manifold and sharp edges are creases, degenerate faces are smooth with any neighbour
+    for edge in edges.values_mut() {
+        let (normal, other_normal) = (face_normals[edge.faces[0]], face_normals[edge.faces[1]]);
+
+        edge.is_crease = edge.face_count != 2
+            || (normal.length2() > 0.0 && other_normal.length2() > 0.0 && (normal ^ other_normal) < min_cos);
+    }
+
+    // Edge count, crease count, neighbour point sum and crease neighbour point sum of every point
+    let mut valences = vec![0u32; points.len()];
+    let mut crease_counts = vec![0u32; points.len()];
+    let mut neighbour_sums = vec![Vec3f::new(0.0, 0.0, 0.0); points.len()];
+    let mut crease_sums = vec![Vec3f::new(0.0, 0.0, 0.0); points.len()];
+    for ((first, second), edge) in &edges {
+        for (point, other) in [(*first, *second), (*second, *first)] {
+            let point = point as usize;
+
+            valences[point] += 1;
+            neighbour_sums[point] += points[other as usize];
+            if edge.is_crease {
+                crease_counts[point] += 1;
+                crease_sums[point] += points[other as usize];
+            }
+        }
+    }
+
+    // Face point sum and face count of every point, used by Catmull-Clark scheme
+    let mut face_sums = vec![Vec3f::new(0.0, 0.0, 0.0); points.len()];
+    let mut face_counts = vec![0u32; points.len()];
+    if !is_loop {
+        for face in faces.iter() {
+            for vertex in face.vertices {
+                let point = vertex_points[*vertex as usize] as usize;
+
+                face_sums[point] += face_points[face.index];
+                face_counts[point] += 1;
+            }
+        }
+    }
+
+    let new_points = (0..points.len())
+        .map(|point| {
+            let position = points[point];
+            let valence = valences[point] as f32;
+
+            match crease_counts[point] {
+                0 if valences[point] > 0 && is_loop => {
+                    let beta = if valences[point] == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * valence) };
+                    position * (1.0 - valence * beta) + neighbour_sums[point] * beta
+                }
+                0 if valences[point] > 0 && face_counts[point] > 0 => {
+                    // Average of adjacent edge midpoints is half of position and half of neighbour average
+                    let face_average = face_sums[point] * (1.0 / face_counts[point] as f32);
+                    let midpoint_average = (position + neighbour_sums[point] * (1.0 / valence)) * 0.5;
+
+                    (face_average + midpoint_average * 2.0 + position * (valence - 3.0)) * (1.0 / valence)
+                }
+                // Boundary vertex of single face is corner, so sheets keep their outline
+                2 if valences[point] > 2 => position * 0.75 + crease_sums[point] * 0.125,
+                _ => position,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let edge_points = edges
+        .iter()
+        .map(|(key, edge)| {
+            let (first, second) = (points[key.0 as usize], points[key.1 as usize]);
+
+            let position = if edge.is_crease {
+                (first + second) * 0.5
+            } else if is_loop {
+                let (opposite, other_opposite) = (points[edge.opposites[0] as usize], points[edge.opposites[1] as usize]);
+                (first + second) * 0.375 + (opposite + other_opposite) * 0.125
+            } else {
+                (first + second + face_points[edge.faces[0]] + face_points[edge.faces[1]]) * 0.25
+            };
+
+            (*key, position)
+        })
+        .collect::<HashMap<_, _>>();
+
+    for (position, point) in data.positions.iter_mut().zip(&vertex_points) {
+        *position = new_points[*point as usize];
+    }
+
+    // Edge vertices are shared by faces through vertex pairs, so seams keep their attributes
+    let mut edge_vertices = HashMap::<(u32, u32), u32>::with_capacity(edges.len());
+    let mut edge_vertex = |data: &mut MeshData, first: u32, second: u32| -> u32 {
+        *edge_vertices.entry(edge_key(first, second)).or_insert_with(|| {
+            let key = edge_key(vertex_points[first as usize], vertex_points[second as usize]);
+            data.add_average_vertex(edge_points[&key], &[first, second])
+        })
+    };
+
+    let mut new_faces = render::Faces::with_capacity(faces.get_vertices().len(), faces.get_vertices().len() * 4);
+    for face in faces.iter() {
+        let vertices = face.vertices;
+        let count = vertices.len();
+        let edge_vertices = (0..count)
+            .map(|index| edge_vertex(&mut data, vertices[index], vertices[(index + 1) % count]))
+            .collect::<Vec<_>>();
+
+        if is_loop {
+            new_faces.push(0, [vertices[0], edge_vertices[0], edge_vertices[2]]);
+            new_faces.push(0, [edge_vertices[0], vertices[1], edge_vertices[1]]);
+            new_faces.push(0, [edge_vertices[2], edge_vertices[1], vertices[2]]);
+            new_faces.push(0, [edge_vertices[0], edge_vertices[1], edge_vertices[2]]);
+        } else {
+            let face_vertex = data.add_average_vertex(face_points[face.index], vertices);
+
+            for index in 0..count {
+                new_faces.push(0, [vertices[index], edge_vertices[index], face_vertex, edge_vertices[(index + count - 1) % count]]);
+            }
+        }
+    }
+
+    data.normals.clear();
+    data.faces = new_faces.iter()
+        .map(|face| {
+            data.normals.push(polygon_normal(face.vertices.len(), |index| data.positions[face.vertices[index] as usize]));
+            (face.index as u32, face.vertices.to_vec())
+        })
+        .collect();
+
+    data
+}
+
+/// Subdivision surface building function.
+///
+/// Smooth vertices follow Loop or Catmull-Clark rules, crease and boundary edges are subdivided as cubic B-splines,
+/// corner vertices stay in place. Line strips and points keep their vertices, that are moved with surface.
+/// If primitive has vertex normals, they are recomputed with crease angle of options.
+/// * `primitive` - subdivision cage
+/// * `options` - subdivision scheme, level and crease angle
+/// * Returns subdivided primitive
+pub fn subdivide(primitive: &render::Primitive, options: &SubdivisionOptions) -> render::Primitive {
+    let is_triangles = primitive.faces().all(|face| face.vertices.len() == 3);
+    let is_loop = match options.scheme {
+        SubdivisionScheme::Auto => is_triangles,
+        SubdivisionScheme::Loop => true,
+        SubdivisionScheme::CatmullClark => false,
+    };
+
+    let mut data = if is_loop && !is_triangles {
+        MeshData::new(&triangulate(primitive).0)
+    } else {
+        MeshData::new(primitive)
+    };
+    let has_vertex_normals = !data.vertex_normals.is_empty();
+    data.vertex_normals.clear();
+
+    let min_cos = if options.crease_angle >= std::f32::consts::PI { -2.0 } else { options.crease_angle.cos() };
+    for _ in 0..options.level {
+        data = subdivide_step(data, is_loop, min_cos);
+    }
+
+    let result = data.build();
+    if has_vertex_normals {
+        smooth_normals(&result, &NormalOptions { crease_angle: options.crease_angle, ..Default::default() })
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::shapes::cube;
+
+    /// Distinct position counting function
+    fn point_count(primitive: &render::Primitive) -> usize {
+        let mut points = primitive.get_positions().iter().map(|position| [position.x, position.y, position.z].map(f32::to_bits)).collect::<Vec<_>>();
+        points.sort_unstable();
+        points.dedup();
+        points.len()
+    }
+
+    /// Maximal distance from position to cube center along any axis
+    fn cube_distance(position: Vec3f) -> f32 {
+        position.x.abs().max(position.y.abs()).max(position.z.abs())
+    }
+
+    #[test]
+    fn catmull_clark_splits_faces_into_quads() {
+        for (level, face_count) in [(1, 24), (2, 96)] {
+            let result = subdivide(&cube(2.0), &SubdivisionOptions { level, ..Default::default() });
+
+            assert_eq!(result.faces().count(), face_count);
+            assert!(result.faces().all(|face| face.vertices.len() == 4));
+            // Vertices of different sides stay together, so surface is closed
+            assert_eq!(point_count(&result), face_count + 2);
+        }
+    }
+
+    #[test]
+    fn loop_splits_triangles_in_four() {
+        let result = subdivide(&cube(2.0), &SubdivisionOptions { scheme: SubdivisionScheme::Loop, ..Default::default() });
+
+        assert_eq!(result.faces().count(), 48);
+        assert!(result.faces().all(|face| face.vertices.len() == 3));
+        // Closed triangle mesh has half as many points as faces plus two
+        assert_eq!(point_count(&result), 26);
+    }
+
+    #[test]
+    fn smooth_surface_shrinks_cube() {
+        for scheme in [SubdivisionScheme::Loop, SubdivisionScheme::CatmullClark] {
+            let result = subdivide(&cube(2.0), &SubdivisionOptions { scheme, level: 2, ..Default::default() });
+
+            assert!(result.get_positions().iter().all(|position| cube_distance(*position) < 1.0), "{scheme:?}");
+        }
+    }
+
+    #[test]
+    fn creases_keep_cube_shape() {
+        for scheme in [SubdivisionScheme::Loop, SubdivisionScheme::CatmullClark] {
+            let result = subdivide(&cube(2.0), &SubdivisionOptions { scheme, level: 2, crease_angle: 1.4 });
+            let positions = result.get_positions();
+
+            // Corners stay in place, edges stay straight and sides stay flat
+            assert!(positions.iter().all(|position| (cube_distance(*position) - 1.0).abs() < 1e-5), "{scheme:?}");
+            for corner in 0..8 {
+                let corner = Vec3f::new([-1.0, 1.0][corner & 1], [-1.0, 1.0][corner >> 1 & 1], [-1.0, 1.0][corner >> 2]);
+                assert!(positions.iter().any(|position| (*position - corner).length2() < 1e-10), "{scheme:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use crate::math::*;
+use crate::mesh::polygon_normal;
+use crate::render;
+use super::bsp::MipTexture;
+
+/// Vertex animation keyframe
+pub struct AnimationFrame {
+    pub name: String,
+    /// Frame vertex positions in viewer space
+    pub positions: Vec<Vec3f>,
+}
+
+/// Named sequence of consecutive frames
+pub struct Animation {
+    /// Common frame name prefix, e.g. `stand` for frames `stand1`..`stand9`
+    pub name: String,
+    pub frames: std::ops::Range<usize>,
+}
+
+/// Vertex animated model, loaded from Quake MDL or MD2 file
+pub struct AnimatedModel {
+    /// Frame vertex index per primitive vertex, frame vertices are split at texture seams
+    vertices: Vec<u32>,
+    /// Triangles of primitive vertices, counter-clockwise
+    triangles: Vec<[u32; 3]>,
+    /// Texture coordinates per primitive vertex
+    tex_coords: Vec<Vec2f>,
+    /// Number of frame vertices
+    frame_vertex_count: usize,
+    frames: Vec<AnimationFrame>,
+    animations: Vec<Animation>,
+    skins: Vec<MipTexture>,
+}
+
+/// Primitive vertices builder, that splits frame vertices with different texture coordinates
+#[derive(Default)]
+pub(super) struct AliasBuilder {
+    /// Primitive vertex index by frame vertex and texture coordinate key
+    vertex_map: HashMap<(u32, u32), u32>,
+    vertices: Vec<u32>,
+    triangles: Vec<[u32; 3]>,
+    tex_coords: Vec<Vec2f>,
+}
+
+impl AliasBuilder {
+    /// Triangle adding function
+    /// * `corners` - frame vertex, texture coordinate key, that is equal for equal texture coordinates of vertex,
+    ///   and texture coordinate of every clockwise triangle corner, as Quake stores them
+    pub fn add_triangle(&mut self, corners: [(u32, u32, Vec2f); 3]) {
+        let mut triangle = [0; 3];
+
+        // Quake triangles are clockwise, so order is reversed
+        for (index, (vertex, key, tex_coord)) in corners.into_iter().rev().enumerate() {
+            triangle[index] = *self.vertex_map.entry((vertex, key)).or_insert_with(|| {
+                self.vertices.push(vertex);
+                self.tex_coords.push(tex_coord);
+                self.vertices.len() as u32 - 1
+            });
+        }
+
+        self.triangles.push(triangle);
+    }
+
+    /// Model building function
+    /// * `frame_vertex_count` - number of vertices in every frame
+    /// * `frames` - animation frames, at least one
+    /// * `skins` - model skins
+    pub fn build(self, frame_vertex_count: usize, frames: Vec<AnimationFrame>, skins: Vec<MipTexture>) -> AnimatedModel {
+        let mut animations: Vec<Animation> = Vec::new();
+
+        for (index, frame) in frames.iter().enumerate() {
+            let name = frame.name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+            match animations.last_mut() {
+                Some(animation) if animation.name == name => animation.frames.end = index + 1,
+                _ => animations.push(Animation { name: name.to_string(), frames: index..index + 1 }),
+            }
+        }
+
+        AnimatedModel {
+            vertices: self.vertices,
+            triangles: self.triangles,
+            tex_coords: self.tex_coords,
+            frame_vertex_count,
+            frames,
+            animations,
+            skins,
+        }
+    }
+}
+
+impl AnimatedModel {
+    /// Frames getting function
+    pub fn get_frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// Animations getting function
+    /// * Returns animations, made of consecutive frames, that differ only in trailing number
+    pub fn get_animations(&self) -> &[Animation] {
+        &self.animations
+    }
+
+    /// Animation by name finding function
+    pub fn find_animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.iter().find(|animation| animation.name == name)
+    }
+
+    /// Skins getting function
+    /// * Returns skins, MDL skins contain Quake palette indices, MD2 skins are named by image paths and have no data
+    pub fn get_skins(&self) -> &[MipTexture] {
+        &self.skins
+    }
+
+    /// Primitive sampling function
+    /// * `frame` - frame number, fractional part blends frame to the next one, wraps around frame count
+    /// * Returns primitive with interpolated positions
+    pub fn sample(&self, frame: f32) -> render::Primitive {
+        self.sample_range(0..self.frames.len(), frame)
+    }
+
+    /// Animation primitive sampling function
+    /// * `frames` - animation frame range, clamped to frame count, whole model frames are used if it's empty
+    /// * `frame` - frame number relative to range start, fractional part blends frame to the next one,
+    ///   wraps around range, so the last frame blends to the first one
+    /// * Returns primitive with interpolated positions, computed face normals and smooth vertex normals
+    pub fn sample_range(&self, frames: std::ops::Range<usize>, frame: f32) -> render::Primitive {
+        let frames = match frames.start.min(self.frames.len())..frames.end.min(self.frames.len()) {
+            frames if frames.is_empty() => 0..self.frames.len(),
+            frames => frames,
+        };
+
+        let frame = if frame.is_finite() { frame.rem_euclid(frames.len() as f32) } else { 0.0 };
+        let first = frame.floor() as usize % frames.len();
+        let second = (first + 1) % frames.len();
+        let blend = frame - frame.floor();
+
+        let first = &self.frames[frames.start + first].positions;
+        let second = &self.frames[frames.start + second].positions;
+        let frame_positions = first.iter().zip(second).map(|(first, second)| *first + (*second - *first) * blend).collect::<Vec<_>>();
+
+        // Vertex normals are accumulated per frame vertex to stay smooth across texture seams
+        let mut frame_normals = vec![Vec3f::new(0.0, 0.0, 0.0); self.frame_vertex_count];
+        let mut normals = Vec::with_capacity(self.triangles.len());
+        let mut faces = render::Faces::with_capacity(self.triangles.len(), self.triangles.len() * 3);
+
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            let [a, b, c] = triangle.map(|vertex| self.vertices[vertex as usize] as usize);
+            let area_normal = (frame_positions[b] - frame_positions[a]) % (frame_positions[c] - frame_positions[a]);
+
+            for vertex in [a, b, c] {
+                frame_normals[vertex] += area_normal;
+            }
+            normals.push(polygon_normal(3, |corner| frame_positions[[a, b, c][corner]]));
+            faces.push(index as u32, *triangle);
+        }
+
+        let positions = self.vertices.iter().map(|vertex| frame_positions[*vertex as usize]).collect();
+        let vertex_normals = self.vertices.iter().map(|vertex| {
+            let normal = frame_normals[*vertex as usize];
+            if normal.length2() > 0.0 { normal.normalized() } else { Vec3f::new(0.0, 1.0, 0.0) }
+        }).collect();
+
+        let mut primitive = render::Primitive::new(positions, normals, faces, 0x00FF00).expect("alias model data is validated on load");
+        primitive.set_tex_coords(self.tex_coords.clone()).expect("alias model data is validated on load");
+        primitive.set_vertex_normals(vertex_normals).expect("alias model data is validated on load");
+
+        primitive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Single triangle model building function
+    /// * `frames` - frame names with X offset of triangle in them
+    fn triangle_model(frames: &[(&str, f32)]) -> AnimatedModel {
+        let mut builder = AliasBuilder::default();
+        builder.add_triangle([(0, 0, Vec2f::new(0.0, 0.0)), (1, 0, Vec2f::new(1.0, 0.0)), (2, 0, Vec2f::new(0.0, 1.0))]);
+
+        let frames = frames
+            .iter()
+            .map(|(name, offset)| AnimationFrame {
+                name: name.to_string(),
+                positions: vec![Vec3f::new(*offset, 0.0, 0.0), Vec3f::new(*offset + 1.0, 0.0, 0.0), Vec3f::new(*offset, 1.0, 0.0)],
+            })
+            .collect();
+
+        builder.build(3, frames, Vec::new())
+    }
+
+    /// Sampled triangle X offset getting function
+    fn sampled_offset(primitive: &render::Primitive) -> f32 {
+        primitive.get_positions().iter().map(|position| position.x).fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn frames_are_grouped_into_animations() {
+        let model = triangle_model(&[("stand1", 0.0), ("stand2", 1.0), ("run1", 10.0), ("run2", 20.0), ("run10", 30.0), ("stand3", 2.0)]);
+        let animations = model.get_animations().iter().map(|animation| (animation.name.as_str(), animation.frames.clone())).collect::<Vec<_>>();
+
+        assert_eq!(animations, vec![("stand", 0..2), ("run", 2..5), ("stand", 5..6)]);
+        assert_eq!(model.find_animation("run").unwrap().frames, 2..5);
+        assert!(model.find_animation("walk").is_none());
+    }
+
+    #[test]
+    fn sample_range_blends_and_wraps() {
+        let model = triangle_model(&[("stand1", 0.0), ("stand2", 1.0), ("run1", 10.0), ("run2", 20.0), ("run3", 30.0)]);
+        let sample = |frames: std::ops::Range<usize>, frame: f32| sampled_offset(&model.sample_range(frames, frame));
+
+        assert_eq!(sample(2..5, 0.0), 10.0);
+        assert_eq!(sample(2..5, 0.5), 15.0);
+        assert_eq!(sample(2..5, 1.25), 22.5);
+        // The last frame blends to the first one of range
+        assert_eq!(sample(2..5, 2.5), 20.0);
+        assert_eq!(sample(2..5, 3.0), 10.0);
+        assert_eq!(sample(2..5, -0.5), 20.0);
+        assert_eq!(sample(2..5, f32::NAN), 10.0);
+
+        // Range is clamped to frame count, empty range means all frames
+        assert_eq!(sample(3..9, 1.5), 25.0);
+        assert_eq!(sample(7..9, 1.25), 3.25);
+        assert_eq!(sampled_offset(&model.sample(4.5)), 15.0);
+    }
+
+    #[test]
+    fn seam_vertices_are_split_and_stay_smooth() {
+        // Two triangles of square share vertices 0 and 2, vertex 2 has different texture coordinates in them
+        let mut builder = AliasBuilder::default();
+        builder.add_triangle([(0, 0, Vec2f::new(0.0, 0.0)), (2, 0, Vec2f::new(1.0, 1.0)), (1, 0, Vec2f::new(1.0, 0.0))]);
+        builder.add_triangle([(0, 0, Vec2f::new(0.0, 0.0)), (3, 0, Vec2f::new(0.0, 1.0)), (2, 1, Vec2f::new(0.5, 1.0))]);
+
+        let positions = vec![Vec3f::new(0.0, 0.0, 0.0), Vec3f::new(1.0, 0.0, 0.0), Vec3f::new(1.0, 1.0, 0.5), Vec3f::new(0.0, 1.0, 0.0)];
+        let model = builder.build(4, vec![AnimationFrame { name: "frame".to_string(), positions }], Vec::new());
+        let primitive = model.sample(0.0);
+
+        assert_eq!(primitive.get_positions().len(), 5);
+        assert_eq!(primitive.get_faces().len(), 2);
+
+        // Seam vertices share position and vertex normal, but not texture coordinates
+        let seam = (0..5).filter(|vertex| (primitive.get_positions()[*vertex] - Vec3f::new(1.0, 1.0, 0.5)).length2() == 0.0).collect::<Vec<_>>();
+        assert_eq!(seam.len(), 2);
+        let [first, second] = [seam[0], seam[1]];
+        let vertex_normals = primitive.get_vertex_normals();
+        assert!((vertex_normals[first] - vertex_normals[second]).length2() < 1e-12);
+        assert!((primitive.get_tex_coords()[first] - primitive.get_tex_coords()[second]).length2() > 0.0);
+
+        // Clockwise Quake triangles become counter-clockwise ones, facing Z axis
+        assert!(primitive.get_normals().iter().all(|normal| normal.z > 0.0));
+    }
+}
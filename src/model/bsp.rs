@@ -0,0 +1,788 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use crate::render;
+use super::error::*;
+
+/// Supported BSP version (Quake 1)
+const BSP_VERSION: i32 = 29;
+
+/// Lump indices
+const LUMP_ENTITIES: usize = 0;
+const LUMP_PLANES: usize = 1;
+const LUMP_TEXTURES: usize = 2;
+const LUMP_VERTICES: usize = 3;
+const LUMP_VISIBILITY: usize = 4;
+const LUMP_NODES: usize = 5;
+const LUMP_TEXINFO: usize = 6;
+const LUMP_FACES: usize = 7;
+const LUMP_LEAVES: usize = 10;
+const LUMP_MARKSURFACES: usize = 11;
+const LUMP_EDGES: usize = 12;
+const LUMP_SURFEDGES: usize = 13;
+const LUMP_MODELS: usize = 14;
+const LUMP_COUNT: usize = 15;
+
+/// Embedded mip texture
+pub struct MipTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Full resolution mip level, one Quake palette index per texel
+    pub data: Vec<u8>,
+}
+
+impl MipTexture {
+    /// Texture to RGB conversion function
+    /// * `palette` - 768 byte palette (e.g. gfx/palette.lmp from pak0.pak)
+    /// * Returns texels in 0x00RRGGBB format
+    pub fn to_rgb(&self, palette: &[u8; 768]) -> Vec<u32> {
+        self.data
+            .iter()
+            .map(|index| {
+                let color = &palette[*index as usize * 3..*index as usize * 3 + 3];
+                (color[0] as u32) << 16 | (color[1] as u32) << 8 | color[2] as u32
+            })
+            .collect()
+    }
+}
+
+/// BSP tree node
+#[derive(Copy, Clone)]
+struct Node {
+    plane: u32,
+    /// Child references, negative value -n means leaf n - 1
+    children: [i32; 2],
+}
+
+/// BSP tree leaf
+#[derive(Clone)]
+struct Leaf {
+    /// Offset of leaf row in visibility lump, None if leaf sees everything
+    visibility: Option<usize>,
+    bounds: Aabb,
+    /// Range of leaf faces in marksurfaces
+    faces: std::ops::Range<usize>,
+}
+
+/// Quake 1 map, loaded from BSP file
+pub struct BspMap {
+    /// Primitive per BSP model, the first one is world
+    models: Vec<render::Primitive>,
+    /// BSP face range of every model, model primitive face index is BSP face number minus range start
+    model_faces: Vec<std::ops::Range<usize>>,
+    /// Texture index per BSP face
+    face_textures: Vec<u32>,
+    textures: Vec<Option<MipTexture>>,
+
+    planes: Vec<Plane>,
+    nodes: Vec<Node>,
+    /// World BSP tree root, uses same encoding as node children
+    root_node: i32,
+    leaves: Vec<Leaf>,
+    marksurfaces: Vec<u16>,
+    visibility: Vec<u8>,
+    /// Number of leaves, covered by visibility rows
+    visible_leaf_count: usize,
+    entities: String,
+
+    /// Quake to viewer space scale
+    scale: f32,
+}
+
+/// Little-endian binary data reader, shared by Quake format loaders
+pub(super) struct Reader<'a> {
+    pub data: &'a [u8],
+    /// Offset of data in file
+    pub base: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// File position getting function
+    /// * `offset` - offset in reader data
+    pub fn position(&self, offset: usize) -> Position {
+        Position::Binary { offset: self.base + offset }
+    }
+
+    pub fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], LoadError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| LoadError::syntax(self.position(offset), format!("{len} bytes of data are out of file")))
+    }
+
+    pub fn u16(&self, offset: usize) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.bytes(offset, 2)?.try_into().unwrap()))
+    }
+
+    pub fn i16(&self, offset: usize) -> Result<i16, LoadError> {
+        Ok(i16::from_le_bytes(self.bytes(offset, 2)?.try_into().unwrap()))
+    }
+
+    pub fn i32(&self, offset: usize) -> Result<i32, LoadError> {
+        Ok(i32::from_le_bytes(self.bytes(offset, 4)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&self, offset: usize) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.bytes(offset, 4)?.try_into().unwrap()))
+    }
+
+    pub fn f32(&self, offset: usize) -> Result<f32, LoadError> {
+        Ok(f32::from_le_bytes(self.bytes(offset, 4)?.try_into().unwrap()))
+    }
+
+    pub fn vec3(&self, offset: usize) -> Result<Vec3f, LoadError> {
+        Ok(Vec3f::new(self.f32(offset)?, self.f32(offset + 4)?, self.f32(offset + 8)?))
+    }
+
+    /// Record array size checking function, used before allocating storage for records, count of which is read from file
+    /// * `offset` - offset of the first record
+    /// * `count` - record count
+    /// * `record_size` - minimal size of single record
+    /// * `what` - records name for error message
+    /// * Returns error, if records don't fit in data
+    pub fn records(&self, offset: usize, count: usize, record_size: usize, what: &str) -> Result<(), LoadError> {
+        let fits = count
+            .checked_mul(record_size)
+            .and_then(|size| size.checked_add(offset))
+            .is_some_and(|end| end <= self.data.len());
+
+        if fits {
+            Ok(())
+        } else {
+            Err(LoadError::syntax(self.position(offset), format!("{count} {what} are out of file")))
+        }
+    }
+
+    /// Fixed size zero-terminated name reading function
+    /// * `offset` - name field offset
+    /// * `len` - name field size
+    pub fn name(&self, offset: usize, len: usize) -> Result<String, LoadError> {
+        let name = self.bytes(offset, len)?;
+        Ok(String::from_utf8_lossy(&name[..name.iter().position(|c| *c == 0).unwrap_or(len)]).to_string())
+    }
+
+    /// Lump records getting function
+    /// * `lump` - lump index
+    /// * `record_size` - size of single lump record
+    /// * Returns lump contents reader and record count
+    fn lump(&self, lump: usize, record_size: usize) -> Result<(Reader<'a>, usize), LoadError> {
+        let offset = self.i32(4 + lump * 8)?;
+        let length = self.i32(8 + lump * 8)?;
+
+        if offset < 0 || length < 0 || !(length as usize).is_multiple_of(record_size) {
+            return Err(LoadError::syntax(self.position(4 + lump * 8), format!("BSP lump {lump} has invalid offset or length")));
+        }
+
+        let data = self.bytes(offset as usize, length as usize)?;
+        Ok((Reader { data, base: self.base + offset as usize }, length as usize / record_size))
+    }
+}
+
+/// Quake (Z up) to viewer (Y up) space conversion function
+pub(super) fn to_viewer_space(v: Vec3f, scale: f32) -> Vec3f {
+    Vec3f::new(v.x, v.z, -v.y) * scale
+}
+
+/// Viewer to Quake space conversion function
+fn to_quake_space(v: Vec3f, scale: f32) -> Vec3f {
+    Vec3f::new(v.x, -v.z, v.y) / scale
+}
+
+impl BspMap {
+    /// BSP file loading function
+    /// * `path` - path to .bsp file
+    /// * `scale` - Quake unit size in viewer space
+    /// * Returns loaded map
+    pub fn load(path: impl AsRef<Path>, scale: f32) -> Result<Self, LoadError> {
+        load_file(path.as_ref(), |file| Self::read(file, scale))
+    }
+
+    /// BSP reading function
+    /// * `reader` - BSP source
+    /// * `scale` - Quake unit size in viewer space
+    /// * Returns map, or error with file offset
+    pub fn read(reader: impl Read, scale: f32) -> Result<Self, LoadError> {
+        Self::parse(&read_bytes(reader)?, scale)
+    }
+
+    /// BSP data parsing function
+    /// * `data` - BSP file contents
+    /// * `scale` - Quake unit size in viewer space
+    /// * Returns parsed map
+    fn parse(data: &[u8], scale: f32) -> Result<Self, LoadError> {
+        let file = Reader { data, base: 0 };
+
+        let version = file.i32(0)?;
+        if version != BSP_VERSION {
+            return Err(LoadError::unsupported(file.position(0), format!("BSP version {version}, only {BSP_VERSION} is supported")));
+        }
+        file.bytes(0, 4 + LUMP_COUNT * 8)?;
+
+        let (entities, _) = file.lump(LUMP_ENTITIES, 1)?;
+        let entities = String::from_utf8_lossy(entities.data).trim_end_matches('\0').to_string();
+
+        // Planes
+        let (lump, count) = file.lump(LUMP_PLANES, 20)?;
+        let mut planes = Vec::with_capacity(count);
+        for i in 0..count {
+            planes.push(Plane {
+                normal: lump.vec3(i * 20)?,
+                distance: -lump.f32(i * 20 + 12)?,
+            });
+        }
+
+        let textures = Self::parse_textures(&file)?;
+
+        let (lump, count) = file.lump(LUMP_VERTICES, 12)?;
+        let vertices = (0..count).map(|i| lump.vec3(i * 12)).collect::<Result<Vec<_>, _>>()?;
+
+        let (visibility, _) = file.lump(LUMP_VISIBILITY, 1)?;
+        let visibility = visibility.data.to_vec();
+
+        // Nodes
+        let (node_lump, count) = file.lump(LUMP_NODES, 24)?;
+        let mut nodes = Vec::with_capacity(count);
+        for i in 0..count {
+            let node = Node {
+                plane: node_lump.i32(i * 24)? as u32,
+                children: [node_lump.i16(i * 24 + 4)? as i32, node_lump.i16(i * 24 + 6)? as i32],
+            };
+
+            if node.plane as usize >= planes.len() {
+                return Err(LoadError::index_range(node_lump.position(i * 24), format!("node {i} references missing plane")));
+            }
+            nodes.push(node);
+        }
+
+        // Texture mapping
+        let (lump, texinfo_count) = file.lump(LUMP_TEXINFO, 40)?;
+        let texinfo = lump;
+
+        // Edges
+        let (lump, count) = file.lump(LUMP_EDGES, 4)?;
+        let edges = (0..count).map(|i| Ok([lump.u16(i * 4)?, lump.u16(i * 4 + 2)?])).collect::<Result<Vec<_>, LoadError>>()?;
+
+        let (lump, count) = file.lump(LUMP_SURFEDGES, 4)?;
+        let surfedges = (0..count).map(|i| lump.i32(i * 4)).collect::<Result<Vec<_>, _>>()?;
+
+        // Faces
+        let (faces, face_count) = file.lump(LUMP_FACES, 20)?;
+
+        // Leaves
+        let (leaf_lump, count) = file.lump(LUMP_LEAVES, 28)?;
+        let mut leaves = Vec::with_capacity(count);
+        for i in 0..count {
+            let offset = i * 28;
+            let visibility_offset = leaf_lump.i32(offset + 4)?;
+            let bound = |at: usize| -> Result<Vec3f, LoadError> {
+                Ok(Vec3f::new(leaf_lump.i16(at)? as f32, leaf_lump.i16(at + 2)? as f32, leaf_lump.i16(at + 4)? as f32))
+            };
+            let first_face = leaf_lump.u16(offset + 20)? as usize;
+
+            leaves.push(Leaf {
+                visibility: if visibility_offset >= 0 { Some(visibility_offset as usize) } else { None },
+                bounds: Aabb::from_points(&[
+                    to_viewer_space(bound(offset + 8)?, scale),
+                    to_viewer_space(bound(offset + 14)?, scale),
+                ]),
+                faces: first_face..first_face + leaf_lump.u16(offset + 22)? as usize,
+            });
+        }
+
+        let (lump, count) = file.lump(LUMP_MARKSURFACES, 2)?;
+        let marksurfaces = (0..count).map(|i| lump.u16(i * 2)).collect::<Result<Vec<_>, _>>()?;
+
+        let is_valid_child = |child: i32| if child >= 0 {
+            (child as usize) < nodes.len()
+        } else {
+            ((-child - 1) as usize) < leaves.len()
+        };
+        if let Some(i) = nodes.iter().position(|node| !node.children.iter().all(|child| is_valid_child(*child))) {
+            return Err(LoadError::index_range(node_lump.position(i * 24), format!("node {i} references missing child")));
+        }
+        if let Some(i) = leaves.iter().position(|leaf| leaf.faces.end > marksurfaces.len()) {
+            return Err(LoadError::index_range(leaf_lump.position(i * 28), format!("leaf {i} references missing marksurfaces")));
+        }
+        if let Some(i) = marksurfaces.iter().position(|face| *face as usize >= face_count) {
+            return Err(LoadError::index_range(lump.position(i * 2), format!("marksurface {i} references missing face")));
+        }
+
+        // Models
+        let (lump, model_count) = file.lump(LUMP_MODELS, 64)?;
+        if model_count == 0 {
+            return Err(LoadError::syntax(file.position(4 + LUMP_MODELS * 8), "BSP contains no models"));
+        }
+
+        let mut models = Vec::with_capacity(model_count);
+        let mut model_faces = Vec::with_capacity(model_count);
+        let face_textures = (0..face_count)
+            .map(|face_index| {
+                let texinfo_index = faces.i16(face_index * 20 + 10)? as usize;
+
+                if texinfo_index >= texinfo_count {
+                    return Err(LoadError::index_range(faces.position(face_index * 20), format!("face {face_index} references missing texinfo")));
+                }
+                texinfo.u32(texinfo_index * 40 + 32)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for model_index in 0..model_count {
+            let faces_error = || LoadError::index_range(lump.position(model_index * 64), format!("model {model_index} references missing faces"));
+            let first_face = usize::try_from(lump.i32(model_index * 64 + 56)?).map_err(|_| faces_error())?;
+            let model_face_count = usize::try_from(lump.i32(model_index * 64 + 60)?).map_err(|_| faces_error())?;
+
+            if first_face.checked_add(model_face_count).is_none_or(|end| end > face_count) {
+                return Err(faces_error());
+            }
+
+            let mut positions = Vec::new();
+            let mut tex_coords = Vec::new();
+            let mut normals = Vec::with_capacity(model_face_count);
+            let mut primitive_faces = render::Faces::new();
+
+            for (face_index, texture) in face_textures.iter().enumerate().skip(first_face).take(model_face_count) {
+                let offset = face_index * 20;
+                let plane = planes
+                    .get(faces.u16(offset)? as usize)
+                    .ok_or_else(|| LoadError::index_range(faces.position(offset), format!("face {face_index} references missing plane")))?;
+                let side = faces.i16(offset + 2)?;
+                let edges_error = || LoadError::syntax(faces.position(offset + 4), format!("face {face_index} has invalid edge range"));
+                let first_edge = usize::try_from(faces.i32(offset + 4)?).map_err(|_| edges_error())?;
+                let edge_count = usize::try_from(faces.i16(offset + 8)?).map_err(|_| edges_error())?;
+                let face_surfedges = first_edge
+                    .checked_add(edge_count)
+                    .and_then(|end| surfedges.get(first_edge..end))
+                    .ok_or_else(edges_error)?;
+                let texinfo_index = faces.i16(offset + 10)? as usize;
+
+                let axis = |at: usize| -> Result<(Vec3f, f32), LoadError> {
+                    Ok((texinfo.vec3(texinfo_index * 40 + at)?, texinfo.f32(texinfo_index * 40 + at + 12)?))
+                };
+                let (s_axis, s_offset) = axis(0)?;
+                let (t_axis, t_offset) = axis(16)?;
+                let (width, height) = textures
+                    .get(*texture as usize)
+                    .and_then(|texture| texture.as_ref())
+                    .map_or((1.0, 1.0), |texture| (texture.width as f32, texture.height as f32));
+
+                normals.push(to_viewer_space(if side != 0 { -plane.normal } else { plane.normal }, 1.0));
+                let first_vertex = positions.len() as u32;
+
+                for surfedge in face_surfedges {
+                    let edge = edges
+                        .get(surfedge.unsigned_abs() as usize)
+                        .ok_or_else(|| LoadError::index_range(faces.position(offset), format!("face {face_index} references missing edge")))?;
+                    let vertex = *vertices
+                        .get(if *surfedge >= 0 { edge[0] } else { edge[1] } as usize)
+                        .ok_or_else(|| LoadError::index_range(faces.position(offset), format!("face {face_index} references missing vertex")))?;
+
+                    positions.push(to_viewer_space(vertex, scale));
+                    tex_coords.push(Vec2f::new(
+                        ((vertex ^ s_axis) + s_offset) / width,
+                        ((vertex ^ t_axis) + t_offset) / height,
+                    ));
+                }
+                primitive_faces.push((face_index - first_face) as u32, first_vertex..positions.len() as u32);
+            }
+
+            let model_error = |err: render::PrimitiveError| LoadError::syntax(lump.position(model_index * 64), format!("model {model_index}: {err}"));
+            let mut primitive = render::Primitive::new(positions, normals, primitive_faces, 0x00FF00).map_err(model_error)?;
+            primitive.set_tex_coords(tex_coords).map_err(model_error)?;
+
+            models.push(primitive);
+            model_faces.push(first_face..first_face + model_face_count);
+        }
+
+        let root_node = lump.i32(36)?;
+        let visible_leaf_count = lump.i32(52)?.max(0) as usize;
+
+        if !is_valid_child(root_node) {
+            return Err(LoadError::index_range(lump.position(36), "world model references missing root node"));
+        }
+
+        Ok(Self {
+            models,
+            model_faces,
+            face_textures,
+            textures,
+            planes,
+            nodes,
+            root_node,
+            leaves,
+            marksurfaces,
+            visibility,
+            visible_leaf_count,
+            entities,
+            scale,
+        })
+    }
+
+    /// Miptex lump parsing function
+    fn parse_textures(file: &Reader) -> Result<Vec<Option<MipTexture>>, LoadError> {
+        let (lump, _) = file.lump(LUMP_TEXTURES, 1)?;
+
+        if lump.data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Count is checked against offset table size, so crafted count can't exhaust memory
+        let count = lump.i32(0)?.max(0) as usize;
+        lump.records(4, count, 4, "miptex lump texture offsets")?;
+        let mut textures = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let offset = lump.i32(4 + i * 4)?;
+
+            // Missing textures are marked by negative offset
+            if offset < 0 {
+                textures.push(None);
+                continue;
+            }
+
+            let offset = offset as usize;
+            let name = lump.name(offset, 16)?;
+            let width = lump.u32(offset + 16)?;
+            let height = lump.u32(offset + 20)?;
+            let data_offset = lump.u32(offset + 24)? as usize;
+
+            // Zero offset means texture data is stored outside of BSP
+            let data = if data_offset != 0 {
+                lump.bytes(offset + data_offset, width as usize * height as usize)?.to_vec()
+            } else {
+                Vec::new()
+            };
+
+            textures.push(Some(MipTexture { name, width, height, data }));
+        }
+
+        Ok(textures)
+    }
+
+    /// BSP model primitives getting function
+    /// * Returns primitive per BSP model, the first one is world geometry
+    pub fn get_models(&self) -> &[render::Primitive] {
+        &self.models
+    }
+
+    /// Embedded textures getting function
+    /// * Returns textures, None for missing textures
+    pub fn get_textures(&self) -> &[Option<MipTexture>] {
+        &self.textures
+    }
+
+    /// Face texture getting function
+    /// * `model` - model index
+    /// * `face` - face index in model primitive
+    /// * Returns index of face texture in textures
+    pub fn get_face_texture(&self, model: usize, face: u32) -> Option<u32> {
+        let faces = self.model_faces.get(model)?;
+        if face as usize >= faces.len() {
+            return None;
+        }
+
+        self.face_textures.get(faces.start + face as usize).copied()
+    }
+
+    /// Entities lump getting function
+    /// * Returns entity definitions text
+    pub fn get_entities(&self) -> &str {
+        &self.entities
+    }
+
+    /// Player start location getting function
+    /// * Returns origin of first info_player_start entity in viewer space
+    pub fn get_player_start(&self) -> Option<Vec3f> {
+        self.entities
+            .split('}')
+            .find(|entity| entity.contains("\"info_player_start\""))
+            .and_then(|entity| {
+                let origin = entity.split("\"origin\"").nth(1)?.split('"').nth(1)?;
+                let mut coordinates = origin.split_whitespace().map(|value| value.parse::<f32>().ok());
+
+                Some(to_viewer_space(Vec3f::new(coordinates.next()??, coordinates.next()??, coordinates.next()??), self.scale))
+            })
+    }
+
+    /// Leaf containing point finding function
+    /// * `point` - point in viewer space
+    /// * Returns index of leaf point is located in
+    pub fn find_leaf(&self, point: Vec3f) -> usize {
+        let point = to_quake_space(point, self.scale);
+        let mut node = self.root_node;
+
+        // Depth limit protects from cycles in malformed trees
+        for _ in 0..=self.nodes.len() {
+            if node < 0 {
+                return (-node - 1) as usize;
+            }
+
+            let Node { plane, children } = self.nodes[node as usize];
+            node = children[(self.planes[plane as usize].distance_to(&point) < 0.0) as usize];
+        }
+
+        0
+    }
+
+    /// Potentially visible leaf set getting function
+    /// * `leaf` - leaf to get visible leaves from
+    /// * `visible` - leaf visibility flags to fill
+    fn decompress_visibility(&self, leaf: usize, visible: &mut [bool]) {
+        visible.fill(false);
+
+        let Some(mut offset) = self.leaves.get(leaf).and_then(|leaf| leaf.visibility).filter(|_| leaf != 0) else {
+            // Solid leaf or leaf without visibility data, so everything may be visible
+            visible.fill(true);
+            return;
+        };
+
+        // Row covers leaves starting from 1, run of zero bytes is encoded by zero followed by run length
+        let mut leaf = 1;
+        while leaf <= self.visible_leaf_count && offset < self.visibility.len() {
+            let byte = self.visibility[offset];
+            offset += 1;
+
+            if byte == 0 {
+                leaf += 8 * self.visibility.get(offset).copied().unwrap_or(0) as usize;
+                offset += 1;
+                continue;
+            }
+
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    if let Some(visible) = visible.get_mut(leaf + bit) {
+                        *visible = true;
+                    }
+                }
+            }
+            leaf += 8;
+        }
+    }
+
+    /// Map displaying function, draws only world faces potentially visible from camera leaf
+    /// * `context` - context to draw map in
+    /// * `location` - camera location in viewer space
+    pub fn draw(&self, context: &mut render::RenderContext, location: Vec3f) {
+        let mut visible = vec![false; self.leaves.len()];
+        self.decompress_visibility(self.find_leaf(location), &mut visible);
+
+        let world_faces = self.model_faces[0].clone();
+        let mut face_marks = vec![false; world_faces.len()];
+        let mut faces = Vec::new();
+
+        for (leaf, is_visible) in self.leaves.iter().zip(visible.iter()) {
+            if !is_visible || context.get_frustum().test_aabb(&leaf.bounds) == Containment::Outside {
+                continue;
+            }
+
+            for face in &self.marksurfaces[leaf.faces.clone()] {
+                // Marksurfaces of world leaves may only reference world faces
+                let Some(face) = (*face as usize).checked_sub(world_faces.start) else {
+                    continue;
+                };
+
+                if let Some(mark) = face_marks.get_mut(face) {
+                    if !*mark {
+                        *mark = true;
+                        faces.push(face as u32);
+                    }
+                }
+            }
+        }
+
+        context.draw_faces(&self.models[0], &faces);
+
+        // Brush entities (doors, lifts, etc.) aren't part of visibility data
+        for model in &self.models[1..] {
+            context.draw(model);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Little-endian record building helper, shared by Quake format loader tests
+    #[derive(Default)]
+    pub(in crate::model) struct Record(pub Vec<u8>);
+
+    impl Record {
+        pub fn bytes(mut self, value: &[u8]) -> Self {
+            self.0.extend(value);
+            self
+        }
+
+        /// Fixed size zero-padded name writing function
+        pub fn name(self, value: &str, len: usize) -> Self {
+            let mut name = value.as_bytes().to_vec();
+            name.resize(len, 0);
+            self.bytes(&name)
+        }
+
+        pub fn i16(mut self, value: i16) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        pub fn u16(mut self, value: u16) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        pub fn i32(mut self, value: i32) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        pub fn f32(mut self, value: f32) -> Self {
+            self.0.extend(value.to_le_bytes());
+            self
+        }
+
+        pub fn vec3(self, x: f32, y: f32, z: f32) -> Self {
+            self.f32(x).f32(y).f32(z)
+        }
+    }
+
+    /// Single triangle BSP lumps building function
+    /// * `first_edge` - first surfedge of triangle face
+    /// * `edge_count` - surfedge count of triangle face
+    fn triangle_lumps(first_edge: i32, edge_count: i16) -> [Vec<u8>; LUMP_COUNT] {
+        let mut lumps: [Vec<u8>; LUMP_COUNT] = Default::default();
+
+        lumps[LUMP_ENTITIES] = b"{\n\"classname\" \"worldspawn\"\n}\n\0".to_vec();
+        lumps[LUMP_PLANES] = Record::default().vec3(0.0, 0.0, 1.0).f32(0.0).i32(2).0;
+        lumps[LUMP_VERTICES] = Record::default().vec3(0.0, 0.0, 0.0).vec3(64.0, 0.0, 0.0).vec3(0.0, 64.0, 0.0).0;
+        lumps[LUMP_TEXINFO] = Record::default().vec3(1.0, 0.0, 0.0).f32(0.0).vec3(0.0, 1.0, 0.0).f32(0.0).i32(0).i32(0).0;
+        lumps[LUMP_FACES] = Record::default().u16(0).i16(0).i32(first_edge).i16(edge_count).i16(0).i32(-1).i32(-1).0;
+        lumps[LUMP_LEAVES] = Record::default().i32(-1).i32(-1).i16(0).i16(0).i16(0).i16(64).i16(64).i16(0).u16(0).u16(0).i32(0).0;
+        lumps[LUMP_EDGES] = Record::default().u16(0).u16(0).u16(0).u16(1).u16(1).u16(2).u16(2).u16(0).0;
+        lumps[LUMP_SURFEDGES] = Record::default().i32(1).i32(2).i32(3).0;
+        lumps[LUMP_MODELS] = Record::default()
+            .vec3(0.0, 0.0, 0.0).vec3(64.0, 64.0, 0.0).vec3(0.0, 0.0, 0.0)
+            .i32(-1).i32(0).i32(0).i32(0)
+            .i32(1).i32(0).i32(1)
+            .0;
+
+        lumps
+    }
+
+    /// BSP file from lumps building function
+    fn build_bsp(lumps: [Vec<u8>; LUMP_COUNT]) -> Vec<u8> {
+        let mut header = Record::default().i32(BSP_VERSION);
+        let mut offset = 4 + LUMP_COUNT * 8;
+        for lump in &lumps {
+            header = header.i32(offset as i32).i32(lump.len() as i32);
+            offset += lump.len();
+        }
+
+        let mut data = header.0;
+        for lump in lumps {
+            data.extend(lump);
+        }
+        data
+    }
+
+    /// Single triangle BSP file building function
+    /// * `first_edge` - first surfedge of triangle face
+    /// * `edge_count` - surfedge count of triangle face
+    fn triangle_bsp(first_edge: i32, edge_count: i16) -> Vec<u8> {
+        build_bsp(triangle_lumps(first_edge, edge_count))
+    }
+
+    /// Single triangle BSP file with miptex lump building function
+    fn textured_bsp(textures: Vec<u8>) -> Vec<u8> {
+        let mut lumps = triangle_lumps(0, 3);
+        lumps[LUMP_TEXTURES] = textures;
+        build_bsp(lumps)
+    }
+
+    /// Invalid face edge range error checking function
+    fn assert_edge_range_error(result: Result<BspMap, LoadError>) {
+        match result {
+            Err(LoadError::Syntax { location, message }) => {
+                assert!(matches!(location.position, Position::Binary { .. }));
+                assert!(message.contains("edge range"), "{message}");
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("invalid edge range is accepted"),
+        }
+    }
+
+    #[test]
+    fn parses_single_triangle() {
+        let map = BspMap::parse(&triangle_bsp(0, 3), 1.0).unwrap();
+
+        assert_eq!(map.get_models().len(), 1);
+        assert_eq!(map.get_models()[0].get_faces().len(), 1);
+        assert_eq!(map.get_models()[0].get_positions().len(), 3);
+        assert!(map.get_entities().contains("worldspawn"));
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let data = triangle_bsp(0, 3);
+
+        for length in 0..data.len() {
+            assert!(BspMap::parse(&data[..length], 1.0).is_err(), "file truncated to {length} bytes is accepted");
+        }
+    }
+
+    #[test]
+    fn rejects_negative_first_edge() {
+        assert_edge_range_error(BspMap::parse(&triangle_bsp(-1, 3), 1.0));
+        assert_edge_range_error(BspMap::parse(&triangle_bsp(i32::MIN, 3), 1.0));
+    }
+
+    #[test]
+    fn rejects_negative_edge_count() {
+        assert_edge_range_error(BspMap::parse(&triangle_bsp(0, -3), 1.0));
+    }
+
+    #[test]
+    fn rejects_edge_range_out_of_lump() {
+        assert_edge_range_error(BspMap::parse(&triangle_bsp(1, 3), 1.0));
+        assert_edge_range_error(BspMap::parse(&triangle_bsp(i32::MAX, i16::MAX), 1.0));
+    }
+
+    #[test]
+    fn parses_embedded_texture() {
+        let lump = Record::default().i32(1).i32(8).name("wall", 16).i32(2).i32(2).i32(40).i32(0).i32(0).i32(0).bytes(&[1, 2, 3, 4]).0;
+
+        let map = BspMap::parse(&textured_bsp(lump), 1.0).unwrap();
+        let texture = map.get_textures()[0].as_ref().unwrap();
+        assert_eq!((texture.name.as_str(), texture.width, texture.height), ("wall", 2, 2));
+        assert_eq!(texture.data, [1, 2, 3, 4]);
+
+        // Missing texture is marked by negative offset
+        let map = BspMap::parse(&textured_bsp(Record::default().i32(1).i32(-1).0), 1.0).unwrap();
+        assert!(map.get_textures()[0].is_none());
+    }
+
+    #[test]
+    fn rejects_texture_count_out_of_lump() {
+        for lump in [
+            Record::default().i32(i32::MAX).0,
+            Record::default().i32(i32::MAX).i32(-1).i32(-1).0,
+            Record::default().i32(3).i32(-1).i32(-1).0,
+        ] {
+            match BspMap::parse(&textured_bsp(lump), 1.0) {
+                Err(LoadError::Syntax { message, .. }) => assert!(message.contains("miptex"), "{message}"),
+                Err(err) => panic!("unexpected error: {err}"),
+                Ok(_) => panic!("texture count out of lump is accepted"),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_texture_out_of_lump() {
+        // Texture header and texture data beyond lump end
+        let header = Record::default().i32(1).i32(8).0;
+        assert!(BspMap::parse(&textured_bsp(header), 1.0).is_err());
+
+        let lump = Record::default().i32(1).i32(8).name("", 16).i32(64).i32(64).i32(40).i32(0).i32(0).i32(0).0;
+        assert!(BspMap::parse(&textured_bsp(lump), 1.0).is_err());
+    }
+}
@@ -0,0 +1,643 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::math::*;
+use crate::render::{self, Cluster, PrimitiveParts};
+use super::error::*;
+use super::mesh::{Material, Model, SubMesh, Texture};
+
+/// Cache file magic
+const CACHE_MAGIC: &[u8; 8] = b"S3DCACHE";
+/// Cache format version, must be increased on every layout change and loader output change
+pub const CACHE_VERSION: u32 = 7;
+/// Header size: magic, version, reserved word, source size and modification time, payload size and checksum
+const HEADER_SIZE: usize = 48;
+/// Extension, appended to source file name to get its automatic cache path
+pub const CACHE_EXTENSION: &str = "s3dcache";
+
+/// Index, that marks missing optional value
+const NONE_INDEX: u32 = u32::MAX;
+
+/// Source file identity, cache is valid for
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SourceStamp {
+    /// Source file size in bytes
+    pub size: u64,
+    /// Source file modification time in nanoseconds since UNIX epoch
+    pub modified: u64,
+}
+
+impl SourceStamp {
+    /// File stamp getting function
+    /// * `path` - path to source file
+    /// * Returns size and modification time of file
+    pub fn of_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata.modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+
+        Ok(Self { size: metadata.len(), modified })
+    }
+}
+
+/// Cache file saving function
+/// * `path` - path to cache file
+/// * `model` - model to save
+/// * `source` - stamp of source file, model is loaded from, None if cache isn't bound to source
+pub fn save_cache(path: impl AsRef<Path>, model: &Model, source: Option<SourceStamp>) -> std::io::Result<()> {
+    write_cache(std::fs::File::create(path)?, model, source)
+}
+
+/// Cache writing function.
+///
+/// Cache is little-endian. It starts with header of magic, format version, source stamp, payload size and checksum,
+/// that is followed by payload with all model data. Primitives are stored with their clusters and cluster face lists,
+/// so they are restored without repartitioning faces.
+/// * `writer` - cache destination
+/// * `model` - model to write
+/// * `source` - stamp of source file, model is loaded from, None if cache isn't bound to source
+pub fn write_cache(mut writer: impl Write, model: &Model, source: Option<SourceStamp>) -> std::io::Result<()> {
+    let mut payload = Payload::default();
+    payload.model(model);
+    let payload = payload.data;
+
+    let source = source.unwrap_or(SourceStamp { size: 0, modified: 0 });
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(CACHE_MAGIC);
+    header.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.extend_from_slice(&source.size.to_le_bytes());
+    header.extend_from_slice(&source.modified.to_le_bytes());
+    header.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    header.extend_from_slice(&checksum(&payload).to_le_bytes());
+
+    writer.write_all(&header)?;
+    writer.write_all(&payload)?;
+    writer.flush()
+}
+
+/// Cache file loading function
+/// * `path` - path to cache file
+/// * Returns cached model
+pub fn load_cache(path: impl AsRef<Path>) -> Result<Model, LoadError> {
+    load_file(path.as_ref(), read_cache)
+}
+
+/// Cache reading function
+/// * `reader` - cache source
+/// * Returns cached model, or error with byte offset, if cache has other version, is corrupted or truncated
+pub fn read_cache(reader: impl Read) -> Result<Model, LoadError> {
+    let data = read_bytes(reader)?;
+    let (_, payload) = read_header(&data)?;
+
+    PayloadReader { data: payload, offset: 0 }.model()
+}
+
+/// Automatically cached model loading function.
+///
+/// Model is read from cache file next to source, named as source with [`CACHE_EXTENSION`] appended, if cache has
+/// current version, is valid and its source stamp matches source file. Otherwise model is loaded from source and
+/// cache is rewritten. Cache writing errors are ignored, as cache is optional (e.g. source directory may be read-only).
+/// Only source file itself is stamped, so changes of files it references (material libraries, external buffers) aren't detected.
+/// * `path` - path to source file
+/// * `load` - source loader
+/// * Returns loaded model
+pub fn load_cached(path: impl AsRef<Path>, load: impl FnOnce(&Path) -> Result<Model, LoadError>) -> Result<Model, LoadError> {
+    let path = path.as_ref();
+    let cache_path = cache_path(path);
+    let source = SourceStamp::of_file(path).map_err(|error| LoadError::io(Position::Unknown, error).with_path(path))?;
+
+    if let Ok(data) = std::fs::read(&cache_path) {
+        if let Ok((stamp, payload)) = read_header(&data) {
+            if stamp == source {
+                if let Ok(model) = (PayloadReader { data: payload, offset: 0 }).model() {
+                    return Ok(model);
+                }
+            }
+        }
+    }
+
+    let model = load(path)?;
+
+    // Cache is written to temporary file and renamed to never leave partially written cache
+    let temporary_path = cache_path.with_extension(format!("{CACHE_EXTENSION}.tmp"));
+    if save_cache(&temporary_path, &model, Some(source)).is_err() || std::fs::rename(&temporary_path, &cache_path).is_err() {
+        _ = std::fs::remove_file(&temporary_path);
+    }
+
+    Ok(model)
+}
+
+/// Automatic cache path getting function
+/// * `path` - path to source file
+/// * Returns path of source cache
+pub fn cache_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut cache_path = path.as_ref().as_os_str().to_owned();
+    cache_path.push(".");
+    cache_path.push(CACHE_EXTENSION);
+
+    cache_path.into()
+}
+
+/// Payload checksum computing function
+/// * Returns word-wise multiplicative hash, that is much faster than reading itself
+fn checksum(data: &[u8]) -> u64 {
+    const MULTIPLIER: u64 = 0x517C_C1B7_2722_0A95;
+
+    let mut hash = data.len() as u64;
+    let mut words = data.chunks_exact(8);
+    for word in &mut words {
+        hash = (hash.rotate_left(5) ^ u64::from_le_bytes(word.try_into().unwrap())).wrapping_mul(MULTIPLIER);
+    }
+    for byte in words.remainder() {
+        hash = (hash.rotate_left(5) ^ *byte as u64).wrapping_mul(MULTIPLIER);
+    }
+
+    hash
+}
+
+/// Cache header reading and checking function
+/// * `data` - cache file contents
+/// * Returns source stamp and checked payload
+fn read_header(data: &[u8]) -> Result<(SourceStamp, &[u8]), LoadError> {
+    let Some(header) = data.get(..HEADER_SIZE) else {
+        return Err(LoadError::syntax(Position::Binary { offset: data.len() }, "cache header is truncated"));
+    };
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+
+    if &header[..8] != CACHE_MAGIC {
+        return Err(LoadError::syntax(Position::Binary { offset: 0 }, "file is not model cache"));
+    }
+    let version = u32_at(8);
+    if version != CACHE_VERSION {
+        return Err(LoadError::unsupported(Position::Binary { offset: 8 }, format!("cache version {version}, only {CACHE_VERSION} is supported")));
+    }
+
+    let stamp = SourceStamp { size: u64_at(16), modified: u64_at(24) };
+    let payload_size = u64_at(32);
+    let payload = &data[HEADER_SIZE..];
+    if payload.len() as u64 != payload_size {
+        return Err(LoadError::syntax(Position::Binary { offset: 32 }, format!("cache payload has {} bytes, {payload_size} expected", payload.len())));
+    }
+    if checksum(payload) != u64_at(40) {
+        return Err(LoadError::syntax(Position::Binary { offset: 40 }, "cache checksum mismatch"));
+    }
+
+    Ok((stamp, payload))
+}
+
+/// Cache payload serializer
+#[derive(Default)]
+struct Payload {
+    data: Vec<u8>,
+}
+
+impl Payload {
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec3(&mut self, value: &Vec3f) {
+        self.f32(value.x);
+        self.f32(value.y);
+        self.f32(value.z);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u64(value.len() as u64);
+        self.data.extend_from_slice(value);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn optional_string(&mut self, value: Option<&str>) {
+        self.u8(value.is_some() as u8);
+        self.string(value.unwrap_or(""));
+    }
+
+    /// Array writing function
+    /// * `items` - array to write, prefixed by its length
+    /// * `write` - item writer
+    fn array<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.u64(items.len() as u64);
+        self.data.reserve(std::mem::size_of_val(items));
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    fn primitive(&mut self, primitive: &render::Primitive) {
+        self.u32(primitive.color);
+        self.array(primitive.get_positions(), Self::vec3);
+        self.array(primitive.get_normals(), Self::vec3);
+        self.array(primitive.get_faces().get_offsets(), |payload, offset| payload.u32(*offset));
+        self.array(primitive.get_faces().get_vertices(), |payload, index| payload.u32(*index));
+        self.array(primitive.get_faces().get_normals(), |payload, index| payload.u32(*index));
+        self.array(primitive.get_tex_coords(), |payload, tex_coord| {
+            payload.f32(tex_coord.x);
+            payload.f32(tex_coord.y);
+        });
+        self.array(primitive.get_colors(), |payload, color| payload.u32(*color));
+        self.array(primitive.get_vertex_normals(), Self::vec3);
+        self.array(primitive.get_line_strips().get_offsets(), |payload, offset| payload.u32(*offset));
+        self.array(primitive.get_line_strips().get_vertices(), |payload, index| payload.u32(*index));
+        self.array(primitive.get_points(), |payload, index| payload.u32(*index));
+        self.array(primitive.get_clusters(), |payload, cluster| {
+            payload.vec3(&cluster.bounds.min);
+            payload.vec3(&cluster.bounds.max);
+            payload.u64(cluster.faces.start as u64);
+            payload.u64(cluster.faces.end as u64);
+        });
+        self.array(primitive.get_cluster_faces(), |payload, face| payload.u32(*face));
+    }
+
+    fn model(&mut self, model: &Model) {
+        self.array(&model.material_libraries, |payload, library| payload.string(library));
+
+        self.array(&model.materials, |payload, material| {
+            payload.string(&material.name);
+            for channel in [material.base_color.x, material.base_color.y, material.base_color.z, material.base_color.w] {
+                payload.f32(channel);
+            }
+            payload.u32(material.base_color_texture.map_or(NONE_INDEX, |texture| texture as u32));
+        });
+
+        self.array(&model.textures, |payload, texture| {
+            payload.string(&texture.name);
+            payload.optional_string(texture.mime_type.as_deref());
+            payload.bytes(&texture.data);
+        });
+
+        self.array(&model.sub_meshes, |payload, sub_mesh| {
+            payload.string(&sub_mesh.name);
+            payload.array(&sub_mesh.groups, |payload, group| payload.string(group));
+            payload.u32(sub_mesh.smoothing_group);
+            payload.optional_string(sub_mesh.material.as_deref());
+            payload.u64(sub_mesh.faces.start as u64);
+            payload.u64(sub_mesh.faces.end as u64);
+            payload.u8(sub_mesh.visible as u8);
+            for value in sub_mesh.transform.data.iter().flatten() {
+                payload.f32(*value);
+            }
+            payload.primitive(&sub_mesh.primitive);
+        });
+    }
+}
+
+/// Cache payload deserializer
+struct PayloadReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    /// Current position getting function
+    fn position(&self) -> Position {
+        Position::Binary { offset: HEADER_SIZE + self.offset }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self.offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| LoadError::syntax(self.position(), "cache payload is truncated"))?;
+
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, LoadError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, LoadError> {
+        let position = self.position();
+        usize::try_from(self.u64()?).map_err(|_| LoadError::syntax(position, "cache value doesn't fit in memory"))
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        let position = self.position();
+        let len = self.usize()?;
+
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| LoadError::syntax(position, "cache string is not valid UTF-8"))
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, LoadError> {
+        let is_some = self.u8()? != 0;
+        let value = self.string()?;
+
+        Ok(is_some.then_some(value))
+    }
+
+    /// Array of variable size items reading function
+    /// * `read` - item reader
+    fn array<T>(&mut self, mut read: impl FnMut(&mut Self) -> Result<T, LoadError>) -> Result<Vec<T>, LoadError> {
+        let len = self.usize()?;
+        // Every item takes at least one byte, so length is checked before allocation
+        if len > self.data.len() - self.offset {
+            return Err(LoadError::syntax(self.position(), "cache payload is truncated"));
+        }
+
+        (0..len).map(|_| read(self)).collect()
+    }
+
+    /// Array of fixed size items reading function, items are converted from raw bytes in single pass
+    /// * `size` - item size in bytes
+    /// * `convert` - item from bytes converter
+    fn plain_array<T>(&mut self, size: usize, convert: impl Fn(&[u8]) -> T) -> Result<Vec<T>, LoadError> {
+        let position = self.position();
+        let len = self.usize()?;
+        let bytes = len.checked_mul(size).ok_or_else(|| LoadError::syntax(position, "cache array is too large"))?;
+
+        Ok(self.bytes(bytes)?.chunks_exact(size).map(convert).collect())
+    }
+
+    fn vec3_array(&mut self) -> Result<Vec<Vec3f>, LoadError> {
+        self.plain_array(12, |bytes| Vec3f::new(f32_at(bytes, 0), f32_at(bytes, 4), f32_at(bytes, 8)))
+    }
+
+    fn u32_array(&mut self) -> Result<Vec<u32>, LoadError> {
+        self.plain_array(4, |bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn primitive(&mut self) -> Result<render::Primitive, LoadError> {
+        let position = self.position();
+        let primitive_error = |error: render::PrimitiveError| LoadError::syntax(position, error.to_string());
+        let color = self.u32()?;
+        let positions = self.vec3_array()?;
+        let normals = self.vec3_array()?;
+        let faces = render::Faces::from_streams(self.u32_array()?, self.u32_array()?, self.u32_array()?).map_err(primitive_error)?;
+        let tex_coords = self.plain_array(8, |bytes| Vec2f::new(f32_at(bytes, 0), f32_at(bytes, 4)))?;
+        let colors = self.u32_array()?;
+        let vertex_normals = self.vec3_array()?;
+        let line_strips = render::LineStrips::from_streams(self.u32_array()?, self.u32_array()?).map_err(primitive_error)?;
+
+        let parts = PrimitiveParts {
+            color,
+            positions,
+            normals,
+            faces,
+            tex_coords,
+            colors,
+            vertex_normals,
+            line_strips,
+            points: self.u32_array()?,
+            clusters: self.plain_array(40, |bytes| Cluster {
+                bounds: Aabb {
+                    min: Vec3f::new(f32_at(bytes, 0), f32_at(bytes, 4), f32_at(bytes, 8)),
+                    max: Vec3f::new(f32_at(bytes, 12), f32_at(bytes, 16), f32_at(bytes, 20)),
+                },
+                faces: u64_at(bytes, 24) as usize..u64_at(bytes, 32) as usize,
+            })?,
+            cluster_faces: self.u32_array()?,
+        };
+
+        render::Primitive::from_parts(parts).map_err(primitive_error)
+    }
+
+    fn model(&mut self) -> Result<Model, LoadError> {
+        let material_libraries = self.array(Self::string)?;
+
+        let materials = self.array(|reader| {
+            let name = reader.string()?;
+            let base_color = Vec4f::new(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+            let base_color_texture = match reader.u32()? {
+                NONE_INDEX => None,
+                texture => Some(texture as usize),
+            };
+
+            Ok(Material { name, base_color, base_color_texture })
+        })?;
+
+        let textures = self.array(|reader| {
+            let name = reader.string()?;
+            let mime_type = reader.optional_string()?;
+            let len = reader.usize()?;
+            let data = reader.bytes(len)?.to_vec();
+
+            Ok(Texture { name, mime_type, data })
+        })?;
+
+        let sub_meshes = self.array(|reader| {
+            let name = reader.string()?;
+            let groups = reader.array(Self::string)?;
+            let smoothing_group = reader.u32()?;
+            let material = reader.optional_string()?;
+            let faces = reader.usize()?..reader.usize()?;
+            let visible = reader.u8()? != 0;
+            let mut transform = Mat4x4f::identity();
+            for value in transform.data.iter_mut().flatten() {
+                *value = reader.f32()?;
+            }
+            let primitive = reader.primitive()?;
+
+            Ok(SubMesh { name, groups, smoothing_group, material, faces, primitive, visible, transform })
+        })?;
+
+        if self.offset != self.data.len() {
+            return Err(LoadError::syntax(self.position(), "unexpected data after cache payload"));
+        }
+
+        Ok(Model { sub_meshes, material_libraries, materials, textures })
+    }
+}
+
+/// Little-endian float at offset getting function
+fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Little-endian 64-bit integer at offset getting function
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh;
+
+    /// Model with every kind of cached data building function
+    fn test_model() -> Model {
+        // Sphere has enough faces to be split into several clusters
+        let mut primitive = mesh::uv_sphere(1.0, 32, 16);
+        primitive.set_colors((0..primitive.get_positions().len() as u32).collect()).unwrap();
+        let mut line_strips = render::LineStrips::new();
+        line_strips.push([0, 1, 2]);
+        primitive.set_line_strips(line_strips).unwrap();
+        primitive.set_points(vec![3, 4]).unwrap();
+        assert!(primitive.get_clusters().len() > 1);
+
+        Model {
+            sub_meshes: vec![SubMesh {
+                name: "ball".to_string(),
+                groups: vec!["round".to_string(), "smooth".to_string()],
+                smoothing_group: 3,
+                material: Some("stone".to_string()),
+                faces: 5..5 + primitive.get_faces().len(),
+                primitive,
+                visible: false,
+                transform: Mat4x4f::translate(1.0, 2.0, 3.0),
+            }],
+            material_libraries: vec!["scene.mtl".to_string()],
+            materials: vec![Material { name: "stone".to_string(), base_color: Vec4f::new(0.5, 0.25, 1.0, 0.75), base_color_texture: Some(0) }],
+            textures: vec![Texture { name: "stone.png".to_string(), mime_type: Some("image/png".to_string()), data: vec![1, 2, 3] }],
+        }
+    }
+
+    /// Cache writing to memory function
+    fn cache(model: &Model, source: Option<SourceStamp>) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_cache(&mut data, model, source).unwrap();
+        data
+    }
+
+    /// Reading error getting function
+    fn read_error(data: &[u8]) -> LoadError {
+        read_cache(data).err().expect("invalid cache is accepted")
+    }
+
+    /// Header payload size and checksum updating function, used to get consistent header for modified payload
+    fn fix_header(data: &mut [u8]) {
+        let payload_size = (data.len() - HEADER_SIZE) as u64;
+        let checksum = checksum(&data[HEADER_SIZE..]);
+        data[32..40].copy_from_slice(&payload_size.to_le_bytes());
+        data[40..48].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    /// Unique temporary directory creating function
+    fn temporary_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("s3d-cache-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn round_trip_keeps_model() {
+        let model = test_model();
+        let data = cache(&model, Some(SourceStamp { size: 10, modified: 20 }));
+        let read = read_cache(data.as_slice()).unwrap();
+
+        // Everything, that is cached, is written back the same way
+        assert_eq!(cache(&read, Some(SourceStamp { size: 10, modified: 20 })), data);
+
+        let (sub_mesh, primitive) = (&read.sub_meshes[0], &read.sub_meshes[0].primitive);
+        let expected = &model.sub_meshes[0];
+        assert_eq!((&sub_mesh.name, &sub_mesh.groups, sub_mesh.smoothing_group), (&expected.name, &expected.groups, expected.smoothing_group));
+        assert_eq!((&sub_mesh.material, &sub_mesh.faces, sub_mesh.visible), (&expected.material, &expected.faces, expected.visible));
+        assert_eq!(sub_mesh.transform.data, expected.transform.data);
+        assert_eq!(primitive.get_clusters().len(), expected.primitive.get_clusters().len());
+        assert_eq!(primitive.get_cluster_faces(), expected.primitive.get_cluster_faces());
+        assert_eq!(primitive.get_faces().get_vertices(), expected.primitive.get_faces().get_vertices());
+        assert_eq!(primitive.get_colors(), expected.primitive.get_colors());
+        assert_eq!(primitive.get_points(), [3, 4]);
+        assert_eq!(read.materials[0].base_color_texture, Some(0));
+        assert_eq!(read.textures[0].data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn rejects_other_version() {
+        let mut data = cache(&test_model(), None);
+        data[8..12].copy_from_slice(&(CACHE_VERSION - 1).to_le_bytes());
+
+        match read_error(&data) {
+            LoadError::Unsupported { location, .. } => assert!(matches!(location.position, Position::Binary { offset: 8 })),
+            err => panic!("unexpected error: {err}"),
+        }
+
+        data[..8].copy_from_slice(b"NOTCACHE");
+        assert!(matches!(read_error(&data), LoadError::Syntax { message, .. } if message.contains("not model cache")));
+    }
+
+    #[test]
+    fn rejects_corrupted_payload() {
+        let mut data = cache(&test_model(), None);
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        assert!(matches!(read_error(&data), LoadError::Syntax { message, .. } if message.contains("checksum mismatch")));
+
+        // Payload, that passes checksum, is still validated
+        let mut data = cache(&test_model(), None);
+        data.push(0);
+        fix_header(&mut data);
+        assert!(matches!(read_error(&data), LoadError::Syntax { message, .. } if message.contains("after cache payload")));
+    }
+
+    #[test]
+    fn rejects_truncated_cache() {
+        let data = cache(&test_model(), None);
+
+        assert!(matches!(read_error(&data[..HEADER_SIZE - 1]), LoadError::Syntax { message, .. } if message.contains("header is truncated")));
+        assert!(matches!(read_error(&data[..data.len() - 1]), LoadError::Syntax { message, .. } if message.contains("bytes, ")));
+
+        for len in [HEADER_SIZE, HEADER_SIZE + 7, HEADER_SIZE + 100, data.len() - 1] {
+            let mut truncated = data[..len].to_vec();
+            fix_header(&mut truncated);
+            assert!(matches!(read_error(&truncated), LoadError::Syntax { message, .. } if message.contains("truncated")), "{len}");
+        }
+    }
+
+    #[test]
+    fn load_cached_reloads_stale_source() {
+        let directory = temporary_directory("stale");
+        let source = directory.join("triangle.obj");
+        std::fs::write(&source, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        let load_count = std::cell::Cell::new(0);
+        let load = |path: &Path| {
+            load_count.set(load_count.get() + 1);
+            super::super::load_obj(path)
+        };
+
+        // First load writes cache, second one reads it
+        assert_eq!(load_cached(&source, load).unwrap().sub_meshes[0].primitive.get_faces().len(), 1);
+        assert!(cache_path(&source).exists());
+        assert_eq!(load_cached(&source, load).unwrap().sub_meshes[0].primitive.get_faces().len(), 1);
+        assert_eq!(load_count.get(), 1);
+
+        // Changed source invalidates cache
+        std::fs::write(&source, "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 2 4 3\n").unwrap();
+        assert_eq!(load_cached(&source, load).unwrap().sub_meshes[0].primitive.get_faces().len(), 2);
+        assert_eq!(load_count.get(), 2);
+        assert_eq!(load_cached(&source, load).unwrap().sub_meshes[0].primitive.get_faces().len(), 2);
+        assert_eq!(load_count.get(), 2);
+
+        // Corrupted cache is rewritten
+        std::fs::write(cache_path(&source), b"S3DCACHE").unwrap();
+        assert_eq!(load_cached(&source, load).unwrap().sub_meshes[0].primitive.get_faces().len(), 2);
+        assert_eq!(load_count.get(), 3);
+        assert!(read_cache(std::fs::read(cache_path(&source)).unwrap().as_slice()).is_ok());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
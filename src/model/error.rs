@@ -0,0 +1,148 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Position in loaded source
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Position {
+    /// Error isn't bound to specific place of source
+    Unknown,
+    /// 1-based line and byte column in text source
+    Text { line: usize, column: usize },
+    /// Byte offset in binary source
+    Binary { offset: usize },
+}
+
+/// Error location
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    /// Path of loaded file, None if source isn't file
+    pub path: Option<PathBuf>,
+    pub position: Position,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}", path.display())?,
+            None => write!(f, "<input>")?,
+        }
+
+        match self.position {
+            Position::Unknown => Ok(()),
+            Position::Text { line, column } => write!(f, ":{line}:{column}"),
+            Position::Binary { offset } => write!(f, " at offset {offset:#x}"),
+        }
+    }
+}
+
+/// Model loading error
+#[derive(Debug)]
+pub enum LoadError {
+    /// Source reading failed
+    Io { location: Location, error: std::io::Error },
+    /// Source is malformed
+    Syntax { location: Location, message: String },
+    /// Source references element, that doesn't exist
+    IndexRange { location: Location, message: String },
+    /// Source uses feature, that isn't supported by loader
+    Unsupported { location: Location, feature: String },
+}
+
+impl LoadError {
+    /// I/O error create function
+    pub fn io(position: Position, error: std::io::Error) -> Self {
+        Self::Io { location: Location { path: None, position }, error }
+    }
+
+    /// Syntax error create function
+    pub fn syntax(position: Position, message: impl Into<String>) -> Self {
+        Self::Syntax { location: Location { path: None, position }, message: message.into() }
+    }
+
+    /// Index range error create function
+    pub fn index_range(position: Position, message: impl Into<String>) -> Self {
+        Self::IndexRange { location: Location { path: None, position }, message: message.into() }
+    }
+
+    /// Unsupported feature error create function
+    pub fn unsupported(position: Position, feature: impl Into<String>) -> Self {
+        Self::Unsupported { location: Location { path: None, position }, feature: feature.into() }
+    }
+
+    /// Error location getting function
+    pub fn get_location(&self) -> &Location {
+        let (Self::Io { location, .. }
+            | Self::Syntax { location, .. }
+            | Self::IndexRange { location, .. }
+            | Self::Unsupported { location, .. }) = self;
+
+        location
+    }
+
+    /// Source path setting function
+    /// * `path` - path of file, error occurred in
+    /// * Returns error with path in location
+    pub fn with_path(mut self, path: &Path) -> Self {
+        let (Self::Io { location, .. }
+            | Self::Syntax { location, .. }
+            | Self::IndexRange { location, .. }
+            | Self::Unsupported { location, .. }) = &mut self;
+        location.path = Some(path.to_path_buf());
+
+        self
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { location, error } => write!(f, "{location}: I/O error: {error}"),
+            Self::Syntax { location, message } => write!(f, "{location}: {message}"),
+            Self::IndexRange { location, message } => write!(f, "{location}: {message}"),
+            Self::Unsupported { location, feature } => write!(f, "{location}: unsupported {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// File loading function, common for all loaders
+/// * `path` - path to file
+/// * `read` - loader, that reads model from file
+/// * Returns loaded model, errors get file path
+pub(crate) fn load_file<T>(path: &Path, read: impl FnOnce(std::fs::File) -> Result<T, LoadError>) -> Result<T, LoadError> {
+    let file = std::fs::File::open(path).map_err(|error| LoadError::io(Position::Unknown, error).with_path(path))?;
+
+    read(file).map_err(|error| error.with_path(path))
+}
+
+/// Whole source reading function
+pub(crate) fn read_bytes(mut reader: impl Read) -> Result<Vec<u8>, LoadError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).map_err(|error| LoadError::io(Position::Unknown, error))?;
+
+    Ok(data)
+}
+
+/// Whole text source reading function
+/// * `reader` - source to read
+/// * Returns source text, or syntax error at first invalid UTF-8 sequence
+pub(crate) fn read_text(reader: impl Read) -> Result<String, LoadError> {
+    String::from_utf8(read_bytes(reader)?).map_err(|error| {
+        let valid = &error.as_bytes()[..error.utf8_error().valid_up_to()];
+        let line_start = valid.iter().rposition(|c| *c == b'\n').map_or(0, |index| index + 1);
+        let position = Position::Text {
+            line: valid.iter().filter(|c| **c == b'\n').count() + 1,
+            column: valid.len() - line_start + 1,
+        };
+
+        LoadError::syntax(position, "invalid UTF-8 sequence")
+    })
+}
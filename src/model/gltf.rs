@@ -0,0 +1,1057 @@
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use crate::mesh::polygon_normal;
+use crate::render;
+use super::error::*;
+use super::json::{Json, Value};
+use super::mesh::{Material, Model, SubMesh, Texture};
+
+/// GLB file magic
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+/// GLB JSON chunk type
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+/// GLB binary buffer chunk type
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+/// Accessor component types
+const COMPONENT_I8: u32 = 5120;
+const COMPONENT_U8: u32 = 5121;
+const COMPONENT_I16: u32 = 5122;
+const COMPONENT_U16: u32 = 5123;
+const COMPONENT_U32: u32 = 5125;
+const COMPONENT_F32: u32 = 5126;
+
+/// Primitive topology modes
+const MODE_POINTS: usize = 0;
+const MODE_LINES: usize = 1;
+const MODE_LINE_LOOP: usize = 2;
+const MODE_LINE_STRIP: usize = 3;
+const MODE_TRIANGLES: usize = 4;
+const MODE_TRIANGLE_STRIP: usize = 5;
+const MODE_TRIANGLE_FAN: usize = 6;
+
+/// glTF file loading function.
+///
+/// External buffers and images are loaded relative to file directory.
+/// * `path` - path to `.gltf` or `.glb` file
+/// * Returns model with sub-mesh for every mesh primitive instance of default scene
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<Model, LoadError> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    load_file(path, |file| read_gltf(file, Some(directory)))
+}
+
+/// glTF 2.0 reading function.
+///
+/// Source is either JSON glTF or GLB container, GLB is detected by its magic. Buffers and images may be stored in GLB
+/// binary chunk, in base64 `data:` URIs and in external files, that are resolved relative to `directory`.
+///
+/// Every primitive of every mesh, instanced by nodes of default scene (or by all root nodes, if file has no scenes),
+/// becomes sub-mesh with node world matrix as its transform, node name (or mesh name, if node is unnamed) as its name
+/// and mesh name as its group. Sub-mesh faces are numbered in order of sub-meshes.
+///
+/// `POSITION`, `NORMAL`, `TEXCOORD_0` and `COLOR_0` attributes are read, texture coordinates are converted to OBJ
+/// convention with V axis pointing up. Triangle lists, strips and fans become faces with normal, that is
+/// normalized sum of vertex normals, or geometric one, if primitive has no normals; lines and points become
+/// line strips and points.
+///
+/// Materials and images are read into model materials and textures, materials without name are named `material<index>`.
+/// Primitive color is material base color factor, converted to sRGB, `COLOR_0` colors are multiplied by it too.
+/// Primitives without material are white.
+///
+/// Sparse accessors, accessors without buffer view (that are zero-filled and make sense with sparse data only) and
+/// required extensions are not supported, animations, skins, cameras and morph targets are ignored.
+/// * `reader` - glTF source
+/// * `directory` - directory to load external resources from, None to make external resources unsupported
+/// * Returns model, or error with line and column in JSON glTF or byte offset in GLB
+pub fn read_gltf(reader: impl Read, directory: Option<&Path>) -> Result<Model, LoadError> {
+    let data = read_bytes(reader)?;
+
+    let (text, chunk_offset, binary) = if data.starts_with(GLB_MAGIC) {
+        let chunks = split_glb(&data)?;
+        (chunks.json, Some(chunks.json_offset), chunks.binary)
+    } else {
+        (&data[..], None, None)
+    };
+
+    let root = Json::parse(text).map_err(|error| LoadError::syntax(json_position(text, chunk_offset, error.offset), error.message))?;
+    let document = Document { root, text, chunk_offset };
+
+    let asset = document.field(&document.root, "asset")?;
+    let version = document.string(asset, "version")?.ok_or_else(|| LoadError::syntax(document.at(asset), "missing \"version\" property"))?;
+    if !version.starts_with("2.") {
+        return Err(LoadError::unsupported(document.at(asset), format!("glTF version {version}")));
+    }
+    if let Some(extension) = document.items(&document.root, "extensionsRequired")?.first() {
+        return Err(LoadError::unsupported(document.at(extension), format!("required extension {}", extension.as_str().unwrap_or("?"))));
+    }
+
+    let mut loader = Loader {
+        document: &document,
+        buffers: Vec::new(),
+        buffer_views: document.objects(&document.root, "bufferViews")?,
+        accessors: document.objects(&document.root, "accessors")?,
+        materials: Vec::new(),
+    };
+    loader.buffers = loader.load_buffers(binary, directory)?;
+
+    let textures = loader.load_textures(directory)?;
+    loader.materials = loader.load_materials(textures.len())?;
+
+    let sub_meshes = loader.load_scene()?;
+
+    Ok(Model {
+        sub_meshes,
+        material_libraries: Vec::new(),
+        materials: loader.materials,
+        textures,
+    })
+}
+
+/// GLB container chunks
+struct GlbChunks<'a> {
+    json: &'a [u8],
+    /// Offset of JSON chunk data in file
+    json_offset: usize,
+    /// Binary buffer chunk, None if file has no one
+    binary: Option<&'a [u8]>,
+}
+
+/// GLB container splitting function
+/// * `data` - GLB file contents
+fn split_glb(data: &[u8]) -> Result<GlbChunks<'_>, LoadError> {
+    let read_u32 = |data: &[u8], offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| LoadError::syntax(Position::Binary { offset: data.len() }, "unexpected end of GLB file"))
+    };
+
+    let version = read_u32(data, 4)?;
+    if version != 2 {
+        return Err(LoadError::unsupported(Position::Binary { offset: 4 }, format!("GLB version {version}")));
+    }
+    let length = read_u32(data, 8)? as usize;
+    if length > data.len() {
+        return Err(LoadError::syntax(Position::Binary { offset: 8 }, format!("GLB length {length} exceeds file size {}", data.len())));
+    }
+
+    let data = &data[..length];
+    let mut json = None;
+    let mut binary = None;
+    let mut offset = 12;
+
+    while offset < data.len() {
+        let chunk_length = read_u32(data, offset)? as usize;
+        let chunk_type = read_u32(data, offset + 4)?;
+        let Some(chunk) = data.get(offset + 8..offset + 8 + chunk_length) else {
+            return Err(LoadError::syntax(Position::Binary { offset }, format!("GLB chunk of {chunk_length} bytes exceeds file")));
+        };
+
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some((offset + 8, chunk)),
+            GLB_CHUNK_BIN if json.is_some() && binary.is_none() => binary = Some(chunk),
+            _ if json.is_none() => return Err(LoadError::syntax(Position::Binary { offset }, "GLB file must start with JSON chunk")),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+
+    let (json_offset, json) = json.ok_or_else(|| LoadError::syntax(Position::Binary { offset }, "GLB file has no JSON chunk"))?;
+
+    Ok(GlbChunks { json, json_offset, binary })
+}
+
+/// JSON source position getting function
+/// * `text` - JSON text
+/// * `chunk_offset` - offset of JSON chunk in GLB file, None if source is JSON text
+/// * `offset` - byte offset in JSON text
+fn json_position(text: &[u8], chunk_offset: Option<usize>, offset: usize) -> Position {
+    if let Some(chunk_offset) = chunk_offset {
+        return Position::Binary { offset: chunk_offset + offset };
+    }
+
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.iter().rposition(|c| *c == b'\n').map_or(0, |index| index + 1);
+
+    Position::Text {
+        line: before.iter().filter(|c| **c == b'\n').count() + 1,
+        column: before.len() - line_start + 1,
+    }
+}
+
+/// Parsed glTF JSON with typed property getters, that report errors at property positions
+struct Document<'a> {
+    root: Json,
+    text: &'a [u8],
+    /// Offset of JSON chunk in GLB file, None if source is JSON text
+    chunk_offset: Option<usize>,
+}
+
+impl<'a> Document<'a> {
+    /// Value position getting function
+    fn at(&self, value: &Json) -> Position {
+        json_position(self.text, self.chunk_offset, value.offset)
+    }
+
+    /// Required property getting function
+    fn field<'j>(&self, object: &'j Json, key: &str) -> Result<&'j Json, LoadError> {
+        object.get(key).ok_or_else(|| LoadError::syntax(self.at(object), format!("missing \"{key}\" property")))
+    }
+
+    /// Non-negative integer getting function
+    fn integer(&self, value: &Json) -> Result<usize, LoadError> {
+        match value.as_f64() {
+            Some(number) if number >= 0.0 && number.fract() == 0.0 && number <= u32::MAX as f64 => Ok(number as usize),
+            Some(number) => Err(LoadError::syntax(self.at(value), format!("expected non-negative integer, got {number}"))),
+            None => Err(LoadError::syntax(self.at(value), format!("expected non-negative integer, got {}", value.kind_name()))),
+        }
+    }
+
+    /// Optional non-negative integer property getting function
+    fn optional_integer(&self, object: &Json, key: &str, default: usize) -> Result<usize, LoadError> {
+        object.get(key).map_or(Ok(default), |value| self.integer(value))
+    }
+
+    /// Optional index property getting function
+    /// * `object` - object to get property of
+    /// * `key` - property name
+    /// * `count` - number of elements, property indexes
+    /// * `kind` - indexed element kind name for error messages
+    /// * Returns index, None if object has no such property
+    fn reference(&self, object: &Json, key: &str, count: usize, kind: &str) -> Result<Option<usize>, LoadError> {
+        let Some(value) = object.get(key) else {
+            return Ok(None);
+        };
+
+        let index = self.integer(value)?;
+        if index >= count {
+            return Err(LoadError::index_range(self.at(value), format!("{kind} {index} is out of range, {count} defined")));
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Optional string property getting function
+    fn string<'j>(&self, object: &'j Json, key: &str) -> Result<Option<&'j str>, LoadError> {
+        object.get(key).map_or(Ok(None), |value| {
+            value.as_str().map(Some).ok_or_else(|| LoadError::syntax(self.at(value), format!("\"{key}\" must be string, got {}", value.kind_name())))
+        })
+    }
+
+    /// Optional array property getting function
+    /// * Returns array items, empty if object has no such property
+    fn items<'j>(&self, object: &'j Json, key: &str) -> Result<&'j [Json], LoadError> {
+        object.get(key).map_or(Ok(&[]), |value| {
+            value.as_array().ok_or_else(|| LoadError::syntax(self.at(value), format!("\"{key}\" must be array, got {}", value.kind_name())))
+        })
+    }
+
+    /// Optional array of objects property getting function
+    /// * Returns array items, empty if object has no such property
+    fn objects<'j>(&self, object: &'j Json, key: &str) -> Result<&'j [Json], LoadError> {
+        let items = self.items(object, key)?;
+
+        match items.iter().find(|item| !matches!(item.value, Value::Object(_))) {
+            Some(item) => Err(LoadError::syntax(self.at(item), format!("\"{key}\" items must be objects, got {}", item.kind_name()))),
+            None => Ok(items),
+        }
+    }
+
+    /// Optional fixed size number array property getting function
+    /// * `default` - value of missing property
+    fn numbers<const N: usize>(&self, object: &Json, key: &str, default: [f32; N]) -> Result<[f32; N], LoadError> {
+        let Some(value) = object.get(key) else {
+            return Ok(default);
+        };
+
+        let numbers = value.as_array()
+            .filter(|items| items.len() == N)
+            .and_then(|items| items.iter().map(|item| item.as_f64().map(|number| number as f32)).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| LoadError::syntax(self.at(value), format!("\"{key}\" must be array of {N} numbers")))?;
+
+        Ok(numbers.try_into().unwrap())
+    }
+}
+
+/// Typed view of accessor data
+struct AccessorView<'a> {
+    /// Data, starting at first element
+    data: &'a [u8],
+    component_type: u32,
+    count: usize,
+    /// Distance between element starts in bytes
+    stride: usize,
+    normalized: bool,
+}
+
+impl<'a> AccessorView<'a> {
+    /// Component size in bytes getting function
+    fn component_size(component_type: u32) -> Option<usize> {
+        match component_type {
+            COMPONENT_I8 | COMPONENT_U8 => Some(1),
+            COMPONENT_I16 | COMPONENT_U16 => Some(2),
+            COMPONENT_U32 | COMPONENT_F32 => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Component value getting function
+    /// * `element` - element index
+    /// * `component` - component index
+    /// * Returns component value, normalized integers are converted to [0, 1] or [-1, 1] range
+    fn get(&self, element: usize, component: usize) -> f32 {
+        let offset = element * self.stride + component * Self::component_size(self.component_type).unwrap_or(0);
+        let bytes = &self.data[offset..];
+        let (value, scale) = match self.component_type {
+            COMPONENT_I8 => (bytes[0] as i8 as f32, i8::MAX as f32),
+            COMPONENT_U8 => (bytes[0] as f32, u8::MAX as f32),
+            COMPONENT_I16 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, i16::MAX as f32),
+            COMPONENT_U16 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, u16::MAX as f32),
+            COMPONENT_U32 => (u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32, u32::MAX as f32),
+            _ => return f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        };
+
+        if self.normalized {
+            (value / scale).max(-1.0)
+        } else {
+            value
+        }
+    }
+
+    /// Unsigned integer element getting function
+    /// * `element` - element index of scalar accessor with unsigned integer components
+    fn index(&self, element: usize) -> u32 {
+        let bytes = &self.data[element * self.stride..];
+        match self.component_type {
+            COMPONENT_U8 => bytes[0] as u32,
+            COMPONENT_U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+
+    /// 3-component vectors reading function
+    fn read_vec3(&self) -> Vec<Vec3f> {
+        (0..self.count).map(|element| Vec3f::new(self.get(element, 0), self.get(element, 1), self.get(element, 2))).collect()
+    }
+}
+
+/// glTF contents loader
+struct Loader<'d> {
+    document: &'d Document<'d>,
+    /// Buffer contents, GLB binary chunk is borrowed
+    buffers: Vec<Cow<'d, [u8]>>,
+    buffer_views: &'d [Json],
+    accessors: &'d [Json],
+    materials: Vec<Material>,
+}
+
+impl<'d> Loader<'d> {
+    /// Buffers loading function
+    /// * `binary` - GLB binary chunk, used by first buffer without URI
+    /// * `directory` - directory to load external buffers from
+    fn load_buffers(&self, binary: Option<&'d [u8]>, directory: Option<&Path>) -> Result<Vec<Cow<'d, [u8]>>, LoadError> {
+        let document = self.document;
+        let mut buffers = Vec::new();
+
+        for (index, buffer) in document.objects(&document.root, "buffers")?.iter().enumerate() {
+            let length = document.integer(document.field(buffer, "byteLength")?)?;
+            let data = match (document.string(buffer, "uri")?, binary) {
+                (Some(uri), _) => Cow::Owned(self.load_uri(buffer, uri, directory)?),
+                (None, Some(binary)) if index == 0 => Cow::Borrowed(binary),
+                (None, _) => return Err(LoadError::syntax(document.at(buffer), "buffer has no uri and isn't GLB binary chunk")),
+            };
+
+            if data.len() < length {
+                return Err(LoadError::syntax(document.at(buffer), format!("buffer {index} has {} bytes, but its byteLength is {length}", data.len())));
+            }
+            buffers.push(data);
+        }
+
+        Ok(buffers)
+    }
+
+    /// URI contents loading function
+    /// * `owner` - object, URI belongs to, errors are reported at its position
+    /// * `uri` - base64 `data:` URI or relative file URI
+    /// * `directory` - directory to resolve relative URIs in
+    fn load_uri(&self, owner: &Json, uri: &str, directory: Option<&Path>) -> Result<Vec<u8>, LoadError> {
+        let position = self.document.at(owner);
+
+        if let Some(data_uri) = uri.strip_prefix("data:") {
+            let Some((header, payload)) = data_uri.split_once(',') else {
+                return Err(LoadError::syntax(position, "data URI has no ',' separator"));
+            };
+            if !header.ends_with(";base64") {
+                return Err(LoadError::unsupported(position, "data URI without base64 encoding"));
+            }
+
+            return decode_base64(payload).ok_or_else(|| LoadError::syntax(position, "invalid base64 data in data URI"));
+        }
+
+        if uri.contains("://") {
+            return Err(LoadError::unsupported(position, format!("URI \"{uri}\"")));
+        }
+        let Some(directory) = directory else {
+            return Err(LoadError::unsupported(position, format!("external resource \"{uri}\" without resource directory")));
+        };
+
+        let path = directory.join(percent_decode(uri));
+        std::fs::read(&path).map_err(|error| {
+            LoadError::io(position, std::io::Error::new(error.kind(), format!("{}: {error}", path.display())))
+        })
+    }
+
+    /// Buffer view getting function
+    /// * `index` - buffer view index
+    /// * Returns view data and byte stride, if view has one
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), LoadError> {
+        let document = self.document;
+        let view = &self.buffer_views[index];
+
+        let buffer = document.reference(view, "buffer", self.buffers.len(), "buffer")?
+            .ok_or_else(|| LoadError::syntax(document.at(view), "missing \"buffer\" property"))?;
+        let offset = document.optional_integer(view, "byteOffset", 0)?;
+        let length = document.integer(document.field(view, "byteLength")?)?;
+
+        let data = self.buffers[buffer].get(offset..offset + length).ok_or_else(|| {
+            LoadError::syntax(document.at(view), format!("buffer view {index} exceeds buffer {buffer} of {} bytes", self.buffers[buffer].len()))
+        })?;
+
+        let stride = match view.get("byteStride") {
+            Some(value) => match document.integer(value)? {
+                0 => return Err(LoadError::syntax(document.at(value), "byteStride must be positive")),
+                stride => Some(stride),
+            },
+            None => None,
+        };
+
+        Ok((data, stride))
+    }
+
+    /// Accessor view getting function
+    /// * `index` - accessor index
+    /// * `types` - allowed accessor types
+    fn accessor(&self, index: usize, types: &[&str]) -> Result<AccessorView<'_>, LoadError> {
+        let document = self.document;
+        let accessor = &self.accessors[index];
+        let position = document.at(accessor);
+
+        if accessor.get("sparse").is_some() {
+            return Err(LoadError::unsupported(position, "sparse accessor"));
+        }
+
+        let component_type = document.integer(document.field(accessor, "componentType")?)? as u32;
+        let component_size = AccessorView::component_size(component_type)
+            .ok_or_else(|| LoadError::syntax(position, format!("invalid accessor component type {component_type}")))?;
+
+        let kind = document.string(accessor, "type")?.ok_or_else(|| LoadError::syntax(position, "missing \"type\" property"))?;
+        if !types.contains(&kind) {
+            return Err(LoadError::syntax(position, format!("accessor {index} has type {kind}, expected {}", types.join(" or "))));
+        }
+        let components = match kind {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            _ => 4,
+        };
+
+        let count = document.integer(document.field(accessor, "count")?)?;
+        let normalized = accessor.get("normalized").and_then(Json::as_bool).unwrap_or(false);
+        let element_size = components * component_size;
+
+        // Element count of accessor without buffer view isn't bounded by any data, so such accessors are rejected
+        let Some(view) = document.reference(accessor, "bufferView", self.buffer_views.len(), "buffer view")? else {
+            return Err(LoadError::unsupported(position, "accessor without buffer view"));
+        };
+
+        let (data, stride) = self.buffer_view(view)?;
+        let stride = stride.unwrap_or(element_size);
+        let offset = document.optional_integer(accessor, "byteOffset", 0)?;
+        let size = if count == 0 { 0 } else { offset + stride * (count - 1) + element_size };
+
+        if size > data.len() {
+            return Err(LoadError::syntax(position, format!("accessor {index} needs {size} bytes, but buffer view {view} has {}", data.len())));
+        }
+
+        Ok(AccessorView { data: &data[offset.min(data.len())..], component_type, count, stride, normalized })
+    }
+
+    /// Images loading function
+    /// * `directory` - directory to load external images from
+    /// * Returns textures, one per image
+    fn load_textures(&self, directory: Option<&Path>) -> Result<Vec<Texture>, LoadError> {
+        let document = self.document;
+        let mut textures = Vec::new();
+
+        for image in document.objects(&document.root, "images")? {
+            let uri = document.string(image, "uri")?;
+            let data = match (document.reference(image, "bufferView", self.buffer_views.len(), "buffer view")?, uri) {
+                (Some(view), _) => self.buffer_view(view)?.0.to_vec(),
+                (None, Some(uri)) => self.load_uri(image, uri, directory)?,
+                (None, None) => return Err(LoadError::syntax(document.at(image), "image has neither uri nor bufferView")),
+            };
+
+            let mime_type = match document.string(image, "mimeType")? {
+                Some(mime_type) => Some(mime_type.to_string()),
+                None => uri.and_then(uri_mime_type).map(str::to_string),
+            };
+            let name = match (document.string(image, "name")?, uri) {
+                (Some(name), _) => name.to_string(),
+                (None, Some(uri)) if !uri.starts_with("data:") => percent_decode(uri),
+                _ => String::new(),
+            };
+
+            textures.push(Texture { name, mime_type, data });
+        }
+
+        Ok(textures)
+    }
+
+    /// Materials loading function
+    /// * `image_count` - number of loaded images
+    fn load_materials(&self, image_count: usize) -> Result<Vec<Material>, LoadError> {
+        let document = self.document;
+        let textures = document.objects(&document.root, "textures")?;
+        let mut materials: Vec<Material> = Vec::new();
+
+        for (index, material) in document.objects(&document.root, "materials")?.iter().enumerate() {
+            let mut name = document.string(material, "name")?.map_or_else(|| format!("material{index}"), str::to_string);
+            if materials.iter().any(|other| other.name == name) {
+                name = format!("{name}.{index}");
+            }
+
+            let (base_color, base_color_texture) = match material.get("pbrMetallicRoughness") {
+                Some(pbr) => {
+                    let [r, g, b, a] = document.numbers(pbr, "baseColorFactor", [1.0; 4])?;
+                    let texture = match pbr.get("baseColorTexture") {
+                        Some(info) => match document.reference(info, "index", textures.len(), "texture")? {
+                            Some(texture) => document.reference(&textures[texture], "source", image_count, "image")?,
+                            None => return Err(LoadError::syntax(document.at(info), "missing \"index\" property")),
+                        },
+                        None => None,
+                    };
+
+                    (Vec4f::new(r, g, b, a), texture)
+                }
+                None => (Vec4f::new(1.0, 1.0, 1.0, 1.0), None),
+            };
+
+            materials.push(Material { name, base_color, base_color_texture });
+        }
+
+        Ok(materials)
+    }
+
+    /// Node local transform getting function
+    fn node_transform(&self, node: &Json) -> Result<Mat4x4f, LoadError> {
+        let document = self.document;
+
+        if node.get("matrix").is_some() {
+            // glTF matrices are column-major for column vectors, so they are row-major for row vectors, used here
+            return Ok(Mat4x4f::from(&document.numbers(node, "matrix", [0.0; 16])?));
+        }
+
+        let [tx, ty, tz] = document.numbers(node, "translation", [0.0; 3])?;
+        let [x, y, z, w] = document.numbers(node, "rotation", [0.0, 0.0, 0.0, 1.0])?;
+        let [sx, sy, sz] = document.numbers(node, "scale", [1.0; 3])?;
+
+        let rotation = Mat4x4f::from([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0],
+            [2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0],
+            [2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        Ok(Mat4x4f::scale(sx, sy, sz) * rotation * Mat4x4f::translate(tx, ty, tz))
+    }
+
+    /// Default scene loading function
+    /// * Returns sub-meshes of all mesh primitive instances
+    fn load_scene(&self) -> Result<Vec<SubMesh>, LoadError> {
+        let document = self.document;
+        let root = &document.root;
+        let nodes = document.objects(root, "nodes")?;
+        let meshes = document.objects(root, "meshes")?;
+        let scenes = document.objects(root, "scenes")?;
+
+        let node_index = |value: &Json| -> Result<usize, LoadError> {
+            let index = document.integer(value)?;
+            if index >= nodes.len() {
+                return Err(LoadError::index_range(document.at(value), format!("node {index} is out of range, {} defined", nodes.len())));
+            }
+            Ok(index)
+        };
+
+        let roots = match document.reference(root, "scene", scenes.len(), "scene")?.or(if scenes.is_empty() { None } else { Some(0) }) {
+            Some(scene) => document.items(&scenes[scene], "nodes")?.iter().map(node_index).collect::<Result<Vec<_>, _>>()?,
+            None => {
+                let mut is_child = vec![false; nodes.len()];
+                for node in nodes {
+                    for child in document.items(node, "children")? {
+                        is_child[node_index(child)?] = true;
+                    }
+                }
+                (0..nodes.len()).filter(|index| !is_child[*index]).collect()
+            }
+        };
+
+        let mut sub_meshes: Vec<SubMesh> = Vec::new();
+        let mut visited = vec![false; nodes.len()];
+        let mut stack = roots.into_iter().rev().map(|index| (index, Mat4x4f::identity())).collect::<Vec<_>>();
+
+        while let Some((index, parent_transform)) = stack.pop() {
+            let node = &nodes[index];
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(LoadError::syntax(document.at(node), format!("node {index} has several parents or is part of cycle")));
+            }
+
+            let transform = self.node_transform(node)? * parent_transform;
+
+            if let Some(mesh_index) = document.reference(node, "mesh", meshes.len(), "mesh")? {
+                let mesh = &meshes[mesh_index];
+                let mesh_name = document.string(mesh, "name")?;
+                let name = document.string(node, "name")?.or(mesh_name).unwrap_or("");
+
+                for primitive in document.objects(mesh, "primitives")? {
+                    let (primitive, material) = self.load_primitive(primitive)?;
+                    let first_face = sub_meshes.last().map_or(0, |sub_mesh| sub_mesh.faces.end);
+
+                    let mut sub_mesh = SubMesh::new(name, primitive);
+                    sub_mesh.groups = mesh_name.map(str::to_string).into_iter().collect();
+                    sub_mesh.material = material.map(|material| self.materials[material].name.clone());
+                    sub_mesh.faces = first_face..first_face + sub_mesh.faces.len();
+                    sub_mesh.transform = transform;
+                    sub_meshes.push(sub_mesh);
+                }
+            }
+
+            for child in document.items(node, "children")?.iter().rev() {
+                stack.push((node_index(child)?, transform));
+            }
+        }
+
+        Ok(sub_meshes)
+    }
+
+    /// Mesh primitive loading function
+    /// * `primitive` - glTF mesh primitive
+    /// * Returns primitive and index of its material
+    fn load_primitive(&self, primitive: &Json) -> Result<(render::Primitive, Option<usize>), LoadError> {
+        let document = self.document;
+        let attributes = document.field(primitive, "attributes")?;
+
+        let position_accessor = document.reference(attributes, "POSITION", self.accessors.len(), "accessor")?
+            .ok_or_else(|| LoadError::syntax(document.at(attributes), "primitive has no POSITION attribute"))?;
+        let positions = self.accessor(position_accessor, &["VEC3"])?.read_vec3();
+        let vertex_count = positions.len();
+
+        if let Some(index) = positions.iter().position(|position| !(position.x.is_finite() && position.y.is_finite() && position.z.is_finite())) {
+            return Err(LoadError::syntax(document.at(attributes.get("POSITION").unwrap()), format!("position {index} is not finite")));
+        }
+
+        let attribute = |name: &str, types: &[&str]| -> Result<Option<AccessorView>, LoadError> {
+            let Some(index) = document.reference(attributes, name, self.accessors.len(), "accessor")? else {
+                return Ok(None);
+            };
+
+            let view = self.accessor(index, types)?;
+            if view.count != vertex_count {
+                let position = document.at(attributes.get(name).unwrap());
+                return Err(LoadError::syntax(position, format!("{name} attribute has {} elements, but POSITION has {vertex_count}", view.count)));
+            }
+            Ok(Some(view))
+        };
+
+        let vertex_normals = attribute("NORMAL", &["VEC3"])?.map_or_else(Vec::new, |view| view.read_vec3());
+        let tex_coords = attribute("TEXCOORD_0", &["VEC2"])?.map_or_else(Vec::new, |view| {
+            (0..view.count).map(|element| Vec2f::new(view.get(element, 0), 1.0 - view.get(element, 1))).collect()
+        });
+
+        let material = document.reference(primitive, "material", self.materials.len(), "material")?;
+        let base_color = material.map_or(Vec4f::new(1.0, 1.0, 1.0, 1.0), |material| self.materials[material].base_color);
+        let colors = attribute("COLOR_0", &["VEC3", "VEC4"])?.map_or_else(Vec::new, |view| {
+            (0..view.count).map(|element| srgb_color(view.get(element, 0) * base_color.x, view.get(element, 1) * base_color.y, view.get(element, 2) * base_color.z)).collect()
+        });
+
+        let indices_json = primitive.get("indices");
+        let indices = match document.reference(primitive, "indices", self.accessors.len(), "accessor")? {
+            Some(index) => {
+                let view = self.accessor(index, &["SCALAR"])?;
+                if !matches!(view.component_type, COMPONENT_U8 | COMPONENT_U16 | COMPONENT_U32) {
+                    return Err(LoadError::syntax(document.at(&self.accessors[index]), "index accessor must have unsigned integer components"));
+                }
+                Some(view)
+            }
+            None => None,
+        };
+
+        let index_count = indices.as_ref().map_or(vertex_count, |view| view.count);
+        let vertex = |index: usize| -> Result<u32, LoadError> {
+            let vertex = indices.as_ref().map_or(index as u32, |view| view.index(index));
+            if vertex as usize >= vertex_count {
+                let position = indices_json.map_or(Position::Unknown, |json| document.at(json));
+                return Err(LoadError::index_range(position, format!("index {index} references vertex {vertex}, but there are {vertex_count} vertices")));
+            }
+            Ok(vertex)
+        };
+
+        let mut faces = render::Faces::new();
+        let mut normals = Vec::new();
+        let mut line_strips = render::LineStrips::new();
+        let mut points = Vec::new();
+
+        let mut add_triangle = |triangle: [u32; 3]| {
+            let normal_sum = triangle.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, index| match vertex_normals.get(*index as usize) {
+                Some(normal) => sum + *normal,
+                None => sum,
+            });
+
+            faces.push(normals.len() as u32, triangle);
+            normals.push(if normal_sum.length2() > 0.0 {
+                normal_sum.normalized()
+            } else {
+                polygon_normal(3, |index| positions[triangle[index] as usize])
+            });
+        };
+
+        let mode = document.optional_integer(primitive, "mode", MODE_TRIANGLES)?;
+        match mode {
+            MODE_POINTS => {
+                for index in 0..index_count {
+                    points.push(vertex(index)?);
+                }
+            }
+            MODE_LINES => {
+                for line in 0..index_count / 2 {
+                    line_strips.push([vertex(line * 2)?, vertex(line * 2 + 1)?]);
+                }
+            }
+            MODE_LINE_LOOP | MODE_LINE_STRIP if index_count >= 2 => {
+                let is_loop = mode == MODE_LINE_LOOP;
+                let mut strip = (0..index_count).map(vertex).collect::<Result<Vec<_>, _>>()?;
+                if is_loop {
+                    strip.push(strip[0]);
+                }
+                line_strips.push(strip);
+            }
+            MODE_LINE_LOOP | MODE_LINE_STRIP => {}
+            MODE_TRIANGLES => {
+                for triangle in 0..index_count / 3 {
+                    add_triangle([vertex(triangle * 3)?, vertex(triangle * 3 + 1)?, vertex(triangle * 3 + 2)?]);
+                }
+            }
+            MODE_TRIANGLE_STRIP => {
+                // Odd triangles are flipped to keep strip winding
+                for triangle in 0..index_count.saturating_sub(2) {
+                    let odd = triangle % 2;
+                    add_triangle([vertex(triangle)?, vertex(triangle + 1 + odd)?, vertex(triangle + 2 - odd)?]);
+                }
+            }
+            MODE_TRIANGLE_FAN => {
+                for triangle in 0..index_count.saturating_sub(2) {
+                    add_triangle([vertex(triangle + 1)?, vertex(triangle + 2)?, vertex(0)?]);
+                }
+            }
+            _ => {
+                let position = primitive.get("mode").map_or(Position::Unknown, |json| document.at(json));
+                return Err(LoadError::syntax(position, format!("invalid primitive mode {mode}")));
+            }
+        }
+
+        let primitive_error = |err: render::PrimitiveError| LoadError::syntax(document.at(primitive), err.to_string());
+
+        let color = srgb_color(base_color.x, base_color.y, base_color.z);
+        let mut result = render::Primitive::new(positions, normals, faces, color).map_err(primitive_error)?;
+        result.set_tex_coords(tex_coords).map_err(primitive_error)?;
+        result.set_colors(colors).map_err(primitive_error)?;
+        result.set_vertex_normals(vertex_normals).map_err(primitive_error)?;
+        if !line_strips.is_empty() {
+            result.set_line_strips(line_strips).map_err(primitive_error)?;
+        }
+        if !points.is_empty() {
+            result.set_points(points).map_err(primitive_error)?;
+        }
+
+        Ok((result, material))
+    }
+}
+
+/// Linear color to sRGB primitive color converting function
+/// * `r`, `g`, `b` - linear color channels in [0, 1] range
+/// * Returns color in `0xRRGGBB` format
+fn srgb_color(r: f32, g: f32, b: f32) -> u32 {
+    let channel = |value: f32| {
+        let value = value.clamp(0.0, 1.0);
+        let encoded = if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 };
+        (encoded * 255.0).round() as u32
+    };
+
+    (channel(r) << 16) | (channel(g) << 8) | channel(b)
+}
+
+/// Base64 decoding function
+/// * `text` - standard alphabet base64 text, padding is optional
+/// * Returns decoded data, None if text is not valid base64
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.strip_suffix("==").or_else(|| text.strip_suffix('=')).unwrap_or(text);
+    if text.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(text.len() / 4 * 3 + 2);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+
+        accumulator = (accumulator << 6 | value as u32) & 0xFFFF;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((accumulator >> bits) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+/// URI percent-encoding decoding function
+/// * `uri` - relative file URI
+/// * Returns decoded URI, invalid escapes are kept as is
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Image MIME type by URI getting function
+/// * `uri` - image data or file URI
+/// * Returns MIME type of data URI or of known image file extension
+fn uri_mime_type(uri: &str) -> Option<&str> {
+    if let Some(data_uri) = uri.strip_prefix("data:") {
+        return data_uri.split([';', ',']).next().filter(|mime_type| !mime_type.is_empty());
+    }
+
+    let extension = uri.rsplit_once('.')?.1;
+    if extension.eq_ignore_ascii_case("png") {
+        Some("image/png")
+    } else if extension.eq_ignore_ascii_case("jpg") || extension.eq_ignore_ascii_case("jpeg") {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Positions of triangle (0, 0, 0), (1, 0, 0), (0, 1, 0) as little-endian floats in base64
+    const TRIANGLE_BASE64: &str = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA";
+
+    /// Single triangle mesh glTF document building function
+    /// * `nodes` - JSON array of nodes, scene contains the first one
+    /// * `accessor` - JSON object of position accessor
+    /// * `buffer` - JSON object of position buffer
+    fn document(nodes: &str, accessor: &str, buffer: &str) -> String {
+        format!(
+            r#"{{"asset": {{"version": "2.0"}}, "scenes": [{{"nodes": [0]}}], "nodes": {nodes},
+                "meshes": [{{"name": "tri", "primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+                "accessors": [{accessor}], "bufferViews": [{{"buffer": 0, "byteLength": 36}}], "buffers": [{buffer}]}}"#
+        )
+    }
+
+    const ACCESSOR: &str = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}"#;
+
+    /// Data URI buffer of triangle positions
+    fn data_buffer() -> String {
+        format!(r#"{{"byteLength": 36, "uri": "data:application/octet-stream;base64,{TRIANGLE_BASE64}"}}"#)
+    }
+
+    /// GLB container building function
+    /// * `json` - JSON chunk contents
+    /// * `binary` - binary chunk contents
+    fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+
+        let mut data = GLB_MAGIC.to_vec();
+        data.extend(2u32.to_le_bytes());
+        data.extend(((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+        data.extend((json.len() as u32).to_le_bytes());
+        data.extend(GLB_CHUNK_JSON.to_le_bytes());
+        data.extend(json);
+        data.extend((binary.len() as u32).to_le_bytes());
+        data.extend(GLB_CHUNK_BIN.to_le_bytes());
+        data.extend(binary);
+        data
+    }
+
+    /// Sub-mesh world positions getting function
+    fn world_positions(sub_mesh: &SubMesh) -> Vec<[f32; 3]> {
+        sub_mesh.primitive.get_positions().iter().map(|position| {
+            let position = sub_mesh.transform.transform_point(*position);
+            [position.x, position.y, position.z].map(|value| (value * 1e4).round() / 1e4)
+        }).collect()
+    }
+
+    /// Reading error getting function
+    fn read_error(data: &[u8]) -> LoadError {
+        read_gltf(data, None).err().expect("invalid glTF is accepted")
+    }
+
+    #[test]
+    fn reads_data_uri_buffer() {
+        let model = read_gltf(document(r#"[{"mesh": 0}]"#, ACCESSOR, &data_buffer()).as_bytes(), None).unwrap();
+
+        assert_eq!(model.sub_meshes.len(), 1);
+        let sub_mesh = &model.sub_meshes[0];
+        assert_eq!((sub_mesh.name.as_str(), sub_mesh.groups.as_slice()), ("tri", ["tri".to_string()].as_slice()));
+        assert_eq!(sub_mesh.primitive.get_faces().len(), 1);
+        assert_eq!(world_positions(sub_mesh), vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert!(sub_mesh.primitive.get_normals()[0].z > 0.999);
+    }
+
+    #[test]
+    fn reads_glb_binary_chunk() {
+        let positions = decode_base64(TRIANGLE_BASE64).unwrap();
+        let json = document(r#"[{"mesh": 0}]"#, ACCESSOR, r#"{"byteLength": 36}"#);
+        let model = read_gltf(glb(&json, &positions).as_slice(), None).unwrap();
+
+        assert_eq!(world_positions(&model.sub_meshes[0]), vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn rejects_invalid_glb() {
+        let positions = decode_base64(TRIANGLE_BASE64).unwrap();
+        let data = glb(&document(r#"[{"mesh": 0}]"#, ACCESSOR, r#"{"byteLength": 36}"#), &positions);
+
+        let mut version = data.clone();
+        version[4] = 1;
+        assert!(matches!(read_error(&version), LoadError::Unsupported { .. }));
+
+        // Declared length exceeds file and chunk exceeds declared length
+        assert!(matches!(read_error(&data[..data.len() - 1]), LoadError::Syntax { message, .. } if message.contains("exceeds file size")));
+        let mut length = data.clone();
+        length[8] -= 1;
+        assert!(matches!(read_error(&length), LoadError::Syntax { message, .. } if message.contains("exceeds file")));
+
+        // Binary chunk goes first
+        let mut order = data.clone();
+        order[16..20].copy_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        match read_error(&order) {
+            LoadError::Syntax { location, message } => {
+                assert!(message.contains("must start with JSON chunk"));
+                assert!(matches!(location.position, Position::Binary { offset: 12 }));
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+
+        // Buffer without URI needs binary chunk
+        let json = document(r#"[{"mesh": 0}]"#, ACCESSOR, r#"{"byteLength": 36}"#);
+        assert!(matches!(read_error(json.as_bytes()), LoadError::Syntax { message, .. } if message.contains("isn't GLB binary chunk")));
+    }
+
+    #[test]
+    fn applies_node_transforms() {
+        // Child is rotated by 90 degrees around Z axis and moved along X, parent scales it twice
+        let nodes = r#"[
+            {"children": [1], "scale": [2, 2, 2]},
+            {"mesh": 0, "translation": [1, 0, 0], "rotation": [0, 0, 0.70710678, 0.70710678]}
+        ]"#;
+        let model = read_gltf(document(nodes, ACCESSOR, &data_buffer()).as_bytes(), None).unwrap();
+        assert_eq!(world_positions(&model.sub_meshes[0]), vec![[2.0, 0.0, 0.0], [2.0, 2.0, 0.0], [0.0, 0.0, 0.0]]);
+
+        // Column-major matrix with translation in the last column
+        let nodes = r#"[{"mesh": 0, "matrix": [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 5, 6, 7, 1]}]"#;
+        let model = read_gltf(document(nodes, ACCESSOR, &data_buffer()).as_bytes(), None).unwrap();
+        assert_eq!(world_positions(&model.sub_meshes[0]), vec![[5.0, 6.0, 7.0], [6.0, 6.0, 7.0], [5.0, 7.0, 7.0]]);
+
+        // Node can't be instanced twice
+        let nodes = r#"[{"children": [1, 1]}, {"mesh": 0}]"#;
+        assert!(matches!(read_error(document(nodes, ACCESSOR, &data_buffer()).as_bytes()), LoadError::Syntax { message, .. } if message.contains("several parents")));
+    }
+
+    #[test]
+    fn rejects_accessor_without_buffer_view() {
+        let accessor = r#"{"componentType": 5126, "count": 4000000000, "type": "VEC3"}"#;
+
+        match read_error(document(r#"[{"mesh": 0}]"#, accessor, &data_buffer()).as_bytes()) {
+            LoadError::Unsupported { feature, .. } => assert_eq!(feature, "accessor without buffer view"),
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn rejects_accessor_out_of_buffer_view() {
+        let accessor = r#"{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"}"#;
+        let error = read_error(document(r#"[{"mesh": 0}]"#, accessor, &data_buffer()).as_bytes());
+
+        assert!(matches!(error, LoadError::Syntax { message, .. } if message.contains("needs 48 bytes")));
+    }
+
+    #[test]
+    fn reports_json_errors_at_line_and_column() {
+        match read_error(b"{\n  \"asset\": [1,]\n}") {
+            LoadError::Syntax { location, .. } => assert!(matches!(location.position, Position::Text { line: 2, column: 15 })),
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TWE").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert_eq!(decode_base64("+/+/").unwrap(), [0xFB, 0xFF, 0xBF]);
+
+        assert!(decode_base64("T").is_none());
+        assert!(decode_base64("TWFuT").is_none());
+        assert!(decode_base64("TW=u").is_none());
+        assert!(decode_base64("TW u").is_none());
+    }
+
+    #[test]
+    fn decodes_uris() {
+        assert_eq!(percent_decode("my%20model%2Fbin.bin"), "my model/bin.bin");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(uri_mime_type("data:image/png;base64,AAAA"), Some("image/png"));
+        assert_eq!(uri_mime_type("textures/wall.JPG"), Some("image/jpeg"));
+        assert_eq!(uri_mime_type("textures/wall.ktx2"), None);
+    }
+}
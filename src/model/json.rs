@@ -0,0 +1,422 @@
+/// Maximal nesting depth of JSON arrays and objects, deeper documents are rejected instead of overflowing stack
+const MAX_DEPTH: usize = 256;
+
+/// JSON value
+pub(super) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Object members in source order
+    Object(Vec<(String, Json)>),
+}
+
+/// JSON value with its source offset
+pub(super) struct Json {
+    pub value: Value,
+    /// Byte offset of value start in source
+    pub offset: usize,
+}
+
+/// JSON syntax error
+pub(super) struct JsonError {
+    /// Byte offset of error in source
+    pub offset: usize,
+    pub message: String,
+}
+
+impl Json {
+    /// JSON document parsing function
+    /// * `data` - UTF-8 JSON text
+    /// * Returns root value, or error with source offset
+    pub fn parse(data: &[u8]) -> Result<Json, JsonError> {
+        let mut parser = Parser { data, offset: 0 };
+
+        parser.skip_whitespace();
+        let root = parser.value(0)?;
+        parser.skip_whitespace();
+
+        if parser.offset < data.len() {
+            return Err(parser.error("unexpected data after JSON value"));
+        }
+
+        Ok(root)
+    }
+
+    /// Object member getting function
+    /// * `key` - member name
+    /// * Returns member value, None if value isn't object or has no such member
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            Value::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Number getting function
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Boolean getting function
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// String getting function
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Array getting function
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match &self.value {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Value kind name for error messages getting function
+    pub fn kind_name(&self) -> &'static str {
+        match self.value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+/// Recursive descent JSON parser
+struct Parser<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Error at current offset create function
+    fn error(&self, message: impl Into<String>) -> JsonError {
+        JsonError { offset: self.offset, message: message.into() }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    /// Literal skipping function
+    /// * `literal` - expected source text
+    fn expect(&mut self, literal: &str) -> Result<(), JsonError> {
+        if self.data[self.offset..].starts_with(literal.as_bytes()) {
+            self.offset += literal.len();
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{literal}'")))
+        }
+    }
+
+    /// Value parsing function
+    /// * `depth` - number of arrays and objects, value is nested in
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        let offset = self.offset;
+        let value = match self.peek() {
+            Some(b'n') => { self.expect("null")?; Value::Null }
+            Some(b't') => { self.expect("true")?; Value::Bool(true) }
+            Some(b'f') => { self.expect("false")?; Value::Bool(false) }
+            Some(b'"') => Value::String(self.string()?),
+            Some(b'-' | b'0'..=b'9') => Value::Number(self.number()?),
+            Some(b'[') => {
+                if depth >= MAX_DEPTH {
+                    return Err(self.error("JSON nesting is too deep"));
+                }
+                Value::Array(self.sequence(b']', |parser| parser.value(depth + 1))?)
+            }
+            Some(b'{') => {
+                if depth >= MAX_DEPTH {
+                    return Err(self.error("JSON nesting is too deep"));
+                }
+                Value::Object(self.sequence(b'}', |parser| {
+                    if parser.peek() != Some(b'"') {
+                        return Err(parser.error("expected member name"));
+                    }
+                    let name = parser.string()?;
+                    parser.skip_whitespace();
+                    parser.expect(":")?;
+                    parser.skip_whitespace();
+                    Ok((name, parser.value(depth + 1)?))
+                })?)
+            }
+            Some(_) => return Err(self.error("expected JSON value")),
+            None => return Err(self.error("unexpected end of JSON")),
+        };
+
+        Ok(Json { value, offset })
+    }
+
+    /// Comma separated array items or object members parsing function
+    /// * `end` - closing bracket
+    /// * `item` - item parser, called at item start
+    fn sequence<T>(&mut self, end: u8, mut item: impl FnMut(&mut Self) -> Result<T, JsonError>) -> Result<Vec<T>, JsonError> {
+        let mut items = Vec::new();
+
+        self.offset += 1;
+        self.skip_whitespace();
+        if self.peek() == Some(end) {
+            self.offset += 1;
+            return Ok(items);
+        }
+
+        loop {
+            items.push(item(self)?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => {
+                    self.offset += 1;
+                    self.skip_whitespace();
+                }
+                Some(c) if c == end => {
+                    self.offset += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error(format!("expected ',' or '{}'", end as char))),
+            }
+        }
+    }
+
+    /// Number parsing function, JSON number grammar is checked before conversion
+    fn number(&mut self) -> Result<f64, JsonError> {
+        let start = self.offset;
+        let digits = |parser: &mut Self| {
+            let digits_start = parser.offset;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.offset += 1;
+            }
+            parser.offset > digits_start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.offset += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.offset += 1;
+        } else if !digits(self) {
+            return Err(self.error("expected digit"));
+        }
+        if self.peek() == Some(b'.') {
+            self.offset += 1;
+            if !digits(self) {
+                return Err(self.error("expected digit"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.offset += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.offset += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected digit"));
+            }
+        }
+
+        // Number grammar is ASCII-only, so slice is valid UTF-8 and float syntax
+        std::str::from_utf8(&self.data[start..self.offset])
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .ok_or_else(|| JsonError { offset: start, message: "invalid number".to_string() })
+    }
+
+    /// String parsing function, offset is at opening quote
+    fn string(&mut self) -> Result<String, JsonError> {
+        let mut bytes = Vec::new();
+
+        self.offset += 1;
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+
+            match c {
+                b'"' => {
+                    self.offset += 1;
+                    break;
+                }
+                b'\\' => {
+                    self.offset += 1;
+                    let escaped = match self.peek() {
+                        Some(b'u') => {
+                            self.offset += 1;
+                            self.unicode_escape()?
+                        }
+                        Some(c) => {
+                            let escaped = match c {
+                                b'"' => '"',
+                                b'\\' => '\\',
+                                b'/' => '/',
+                                b'b' => '\u{8}',
+                                b'f' => '\u{c}',
+                                b'n' => '\n',
+                                b'r' => '\r',
+                                b't' => '\t',
+                                _ => return Err(self.error("invalid escape sequence")),
+                            };
+                            self.offset += 1;
+                            escaped
+                        }
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                0..=0x1F => return Err(self.error("control character in string")),
+                _ => {
+                    bytes.push(c);
+                    self.offset += 1;
+                }
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    /// Hexadecimal code unit of `\u` escape parsing function
+    fn code_unit(&mut self) -> Result<u32, JsonError> {
+        let digits = self.data.get(self.offset..self.offset + 4).ok_or_else(|| self.error("expected 4 hexadecimal digits"))?;
+        let unit = std::str::from_utf8(digits)
+            .ok()
+            .filter(|digits| digits.bytes().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("expected 4 hexadecimal digits"))?;
+
+        self.offset += 4;
+        Ok(unit)
+    }
+
+    /// `\u` escape parsing function, offset is after `\u`
+    /// * Returns escaped character, surrogate pairs are combined
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let start = self.offset - 2;
+        let mut code = self.code_unit()?;
+
+        if (0xD800..0xDC00).contains(&code) && self.data[self.offset..].starts_with(b"\\u") {
+            self.offset += 2;
+            let low = self.code_unit()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(JsonError { offset: start, message: "invalid surrogate pair".to_string() });
+            }
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+        }
+
+        char::from_u32(code).ok_or_else(|| JsonError { offset: start, message: "unpaired surrogate in string".to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Successful parsing function
+    fn parse(text: &str) -> Json {
+        Json::parse(text.as_bytes()).unwrap_or_else(|error| panic!("{text}: {} at {}", error.message, error.offset))
+    }
+
+    /// Failed parsing function
+    /// * Returns error offset and message
+    fn parse_error(text: &str) -> (usize, String) {
+        match Json::parse(text.as_bytes()) {
+            Ok(_) => panic!("{text} is accepted"),
+            Err(error) => (error.offset, error.message),
+        }
+    }
+
+    #[test]
+    fn parses_values_with_offsets() {
+        let root = parse(r#" { "a" : [1, -2.5e1, 0.25, true, false, null], "b": {"c": "d"}, "e": [] } "#);
+
+        assert_eq!(root.offset, 1);
+        assert_eq!(root.kind_name(), "object");
+        let items = root.get("a").unwrap().as_array().unwrap();
+        assert_eq!(items.iter().map(Json::as_f64).collect::<Vec<_>>(), vec![Some(1.0), Some(-25.0), Some(0.25), None, None, None]);
+        assert_eq!((items[3].as_bool(), items[4].as_bool()), (Some(true), Some(false)));
+        assert_eq!(items[5].kind_name(), "null");
+        assert_eq!(items[1].offset, 13);
+        assert_eq!(root.get("b").unwrap().get("c").unwrap().as_str(), Some("d"));
+        assert!(root.get("e").unwrap().as_array().unwrap().is_empty());
+        assert!(root.get("f").is_none());
+        assert!(items[0].get("a").is_none());
+    }
+
+    #[test]
+    fn keeps_member_order() {
+        let Value::Object(members) = parse(r#"{"z": 1, "a": 2, "z": 3}"#).value else {
+            panic!("object is expected");
+        };
+
+        assert_eq!(members.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["z", "a", "z"]);
+    }
+
+    #[test]
+    fn decodes_string_escapes() {
+        let text = parse(r#""q\" b\\ s\/ \b\f\n\r\t Aé 😀 ы""#);
+
+        assert_eq!(text.as_str(), Some("q\" b\\ s/ \u{8}\u{c}\n\r\t Aé 😀 ы"));
+    }
+
+    #[test]
+    fn rejects_invalid_strings() {
+        assert_eq!(parse_error(r#""abc"#).1, "unterminated string");
+        assert_eq!(parse_error("\"a\nb\"").1, "control character in string");
+        assert_eq!(parse_error(r#""\x""#).1, "invalid escape sequence");
+        assert_eq!(parse_error(r#""\u12G4""#).1, "expected 4 hexadecimal digits");
+        assert_eq!(parse_error(r#""\ud83d""#), (1, "unpaired surrogate in string".to_string()));
+        assert_eq!(parse_error(r#""\ud83dA""#), (1, "unpaired surrogate in string".to_string()));
+        assert_eq!(parse_error(r#""\ud83d\u0041""#), (1, "invalid surrogate pair".to_string()));
+        assert!(matches!(Json::parse(b"\"\xFF\""), Err(JsonError { message, .. }) if message == "invalid UTF-8 in string"));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        for (text, offset) in [("-", 1), ("+1", 0), ("1.", 2), (".5", 0), ("1e", 2), ("1e+", 3), ("-x", 1)] {
+            assert_eq!(parse_error(text).0, offset, "{text}");
+        }
+
+        // Leading zero ends number
+        assert_eq!(parse_error("01"), (1, "unexpected data after JSON value".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_structure() {
+        assert_eq!(parse_error(""), (0, "unexpected end of JSON".to_string()));
+        assert_eq!(parse_error("[1,]"), (3, "expected JSON value".to_string()));
+        assert_eq!(parse_error("[1 2]"), (3, "expected ',' or ']'".to_string()));
+        assert_eq!(parse_error(r#"{"a" 1}"#), (5, "expected ':'".to_string()));
+        assert_eq!(parse_error("{1: 2}"), (1, "expected member name".to_string()));
+        assert_eq!(parse_error("nul"), (0, "expected 'null'".to_string()));
+        assert_eq!(parse_error("{} {}"), (3, "unexpected data after JSON value".to_string()));
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+        parse(&nested(MAX_DEPTH));
+        assert_eq!(parse_error(&nested(MAX_DEPTH + 1)), (MAX_DEPTH, "JSON nesting is too deep".to_string()));
+    }
+}
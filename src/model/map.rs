@@ -0,0 +1,781 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use crate::render;
+use super::error::*;
+
+/// Half-size of initial face polygon, must exceed any map coordinate
+const BASE_WINDING_SIZE: f64 = 65536.0;
+
+/// Distance, under which points are considered lying on plane
+const PLANE_EPSILON: f64 = 0.01;
+
+/// Brush face texture mapping
+#[derive(Copy, Clone)]
+pub enum TextureMapping {
+    /// Standard Quake mapping, axes are derived from face plane
+    Standard {
+        offset: Vec2<f64>,
+        rotation: f64,
+        scale: Vec2<f64>,
+    },
+    /// Valve 220 mapping with explicit texture axes
+    Valve {
+        u_axis: Vec3<f64>,
+        v_axis: Vec3<f64>,
+        offset: Vec2<f64>,
+        scale: Vec2<f64>,
+    },
+}
+
+/// Single brush face, defined by three plane points
+#[derive(Clone)]
+pub struct BrushFace {
+    pub points: [Vec3<f64>; 3],
+    pub texture: String,
+    pub mapping: TextureMapping,
+}
+
+/// Convex brush, intersection of face half-spaces
+#[derive(Clone)]
+pub struct Brush {
+    pub faces: Vec<BrushFace>,
+}
+
+/// Map entity
+#[derive(Clone)]
+pub struct Entity {
+    /// Key-value pairs in file order
+    pub properties: Vec<(String, String)>,
+    pub brushes: Vec<Brush>,
+}
+
+impl Entity {
+    /// Property getting function
+    /// * `key` - property key
+    /// * Returns value of first property with key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Brush to polygon conversion options
+pub struct MapOptions {
+    /// Quake unit size in viewer space
+    pub scale: f32,
+    /// Texture sizes by name, used to normalize texture coordinates. Missing textures get coordinates in texels.
+    pub texture_sizes: HashMap<String, (u32, u32)>,
+    /// Merge adjacent coplanar faces with same texture mapping into convex polygons
+    pub merge_coplanar: bool,
+    /// Remove faces, completely hidden inside other brushes
+    pub remove_hidden: bool,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            texture_sizes: HashMap::new(),
+            merge_coplanar: false,
+            remove_hidden: false,
+        }
+    }
+}
+
+/// Quake .map file contents
+pub struct MapFile {
+    pub entities: Vec<Entity>,
+}
+
+/// Whitespace-separated token reader
+struct Tokenizer<'a> {
+    text: &'a str,
+    /// Current line number
+    line: usize,
+    /// Length of text, that remained at current line start, used to compute columns
+    line_rest: usize,
+    /// Was last token quoted
+    quoted: bool,
+    /// Position of last token
+    position: Position,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Tokenizer create function
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            line: 1,
+            line_rest: text.len(),
+            quoted: false,
+            position: Position::Text { line: 1, column: 1 },
+        }
+    }
+
+    /// Current position getting function
+    fn current_position(&self) -> Position {
+        Position::Text { line: self.line, column: self.line_rest - self.text.len() + 1 }
+    }
+
+    /// Next token getting function
+    /// * Returns next token, quoted strings are returned without quotes
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            let trimmed = self.text.trim_start_matches(|c: char| c.is_whitespace() && c != '\n');
+
+            if let Some(rest) = trimmed.strip_prefix('\n') {
+                self.line += 1;
+                self.line_rest = rest.len();
+                self.text = rest;
+            } else if trimmed.starts_with("//") {
+                self.text = &trimmed[trimmed.find('\n').unwrap_or(trimmed.len())..];
+            } else {
+                self.text = trimmed;
+                break;
+            }
+        }
+
+        if self.text.is_empty() {
+            return None;
+        }
+
+        self.position = self.current_position();
+        self.quoted = self.text.starts_with('"');
+
+        if let Some(rest) = self.text.strip_prefix('"') {
+            let end = rest.find('"').unwrap_or(rest.len());
+            let value = &rest[..end];
+
+            self.text = rest.get(end + 1..).unwrap_or("");
+            if let Some(newline) = value.rfind('\n') {
+                self.line += value.matches('\n').count();
+                self.line_rest = rest.len() - newline - 1;
+            }
+            return Some(value);
+        }
+
+        let end = self.text.find(char::is_whitespace).unwrap_or(self.text.len());
+        let token = &self.text[..end];
+        self.text = &self.text[end..];
+        Some(token)
+    }
+
+    /// Required token getting function
+    fn expect_any(&mut self) -> Result<&'a str, LoadError> {
+        self.next().ok_or_else(|| LoadError::syntax(self.current_position(), "unexpected end of file"))
+    }
+
+    /// Exact token reading function
+    fn expect(&mut self, expected: &str) -> Result<(), LoadError> {
+        let token = self.expect_any()?;
+
+        if token != expected {
+            return Err(LoadError::syntax(self.position, format!("expected '{expected}', got '{token}'")));
+        }
+        Ok(())
+    }
+
+    /// Number reading function
+    fn number(&mut self) -> Result<f64, LoadError> {
+        let token = self.expect_any()?;
+
+        match token.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(LoadError::syntax(self.position, format!("expected finite number, got '{token}'"))),
+        }
+    }
+
+    /// Parenthesized point reading function
+    fn point(&mut self) -> Result<Vec3<f64>, LoadError> {
+        self.expect("(")?;
+        let point = Vec3::new(self.number()?, self.number()?, self.number()?);
+        self.expect(")")?;
+        Ok(point)
+    }
+
+    /// Bracketed Valve 220 texture axis reading function
+    fn axis(&mut self) -> Result<(Vec3<f64>, f64), LoadError> {
+        self.expect("[")?;
+        let axis = Vec3::new(self.number()?, self.number()?, self.number()?);
+        let offset = self.number()?;
+        self.expect("]")?;
+        Ok((axis, offset))
+    }
+}
+
+/// Face polygon, built from brush face
+#[derive(Clone)]
+struct Polygon {
+    points: Vec<Vec3<f64>>,
+    normal: Vec3<f64>,
+    distance: f64,
+    brush: usize,
+    face: BrushFace,
+}
+
+/// Quake standard texture axes, first vector of each triple is plane normal to compare with
+const BASE_AXES: [[[f64; 3]; 3]; 6] = [
+    [[ 0.0,  0.0,  1.0], [1.0, 0.0, 0.0], [0.0, -1.0,  0.0]],
+    [[ 0.0,  0.0, -1.0], [1.0, 0.0, 0.0], [0.0, -1.0,  0.0]],
+    [[ 1.0,  0.0,  0.0], [0.0, 1.0, 0.0], [0.0,  0.0, -1.0]],
+    [[-1.0,  0.0,  0.0], [0.0, 1.0, 0.0], [0.0,  0.0, -1.0]],
+    [[ 0.0,  1.0,  0.0], [1.0, 0.0, 0.0], [0.0,  0.0, -1.0]],
+    [[ 0.0, -1.0,  0.0], [1.0, 0.0, 0.0], [0.0,  0.0, -1.0]],
+];
+
+impl BrushFace {
+    /// Face plane getting function
+    /// * Returns outward plane normal and distance (n ^ p = distance on plane), None if points are collinear
+    fn plane(&self) -> Option<(Vec3<f64>, f64)> {
+        let [p0, p1, p2] = self.points;
+        let normal = (p0 - p1) % (p2 - p1);
+        let length = normal.length();
+
+        if length <= f64::EPSILON {
+            return None;
+        }
+
+        let normal = normal / length;
+        Some((normal, normal ^ p1))
+    }
+
+    /// Texture space axes getting function
+    /// * `normal` - face normal
+    /// * Returns U and V axes with offsets, both in texels per unit
+    fn texture_axes(&self, normal: &Vec3<f64>) -> ((Vec3<f64>, f64), (Vec3<f64>, f64)) {
+        let scale = |scale: f64| if scale == 0.0 { 1.0 } else { scale };
+
+        match self.mapping {
+            TextureMapping::Valve { u_axis, v_axis, offset, scale: axis_scale } => (
+                (u_axis / scale(axis_scale.x), offset.x),
+                (v_axis / scale(axis_scale.y), offset.y),
+            ),
+            TextureMapping::Standard { offset, rotation, scale: axis_scale } => {
+                let mut best = 0;
+                let mut best_dot = 0.0;
+
+                for (i, axes) in BASE_AXES.iter().enumerate() {
+                    let dot = normal.x * axes[0][0] + normal.y * axes[0][1] + normal.z * axes[0][2];
+                    if dot > best_dot {
+                        best_dot = dot;
+                        best = i;
+                    }
+                }
+
+                let mut axes = [BASE_AXES[best][1], BASE_AXES[best][2]];
+
+                // Rotate axes in plane of their nonzero components
+                let (sin, cos) = rotation.to_radians().sin_cos();
+                let s_component = axes[0].iter().position(|v| *v != 0.0).unwrap_or(0);
+                let t_component = axes[1].iter().position(|v| *v != 0.0).unwrap_or(0);
+
+                for axis in &mut axes {
+                    let (s, t) = (axis[s_component], axis[t_component]);
+                    axis[s_component] = cos * s - sin * t;
+                    axis[t_component] = sin * s + cos * t;
+                }
+
+                let to_vec = |axis: [f64; 3]| Vec3::new(axis[0], axis[1], axis[2]);
+
+                (
+                    (to_vec(axes[0]) / scale(axis_scale.x), offset.x),
+                    (to_vec(axes[1]) / scale(axis_scale.y), offset.y),
+                )
+            },
+        }
+    }
+}
+
+/// Convex polygon by plane clipping function
+/// * `points` - polygon to clip
+/// * `normal`, `distance` - clip plane
+/// * Returns part of polygon behind plane
+fn clip_polygon(points: &[Vec3<f64>], normal: &Vec3<f64>, distance: f64) -> Vec<Vec3<f64>> {
+    let mut result = Vec::with_capacity(points.len() + 1);
+
+    for (i, point) in points.iter().enumerate() {
+        let next = &points[(i + 1) % points.len()];
+        let point_distance = (*normal ^ *point) - distance;
+        let next_distance = (*normal ^ *next) - distance;
+
+        if point_distance <= PLANE_EPSILON {
+            result.push(*point);
+        }
+
+        if (point_distance > PLANE_EPSILON && next_distance < -PLANE_EPSILON) || (point_distance < -PLANE_EPSILON && next_distance > PLANE_EPSILON) {
+            let t = point_distance / (point_distance - next_distance);
+            result.push(*point + (*next - *point) * t);
+        }
+    }
+
+    result
+}
+
+/// Huge square on plane getting function, vertices are counter-clockwise if viewed from normal direction
+fn base_winding(normal: &Vec3<f64>, distance: f64) -> Vec<Vec3<f64>> {
+    let up = if normal.z.abs() > normal.x.abs() && normal.z.abs() > normal.y.abs() {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 0.0, 1.0)
+    };
+    let u = (up - *normal * (up ^ *normal)).normalized() * BASE_WINDING_SIZE;
+    let v = *normal % u;
+    let center = *normal * distance;
+
+    vec![center - u - v, center + u - v, center + u + v, center - u + v]
+}
+
+impl MapFile {
+    /// Map file loading function
+    /// * `path` - path to .map file
+    /// * Returns parsed map
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        load_file(path.as_ref(), Self::read)
+    }
+
+    /// Map reading function, supports both standard Quake and Valve 220 face formats
+    /// * `reader` - map source
+    /// * Returns parsed map, or error with line and column
+    pub fn read(reader: impl Read) -> Result<Self, LoadError> {
+        Self::parse(&read_text(reader)?)
+    }
+
+    /// Map text parsing function
+    /// * `text` - map file contents
+    /// * Returns parsed map
+    fn parse(text: &str) -> Result<Self, LoadError> {
+        let mut tokens = Tokenizer::new(text);
+        let mut entities = Vec::new();
+
+        while let Some(token) = tokens.next() {
+            if token != "{" {
+                return Err(LoadError::syntax(tokens.position, format!("expected entity, got '{token}'")));
+            }
+
+            let mut entity = Entity { properties: Vec::new(), brushes: Vec::new() };
+
+            loop {
+                let token = tokens.expect_any()?;
+                let position = tokens.position;
+
+                match token {
+                    "}" => break,
+                    "{" => entity.brushes.push(Self::parse_brush(&mut tokens)?),
+                    key => {
+                        if !tokens.quoted {
+                            return Err(LoadError::syntax(position, format!("expected property, brush or '}}', got '{key}'")));
+                        }
+                        let value = tokens.expect_any()?;
+                        entity.properties.push((key.to_string(), value.to_string()));
+                    },
+                }
+            }
+
+            entities.push(entity);
+        }
+
+        Ok(Self { entities })
+    }
+
+    /// Brush parsing function, brush opening brace is expected to be already read
+    fn parse_brush(tokens: &mut Tokenizer) -> Result<Brush, LoadError> {
+        let mut faces = Vec::new();
+
+        loop {
+            let token = tokens.expect_any()?;
+
+            if token == "}" {
+                break;
+            }
+            if token != "(" {
+                return Err(LoadError::syntax(tokens.position, format!("expected face or '}}', got '{token}'")));
+            }
+
+            let first = Vec3::new(tokens.number()?, tokens.number()?, tokens.number()?);
+            tokens.expect(")")?;
+            let points = [first, tokens.point()?, tokens.point()?];
+            let texture = tokens.expect_any()?.to_string();
+
+            // Valve 220 axes are enclosed in brackets
+            let mapping = if tokens.text.trim_start().starts_with('[') {
+                let (u_axis, u_offset) = tokens.axis()?;
+                let (v_axis, v_offset) = tokens.axis()?;
+                let _rotation = tokens.number()?;
+
+                TextureMapping::Valve {
+                    u_axis,
+                    v_axis,
+                    offset: Vec2::new(u_offset, v_offset),
+                    scale: Vec2::new(tokens.number()?, tokens.number()?),
+                }
+            } else {
+                TextureMapping::Standard {
+                    offset: Vec2::new(tokens.number()?, tokens.number()?),
+                    rotation: tokens.number()?,
+                    scale: Vec2::new(tokens.number()?, tokens.number()?),
+                }
+            };
+
+            faces.push(BrushFace { points, texture, mapping });
+        }
+
+        Ok(Brush { faces })
+    }
+
+    /// Worldspawn entity getting function
+    /// * Returns first entity, which is worldspawn in valid maps
+    pub fn get_worldspawn(&self) -> Option<&Entity> {
+        self.entities.first()
+    }
+
+    /// Player start location getting function
+    /// * `scale` - Quake unit size in viewer space
+    /// * Returns origin of first info_player_start entity in viewer space
+    pub fn get_player_start(&self, scale: f32) -> Option<Vec3f> {
+        let origin = self.entities
+            .iter()
+            .find(|entity| entity.get("classname") == Some("info_player_start"))?
+            .get("origin")?;
+        let mut coordinates = origin.split_whitespace().map(|value| value.parse::<f32>().ok());
+
+        Some(super::bsp::to_viewer_space(Vec3f::new(coordinates.next()??, coordinates.next()??, coordinates.next()??), scale))
+    }
+
+    /// Brush polygons building function
+    /// * Returns polygon for every brush face that has non-zero area
+    fn build_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = Vec::new();
+
+        for (brush_index, brush) in self.entities.iter().flat_map(|entity| entity.brushes.iter()).enumerate() {
+            let planes = brush.faces.iter().map(|face| face.plane()).collect::<Vec<_>>();
+
+            for (face_index, face) in brush.faces.iter().enumerate() {
+                let Some((normal, distance)) = planes[face_index] else {
+                    continue;
+                };
+
+                let mut points = base_winding(&normal, distance);
+
+                for (clip_index, clip_plane) in planes.iter().enumerate() {
+                    let Some((clip_normal, clip_distance)) = clip_plane else {
+                        continue;
+                    };
+
+                    if clip_index != face_index {
+                        points = clip_polygon(&points, clip_normal, *clip_distance);
+                    }
+                    if points.len() < 3 {
+                        break;
+                    }
+                }
+
+                if points.len() >= 3 {
+                    polygons.push(Polygon { points, normal, distance, brush: brush_index, face: face.clone() });
+                }
+            }
+        }
+
+        polygons
+    }
+
+    /// Map geometry building function
+    /// * `options` - conversion options
+    /// * Returns primitive, made of all entity brush faces, or error if brush coordinates are not finite
+    pub fn build_primitive(&self, options: &MapOptions) -> Result<render::Primitive, render::PrimitiveError> {
+        let polygons = self.build_polygons();
+        let brush_planes = self.entities
+            .iter()
+            .flat_map(|entity| entity.brushes.iter())
+            .map(|brush| brush.faces.iter().filter_map(|face| face.plane()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut polygons = polygons
+            .into_iter()
+            .filter(|polygon| !options.remove_hidden || !is_hidden(polygon, &brush_planes))
+            .collect::<Vec<_>>();
+
+        if options.merge_coplanar {
+            polygons = merge_coplanar(polygons);
+        }
+
+        let mut positions = Vec::new();
+        let mut tex_coords = Vec::new();
+        let mut normals = Vec::with_capacity(polygons.len());
+        let mut faces = render::Faces::with_capacity(polygons.len(), polygons.iter().map(|polygon| polygon.points.len()).sum());
+
+        for polygon in &polygons {
+            let ((u_axis, u_offset), (v_axis, v_offset)) = polygon.face.texture_axes(&polygon.normal);
+            let (width, height) = options.texture_sizes
+                .get(&polygon.face.texture)
+                .map_or((1.0, 1.0), |(width, height)| (*width as f64, *height as f64));
+
+            let first_vertex = positions.len() as u32;
+            for point in &polygon.points {
+                positions.push(super::bsp::to_viewer_space(Vec3f::new(point.x as f32, point.y as f32, point.z as f32), options.scale));
+                tex_coords.push(Vec2f::new(
+                    (((*point ^ u_axis) + u_offset) / width) as f32,
+                    (((*point ^ v_axis) + v_offset) / height) as f32,
+                ));
+            }
+
+            faces.push(normals.len() as u32, first_vertex..positions.len() as u32);
+            normals.push(super::bsp::to_viewer_space(Vec3f::new(polygon.normal.x as f32, polygon.normal.y as f32, polygon.normal.z as f32), 1.0));
+        }
+
+        let mut primitive = render::Primitive::new(positions, normals, faces, 0x00FF00)?;
+        primitive.set_tex_coords(tex_coords)?;
+
+        Ok(primitive)
+    }
+}
+
+/// Polygon hiding by other brushes checking function. Polygons are checked as a whole, partially hidden ones are kept.
+/// * `polygon` - polygon to check
+/// * `brush_planes` - planes of every brush
+/// * Returns true if polygon is inside (or on boundary of) some other brush
+fn is_hidden(polygon: &Polygon, brush_planes: &[Vec<(Vec3<f64>, f64)>]) -> bool {
+    brush_planes.iter().enumerate().any(|(brush, planes)| {
+        if brush == polygon.brush || planes.is_empty() {
+            return false;
+        }
+
+        let is_inside = polygon.points.iter().all(|point| {
+            planes.iter().all(|(normal, distance)| (*normal ^ *point) - distance <= PLANE_EPSILON)
+        });
+
+        // Faces, that lie on same-facing face of other brush, are visible, so keep one of them
+        let is_shared = planes.iter().any(|(normal, distance)| {
+            (*normal ^ polygon.normal) > 1.0 - 1e-6 && (distance - polygon.distance).abs() <= PLANE_EPSILON
+        });
+
+        is_inside && (!is_shared || brush < polygon.brush)
+    })
+}
+
+/// Edge end point key, points closer than PLANE_EPSILON to each other usually get the same one
+fn point_key(point: &Vec3<f64>) -> (i64, i64, i64) {
+    let key = |value: f64| (value / PLANE_EPSILON).round() as i64;
+    (key(point.x), key(point.y), key(point.z))
+}
+
+/// Coplanar polygons with same texture merging function.
+///
+/// Merge candidates are found by edge map, so every polygon is checked against its edge neighbours only.
+/// Merged polygon is queued again, so it can absorb more neighbours.
+/// * `polygons` - polygons to merge
+/// * Returns polygons, where every pair of merged polygons is replaced with its convex union
+fn merge_coplanar(polygons: Vec<Polygon>) -> Vec<Polygon> {
+    let mut polygons = polygons.into_iter().map(Some).collect::<Vec<_>>();
+
+    // Directed edge to polygons, that contain it. Entries of merged polygons stay, and are rejected by try_merge.
+    let mut edges = HashMap::<((i64, i64, i64), (i64, i64, i64)), Vec<usize>>::new();
+    let add_edges = |edges: &mut HashMap<_, Vec<usize>>, index: usize, points: &[Vec3<f64>]| {
+        for (i, point) in points.iter().enumerate() {
+            let next = &points[(i + 1) % points.len()];
+            edges.entry((point_key(point), point_key(next))).or_default().push(index);
+        }
+    };
+
+    for (index, polygon) in polygons.iter().enumerate() {
+        if let Some(polygon) = polygon {
+            add_edges(&mut edges, index, &polygon.points);
+        }
+    }
+
+    let mut queue = (0..polygons.len()).rev().collect::<Vec<_>>();
+
+    while let Some(index) = queue.pop() {
+        let Some(polygon) = &polygons[index] else {
+            continue;
+        };
+
+        let merge = polygon.points.iter().enumerate().find_map(|(i, point)| {
+            let next = &polygon.points[(i + 1) % polygon.points.len()];
+
+            edges.get(&(point_key(next), point_key(point)))?
+                .iter()
+                .filter(|other| **other != index)
+                .find_map(|other| {
+                    let points = try_merge(polygon, polygons[*other].as_ref()?)?;
+                    Some((*other, points))
+                })
+        });
+
+        if let Some((other, points)) = merge {
+            polygons[other] = None;
+            add_edges(&mut edges, index, &points);
+            polygons[index].as_mut().expect("merged polygon exists").points = points;
+            queue.push(index);
+        }
+    }
+
+    polygons.into_iter().flatten().collect()
+}
+
+/// Two polygons merging function
+/// * Returns merged polygon points if polygons are coplanar, share an edge, have same texture mapping and result is convex
+fn try_merge(lhs: &Polygon, rhs: &Polygon) -> Option<Vec<Vec3<f64>>> {
+    if (lhs.normal ^ rhs.normal) < 1.0 - 1e-6
+        || (lhs.distance - rhs.distance).abs() > PLANE_EPSILON
+        || lhs.face.texture != rhs.face.texture
+        || !is_same_mapping(lhs, rhs) {
+        return None;
+    }
+
+    let is_same = |a: &Vec3<f64>, b: &Vec3<f64>| (*a - *b).length2() <= PLANE_EPSILON * PLANE_EPSILON;
+
+    // Find edge i -> i + 1 of lhs, that is j + 1 -> j edge of rhs
+    for i in 0..lhs.points.len() {
+        let (a, b) = (&lhs.points[i], &lhs.points[(i + 1) % lhs.points.len()]);
+
+        for j in 0..rhs.points.len() {
+            let (c, d) = (&rhs.points[j], &rhs.points[(j + 1) % rhs.points.len()]);
+
+            if !is_same(a, d) || !is_same(b, c) {
+                continue;
+            }
+
+            // lhs from b around to a, then rhs from a (j + 1) around to b (j) exclusive
+            let mut points = Vec::with_capacity(lhs.points.len() + rhs.points.len() - 2);
+            for k in 0..lhs.points.len() {
+                points.push(lhs.points[(i + 1 + k) % lhs.points.len()]);
+            }
+            for k in 2..rhs.points.len() {
+                points.push(rhs.points[(j + k) % rhs.points.len()]);
+            }
+
+            return remove_collinear(&points, &lhs.normal).filter(|points| points.len() >= 3);
+        }
+    }
+
+    None
+}
+
+/// Polygon texture mappings equality checking function
+fn is_same_mapping(lhs: &Polygon, rhs: &Polygon) -> bool {
+    let ((lhs_u, lhs_u_offset), (lhs_v, lhs_v_offset)) = lhs.face.texture_axes(&lhs.normal);
+    let ((rhs_u, rhs_u_offset), (rhs_v, rhs_v_offset)) = rhs.face.texture_axes(&rhs.normal);
+
+    (lhs_u - rhs_u).length2() <= 1e-12
+        && (lhs_v - rhs_v).length2() <= 1e-12
+        && (lhs_u_offset - rhs_u_offset).abs() <= 1e-6
+        && (lhs_v_offset - rhs_v_offset).abs() <= 1e-6
+}
+
+/// Collinear points removal and convexity check function
+/// * Returns polygon without collinear points, None if polygon is not convex
+fn remove_collinear(points: &[Vec3<f64>], normal: &Vec3<f64>) -> Option<Vec<Vec3<f64>>> {
+    let mut result = Vec::with_capacity(points.len());
+
+    for i in 0..points.len() {
+        let previous = points[(i + points.len() - 1) % points.len()];
+        let next = points[(i + 1) % points.len()];
+        let (incoming, outgoing) = (points[i] - previous, next - points[i]);
+        let turn = ((incoming % outgoing) ^ *normal) / (incoming.length() * outgoing.length()).max(f64::MIN_POSITIVE);
+
+        if turn < -1e-6 {
+            return None;
+        }
+        if turn > 1e-6 {
+            result.push(points[i]);
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Axis-aligned box brush text building function
+    /// * `min`, `max` - box corners
+    fn box_brush(min: [i32; 3], max: [i32; 3]) -> String {
+        let mut text = String::from("{\n");
+
+        // Points p + a, p, p + b of every face, where a % b is outward face normal
+        for axis in 0..3 {
+            let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+
+            for (corner, (first, second)) in [(min, (b, a)), (max, (a, b))] {
+                let point = |offset: Option<usize>| {
+                    let mut point = corner;
+                    if let Some(offset) = offset {
+                        point[offset] += 16;
+                    }
+                    format!("( {} {} {} )", point[0], point[1], point[2])
+                };
+
+                text += &format!("{} {} {} base 0 0 0 1 1\n", point(Some(first)), point(None), point(Some(second)));
+            }
+        }
+
+        text + "}\n"
+    }
+
+    /// Worldspawn with brushes building function
+    fn build(brushes: &[([i32; 3], [i32; 3])], options: &MapOptions) -> render::Primitive {
+        let text = brushes.iter().fold(String::from("{\n\"classname\" \"worldspawn\"\n"), |text, (min, max)| text + &box_brush(*min, *max)) + "}\n";
+
+        MapFile::parse(&text).unwrap().build_primitive(options).unwrap()
+    }
+
+    #[test]
+    fn box_brush_has_outward_faces() {
+        let primitive = build(&[([0, 0, 0], [64, 64, 64])], &MapOptions::default());
+        let center = Vec3f::new(32.0, 32.0, 32.0);
+
+        assert_eq!(primitive.get_faces().len(), 6);
+        for face in primitive.faces() {
+            let position = primitive.get_positions()[face.vertices[0] as usize];
+            let normal = primitive.get_normals()[face.normal as usize];
+
+            assert_eq!(face.vertices.len(), 4);
+            assert!(((position - super::super::bsp::to_viewer_space(center, 1.0)) ^ normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn overlapping_brushes_lose_hidden_faces() {
+        let brushes = [([0, 0, 0], [64, 64, 64]), ([32, 0, 0], [96, 64, 64])];
+
+        assert_eq!(build(&brushes, &MapOptions::default()).get_faces().len(), 12);
+
+        // Only faces of each brush, that lie inside the other one, are removed; partially covered ones stay
+        let primitive = build(&brushes, &MapOptions { remove_hidden: true, ..Default::default() });
+        assert_eq!(primitive.get_faces().len(), 10);
+    }
+
+    #[test]
+    fn adjacent_coplanar_quads_are_merged() {
+        let brushes = [([0, 0, 0], [64, 64, 64]), ([64, 0, 0], [128, 64, 64])];
+
+        // Side faces merge into single quads, opposite contact faces don't
+        let primitive = build(&brushes, &MapOptions { merge_coplanar: true, ..Default::default() });
+        assert_eq!(primitive.get_faces().len(), 8);
+
+        let primitive = build(&brushes, &MapOptions { merge_coplanar: true, remove_hidden: true, ..Default::default() });
+        assert_eq!(primitive.get_faces().len(), 6);
+        assert!(primitive.faces().all(|face| face.vertices.len() == 4));
+        assert!((primitive.get_bounds().size().length2() - Vec3f::new(128.0, 64.0, 64.0).length2()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn merged_polygons_absorb_further_neighbours() {
+        let brushes = (0..8).map(|index| ([index * 16, 0, 0], [index * 16 + 16, 64, 64])).collect::<Vec<_>>();
+
+        let primitive = build(&brushes, &MapOptions { merge_coplanar: true, remove_hidden: true, ..Default::default() });
+        assert_eq!(primitive.get_faces().len(), 6);
+        assert!(primitive.faces().all(|face| face.vertices.len() == 4));
+    }
+
+    #[test]
+    fn different_textures_are_not_merged() {
+        let text = format!("{{\n{}{}}}\n", box_brush([0, 0, 0], [64, 64, 64]), box_brush([64, 0, 0], [128, 64, 64]).replace("base", "other"));
+        let primitive = MapFile::parse(&text).unwrap().build_primitive(&MapOptions { merge_coplanar: true, remove_hidden: true, ..Default::default() }).unwrap();
+
+        assert_eq!(primitive.get_faces().len(), 10);
+    }
+}
@@ -0,0 +1,263 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use super::alias::{AliasBuilder, AnimatedModel, AnimationFrame};
+use super::bsp::{to_viewer_space, MipTexture, Reader};
+use super::error::*;
+
+/// MD2 file magic
+const MD2_IDENT: &[u8; 4] = b"IDP2";
+/// Supported MD2 version (Quake 2)
+const MD2_VERSION: i32 = 8;
+/// Header size
+const HEADER_SIZE: usize = 68;
+
+/// MD2 file loading function
+/// * `path` - path to .md2 file
+/// * `scale` - Quake unit size in viewer space
+/// * Returns model with all frames and skins
+pub fn load_md2(path: impl AsRef<Path>, scale: f32) -> Result<AnimatedModel, LoadError> {
+    load_file(path.as_ref(), |file| read_md2(file, scale))
+}
+
+/// Quake 2 MD2 reading function.
+///
+/// Skins are external images, so they are named by image paths and have no data.
+/// OpenGL commands are skipped, model is built from triangles.
+/// * `reader` - MD2 source
+/// * `scale` - Quake unit size in viewer space
+/// * Returns model, or error with file offset
+pub fn read_md2(reader: impl Read, scale: f32) -> Result<AnimatedModel, LoadError> {
+    let data = read_bytes(reader)?;
+    let file = Reader { data: &data, base: 0 };
+
+    if file.bytes(0, 4)? != MD2_IDENT {
+        return Err(LoadError::syntax(file.position(0), "file is not MD2 model"));
+    }
+    let version = file.i32(4)?;
+    if version != MD2_VERSION {
+        return Err(LoadError::unsupported(file.position(4), format!("MD2 version {version}, only {MD2_VERSION} is supported")));
+    }
+    file.bytes(0, HEADER_SIZE)?;
+
+    let field = |offset: usize, what: &str| -> Result<usize, LoadError> {
+        let value = file.i32(offset)?;
+        if value < 0 {
+            return Err(LoadError::syntax(file.position(offset), format!("{what} is negative")));
+        }
+        Ok(value as usize)
+    };
+
+    let skin_width = field(8, "skin width")?;
+    let skin_height = field(12, "skin height")?;
+    let frame_size = field(16, "frame size")?;
+    let skin_count = field(20, "skin count")?;
+    let vertex_count = field(24, "vertex count")?;
+    let tex_coord_count = field(28, "texture coordinate count")?;
+    let triangle_count = field(32, "triangle count")?;
+    let frame_count = field(40, "frame count")?;
+    let skins_offset = field(44, "skin offset")?;
+    let tex_coords_offset = field(48, "texture coordinate offset")?;
+    let triangles_offset = field(52, "triangle offset")?;
+    let frames_offset = field(56, "frame offset")?;
+
+    if frame_count == 0 {
+        return Err(LoadError::syntax(file.position(40), "model has no frames"));
+    }
+    if skin_width == 0 || skin_height == 0 {
+        return Err(LoadError::syntax(file.position(8), format!("invalid skin size {skin_width}x{skin_height}")));
+    }
+    if frame_size < 40 + vertex_count * 4 {
+        return Err(LoadError::syntax(file.position(16), format!("frame size {frame_size} is too small for {vertex_count} vertices")));
+    }
+
+    file.records(skins_offset, skin_count, 64, "skins")?;
+    file.records(frames_offset, frame_count, frame_size, "frames")?;
+
+    let skins = (0..skin_count)
+        .map(|skin| Ok(MipTexture {
+            name: file.name(skins_offset + skin * 64, 64)?,
+            width: skin_width as u32,
+            height: skin_height as u32,
+            data: Vec::new(),
+        }))
+        .collect::<Result<Vec<_>, LoadError>>()?;
+
+    let tex_coords = Reader { data: file.bytes(tex_coords_offset, tex_coord_count * 4)?, base: tex_coords_offset };
+    let triangles = Reader { data: file.bytes(triangles_offset, triangle_count * 12)?, base: triangles_offset };
+
+    let mut builder = AliasBuilder::default();
+    for triangle in 0..triangle_count {
+        let mut corners = [(0, 0, Vec2f::new(0.0, 0.0)); 3];
+
+        for (corner, (vertex, key, tex_coord)) in corners.iter_mut().enumerate() {
+            let vertex_offset = triangle * 12 + corner * 2;
+            let tex_coord_offset = vertex_offset + 6;
+            let vertex_index = triangles.u16(vertex_offset)? as usize;
+            let tex_coord_index = triangles.u16(tex_coord_offset)? as usize;
+
+            if vertex_index >= vertex_count {
+                return Err(LoadError::index_range(
+                    triangles.position(vertex_offset),
+                    format!("triangle {triangle} references vertex {vertex_index}, but there are {vertex_count} vertices"),
+                ));
+            }
+            if tex_coord_index >= tex_coord_count {
+                return Err(LoadError::index_range(
+                    triangles.position(tex_coord_offset),
+                    format!("triangle {triangle} references texture coordinate {tex_coord_index}, but there are {tex_coord_count} texture coordinates"),
+                ));
+            }
+
+            let s = tex_coords.i16(tex_coord_index * 4)? as f32;
+            let t = tex_coords.i16(tex_coord_index * 4 + 2)? as f32;
+
+            *vertex = vertex_index as u32;
+            *key = tex_coord_index as u32;
+            *tex_coord = Vec2f::new(s / skin_width as f32, 1.0 - t / skin_height as f32);
+        }
+
+        builder.add_triangle(corners);
+    }
+
+    let mut frames = Vec::with_capacity(frame_count);
+    for frame in 0..frame_count {
+        let offset = frames_offset + frame * frame_size;
+        let vertex_scale = file.vec3(offset)?;
+        let vertex_translation = file.vec3(offset + 12)?;
+        let name = file.name(offset + 24, 16)?;
+        let vertices = file.bytes(offset + 40, vertex_count * 4)?;
+
+        let positions = vertices
+            .chunks_exact(4)
+            .map(|vertex| to_viewer_space(Vec3f::new(vertex[0] as f32, vertex[1] as f32, vertex[2] as f32) * vertex_scale + vertex_translation, scale))
+            .collect::<Vec<_>>();
+
+        if !positions.iter().all(|position| position.x.is_finite() && position.y.is_finite() && position.z.is_finite()) {
+            return Err(LoadError::syntax(file.position(offset), format!("frame {name} has non-finite positions")));
+        }
+        frames.push(AnimationFrame { name, positions });
+    }
+
+    Ok(builder.build(vertex_count, frames, skins))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::bsp::tests::Record;
+
+    /// Square model building function.
+    ///
+    /// Model has single skin, 4 vertices and 2 triangles, that use vertex 1 with different texture coordinates,
+    /// and frames `pain1` and `pain2`.
+    /// * `frame_size` - frame size in header
+    /// * `frame_count` - frame count in header
+    fn square_md2(frame_size: i32, frame_count: i32) -> Vec<u8> {
+        let mut file = Record::default()
+            .bytes(MD2_IDENT).i32(MD2_VERSION)
+            .i32(8).i32(4).i32(frame_size)
+            .i32(1).i32(4).i32(5).i32(2).i32(0).i32(frame_count)
+            .i32(68).i32(132).i32(152).i32(176).i32(288).i32(288);
+        assert_eq!(file.0.len(), HEADER_SIZE);
+
+        file = file.name("models/square.pcx", 64);
+        for (s, t) in [(0, 0), (8, 0), (0, 4), (8, 4), (4, 0)] {
+            file = file.i16(s).i16(t);
+        }
+        file = file.u16(0).u16(1).u16(2).u16(0).u16(1).u16(2);
+        file = file.u16(1).u16(3).u16(2).u16(4).u16(3).u16(2);
+
+        for (name, z) in [("pain1", 0), ("pain2", 2)] {
+            file = file.vec3(2.0, 2.0, 2.0).vec3(0.0, 0.0, -1.0).name(name, 16);
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                file = file.bytes(&[x, y, z, 0]);
+            }
+        }
+        assert_eq!(file.0.len(), 288);
+
+        file.0
+    }
+
+    /// Error message getting function
+    fn error_message(data: &[u8]) -> String {
+        match read_md2(data, 1.0) {
+            Err(LoadError::Syntax { message, .. } | LoadError::IndexRange { message, .. }) => message,
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("invalid model is accepted"),
+        }
+    }
+
+    #[test]
+    fn reads_square_model() {
+        let model = read_md2(square_md2(56, 2).as_slice(), 1.0).unwrap();
+
+        assert_eq!(model.get_frames().len(), 2);
+        assert_eq!(model.get_animations()[0].name, "pain");
+        let skin = &model.get_skins()[0];
+        assert_eq!((skin.name.as_str(), skin.width, skin.height), ("models/square.pcx", 8, 4));
+        assert!(skin.data.is_empty());
+
+        // Frame scale and translation are applied
+        let positions = &model.get_frames()[1].positions;
+        assert!(positions.iter().all(|position| position.y == 3.0));
+        assert!(positions.iter().any(|position| position.x == 2.0 && position.z == -2.0));
+    }
+
+    #[test]
+    fn vertices_are_split_by_texture_coordinates() {
+        let primitive = read_md2(square_md2(56, 2).as_slice(), 1.0).unwrap().sample(0.0);
+
+        assert_eq!(primitive.get_positions().len(), 5);
+        let mut seam_tex_coords = (0..5)
+            .filter(|vertex| (primitive.get_positions()[*vertex] - Vec3f::new(2.0, -1.0, 0.0)).length2() == 0.0)
+            .map(|vertex| [primitive.get_tex_coords()[vertex].x, primitive.get_tex_coords()[vertex].y])
+            .collect::<Vec<_>>();
+        seam_tex_coords.sort_by(|first, second| first[0].total_cmp(&second[0]));
+        assert_eq!(seam_tex_coords, vec![[0.5, 1.0], [1.0, 1.0]]);
+    }
+
+    #[test]
+    fn rejects_invalid_header() {
+        let mut data = square_md2(56, 2);
+        data[0] = b'X';
+        assert!(error_message(&data).contains("not MD2"));
+
+        let mut data = square_md2(56, 2);
+        data[4] = 7;
+        assert!(matches!(read_md2(data.as_slice(), 1.0), Err(LoadError::Unsupported { .. })));
+
+        assert!(error_message(&square_md2(-56, 2)).contains("negative"));
+        assert!(error_message(&square_md2(55, 2)).contains("too small"));
+        assert!(error_message(&square_md2(56, 0)).contains("no frames"));
+    }
+
+    #[test]
+    fn rejects_counts_out_of_file() {
+        assert!(error_message(&square_md2(56, i32::MAX)).contains("frames are out of file"));
+        assert!(error_message(&square_md2(56, 3)).contains("frames are out of file"));
+        assert!(error_message(&square_md2(i32::MAX, 2)).contains("frames are out of file"));
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        // Triangle references the fifth vertex and the sixth texture coordinate
+        let mut data = square_md2(56, 2);
+        data[152] = 4;
+        assert!(error_message(&data).contains("references vertex 4"));
+
+        let mut data = square_md2(56, 2);
+        data[158] = 5;
+        assert!(error_message(&data).contains("references texture coordinate 5"));
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let data = square_md2(56, 2);
+
+        for length in 0..data.len() {
+            assert!(read_md2(&data[..length], 1.0).is_err(), "file truncated to {length} bytes is accepted");
+        }
+    }
+}
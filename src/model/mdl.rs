@@ -0,0 +1,284 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use super::alias::{AliasBuilder, AnimatedModel, AnimationFrame};
+use super::bsp::{to_viewer_space, MipTexture, Reader};
+use super::error::*;
+
+/// MDL file magic
+const MDL_IDENT: &[u8; 4] = b"IDPO";
+/// Supported MDL version (Quake 1)
+const MDL_VERSION: i32 = 6;
+/// Header size
+const HEADER_SIZE: usize = 84;
+
+/// MDL file loading function
+/// * `path` - path to .mdl file
+/// * `scale` - Quake unit size in viewer space
+/// * Returns model with all frames and skins
+pub fn load_mdl(path: impl AsRef<Path>, scale: f32) -> Result<AnimatedModel, LoadError> {
+    load_file(path.as_ref(), |file| read_mdl(file, scale))
+}
+
+/// Quake 1 MDL reading function.
+///
+/// Frame groups and skin groups are flattened, so every group frame becomes separate model frame and every group skin
+/// becomes separate skin, named `skin<index>.<group frame index>`, single skins are named `skin<index>`.
+/// Back side texture coordinates of seam vertices are shifted by half of skin width, as Quake does.
+/// * `reader` - MDL source
+/// * `scale` - Quake unit size in viewer space
+/// * Returns model, or error with file offset
+pub fn read_mdl(reader: impl Read, scale: f32) -> Result<AnimatedModel, LoadError> {
+    let data = read_bytes(reader)?;
+    let file = Reader { data: &data, base: 0 };
+
+    if file.bytes(0, 4)? != MDL_IDENT {
+        return Err(LoadError::syntax(file.position(0), "file is not MDL model"));
+    }
+    let version = file.i32(4)?;
+    if version != MDL_VERSION {
+        return Err(LoadError::unsupported(file.position(4), format!("MDL version {version}, only {MDL_VERSION} is supported")));
+    }
+    file.bytes(0, HEADER_SIZE)?;
+
+    let vertex_scale = file.vec3(8)?;
+    let vertex_translation = file.vec3(20)?;
+    let count = |offset: usize, what: &str| -> Result<usize, LoadError> {
+        let value = file.i32(offset)?;
+        if value < 0 {
+            return Err(LoadError::syntax(file.position(offset), format!("{what} is negative")));
+        }
+        Ok(value as usize)
+    };
+
+    let skin_count = count(48, "skin count")?;
+    let skin_width = count(52, "skin width")?;
+    let skin_height = count(56, "skin height")?;
+    let vertex_count = count(60, "vertex count")?;
+    let triangle_count = count(64, "triangle count")?;
+    let frame_count = count(68, "frame count")?;
+
+    if frame_count == 0 {
+        return Err(LoadError::syntax(file.position(68), "model has no frames"));
+    }
+    if skin_width == 0 || skin_height == 0 {
+        return Err(LoadError::syntax(file.position(52), format!("invalid skin size {skin_width}x{skin_height}")));
+    }
+
+    let mut offset = HEADER_SIZE;
+
+    // Skins, every skin takes at least its type and data
+    let skin_size = skin_width * skin_height;
+    file.records(offset, skin_count, 4 + skin_size, "skins")?;
+    let mut skins = Vec::with_capacity(skin_count);
+    for skin in 0..skin_count {
+        let skin_type = file.i32(offset)?;
+        offset += 4;
+
+        let mut add_skin = |name: String, offset: usize| -> Result<(), LoadError> {
+            skins.push(MipTexture {
+                name,
+                width: skin_width as u32,
+                height: skin_height as u32,
+                data: file.bytes(offset, skin_size)?.to_vec(),
+            });
+            Ok(())
+        };
+
+        if skin_type == 0 {
+            add_skin(format!("skin{skin}"), offset)?;
+            offset += skin_size;
+        } else {
+            let group_size = count(offset, "skin group size")?;
+            offset += 4 + group_size * 4;
+            for group_skin in 0..group_size {
+                add_skin(format!("skin{skin}.{group_skin}"), offset)?;
+                offset += skin_size;
+            }
+        }
+    }
+
+    // Texture coordinates
+    let tex_coords = Reader { data: file.bytes(offset, vertex_count * 12)?, base: offset };
+    offset += vertex_count * 12;
+
+    // Triangles
+    let mut builder = AliasBuilder::default();
+    for triangle in 0..triangle_count {
+        let triangle_offset = offset + triangle * 16;
+        let faces_front = file.i32(triangle_offset)? != 0;
+        let mut corners = [(0, 0, Vec2f::new(0.0, 0.0)); 3];
+
+        for (corner, (vertex, key, tex_coord)) in corners.iter_mut().enumerate() {
+            let index = file.i32(triangle_offset + 4 + corner * 4)?;
+            if index < 0 || index as usize >= vertex_count {
+                return Err(LoadError::index_range(
+                    file.position(triangle_offset + 4 + corner * 4),
+                    format!("triangle {triangle} references vertex {index}, but there are {vertex_count} vertices"),
+                ));
+            }
+
+            let on_seam = tex_coords.i32(index as usize * 12)? != 0;
+            let s = tex_coords.i32(index as usize * 12 + 4)? as f32;
+            let t = tex_coords.i32(index as usize * 12 + 8)? as f32;
+            let back_seam = on_seam && !faces_front;
+            let s = if back_seam { s + (skin_width / 2) as f32 } else { s };
+
+            *vertex = index as u32;
+            *key = back_seam as u32;
+            *tex_coord = Vec2f::new((s + 0.5) / skin_width as f32, 1.0 - (t + 0.5) / skin_height as f32);
+        }
+
+        builder.add_triangle(corners);
+    }
+    offset += triangle_count * 16;
+
+    // Frames, every frame takes at least its type and single frame data
+    let frame_size = 24 + vertex_count * 4;
+    file.records(offset, frame_count, 4 + frame_size, "frames")?;
+    let mut frames = Vec::with_capacity(frame_count);
+    let mut read_frame = |offset: usize| -> Result<(), LoadError> {
+        let name = file.name(offset + 8, 16)?;
+        let vertices = file.bytes(offset + 24, vertex_count * 4)?;
+        let positions = vertices
+            .chunks_exact(4)
+            .map(|vertex| to_viewer_space(Vec3f::new(vertex[0] as f32, vertex[1] as f32, vertex[2] as f32) * vertex_scale + vertex_translation, scale))
+            .collect::<Vec<_>>();
+
+        if !positions.iter().all(|position| position.x.is_finite() && position.y.is_finite() && position.z.is_finite()) {
+            return Err(LoadError::syntax(file.position(offset), format!("frame {name} has non-finite positions")));
+        }
+        frames.push(AnimationFrame { name, positions });
+        Ok(())
+    };
+
+    for _ in 0..frame_count {
+        let frame_type = file.i32(offset)?;
+        offset += 4;
+
+        if frame_type == 0 {
+            read_frame(offset)?;
+            offset += frame_size;
+        } else {
+            let group_size = count(offset, "frame group size")?;
+            offset += 12 + group_size * 4;
+            for _ in 0..group_size {
+                read_frame(offset)?;
+                offset += frame_size;
+            }
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(LoadError::syntax(file.position(HEADER_SIZE), "model frame groups are empty"));
+    }
+
+    Ok(builder.build(vertex_count, frames, skins))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::bsp::tests::Record;
+
+    /// Square model building function.
+    ///
+    /// Model has single 8x4 skin, 4 vertices, front and back triangle, that share seam vertex 1, and frames `run1` and `run2`.
+    /// * `skin_count` - skin count in header
+    /// * `frame_count` - frame count in header
+    fn square_mdl(skin_count: i32, frame_count: i32) -> Vec<u8> {
+        let mut file = Record::default()
+            .bytes(MDL_IDENT).i32(MDL_VERSION)
+            .vec3(1.0, 1.0, 1.0).vec3(0.0, 0.0, 0.0).f32(1.0).vec3(0.0, 0.0, 0.0)
+            .i32(skin_count).i32(8).i32(4).i32(4).i32(2).i32(frame_count)
+            .i32(0).i32(0).f32(0.0);
+        assert_eq!(file.0.len(), HEADER_SIZE);
+
+        file = file.i32(0).bytes(&[7; 32]);
+        for (on_seam, s, t) in [(0, 0, 0), (32, 2, 0), (0, 0, 2), (0, 6, 2)] {
+            file = file.i32(on_seam).i32(s).i32(t);
+        }
+        file = file.i32(1).i32(0).i32(1).i32(2);
+        file = file.i32(0).i32(1).i32(3).i32(2);
+
+        for (name, z) in [("run1", 0), ("run2", 2)] {
+            file = file.i32(0).bytes(&[0; 8]).name(name, 16);
+            for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                file = file.bytes(&[x, y, z, 0]);
+            }
+        }
+
+        file.0
+    }
+
+    /// Syntax error message getting function
+    fn syntax_error(data: &[u8]) -> String {
+        match read_mdl(data, 1.0) {
+            Err(LoadError::Syntax { message, .. }) => message,
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("invalid model is accepted"),
+        }
+    }
+
+    #[test]
+    fn reads_square_model() {
+        let model = read_mdl(square_mdl(1, 2).as_slice(), 1.0).unwrap();
+
+        assert_eq!(model.get_frames().len(), 2);
+        assert_eq!(model.get_animations()[0].name, "run");
+        let skin = &model.get_skins()[0];
+        assert_eq!((skin.name.as_str(), skin.width, skin.height, skin.data.as_slice()), ("skin0", 8, 4, [7; 32].as_slice()));
+
+        // Quake Z axis is viewer Y one
+        assert!(model.get_frames()[1].positions.iter().all(|position| position.y == 2.0));
+
+        let primitive = model.sample(0.0);
+        assert_eq!(primitive.get_faces().len(), 2);
+        assert!(primitive.get_normals().iter().all(|normal| normal.y < -0.999));
+    }
+
+    #[test]
+    fn back_side_seam_vertices_are_shifted() {
+        let primitive = read_mdl(square_mdl(1, 2).as_slice(), 1.0).unwrap().sample(0.0);
+
+        // Seam vertex is split, back side copy is shifted by half of skin width
+        assert_eq!(primitive.get_positions().len(), 5);
+        let mut seam_s = (0..5)
+            .filter(|vertex| (primitive.get_positions()[*vertex] - Vec3f::new(1.0, 0.0, 0.0)).length2() == 0.0)
+            .map(|vertex| primitive.get_tex_coords()[vertex].x * 8.0)
+            .collect::<Vec<_>>();
+        seam_s.sort_by(f32::total_cmp);
+        assert_eq!(seam_s, vec![2.5, 6.5]);
+    }
+
+    #[test]
+    fn rejects_invalid_header() {
+        let mut data = square_mdl(1, 2);
+        data[0] = b'X';
+        assert!(syntax_error(&data).contains("not MDL"));
+
+        let mut data = square_mdl(1, 2);
+        data[4] = 7;
+        assert!(matches!(read_mdl(data.as_slice(), 1.0), Err(LoadError::Unsupported { .. })));
+
+        assert!(syntax_error(&square_mdl(-1, 2)).contains("negative"));
+        assert!(syntax_error(&square_mdl(1, 0)).contains("no frames"));
+    }
+
+    #[test]
+    fn rejects_counts_out_of_file() {
+        assert!(syntax_error(&square_mdl(i32::MAX, 2)).contains("skins are out of file"));
+        assert!(syntax_error(&square_mdl(1, i32::MAX)).contains("frames are out of file"));
+        assert!(syntax_error(&square_mdl(1, 3)).contains("frames are out of file"));
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let data = square_mdl(1, 2);
+
+        for length in 0..data.len() {
+            assert!(read_mdl(&data[..length], 1.0).is_err(), "file truncated to {length} bytes is accepted");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::math::*;
+use crate::render;
+
+/// Named model part with its own material, displayed as a whole
+pub struct SubMesh {
+    /// Name of object, sub-mesh belongs to, empty if model has no objects
+    pub name: String,
+    /// Names of groups, sub-mesh belongs to
+    pub groups: Vec<String>,
+    /// Smoothing group, 0 if smoothing is off
+    pub smoothing_group: u32,
+    /// Material name, None if no material is used
+    pub material: Option<String>,
+    /// Range of sub-mesh faces in source file face order
+    pub faces: std::ops::Range<usize>,
+    /// Sub-mesh geometry, its color is sub-mesh color
+    pub primitive: render::Primitive,
+    /// Is sub-mesh displayed
+    pub visible: bool,
+    /// Affine sub-mesh to model space transformation
+    pub transform: Mat4x4f,
+}
+
+impl SubMesh {
+    /// Sub-mesh create function
+    /// * `name` - object name
+    /// * `primitive` - sub-mesh geometry
+    /// * Returns visible untransformed sub-mesh without groups and material
+    pub fn new(name: &str, primitive: render::Primitive) -> Self {
+        Self {
+            name: name.to_string(),
+            groups: Vec::new(),
+            smoothing_group: 0,
+            material: None,
+            faces: 0..primitive.faces().count(),
+            primitive,
+            visible: true,
+            transform: Mat4x4f::identity(),
+        }
+    }
+
+    /// Model space bounding box getting function
+    /// * Returns bounding box of transformed primitive bounding box
+    pub fn get_bounds(&self) -> Aabb {
+        let bounds = self.primitive.get_bounds();
+
+        if bounds.is_empty() {
+            return *bounds;
+        }
+
+        let corners = (0..8)
+            .map(|corner| self.transform.transform_point(Vec3f::new(
+                if corner & 1 == 0 { bounds.min.x } else { bounds.max.x },
+                if corner & 2 == 0 { bounds.min.y } else { bounds.max.y },
+                if corner & 4 == 0 { bounds.min.z } else { bounds.max.z },
+            )))
+            .collect::<Vec<_>>();
+
+        Aabb::from_points(&corners)
+    }
+}
+
+/// Surface material
+pub struct Material {
+    /// Material name, sub-meshes reference material by it
+    pub name: String,
+    /// Linear RGBA base color, texture and vertex colors are multiplied by it
+    pub base_color: Vec4f,
+    /// Index of base color texture in model textures, None if material isn't textured
+    pub base_color_texture: Option<usize>,
+}
+
+/// Texture image
+pub struct Texture {
+    pub name: String,
+    /// Image MIME type, None if it isn't known
+    pub mime_type: Option<String>,
+    /// Encoded image file contents
+    pub data: Vec<u8>,
+}
+
+/// Model, made of separately displayed sub-meshes
+pub struct Model {
+    pub sub_meshes: Vec<SubMesh>,
+    /// Names of material libraries, referenced by model
+    pub material_libraries: Vec<String>,
+    /// Materials, defined by model itself
+    pub materials: Vec<Material>,
+    /// Textures, referenced by model materials
+    pub textures: Vec<Texture>,
+}
+
+impl From<render::Primitive> for Model {
+    /// Single primitive model create function
+    /// * `primitive` - model geometry
+    /// * Returns model with single unnamed sub-mesh
+    fn from(primitive: render::Primitive) -> Self {
+        Self {
+            sub_meshes: vec![SubMesh::new("", primitive)],
+            material_libraries: Vec::new(),
+            materials: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+}
+
+impl Model {
+    /// Sub-meshes by name finding function
+    /// * `name` - object or group name
+    /// * Returns iterator over sub-meshes, that belong to object or group
+    pub fn find<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a SubMesh> + 'a {
+        self.sub_meshes.iter().filter(move |sub_mesh| sub_mesh.name == name || sub_mesh.groups.iter().any(|group| group == name))
+    }
+
+    /// Mutable sub-meshes by name finding function
+    /// * `name` - object or group name
+    /// * Returns iterator over sub-meshes, that belong to object or group
+    pub fn find_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item = &'a mut SubMesh> + 'a {
+        self.sub_meshes.iter_mut().filter(move |sub_mesh| sub_mesh.name == name || sub_mesh.groups.iter().any(|group| group == name))
+    }
+
+    /// Material by name finding function
+    /// * `name` - material name
+    /// * Returns material, defined by model, None if there is no such material
+    pub fn find_material(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    /// Bounding box getting function
+    /// * Returns bounding box of all visible sub-meshes
+    pub fn get_bounds(&self) -> Aabb {
+        self.sub_meshes
+            .iter()
+            .filter(|sub_mesh| sub_mesh.visible)
+            .fold(Aabb::empty(), |bounds, sub_mesh| bounds.union(&sub_mesh.get_bounds()))
+    }
+
+    /// Model displaying function
+    /// * `context` - context to display model in
+    pub fn draw(&self, context: &mut render::RenderContext) {
+        for sub_mesh in self.sub_meshes.iter().filter(|sub_mesh| sub_mesh.visible) {
+            context.draw_transformed(&sub_mesh.primitive, &sub_mesh.transform);
+        }
+    }
+}
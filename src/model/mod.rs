@@ -0,0 +1,26 @@
+mod error;
+mod json;
+mod text;
+
+pub mod alias;
+pub mod bsp;
+pub mod cache;
+pub mod gltf;
+pub mod map;
+pub mod md2;
+pub mod mdl;
+pub mod mesh;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+pub use alias::{AnimatedModel, Animation, AnimationFrame};
+pub use cache::{load_cache, load_cached, read_cache, save_cache, write_cache, SourceStamp};
+pub use error::{LoadError, Location, Position};
+pub use gltf::{load_gltf, read_gltf};
+pub use md2::{load_md2, read_md2};
+pub use mdl::{load_mdl, read_mdl};
+pub use mesh::{Material, Model, SubMesh, Texture};
+pub use obj::{load_obj, load_obj_with, read_obj, read_obj_with, save_obj, write_obj, ObjOptions};
+pub use ply::{load_ply, read_ply};
+pub use stl::{load_stl, read_stl, save_stl, write_stl, StlFormat};
@@ -0,0 +1,1297 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::math::*;
+use crate::mesh::{self, polygon_normal};
+use crate::render;
+use super::error::*;
+use super::mesh::{Model, SubMesh};
+
+/// Sub-mesh, that is being read
+struct SubMeshData {
+    name: String,
+    groups: Vec<String>,
+    smoothing_group: u32,
+    material: Option<String>,
+    /// Number of first sub-mesh face in file
+    first_face: usize,
+    face_normals: Vec<Vec3f>,
+    /// Faces, referencing file positions
+    faces: render::Faces,
+    /// Texture coordinate index of every face vertex in face vertices order
+    vertex_tex_coords: Vec<Option<u32>>,
+    /// Normal index of every face vertex in face vertices order
+    vertex_normals: Vec<Option<u32>>,
+    /// Line strips, referencing file positions
+    line_strips: render::LineStrips,
+    /// Points, referencing file positions
+    points: Vec<u32>,
+}
+
+/// Vertex data, defined by `v`, `vt` and `vn` statements
+#[derive(Default)]
+struct VertexData {
+    positions: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+    tex_coords: Vec<Vec2f>,
+}
+
+impl VertexData {
+    /// Vertex statement parsing function
+    /// * `statement` - statement token, non-vertex statements are ignored
+    /// * `arguments` - statement arguments
+    fn parse(&mut self, statement: &Token, arguments: &[Token]) -> Result<(), LoadError> {
+        match statement.text {
+            b"v" => {
+                // Weight or vertex color is ignored
+                let [x, y, z, ..] = parse_numbers::<6>(statement, arguments, 3, "position")?;
+                self.positions.push(Vec3f::new(x, y, z));
+            }
+            b"vt" => {
+                let [u, v, _] = parse_numbers::<3>(statement, arguments, 1, "texture coordinate")?;
+                self.tex_coords.push(Vec2f::new(u, v));
+            }
+            b"vn" => {
+                let [x, y, z] = parse_numbers::<3>(statement, arguments, 3, "normal")?;
+                self.normals.push(Vec3f::new(x, y, z));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Loaded OBJ file contents
+struct ObjData {
+    /// Vertex data, may be parsed ahead of other statements
+    vertices: VertexData,
+    /// Numbers of positions, texture coordinates and normals, defined before current statement
+    position_count: usize,
+    tex_coord_count: usize,
+    normal_count: usize,
+    face_count: usize,
+    /// Crease angle of vertex normals, computed for sub-meshes without normal references
+    crease_angle: f32,
+
+    current: SubMeshData,
+    sub_meshes: Vec<SubMesh>,
+    material_libraries: Vec<String>,
+
+    /// Number of last sub-mesh, that used position, by position
+    position_sub_meshes: Vec<u32>,
+    /// Index of position in last sub-mesh, that used it
+    position_remap: Vec<u32>,
+}
+
+impl ObjData {
+    /// Current sub-mesh finishing function, starts new sub-mesh with same object, groups, smoothing group and material
+    fn finish_sub_mesh(&mut self) -> Result<(), LoadError> {
+        if self.current.face_normals.is_empty() && self.current.line_strips.is_empty() && self.current.points.is_empty() {
+            self.current.first_face = self.face_count;
+            return Ok(());
+        }
+
+        let next = SubMeshData {
+            name: self.current.name.clone(),
+            groups: self.current.groups.clone(),
+            smoothing_group: self.current.smoothing_group,
+            material: self.current.material.clone(),
+            first_face: self.face_count,
+            face_normals: Vec::new(),
+            faces: render::Faces::new(),
+            vertex_tex_coords: Vec::new(),
+            vertex_normals: Vec::new(),
+            line_strips: render::LineStrips::new(),
+            points: Vec::new(),
+        };
+        let mut current = std::mem::replace(&mut self.current, next);
+
+        // Sub-mesh gets only positions it uses
+        let sub_mesh_number = self.sub_meshes.len() as u32 + 1;
+        self.position_sub_meshes.resize(self.vertices.positions.len(), 0);
+        self.position_remap.resize(self.vertices.positions.len(), 0);
+
+        let mut positions = Vec::new();
+        let mut tex_coords = Vec::new();
+        let mut normals = Vec::new();
+        let has_tex_coords = current.vertex_tex_coords.iter().any(Option::is_some);
+        let references_normals = current.vertex_normals.iter().any(Option::is_some);
+        let has_normals = current.vertex_normals.iter().all(Option::is_some) && references_normals;
+        // Texture coordinate and normal indices of every sub-mesh vertex and vertices, split from their position at texture or normal seams
+        let mut vertex_attributes = Vec::<(Option<u32>, Option<u32>)>::new();
+        let mut seam_vertices = std::collections::HashMap::<(u32, Option<u32>, Option<u32>), u32>::new();
+
+        let vertices = &self.vertices;
+        let tex_coord_value = |tex_coord: Option<u32>| tex_coord.map_or(Vec2f::new(0.0, 0.0), |tex_coord| vertices.tex_coords[tex_coord as usize]);
+        let normal_value = |normal: Option<u32>| normal.map_or(Vec3f::new(0.0, 0.0, 0.0), |normal| {
+            let normal = vertices.normals[normal as usize];
+            if normal.length2() > 0.0 { normal.normalized() } else { normal }
+        });
+
+        let mut remap = |index: &mut u32, tex_coord: Option<u32>, normal: Option<u32>| {
+            let position = *index as usize;
+            let normal = normal.filter(|_| has_normals);
+
+            if self.position_sub_meshes[position] != sub_mesh_number {
+                self.position_sub_meshes[position] = sub_mesh_number;
+                self.position_remap[position] = positions.len() as u32;
+                positions.push(vertices.positions[position]);
+                vertex_attributes.push((None, None));
+                if has_tex_coords {
+                    tex_coords.push(Vec2f::new(0.0, 0.0));
+                }
+                if has_normals {
+                    normals.push(Vec3f::new(0.0, 0.0, 0.0));
+                }
+            }
+            *index = self.position_remap[position];
+
+            // Attributes, equal by value, don't split vertex, and missing ones match any
+            let (first_tex_coord, first_normal) = vertex_attributes[*index as usize];
+            let tex_coord_matches = match (first_tex_coord, tex_coord) {
+                (Some(first), Some(tex_coord)) => first == tex_coord || (tex_coord_value(Some(first)) - tex_coord_value(Some(tex_coord))).length2() == 0.0,
+                _ => true,
+            };
+            let normal_matches = match (first_normal, normal) {
+                (Some(first), Some(normal)) => first == normal || (normal_value(Some(first)) - normal_value(Some(normal))).length2() == 0.0,
+                _ => true,
+            };
+
+            if tex_coord_matches && normal_matches {
+                let attributes = &mut vertex_attributes[*index as usize];
+                if first_tex_coord.is_none() && tex_coord.is_some() {
+                    attributes.0 = tex_coord;
+                    tex_coords[*index as usize] = tex_coord_value(tex_coord);
+                }
+                if first_normal.is_none() && normal.is_some() {
+                    attributes.1 = normal;
+                    normals[*index as usize] = normal_value(normal);
+                }
+                return;
+            }
+
+            *index = *seam_vertices.entry((position as u32, tex_coord, normal)).or_insert_with(|| {
+                positions.push(vertices.positions[position]);
+                vertex_attributes.push((tex_coord, normal));
+                if has_tex_coords {
+                    tex_coords.push(tex_coord_value(tex_coord));
+                }
+                if has_normals {
+                    normals.push(normal_value(normal));
+                }
+                positions.len() as u32 - 1
+            });
+        };
+
+        let face_attributes = current.vertex_tex_coords.iter().zip(&current.vertex_normals);
+        for (index, (tex_coord, normal)) in current.faces.get_vertices_mut().iter_mut().zip(face_attributes) {
+            remap(index, *tex_coord, *normal);
+        }
+
+        for index in current.line_strips.get_vertices_mut() {
+            remap(index, None, None);
+        }
+
+        for index in &mut current.points {
+            remap(index, None, None);
+        }
+
+        // Data is validated during parsing, so primitive construction can't fail
+        let primitive_error = |err: render::PrimitiveError| LoadError::syntax(Position::Unknown, err.to_string());
+        let mut primitive = render::Primitive::new(positions, current.face_normals, current.faces, 0x00FF00).map_err(primitive_error)?;
+        if has_tex_coords {
+            primitive.set_tex_coords(tex_coords).map_err(primitive_error)?;
+        }
+        // Element setters recompute bounding volumes, so they are called only if needed
+        if !current.line_strips.is_empty() {
+            primitive.set_line_strips(current.line_strips).map_err(primitive_error)?;
+        }
+        if !current.points.is_empty() {
+            primitive.set_points(current.points).map_err(primitive_error)?;
+        }
+        if has_normals {
+            primitive.set_vertex_normals(normals).map_err(primitive_error)?;
+        } else if !references_normals && !primitive.get_faces().is_empty() {
+            // Sub-mesh of smoothing group is smooth everywhere, group borders are sub-mesh borders
+            let crease_angle = if current.smoothing_group != 0 { std::f32::consts::PI } else { self.crease_angle };
+            primitive = mesh::smooth_normals(&primitive, &mesh::NormalOptions { crease_angle, ..Default::default() });
+        }
+
+        self.sub_meshes.push(SubMesh {
+            name: current.name,
+            groups: current.groups,
+            smoothing_group: current.smoothing_group,
+            material: current.material,
+            faces: current.first_face..self.face_count,
+            primitive,
+            visible: true,
+            transform: Mat4x4f::identity(),
+        });
+
+        Ok(())
+    }
+}
+
+/// Single face vertex, indices are resolved and zero-based
+#[derive(Copy, Clone)]
+struct FaceVertex {
+    position: u32,
+    tex_coord: Option<u32>,
+    normal: Option<u32>,
+}
+
+/// Statement token, subslice of source
+#[derive(Copy, Clone)]
+struct Token<'a> {
+    text: &'a [u8],
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Token position getting function
+    /// * `offset` - byte offset in token
+    fn position(&self, offset: usize) -> Position {
+        Position::Text { line: self.line, column: self.column + offset }
+    }
+
+    /// Position right after token getting function
+    fn end_position(&self) -> Position {
+        self.position(self.text.len())
+    }
+
+    /// Token text for error messages getting function
+    fn display(&self) -> std::borrow::Cow<'a, str> {
+        String::from_utf8_lossy(self.text)
+    }
+
+    /// Name token text getting function
+    /// * Returns token text, or error at first invalid UTF-8 sequence
+    fn name(&self) -> Result<&'a str, LoadError> {
+        std::str::from_utf8(self.text).map_err(|error| LoadError::syntax(self.position(error.valid_up_to()), "invalid UTF-8 sequence"))
+    }
+}
+
+/// Source statement splitter, that doesn't allocate anything except of token vector
+struct Statements<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// 1-based number of next line
+    line: usize,
+}
+
+impl<'a> Statements<'a> {
+    /// Splitter create function
+    /// * `data` - source to split
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0, line: 1 }
+    }
+
+    /// Next statement reading function
+    /// * `tokens` - vector to write statement tokens to, cleared before writing
+    /// * Returns false if there are no more statements
+    fn next(&mut self, tokens: &mut Vec<Token<'a>>) -> bool {
+        tokens.clear();
+
+        while self.offset < self.data.len() {
+            let rest = &self.data[self.offset..];
+            let length = rest.iter().position(|c| *c == b'\n').unwrap_or(rest.len());
+
+            self.offset += length + 1;
+            self.line += 1;
+
+            // Tokens of continued lines are gathered into single statement
+            if !tokenize_line(&rest[..length], self.line - 1, tokens) && !tokens.is_empty() {
+                return true;
+            }
+        }
+
+        !tokens.is_empty()
+    }
+}
+
+/// OBJ loading options
+#[derive(Copy, Clone, Debug)]
+pub struct ObjOptions {
+    /// Number of threads, that parse `v`, `vt` and `vn` statements, 0 to use all available cores
+    pub threads: usize,
+    /// Minimal number of source bytes, parsed by single thread
+    pub min_chunk_size: usize,
+    /// Crease angle in radians of vertex normals, computed for sub-meshes without `vn` references outside of smoothing groups
+    pub crease_angle: f32,
+}
+
+impl Default for ObjOptions {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            min_chunk_size: 1 << 20,
+            crease_angle: mesh::NormalOptions::default().crease_angle,
+        }
+    }
+}
+
+/// OBJ file loading function
+/// * `path` - path to file
+/// * Returns model with all file faces
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Model, LoadError> {
+    load_obj_with(path, &ObjOptions::default())
+}
+
+/// OBJ file loading with options function
+/// * `path` - path to file
+/// * `options` - loading options
+/// * Returns model with all file faces
+pub fn load_obj_with(path: impl AsRef<Path>, options: &ObjOptions) -> Result<Model, LoadError> {
+    load_file(path.as_ref(), |file| read_obj_with(file, options))
+}
+
+/// OBJ reading function, see [`read_obj_with`] for supported syntax
+/// * `reader` - OBJ source
+/// * Returns model, or error with line and column
+pub fn read_obj(reader: impl Read) -> Result<Model, LoadError> {
+    read_obj_with(reader, &ObjOptions::default())
+}
+
+/// OBJ reading with options function.
+///
+/// Supported statements are `v`, `vt`, `vn`, `f`, `l`, `p`, `o`, `g`, `s`, `usemtl` and `mtllib`, all other statements are ignored:
+/// * Tokens are separated by any amount of spaces and tabs, lines ending with `\` continue on next line.
+/// * `#` starts comment, that lasts until end of line.
+/// * `v x y z [w]` - position, `w` is ignored, same as `r g b` vertex color extension.
+/// * `vt u [v [w]]` - texture coordinate, `v` is 0 if missing, `w` is ignored.
+/// * `vn x y z` - normal.
+/// * `f` - face of 3 or more vertices, each written as `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+/// * `l` - line strip of 2 or more vertices, each written as `v` or `v/vt`, texture coordinates are ignored.
+/// * `p` - points, each written as `v`.
+/// * `o name`, `g [names]`, `s group|off`, `usemtl name` - set object name, group names (`default` if none),
+///   smoothing group and material of following faces.
+/// * `mtllib names` - add material libraries to model.
+///
+/// Faces are split into sub-meshes, new sub-mesh starts at every `o`, `g`, `s` and `usemtl` statement,
+/// sub-meshes without faces are skipped. Each sub-mesh gets its own primitive, that contains only positions it uses.
+///
+/// Indices are 1-based if positive and relative to the last defined element if negative (`-1` is the last one),
+/// so every index must reference an element, defined before face. Zero, unparsable and out of range indices are errors,
+/// same as unparsable or non-finite numbers and missing components. Source doesn't have to be UTF-8,
+/// but names must be.
+///
+/// Face normal is the normalized sum of its vertex normals. Faces without vertex normals (or with ones,
+/// that sum up to zero) get geometric normal, computed from vertex positions. If every sub-mesh face vertex
+/// references normal, sub-mesh primitive gets these normals, normalized, as vertex normals. Sub-meshes without any
+/// normal references get smooth ones, computed by [`mesh::smooth_normals`]: sub-meshes of smoothing groups
+/// are smoothed over every edge, other ones are split along edges, sharper than `options.crease_angle`.
+/// If any sub-mesh face references texture coordinates, sub-mesh primitive gets per-vertex texture coordinates.
+/// Position, that is used with different texture coordinates or normals (seam), gets vertex for each of them,
+/// line strips and points use the first one. Positions, never used with texture coordinates, get zero ones.
+///
+/// Sources, larger than `options.min_chunk_size`, are split into chunks, `v`, `vt` and `vn` statements
+/// of which are parsed in parallel, then all other statements are parsed in order. Result doesn't depend on thread count.
+/// * `reader` - OBJ source
+/// * `options` - loading options
+/// * Returns model, or error with line and column
+pub fn read_obj_with(reader: impl Read, options: &ObjOptions) -> Result<Model, LoadError> {
+    let source = read_bytes(reader)?;
+
+    let threads = match options.threads {
+        0 => std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+        threads => threads,
+    };
+    let chunk_count = threads.min(source.len() / options.min_chunk_size.max(1)).max(1);
+
+    // Chunk with invalid vertex statement makes whole source parsed sequentially to report first error in source
+    let vertices = if chunk_count > 1 { parse_vertices_parallel(&source, chunk_count) } else { None };
+
+    let mut data = ObjData {
+        vertices: VertexData::default(),
+        position_count: 0,
+        tex_coord_count: 0,
+        normal_count: 0,
+        face_count: 0,
+        crease_angle: options.crease_angle,
+
+        current: SubMeshData {
+            name: String::new(),
+            groups: vec!["default".to_string()],
+            smoothing_group: 0,
+            material: None,
+            first_face: 0,
+            face_normals: Vec::new(),
+            faces: render::Faces::new(),
+            vertex_tex_coords: Vec::new(),
+            vertex_normals: Vec::new(),
+            line_strips: render::LineStrips::new(),
+            points: Vec::new(),
+        },
+        sub_meshes: Vec::new(),
+        material_libraries: Vec::new(),
+
+        position_sub_meshes: Vec::new(),
+        position_remap: Vec::new(),
+    };
+    let vertices_parsed = vertices.is_some();
+    if let Some(vertices) = vertices {
+        data.vertices = vertices;
+    }
+
+    let mut face = Vec::<FaceVertex>::new();
+    let mut tokens = Vec::<Token>::new();
+    let mut statements = Statements::new(&source);
+
+    while statements.next(&mut tokens) {
+        let (statement, arguments) = tokens.split_first().unwrap();
+
+        if !vertices_parsed {
+            data.vertices.parse(statement, arguments)?;
+        }
+
+        match statement.text {
+            b"v" => data.position_count += 1,
+            b"vt" => data.tex_coord_count += 1,
+            b"vn" => data.normal_count += 1,
+            b"f" => {
+                if arguments.len() < 3 {
+                    let end = arguments.last().unwrap_or(statement);
+                    return Err(LoadError::syntax(end.end_position(), format!("face must have at least 3 vertices, got {}", arguments.len())));
+                }
+
+                face.clear();
+                for token in arguments {
+                    face.push(parse_face_vertex(token, &data)?);
+                }
+                add_face(&mut data, &face);
+            }
+            b"l" => {
+                if arguments.len() < 2 {
+                    let end = arguments.last().unwrap_or(statement);
+                    return Err(LoadError::syntax(end.end_position(), format!("line must have at least 2 vertices, got {}", arguments.len())));
+                }
+
+                face.clear();
+                for token in arguments {
+                    let vertex = parse_face_vertex(token, &data)?;
+                    if vertex.normal.is_some() {
+                        return Err(LoadError::syntax(token.position(0), format!("invalid line vertex '{}', expected v or v/vt", token.display())));
+                    }
+                    face.push(vertex);
+                }
+                data.current.line_strips.push(face.iter().map(|vertex| vertex.position));
+            }
+            b"p" => {
+                if arguments.is_empty() {
+                    return Err(LoadError::syntax(statement.end_position(), "expected point vertices"));
+                }
+
+                for token in arguments {
+                    let vertex = parse_face_vertex(token, &data)?;
+                    if vertex.normal.is_some() || vertex.tex_coord.is_some() {
+                        return Err(LoadError::syntax(token.position(0), format!("invalid point vertex '{}', expected v", token.display())));
+                    }
+                    data.current.points.push(vertex.position);
+                }
+            }
+            b"o" => {
+                let name = join_names(arguments)?;
+                data.finish_sub_mesh()?;
+                data.current.name = name;
+            }
+            b"g" => {
+                let groups = if arguments.is_empty() {
+                    vec!["default".to_string()]
+                } else {
+                    arguments.iter().map(|token| token.name().map(str::to_string)).collect::<Result<_, _>>()?
+                };
+                data.finish_sub_mesh()?;
+                data.current.groups = groups;
+            }
+            b"s" => {
+                let [group] = arguments else {
+                    return Err(LoadError::syntax(statement.end_position(), "expected single smoothing group"));
+                };
+                let smoothing_group = match group.text {
+                    b"off" => 0,
+                    text => parse_integer(text)
+                        .and_then(|group| u32::try_from(group).ok())
+                        .ok_or_else(|| LoadError::syntax(group.position(0), format!("expected smoothing group, got '{}'", group.display())))?,
+                };
+                data.finish_sub_mesh()?;
+                data.current.smoothing_group = smoothing_group;
+            }
+            b"usemtl" => {
+                if arguments.is_empty() {
+                    return Err(LoadError::syntax(statement.end_position(), "expected material name"));
+                }
+                let material = join_names(arguments)?;
+                data.finish_sub_mesh()?;
+                data.current.material = Some(material);
+            }
+            b"mtllib" => {
+                for token in arguments {
+                    data.material_libraries.push(token.name()?.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    data.finish_sub_mesh()?;
+
+    Ok(Model {
+        sub_meshes: data.sub_meshes,
+        material_libraries: data.material_libraries,
+        materials: Vec::new(),
+        textures: Vec::new(),
+    })
+}
+
+/// OBJ file saving function
+/// * `path` - path to file
+/// * `model` - model to save
+pub fn save_obj(path: impl AsRef<Path>, model: &Model) -> std::io::Result<()> {
+    write_obj(std::fs::File::create(path)?, model)
+}
+
+/// OBJ writing function.
+///
+/// Sub-meshes are written in order with their transforms applied, normals are transformed as directions and renormalized.
+/// Object name, groups, smoothing group and material are written only if they differ from ones of the previous sub-mesh,
+/// so sub-meshes, that differ in any of them, are read back by [`read_obj`] as separate sub-meshes.
+/// Sub-meshes without groups get `default` one. Material can't be reset, so sub-mesh without material,
+/// that follows one with material, keeps it.
+///
+/// Every face vertex gets vertex normal of its position, if primitive has vertex normals, and face normal otherwise,
+/// and texture coordinate of its position, if primitive has texture coordinates. Line strips and points are written as `l` and `p` statements.
+/// Numbers are written in shortest form, that is read back exactly.
+/// * `writer` - destination
+/// * `model` - model to write
+pub fn write_obj(writer: impl Write, model: &Model) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(writer);
+
+    if !model.material_libraries.is_empty() {
+        writeln!(writer, "mtllib {}", model.material_libraries.join(" "))?;
+    }
+
+    // Values, that are set by reader before first statement
+    let default_groups = ["default".to_string()];
+    let mut name = "";
+    let mut groups = &default_groups[..];
+    let mut smoothing_group = 0;
+    let mut material = None;
+
+    // Numbers of already written elements
+    let mut position_count = 0;
+    let mut tex_coord_count = 0;
+    let mut normal_count = 0;
+
+    for sub_mesh in &model.sub_meshes {
+        let primitive = &sub_mesh.primitive;
+
+        if sub_mesh.name != name {
+            name = &sub_mesh.name;
+            writeln!(writer, "o {name}")?;
+        }
+        let sub_mesh_groups = if sub_mesh.groups.is_empty() { &default_groups[..] } else { &sub_mesh.groups[..] };
+        if sub_mesh_groups != groups {
+            groups = sub_mesh_groups;
+            writeln!(writer, "g {}", groups.join(" "))?;
+        }
+        if sub_mesh.smoothing_group != smoothing_group {
+            smoothing_group = sub_mesh.smoothing_group;
+            match smoothing_group {
+                0 => writeln!(writer, "s off")?,
+                group => writeln!(writer, "s {group}")?,
+            }
+        }
+        if sub_mesh.material.is_some() && sub_mesh.material != material {
+            material = sub_mesh.material.clone();
+            writeln!(writer, "usemtl {}", sub_mesh.material.as_deref().unwrap_or_default())?;
+        }
+
+        for position in primitive.get_positions() {
+            let position = sub_mesh.transform.transform_point(*position);
+            writeln!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+        }
+        for tex_coord in primitive.get_tex_coords() {
+            writeln!(writer, "vt {} {}", tex_coord.x, tex_coord.y)?;
+        }
+        // Vertex normals replace face ones, as read face normal is the normalized sum of its vertex normals
+        let has_vertex_normals = !primitive.get_vertex_normals().is_empty();
+        let normals = if has_vertex_normals { primitive.get_vertex_normals() } else { primitive.get_normals() };
+        for normal in normals {
+            let normal = sub_mesh.transform.transform_vector(*normal);
+            let normal = if normal.length2() > 0.0 { normal.normalized() } else { normal };
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+
+        let has_tex_coords = !primitive.get_tex_coords().is_empty();
+
+        for face in primitive.faces() {
+            write!(writer, "f")?;
+            for index in face.vertices {
+                let position = position_count + *index as usize + 1;
+                let normal = normal_count + if has_vertex_normals { *index } else { face.normal } as usize + 1;
+                if has_tex_coords {
+                    write!(writer, " {position}/{}/{normal}", tex_coord_count + *index as usize + 1)?;
+                } else {
+                    write!(writer, " {position}//{normal}")?;
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        for strip in primitive.line_strips() {
+            write!(writer, "l")?;
+            for index in strip {
+                write!(writer, " {}", position_count + *index as usize + 1)?;
+            }
+            writeln!(writer)?;
+        }
+
+        if !primitive.get_points().is_empty() {
+            write!(writer, "p")?;
+            for index in primitive.get_points() {
+                write!(writer, " {}", position_count + *index as usize + 1)?;
+            }
+            writeln!(writer)?;
+        }
+
+        position_count += primitive.get_positions().len();
+        tex_coord_count += primitive.get_tex_coords().len();
+        normal_count += normals.len();
+    }
+
+    writer.flush()
+}
+
+/// Parallel vertex statements parsing function
+/// * `source` - whole OBJ source
+/// * `chunk_count` - number of chunks to split source into, each chunk is parsed by its own thread
+/// * Returns vertex data of whole source, None if any vertex statement is invalid
+fn parse_vertices_parallel(source: &[u8], chunk_count: usize) -> Option<VertexData> {
+    let chunks = split_chunks(source, chunk_count);
+
+    let parse_chunk = |chunk: &[u8]| -> Option<VertexData> {
+        let mut vertices = VertexData::default();
+        let mut tokens = Vec::new();
+        let mut statements = Statements::new(chunk);
+
+        while statements.next(&mut tokens) {
+            let (statement, arguments) = tokens.split_first().unwrap();
+            vertices.parse(statement, arguments).ok()?;
+        }
+
+        Some(vertices)
+    };
+
+    let parsed = std::thread::scope(|scope| {
+        let handles = chunks[1..]
+            .iter()
+            .map(|chunk| scope.spawn(|| parse_chunk(chunk)))
+            .collect::<Vec<_>>();
+
+        // First chunk is parsed by current thread
+        let mut parsed = vec![parse_chunk(chunks[0])];
+        parsed.extend(handles.into_iter().map(|handle| handle.join().unwrap()));
+        parsed
+    });
+
+    let mut vertices = VertexData::default();
+    for chunk in parsed {
+        let chunk = chunk?;
+        vertices.positions.extend_from_slice(&chunk.positions);
+        vertices.normals.extend_from_slice(&chunk.normals);
+        vertices.tex_coords.extend_from_slice(&chunk.tex_coords);
+    }
+
+    Some(vertices)
+}
+
+/// Source splitting function
+/// * `source` - source to split
+/// * `count` - number of chunks
+/// * Returns chunks of roughly equal size, that end at statement ends
+fn split_chunks(source: &[u8], count: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::with_capacity(count);
+    let mut start = 0;
+
+    for index in 1..count {
+        let mut end = (source.len() * index / count).max(start);
+
+        // Chunk can't end after line, that continues on the next one
+        while end < source.len() {
+            let line_start = source[..end].iter().rposition(|c| *c == b'\n').map_or(0, |index| index + 1);
+            let line_length = source[line_start..].iter().position(|c| *c == b'\n').unwrap_or(source.len() - line_start);
+
+            end = (line_start + line_length + 1).min(source.len());
+            if !strip_line(&source[line_start..line_start + line_length]).1 {
+                break;
+            }
+        }
+
+        chunks.push(&source[start..end]);
+        start = end;
+    }
+    chunks.push(&source[start..]);
+
+    chunks
+}
+
+/// Name, that may contain spaces, joining function
+fn join_names(tokens: &[Token]) -> Result<String, LoadError> {
+    Ok(tokens.iter().map(Token::name).collect::<Result<Vec<_>, _>>()?.join(" "))
+}
+
+/// Line comment and continuation stripping function
+/// * `line` - single source line
+/// * Returns line without comment and continuation mark, and flag, that is set if line continues on next line
+fn strip_line(line: &[u8]) -> (&[u8], bool) {
+    let line = &line[..line.iter().position(|c| *c == b'#').unwrap_or(line.len())];
+    let line = line.trim_ascii_end();
+
+    match line.strip_suffix(b"\\") {
+        Some(line) => (line, true),
+        None => (line, false),
+    }
+}
+
+/// Single line splitting function
+/// * `line` - line to split
+/// * `line_number` - 1-based line number
+/// * `tokens` - vector to push line tokens to
+/// * Returns true if line ends with `\` and continues on next line
+fn tokenize_line<'a>(line: &'a [u8], line_number: usize, tokens: &mut Vec<Token<'a>>) -> bool {
+    let (line, continued) = strip_line(line);
+
+    let mut offset = 0;
+    loop {
+        while offset < line.len() && line[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        if offset == line.len() {
+            break;
+        }
+
+        let start = offset;
+        while offset < line.len() && !line[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        tokens.push(Token {
+            text: &line[start..offset],
+            line: line_number,
+            column: start + 1,
+        });
+    }
+
+    continued
+}
+
+/// Statement numbers parsing function
+/// * `N` - maximal number of numbers
+/// * `statement` - statement token
+/// * `arguments` - statement arguments
+/// * `count` - number of required numbers
+/// * `element` - parsed element name, used in error messages
+/// * Returns numbers, missing optional ones are 0
+fn parse_numbers<const N: usize>(statement: &Token, arguments: &[Token], count: usize, element: &str) -> Result<[f32; N], LoadError> {
+    if arguments.len() < count {
+        let end = arguments.last().unwrap_or(statement);
+        return Err(LoadError::syntax(end.end_position(), format!("{element} must have at least {count} components, got {}", arguments.len())));
+    }
+    if arguments.len() > N {
+        let extra = &arguments[N];
+        return Err(LoadError::syntax(extra.position(0), format!("unexpected {element} component '{}'", extra.display())));
+    }
+
+    let mut numbers = [0.0; N];
+    for (number, token) in numbers.iter_mut().zip(arguments) {
+        *number = match parse_float(token.text) {
+            Some(number) if number.is_finite() => number,
+            Some(_) => return Err(LoadError::syntax(token.position(0), format!("number '{}' is not finite", token.display()))),
+            None => return Err(LoadError::syntax(token.position(0), format!("expected number, got '{}'", token.display()))),
+        };
+    }
+
+    Ok(numbers)
+}
+
+/// Decimal number parsing function
+/// * `text` - number text
+/// * Returns correctly rounded number, None if text isn't a number
+pub(super) fn parse_float(text: &[u8]) -> Option<f32> {
+    const POWERS_OF_10: [f64; 23] = [
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11,
+        1e12, 1e13, 1e14, 1e15, 1e16, 1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+    ];
+
+    // Fast path for common `[sign]digits[.digits]` form, rest is parsed by standard library
+    let (negative, digits) = match text {
+        [b'-', digits @ ..] => (true, digits),
+        [b'+', digits @ ..] => (false, digits),
+        digits => (false, digits),
+    };
+
+    let mut mantissa = 0u64;
+    let mut digit_count = 0;
+    let mut fraction_length = None;
+    for (index, c) in digits.iter().enumerate() {
+        match c {
+            b'0'..=b'9' => {
+                mantissa = mantissa.wrapping_mul(10).wrapping_add((c - b'0') as u64);
+                digit_count += 1;
+            }
+            b'.' if fraction_length.is_none() => fraction_length = Some(digits.len() - index - 1),
+            _ => {
+                digit_count = usize::MAX;
+                break;
+            }
+        }
+    }
+
+    let exponent = fraction_length.unwrap_or(0);
+    if digit_count != 0 && digit_count <= 15 && exponent < POWERS_OF_10.len() {
+        // Both operands are exact, so quotient is correctly rounded double
+        let number = mantissa as f64 / POWERS_OF_10[exponent];
+
+        // Rounding double to float gives correctly rounded float unless double is exactly between two floats
+        let is_halfway = number.to_bits() & 0x1FFF_FFFF == 0x1000_0000;
+        if !is_halfway && (number == 0.0 || number >= f32::MIN_POSITIVE as f64) {
+            let number = number as f32;
+            return Some(if negative { -number } else { number });
+        }
+    }
+
+    std::str::from_utf8(text).ok()?.parse::<f32>().ok()
+}
+
+/// Decimal integer parsing function
+/// * `text` - number with optional sign
+/// * Returns number, None if text isn't a number or number doesn't fit into 64 bits
+pub(super) fn parse_integer(text: &[u8]) -> Option<i64> {
+    let (negative, digits) = match text {
+        [b'-', digits @ ..] => (true, digits),
+        [b'+', digits @ ..] => (false, digits),
+        digits => (false, digits),
+    };
+
+    if digits.is_empty() {
+        return None;
+    }
+
+    let number = digits.iter().try_fold(0i64, |number, c| {
+        let digit = c.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        number.checked_mul(10)?.checked_add(digit as i64)
+    })?;
+
+    Some(if negative { -number } else { number })
+}
+
+/// Face vertex parsing function
+/// * `token` - vertex token in `v`, `v/vt`, `v//vn` or `v/vt/vn` form
+/// * `data` - already loaded data, used to resolve indices
+/// * Returns vertex with resolved indices
+fn parse_face_vertex(token: &Token, data: &ObjData) -> Result<FaceVertex, LoadError> {
+    let mut components = token.text.split(|c| *c == b'/');
+
+    let position = components.next().unwrap_or(&[]);
+    let tex_coord = components.next();
+    let normal = components.next();
+
+    if components.next().is_some() || normal == Some(&[]) || (normal.is_none() && token.text.ends_with(b"/")) {
+        return Err(LoadError::syntax(
+            token.position(0),
+            format!("invalid face vertex '{}', expected v, v/vt, v//vn or v/vt/vn", token.display()),
+        ));
+    }
+
+    // Components are subslices of token, so their offsets give columns
+    let resolve = |component: &[u8], count: usize, element: &str| {
+        let offset = component.as_ptr() as usize - token.text.as_ptr() as usize;
+        resolve_index(component, count, element).map_err(|(is_range, message)| if is_range {
+            LoadError::index_range(token.position(offset), message)
+        } else {
+            LoadError::syntax(token.position(offset), message)
+        })
+    };
+
+    Ok(FaceVertex {
+        position: resolve(position, data.position_count, "position")?,
+        tex_coord: tex_coord
+            .filter(|component| !component.is_empty())
+            .map(|index| resolve(index, data.tex_coord_count, "texture coordinate"))
+            .transpose()?,
+        normal: normal.map(|index| resolve(index, data.normal_count, "normal")).transpose()?,
+    })
+}
+
+/// OBJ index resolving function
+/// * `token` - 1-based positive or relative negative index
+/// * `count` - number of already defined elements
+/// * `element` - indexed element name, used in error message
+/// * Returns zero-based index, or error message with flag, that is set if index is out of range
+fn resolve_index(token: &[u8], count: usize, element: &str) -> Result<u32, (bool, String)> {
+    let index = parse_integer(token).ok_or_else(|| (false, format!("expected {element} index, got '{}'", String::from_utf8_lossy(token))))?;
+
+    let resolved = match index {
+        1.. => index - 1,
+        ..0 => count as i64 + index,
+        0 => return Err((false, format!("{element} index must not be 0"))),
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err((true, format!("{element} index {index} is out of range, {count} {element}s defined")));
+    }
+
+    Ok(resolved as u32)
+}
+
+/// Face adding function
+/// * `data` - data to add face to
+/// * `face` - face vertices, at least 3
+fn add_face(data: &mut ObjData, face: &[FaceVertex]) {
+    let normal_sum = face.iter()
+        .filter_map(|vertex| vertex.normal)
+        .fold(Vec3f::new(0.0, 0.0, 0.0), |sum, index| sum + data.vertices.normals[index as usize]);
+
+    let normal = if face.iter().all(|vertex| vertex.normal.is_some()) && normal_sum.length2() > 0.0 {
+        normal_sum.normalized()
+    } else {
+        polygon_normal(face.len(), |index| data.vertices.positions[face[index].position as usize])
+    };
+
+    let current = &mut data.current;
+    current.faces.push(current.face_normals.len() as u32, face.iter().map(|vertex| vertex.position));
+    current.vertex_tex_coords.extend(face.iter().map(|vertex| vertex.tex_coord));
+    current.vertex_normals.extend(face.iter().map(|vertex| vertex.normal));
+    current.face_normals.push(normal);
+
+    data.face_count += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+";
+
+    /// Single sub-mesh OBJ reading function
+    fn read_primitive(text: &str) -> render::Primitive {
+        let mut model = read_obj(text.as_bytes()).unwrap();
+        assert_eq!(model.sub_meshes.len(), 1);
+        model.sub_meshes.remove(0).primitive
+    }
+
+    /// Face positions getting function
+    fn face_positions(primitive: &render::Primitive) -> Vec<Vec<[f32; 3]>> {
+        primitive.faces()
+            .map(|face| face.vertices.iter().map(|vertex| {
+                let position = primitive.get_positions()[*vertex as usize];
+                [position.x, position.y, position.z]
+            }).collect())
+            .collect()
+    }
+
+    /// Invalid OBJ reading function
+    fn read_error(text: &str) -> LoadError {
+        read_obj(text.as_bytes()).err().expect("source is invalid")
+    }
+
+    /// Error location line and column getting function
+    fn error_position(err: &LoadError) -> (usize, usize) {
+        let location = match err {
+            LoadError::Syntax { location, .. } | LoadError::IndexRange { location, .. } => location,
+            _ => panic!("unexpected error {err}"),
+        };
+        let Position::Text { line, column } = location.position else {
+            panic!("unexpected error position {:?}", location.position);
+        };
+        (line, column)
+    }
+
+    #[test]
+    fn reads_every_face_vertex_form() {
+        let expected = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]];
+
+        let forms = [
+            ("f 1 2 3", false),
+            ("f 1/1 2/2 3/3", true),
+            ("f 1//1 2//1 3//1", false),
+            ("f 1/1/1 2/2/1 3/3/1", true),
+        ];
+
+        for (face, has_tex_coords) in forms {
+            let primitive = read_primitive(&format!("{SQUARE}{face}\n"));
+
+            assert_eq!(face_positions(&primitive), vec![expected.clone()], "{face}");
+            assert_eq!(primitive.get_tex_coords().len(), if has_tex_coords { 3 } else { 0 }, "{face}");
+            assert!((primitive.get_normals()[0] - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12, "{face}");
+
+            // Referenced normals become vertex normals, other faces get smooth ones
+            assert_eq!(primitive.get_vertex_normals().len(), 3, "{face}");
+            assert!(primitive.get_vertex_normals().iter().all(|normal| (*normal - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12), "{face}");
+        }
+
+        let primitive = read_primitive(&format!("{SQUARE}f 1/2 2/3 3/4\n"));
+        let tex_coords = primitive.faces().next().unwrap().vertices.iter().map(|vertex| primitive.get_tex_coords()[*vertex as usize]).collect::<Vec<_>>();
+        assert_eq!(tex_coords.iter().map(|tex_coord| [tex_coord.x, tex_coord.y]).collect::<Vec<_>>(), vec![[1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    }
+
+    #[test]
+    fn resolves_negative_indices_relative_to_statement() {
+        let positive = read_primitive(&format!("{SQUARE}f 2/2/1 3/3/1 4/4/1\n"));
+        let negative = read_primitive(&format!("{SQUARE}f -3/-3/-1 -2/-2/-1 -1/-1/-1\nv 5 5 5\n"));
+
+        assert_eq!(face_positions(&positive), face_positions(&negative));
+
+        // Later vertices don't change meaning of already read relative indices
+        let primitive = read_primitive("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 0 0 1\nf -4 -3 -1\n");
+        assert_eq!(face_positions(&primitive), vec![
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        ]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        for (face, column) in [("f 1 2 5", 7), ("f 1 2 -5", 7), ("f 1/5 2 3", 5), ("f 1 2//2 3", 8)] {
+            let err = read_error(&format!("{SQUARE}{face}\n"));
+
+            assert!(matches!(err, LoadError::IndexRange { .. }), "{face}: {err}");
+            assert_eq!(error_position(&err), (10, column), "{face}");
+        }
+
+        // Elements, defined after face, can't be referenced
+        let err = read_error("v 0 0 0\nv 1 0 0\nf 1 2 3\nv 0 1 0\n");
+        assert!(matches!(err, LoadError::IndexRange { .. }), "{err}");
+    }
+
+    #[test]
+    fn rejects_malformed_face_vertices() {
+        for face in ["f 0 1 2", "f 1 2 x", "f 1/ 2 3", "f 1// 2 3", "f 1/1/1/1 2 3", "f 1 2"] {
+            let err = read_error(&format!("{SQUARE}{face}\n"));
+
+            assert!(matches!(err, LoadError::Syntax { .. }), "{face}: {err}");
+            assert_eq!(error_position(&err).0, 10, "{face}");
+        }
+    }
+
+    #[test]
+    fn splits_positions_at_texture_seams() {
+        // Two triangles share edge 1-3, but second one maps it to other texture coordinates
+        let primitive = read_primitive(&format!("{SQUARE}vt 2 0\nvt 2 1\nf 1/1 2/2 3/3\nf 1/5 3/6 4/4\n"));
+
+        assert_eq!(primitive.get_positions().len(), 6);
+        for (face, expected) in primitive.faces().zip([[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]], [[2.0, 0.0], [2.0, 1.0], [0.0, 1.0]]]) {
+            for (vertex, expected) in face.vertices.iter().zip(expected) {
+                let tex_coord = primitive.get_tex_coords()[*vertex as usize];
+                assert_eq!([tex_coord.x, tex_coord.y], expected);
+            }
+        }
+
+        // Coplanar faces are still smoothed across seam
+        let normals = primitive.get_vertex_normals();
+        assert!(normals.iter().all(|normal| (*normal - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12));
+
+        // Equal texture coordinates with different indices don't split positions
+        let primitive = read_primitive(&format!("{SQUARE}vt 0 0\nf 1/1 2/2 3/3\nf 1/5 3/3 4/4\n"));
+        assert_eq!(primitive.get_positions().len(), 4);
+    }
+
+    #[test]
+    fn line_strips_and_points_use_first_seam_vertex() {
+        let primitive = read_primitive(&format!("{SQUARE}vt 2 0\nf 1/1 2/2 3/3\nf 1/5 3/3 4/4\nl 1/1 3\np 1\n"));
+
+        assert_eq!(primitive.get_positions().len(), 5);
+        assert_eq!(primitive.line_strips().collect::<Vec<_>>(), vec![&[0, 2]]);
+        assert_eq!(primitive.get_points(), &[0]);
+    }
+
+    #[test]
+    fn separates_tokens_by_tabs_and_spaces() {
+        let spaced = read_primitive("v\t0 0\t0\n  v  1   0 0  \nv 1\t\t1 \t 0\n\tf  1\t2   3 \t\n");
+        let plain = read_primitive("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\n");
+
+        assert_eq!(face_positions(&spaced), face_positions(&plain));
+    }
+
+    #[test]
+    fn joins_continued_lines() {
+        let primitive = read_primitive("v 0 0 \\\n0\nv 1 0 0\nv 1 1 0\nf 1 \\\n2 \\\n3\n");
+        assert_eq!(face_positions(&primitive), vec![vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]]);
+
+        // Errors in continued lines are reported at their own lines
+        let err = read_error("v 0 0 0\nv 1 0 0\nf 1 2 \\\n  4\n");
+        assert!(matches!(err, LoadError::IndexRange { .. }), "{err}");
+        assert_eq!(error_position(&err), (4, 3));
+    }
+
+    #[test]
+    fn ignores_trailing_comments() {
+        // Backslash in comment doesn't continue line
+        let primitive = read_primitive("# square\nv 0 0 0 # origin\nv 1 0 0#x\nv 1 1 0\t# corner \\\nf 1 2 3 # face 4\n");
+        assert_eq!(face_positions(&primitive), vec![vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]]);
+    }
+
+    #[test]
+    fn rejects_unparsable_numbers() {
+        for (statement, column) in [("v 0 0 x", 7), ("v 1,5 0 0", 3), ("v 0 1e 0", 5), ("vt 0.5.5", 4), ("vn 0 0 nan", 8), ("v 0 0 1e39", 7)] {
+            let err = read_error(&format!("v 0 0 0\n{statement}\n"));
+
+            assert!(matches!(err, LoadError::Syntax { .. }), "{statement}: {err}");
+            assert_eq!(error_position(&err), (2, column), "{statement}");
+        }
+    }
+
+    /// Face vertex world positions, texture coordinates and normals getting function
+    fn face_vertices(sub_mesh: &SubMesh) -> Vec<[f32; 8]> {
+        let primitive = &sub_mesh.primitive;
+
+        primitive.faces().flat_map(|face| face.vertices.iter().map(move |vertex| {
+            let vertex = *vertex as usize;
+            let position = sub_mesh.transform.transform_point(primitive.get_positions()[vertex]);
+            let tex_coord = primitive.get_tex_coords().get(vertex).copied().unwrap_or(Vec2f::new(0.0, 0.0));
+            let normal = sub_mesh.transform.transform_vector(primitive.get_vertex_normals()[vertex]).normalized();
+
+            [position.x, position.y, position.z, tex_coord.x, tex_coord.y, normal.x, normal.y, normal.z]
+        })).collect()
+    }
+
+    /// Model writing and reading back function
+    fn write_and_read(model: &Model) -> Model {
+        let mut text = Vec::new();
+        write_obj(&mut text, model).unwrap();
+        read_obj(text.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn write_round_trip_keeps_vertex_attributes() {
+        let sub_mesh = |name: &str, groups: &[&str], material: Option<&str>, primitive, transform| SubMesh {
+            name: name.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            smoothing_group: 0,
+            material: material.map(str::to_string),
+            faces: 0..0,
+            primitive,
+            visible: true,
+            transform,
+        };
+        let model = Model {
+            sub_meshes: vec![
+                sub_mesh("ball", &["round", "smooth"], Some("stone"), mesh::uv_sphere(1.0, 8, 4), Mat4x4f::identity()),
+                sub_mesh("box", &["default"], Some("wood"), mesh::cube(2.0), Mat4x4f::rotate_y(0.5) * Mat4x4f::translate(3.0, 0.0, 0.0)),
+            ],
+            material_libraries: vec!["scene.mtl".to_string()],
+            materials: Vec::new(),
+            textures: Vec::new(),
+        };
+
+        let read = write_and_read(&model);
+
+        assert_eq!(read.material_libraries, model.material_libraries);
+        assert_eq!(read.sub_meshes.len(), 2);
+        for (read, written) in read.sub_meshes.iter().zip(&model.sub_meshes) {
+            assert_eq!((&read.name, &read.groups, &read.material), (&written.name, &written.groups, &written.material));
+            assert_eq!(read.primitive.get_faces().len(), written.primitive.get_faces().len());
+
+            let (read_vertices, written_vertices) = (face_vertices(read), face_vertices(written));
+            assert_eq!(read_vertices.len(), written_vertices.len());
+            for (read_vertex, written_vertex) in read_vertices.iter().zip(&written_vertices) {
+                assert!(read_vertex.iter().zip(written_vertex).all(|(read, written)| (read - written).abs() < 1e-6), "{read_vertex:?} != {written_vertex:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn write_round_trip_keeps_face_normals_without_vertex_normals() {
+        let positions = vec![Vec3f::new(0.0, 0.0, 0.0), Vec3f::new(1.0, 0.0, 0.0), Vec3f::new(0.0, 1.0, 0.0), Vec3f::new(0.0, 0.0, 1.0)];
+        let normals = vec![Vec3f::new(0.0, 0.0, -1.0), Vec3f::new(0.0, -1.0, 0.0), Vec3f::new(1.0, 1.0, 1.0).normalized(), Vec3f::new(-1.0, 0.0, 0.0)];
+        let mut faces = render::Faces::new();
+        for (normal, face) in [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]].into_iter().enumerate() {
+            faces.push(normal as u32, face);
+        }
+        let mut primitive = render::Primitive::new(positions, normals, faces, 0x00FF00).unwrap();
+        primitive.set_line_strips({
+            let mut strips = render::LineStrips::new();
+            strips.push([0, 1, 2]);
+            strips
+        }).unwrap();
+
+        let model = Model {
+            sub_meshes: vec![SubMesh {
+                name: String::new(),
+                groups: Vec::new(),
+                smoothing_group: 0,
+                material: None,
+                faces: 0..4,
+                primitive,
+                visible: true,
+                transform: Mat4x4f::identity(),
+            }],
+            material_libraries: Vec::new(),
+            materials: Vec::new(),
+            textures: Vec::new(),
+        };
+        let read = write_and_read(&model);
+        let (read, written) = (&read.sub_meshes[0].primitive, &model.sub_meshes[0].primitive);
+
+        assert_eq!(face_positions(read), face_positions(written));
+        for (read_face, written_face) in read.faces().zip(written.faces()) {
+            let (read_normal, written_normal) = (read.get_normals()[read_face.normal as usize], written.get_normals()[written_face.normal as usize]);
+            assert!((read_normal - written_normal).length2() < 1e-12);
+        }
+        assert_eq!(read.line_strips().count(), 1);
+    }
+
+    #[test]
+    fn computes_vertex_normals_without_normal_references() {
+        const CUBE: &str = "\
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 4 8 7 3
+f 1 5 8 4
+f 2 3 7 6
+";
+        // Hard edges split vertices, every corner gets normal of its side
+        let primitive = read_primitive(CUBE);
+        assert_eq!(primitive.get_positions().len(), 24);
+        for face in primitive.faces() {
+            let normal = primitive.get_normals()[face.normal as usize];
+            assert!(face.vertices.iter().all(|vertex| (primitive.get_vertex_normals()[*vertex as usize] - normal).length2() < 1e-10));
+        }
+
+        // Smoothing group and crease angle, wider than cube edges, smooth over every edge
+        let options = ObjOptions { crease_angle: std::f32::consts::PI, ..Default::default() };
+        for primitive in [read_primitive(&format!("s 1\n{CUBE}")), read_obj_with(CUBE.as_bytes(), &options).unwrap().sub_meshes.remove(0).primitive] {
+            assert_eq!(primitive.get_positions().len(), 8);
+            for (normal, position) in primitive.get_vertex_normals().iter().zip(primitive.get_positions()) {
+                assert!((*normal - position.normalized()).length2() < 1e-10);
+            }
+        }
+
+        // Partially referenced normals don't make vertex normals, faces keep face normals only
+        let primitive = read_primitive(&format!("{SQUARE}f 1//1 2//1 3//1\nf 1 3 4\n"));
+        assert!(primitive.get_vertex_normals().is_empty());
+    }
+}
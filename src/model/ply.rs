@@ -0,0 +1,697 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::math::*;
+use crate::mesh::{self, polygon_normal};
+use crate::render;
+use super::error::*;
+use super::obj::{parse_float, parse_integer};
+use super::text::{Token, Tokenizer};
+
+/// PLY body format
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// PLY scalar type
+#[derive(Copy, Clone, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    /// Type by name getting function
+    /// * `name` - PLY type name, both old (`uchar`) and new (`uint8`) ones are supported
+    fn from_name(name: &[u8]) -> Option<Self> {
+        Some(match name {
+            b"char" | b"int8" => Self::I8,
+            b"uchar" | b"uint8" => Self::U8,
+            b"short" | b"int16" => Self::I16,
+            b"ushort" | b"uint16" => Self::U16,
+            b"int" | b"int32" => Self::I32,
+            b"uint" | b"uint32" => Self::U32,
+            b"float" | b"float32" => Self::F32,
+            b"double" | b"float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    /// Type name getting function
+    fn name(self) -> &'static str {
+        match self {
+            Self::I8 => "char",
+            Self::U8 => "uchar",
+            Self::I16 => "short",
+            Self::U16 => "ushort",
+            Self::I32 => "int",
+            Self::U32 => "uint",
+            Self::F32 => "float",
+            Self::F64 => "double",
+        }
+    }
+
+    /// Binary value size getting function
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Value range getting function
+    /// * Returns minimal and maximal value of integer type, None for floating point types
+    fn integer_range(self) -> Option<(i64, i64)> {
+        match self {
+            Self::I8 => Some((i8::MIN as i64, i8::MAX as i64)),
+            Self::U8 => Some((0, u8::MAX as i64)),
+            Self::I16 => Some((i16::MIN as i64, i16::MAX as i64)),
+            Self::U16 => Some((0, u16::MAX as i64)),
+            Self::I32 => Some((i32::MIN as i64, i32::MAX as i64)),
+            Self::U32 => Some((0, u32::MAX as i64)),
+            Self::F32 | Self::F64 => None,
+        }
+    }
+}
+
+/// Element property type
+#[derive(Copy, Clone)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+/// Element property
+struct Property<'a> {
+    name: &'a [u8],
+    kind: PropertyType,
+}
+
+/// Element, declared in header
+struct Element<'a> {
+    name: &'a [u8],
+    count: usize,
+    properties: Vec<Property<'a>>,
+    /// Element declaration position
+    position: Position,
+}
+
+impl Element<'_> {
+    /// Minimal binary element size getting function
+    /// * Returns element size with all lists being empty
+    fn min_binary_size(&self) -> usize {
+        self.properties.iter().map(|property| match property.kind {
+            PropertyType::Scalar(kind) => kind.size(),
+            PropertyType::List { count, .. } => count.size(),
+        }).sum()
+    }
+}
+
+/// PLY body value reader
+enum Body<'a> {
+    Ascii(Tokenizer<'a>),
+    Binary { data: &'a [u8], offset: usize, format: Format },
+}
+
+impl Body<'_> {
+    /// Element storage capacity getting function, used before allocating storage for elements, count of which is read from header
+    /// * `element` - element, that is going to be read
+    /// * Returns element count for binary body, checked against the rest of file size,
+    ///   and zero for text one, as text values have no fixed size to check count against
+    fn capacity(&self, element: &Element) -> Result<usize, LoadError> {
+        let Self::Binary { data, offset, .. } = self else {
+            return Ok(0);
+        };
+
+        match element.count.checked_mul(element.min_binary_size()) {
+            Some(size) if size <= data.len() - *offset => Ok(element.count),
+            _ => Err(LoadError::syntax(element.position, format!(
+                "{} '{}' elements don't fit in {} bytes of file",
+                element.count,
+                String::from_utf8_lossy(element.name),
+                data.len() - *offset,
+            ))),
+        }
+    }
+
+    /// Value reading function
+    /// * `kind` - value type
+    /// * Returns value and its position in source
+    fn read(&mut self, kind: ScalarType) -> Result<(f64, Position), LoadError> {
+        match self {
+            Self::Ascii(tokenizer) => {
+                let token = tokenizer.next().ok_or_else(|| LoadError::syntax(tokenizer.position(), "expected number, got end of file"))?;
+
+                let value = match kind {
+                    ScalarType::F32 => parse_float(token.text).map(f64::from),
+                    ScalarType::F64 => std::str::from_utf8(token.text).ok().and_then(|text| text.parse::<f64>().ok()),
+                    _ => {
+                        let (min, max) = kind.integer_range().unwrap_or_default();
+                        parse_integer(token.text).filter(|value| (min..=max).contains(value)).map(|value| value as f64)
+                    }
+                };
+
+                match value {
+                    Some(value) => Ok((value, token.position)),
+                    None => Err(LoadError::syntax(token.position, format!("expected {} value, got '{}'", kind.name(), token.display()))),
+                }
+            }
+            Self::Binary { data, offset, format } => {
+                let position = Position::Binary { offset: *offset };
+                let Some(bytes) = data.get(*offset..*offset + kind.size()) else {
+                    return Err(LoadError::syntax(Position::Binary { offset: data.len() }, "unexpected end of file"));
+                };
+                *offset += kind.size();
+
+                // Bytes in little-endian order
+                let bytes = |size: usize| {
+                    let mut array = [0u8; 8];
+                    array[..size].copy_from_slice(bytes);
+                    if *format == Format::BinaryBigEndian {
+                        array[..size].reverse();
+                    }
+                    array
+                };
+
+                let value = match kind {
+                    ScalarType::I8 => bytes(1)[0] as i8 as f64,
+                    ScalarType::U8 => bytes(1)[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes(bytes(2)[..2].try_into().unwrap()) as f64,
+                    ScalarType::U16 => u16::from_le_bytes(bytes(2)[..2].try_into().unwrap()) as f64,
+                    ScalarType::I32 => i32::from_le_bytes(bytes(4)[..4].try_into().unwrap()) as f64,
+                    ScalarType::U32 => u32::from_le_bytes(bytes(4)[..4].try_into().unwrap()) as f64,
+                    ScalarType::F32 => f32::from_le_bytes(bytes(4)[..4].try_into().unwrap()) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(bytes(8)),
+                };
+
+                Ok((value, position))
+            }
+        }
+    }
+
+    /// List length reading function
+    /// * `kind` - list length type
+    /// * Returns length and its position in source
+    fn read_count(&mut self, kind: ScalarType) -> Result<(usize, Position), LoadError> {
+        let (count, position) = self.read(kind)?;
+
+        if count < 0.0 {
+            return Err(LoadError::syntax(position, format!("invalid list length {count}")));
+        }
+
+        Ok((count as usize, position))
+    }
+
+    /// Property value skipping function
+    /// * `kind` - property type
+    fn skip(&mut self, kind: PropertyType) -> Result<(), LoadError> {
+        match kind {
+            PropertyType::Scalar(kind) => {
+                self.read(kind)?;
+            }
+            PropertyType::List { count, item } => {
+                for _ in 0..self.read_count(count)?.0 {
+                    self.read(item)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Vertex property meaning
+#[derive(Copy, Clone, PartialEq)]
+enum VertexAttribute {
+    Position(usize),
+    Normal(usize),
+    /// Color channel, 0 is red
+    Color(usize),
+    Unknown,
+}
+
+impl VertexAttribute {
+    /// Attribute by property name getting function
+    fn from_name(name: &[u8]) -> Self {
+        match name {
+            b"x" => Self::Position(0),
+            b"y" => Self::Position(1),
+            b"z" => Self::Position(2),
+            b"nx" => Self::Normal(0),
+            b"ny" => Self::Normal(1),
+            b"nz" => Self::Normal(2),
+            b"red" | b"diffuse_red" | b"r" => Self::Color(0),
+            b"green" | b"diffuse_green" | b"g" => Self::Color(1),
+            b"blue" | b"diffuse_blue" | b"b" => Self::Color(2),
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// PLY file loading function
+/// * `path` - path to file
+/// * Returns primitive with all file faces
+pub fn load_ply(path: impl AsRef<Path>) -> Result<render::Primitive, LoadError> {
+    load_file(path.as_ref(), read_ply)
+}
+
+/// PLY reading function.
+///
+/// Supported formats are `ascii`, `binary_little_endian` and `binary_big_endian` of version 1.0
+/// with all standard scalar types under both old (`uchar`) and new (`uint8`) names.
+///
+/// `vertex` element must have `x`, `y` and `z` properties and may have `nx`, `ny` and `nz` normal properties and
+/// `red`, `green` and `blue` color properties (`diffuse_` prefixed and single letter names are supported too).
+/// Integer colors are in [0, 255] range (`ushort` ones are in [0, 65535]), floating point ones are in [0, 1] range.
+/// `face` element must have `vertex_indices` (or `vertex_index`) list of at least 3 indices.
+/// All other elements and properties are skipped.
+///
+/// Vertex colors and normals become primitive vertex colors and normals. Face normal is the normalized sum of its
+/// vertex normals, if file has them, and geometric normal otherwise. Files without vertex normals get smooth ones,
+/// computed by [`mesh::smooth_normals`] with default options, so vertices may be split along hard edges.
+/// If file has no faces, every vertex becomes point.
+/// * `reader` - PLY source
+/// * Returns primitive, or error with line and column for header and text body, or byte offset for binary body
+pub fn read_ply(reader: impl Read) -> Result<render::Primitive, LoadError> {
+    let data = read_bytes(reader)?;
+    let mut tokenizer = Tokenizer::new(&data);
+
+    let (format, elements) = read_header(&mut tokenizer)?;
+    let mut body = match format {
+        Format::Ascii => Body::Ascii(tokenizer),
+        format => Body::Binary { data: &data, offset: (tokenizer.get_offset() + 1).min(data.len()), format },
+    };
+
+    let vertex_count = elements.iter().find(|element| element.name == b"vertex").map_or(0, |element| element.count);
+
+    let mut positions = Vec::new();
+    let mut vertex_normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces = render::Faces::new();
+    let mut face_vertices = Vec::new();
+
+    for element in &elements {
+        let capacity = body.capacity(element)?;
+
+        match element.name {
+            b"vertex" => {
+                let attributes = element.properties.iter().map(|property| VertexAttribute::from_name(property.name)).collect::<Vec<_>>();
+                let has = |attribute: fn(usize) -> VertexAttribute| (0..3).all(|axis| attributes.contains(&attribute(axis)));
+
+                if !has(VertexAttribute::Position) {
+                    return Err(LoadError::syntax(element.position, "vertex element must have x, y and z properties"));
+                }
+                let has_normals = has(VertexAttribute::Normal);
+                let has_colors = has(VertexAttribute::Color);
+
+                positions.reserve(capacity);
+                vertex_normals.reserve(if has_normals { capacity } else { 0 });
+                colors.reserve(if has_colors { capacity } else { 0 });
+
+                for _ in 0..element.count {
+                    let mut position = [0.0f32; 3];
+                    let mut normal = [0.0f32; 3];
+                    let mut color = [0u32; 3];
+                    let mut start = None;
+
+                    for (property, attribute) in element.properties.iter().zip(&attributes) {
+                        let PropertyType::Scalar(kind) = property.kind else {
+                            body.skip(property.kind)?;
+                            continue;
+                        };
+
+                        let (value, value_position) = body.read(kind)?;
+                        start.get_or_insert(value_position);
+                        match *attribute {
+                            VertexAttribute::Position(axis) => position[axis] = value as f32,
+                            VertexAttribute::Normal(axis) => normal[axis] = value as f32,
+                            VertexAttribute::Color(channel) => color[channel] = match kind {
+                                ScalarType::F32 | ScalarType::F64 => (value.clamp(0.0, 1.0) * 255.0).round() as u32,
+                                ScalarType::U16 => value as u32 >> 8,
+                                _ => value.clamp(0.0, 255.0) as u32,
+                            },
+                            VertexAttribute::Unknown => {}
+                        }
+                    }
+
+                    if !position.iter().all(|coordinate| coordinate.is_finite()) {
+                        return Err(LoadError::syntax(start.unwrap_or(element.position), "vertex position is not finite"));
+                    }
+
+                    positions.push(Vec3f::new(position[0], position[1], position[2]));
+                    if has_normals {
+                        vertex_normals.push(Vec3f::new(normal[0], normal[1], normal[2]));
+                    }
+                    if has_colors {
+                        colors.push((color[0] << 16) | (color[1] << 8) | color[2]);
+                    }
+                }
+            }
+            b"face" => {
+                let Some(list) = element.properties.iter().position(|property| {
+                    matches!(property.kind, PropertyType::List { .. }) && (property.name == b"vertex_indices" || property.name == b"vertex_index")
+                }) else {
+                    return Err(LoadError::syntax(element.position, "face element must have vertex_indices list property"));
+                };
+
+                for _ in 0..element.count {
+                    for (index, property) in element.properties.iter().enumerate() {
+                        match property.kind {
+                            PropertyType::List { count, item } if index == list => {
+                                let (count, count_position) = body.read_count(count)?;
+                                if count < 3 {
+                                    return Err(LoadError::syntax(count_position, format!("face must have at least 3 vertices, got {count}")));
+                                }
+
+                                for _ in 0..count {
+                                    let (vertex, position) = body.read(item)?;
+                                    if vertex < 0.0 || vertex.fract() != 0.0 || vertex >= vertex_count as f64 {
+                                        return Err(LoadError::index_range(position, format!("vertex index {vertex} is out of range, {vertex_count} vertices defined")));
+                                    }
+                                    face_vertices.push(vertex as u32);
+                                }
+                                faces.push(faces.len() as u32, face_vertices.drain(..));
+                            }
+                            kind => body.skip(kind)?,
+                        }
+                    }
+                }
+            }
+            // Elements without properties have nothing to skip, whatever their count is
+            _ if element.properties.is_empty() => {}
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.skip(property.kind)?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Vertices may follow faces, so normals are computed after all elements are read
+    let mut normals = Vec::with_capacity(faces.len());
+    for face in &faces {
+        let face = face.vertices;
+        let normal_sum = face.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, index| match vertex_normals.get(*index as usize) {
+            Some(normal) => sum + *normal,
+            None => sum,
+        });
+
+        normals.push(if normal_sum.length2() > 0.0 {
+            normal_sum.normalized()
+        } else {
+            polygon_normal(face.len(), |index| positions[face[index] as usize])
+        });
+    }
+
+    let primitive_error = |err: render::PrimitiveError| LoadError::syntax(Position::Unknown, err.to_string());
+    let point_count = if faces.is_empty() { positions.len() as u32 } else { 0 };
+    let has_vertex_normals = !vertex_normals.is_empty();
+
+    let mut primitive = render::Primitive::new(positions, normals, faces, 0x00FF00).map_err(primitive_error)?;
+    primitive.set_colors(colors).map_err(primitive_error)?;
+    primitive.set_vertex_normals(vertex_normals).map_err(primitive_error)?;
+    if point_count != 0 {
+        primitive.set_points((0..point_count).collect()).map_err(primitive_error)?;
+    }
+
+    if has_vertex_normals || point_count != 0 {
+        Ok(primitive)
+    } else {
+        Ok(mesh::smooth_normals(&primitive, &mesh::NormalOptions::default()))
+    }
+}
+
+/// PLY header reading function
+/// * `tokenizer` - tokenizer at source start, is left at `end_header` line end
+/// * Returns body format and declared elements
+fn read_header<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<(Format, Vec<Element<'a>>), LoadError> {
+    match tokenizer.next() {
+        Some(token) if token.text == b"ply" => {}
+        Some(token) => return Err(LoadError::syntax(token.position, format!("expected 'ply' signature, got '{}'", token.display()))),
+        None => return Err(LoadError::syntax(tokenizer.position(), "expected 'ply' signature, got end of file")),
+    }
+
+    let mut format = None;
+    let mut elements = Vec::<Element>::new();
+
+    loop {
+        let Some(keyword) = tokenizer.next() else {
+            return Err(LoadError::syntax(tokenizer.position(), "expected 'end_header', got end of file"));
+        };
+
+        let mut argument = |name: &str| {
+            tokenizer.next_in_line().ok_or_else(|| LoadError::syntax(tokenizer.position(), format!("expected {name}")))
+        };
+
+        match keyword.text {
+            b"format" => {
+                let name = argument("format name")?;
+                let version = argument("format version")?;
+
+                format = Some(match name.text {
+                    b"ascii" => Format::Ascii,
+                    b"binary_little_endian" => Format::BinaryLittleEndian,
+                    b"binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(LoadError::unsupported(name.position, format!("PLY format '{}'", name.display()))),
+                });
+                if version.text != b"1.0" {
+                    return Err(LoadError::unsupported(version.position, format!("PLY version '{}'", version.display())));
+                }
+            }
+            b"comment" | b"obj_info" => {
+                tokenizer.skip_line();
+                continue;
+            }
+            b"element" => {
+                let name = argument("element name")?;
+                let count = argument("element count")?;
+
+                elements.push(Element {
+                    name: name.text,
+                    count: parse_integer(count.text)
+                        .and_then(|count| usize::try_from(count).ok())
+                        .ok_or_else(|| LoadError::syntax(count.position, format!("expected element count, got '{}'", count.display())))?,
+                    properties: Vec::new(),
+                    position: keyword.position,
+                });
+            }
+            b"property" => {
+                let kind = argument("property type")?;
+                let scalar = |token: &Token| {
+                    ScalarType::from_name(token.text).ok_or_else(|| LoadError::syntax(token.position, format!("unknown property type '{}'", token.display())))
+                };
+
+                let kind = if kind.text == b"list" {
+                    let count = argument("list length type")?;
+                    let item = argument("list item type")?;
+
+                    let count_type = scalar(&count)?;
+                    if count_type.integer_range().is_none() {
+                        return Err(LoadError::syntax(count.position, "list length type must be integer"));
+                    }
+                    PropertyType::List { count: count_type, item: scalar(&item)? }
+                } else {
+                    PropertyType::Scalar(scalar(&kind)?)
+                };
+                let name = argument("property name")?;
+
+                let Some(element) = elements.last_mut() else {
+                    return Err(LoadError::syntax(keyword.position, "property is declared before any element"));
+                };
+                element.properties.push(Property { name: name.text, kind });
+            }
+            b"end_header" => {
+                if format.is_none() {
+                    return Err(LoadError::syntax(keyword.position, "format isn't declared"));
+                }
+                tokenizer.skip_line();
+                break;
+            }
+            _ => return Err(LoadError::syntax(keyword.position, format!("unknown header keyword '{}'", keyword.display()))),
+        }
+
+        if let Some(token) = tokenizer.next_in_line() {
+            return Err(LoadError::syntax(token.position, format!("unexpected '{}'", token.display())));
+        }
+    }
+
+    Ok((format.unwrap_or(Format::Ascii), elements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of square with vertex colors, vertex list property and face flags, that is skipped
+    const SQUARE_HEADER: &str = "\
+        element vertex 4\n\
+        property float x\n\
+        property float y\n\
+        property float z\n\
+        property list uchar int weights\n\
+        property uchar red\n\
+        property uchar green\n\
+        property uchar blue\n\
+        element face 2\n\
+        property uchar flags\n\
+        property list uchar uint vertex_indices\n\
+        end_header\n";
+
+    /// Binary square building function
+    /// * `format` - PLY format name
+    /// * `to_bytes` - big or little endian byte converting function
+    fn binary_square(format: &str, to_bytes: fn(u32) -> [u8; 4]) -> Vec<u8> {
+        let mut data = format!("ply\nformat {format} 1.0\ncomment square\n{SQUARE_HEADER}").into_bytes();
+
+        for (index, [x, y]) in [[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].into_iter().enumerate() {
+            for value in [x, y, 0.0] {
+                data.extend(to_bytes(value.to_bits()));
+            }
+            data.push(index as u8);
+            data.extend(std::iter::repeat_n(to_bytes(7), index).flatten());
+            data.extend([255, 128, index as u8]);
+        }
+        for face in [[0u32, 1, 2], [0, 2, 3]] {
+            data.extend([9, 3]);
+            data.extend(face.into_iter().flat_map(to_bytes));
+        }
+
+        data
+    }
+
+    /// Reading error getting function
+    fn read_error(data: &[u8]) -> LoadError {
+        read_ply(data).err().expect("invalid PLY is accepted")
+    }
+
+    /// Square contents checking function
+    fn assert_square(primitive: &render::Primitive) {
+        let positions = primitive.get_positions();
+        assert_eq!(positions.len(), 4);
+        assert!((positions[2] - Vec3f::new(1.0, 1.0, 0.0)).length2() < 1e-12);
+
+        let faces = primitive.get_faces().iter().map(|face| face.vertices.to_vec()).collect::<Vec<_>>();
+        assert_eq!(faces, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+        assert_eq!(primitive.get_colors(), [0xFF8000, 0xFF8001, 0xFF8002, 0xFF8003]);
+        assert!(primitive.get_normals().iter().all(|normal| normal.z > 0.999));
+        assert!(primitive.get_vertex_normals().iter().all(|normal| normal.z > 0.999));
+    }
+
+    #[test]
+    fn reads_ascii() {
+        let text = format!("ply\nformat ascii 1.0\n{SQUARE_HEADER}\
+            0 0 0 0 255 128 0\n\
+            1 0 0 1 7 255 128 1\n\
+            1 1 0 2 7 7 255 128 2\n\
+            0 1 0 3 7 7 7 255 128 3\n\
+            9 3 0 1 2\n\
+            9 3 0 2 3\n");
+
+        assert_square(&read_ply(text.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_little_endian() {
+        assert_square(&read_ply(binary_square("binary_little_endian", u32::to_le_bytes).as_slice()).unwrap());
+    }
+
+    #[test]
+    fn reads_binary_big_endian() {
+        assert_square(&read_ply(binary_square("binary_big_endian", u32::to_be_bytes).as_slice()).unwrap());
+    }
+
+    #[test]
+    fn reads_vertex_normals_and_points() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 2\n\
+            property double x\nproperty double y\nproperty double z\n\
+            property float nx\nproperty float ny\nproperty float nz\n\
+            element edge 0\nend_header\n\
+            0 0 0 0 0 1\n1 0 0 0 1 0\n";
+        let primitive = read_ply(text.as_bytes()).unwrap();
+
+        assert!(primitive.get_faces().is_empty());
+        assert_eq!(primitive.get_points(), [0, 1]);
+        assert!(primitive.get_vertex_normals()[1].y > 0.999);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+
+        assert!(matches!(read_error(format!("{header}3 0 1 3\n").as_bytes()), LoadError::IndexRange { .. }));
+        assert!(matches!(read_error(format!("{header}2 0 1\n").as_bytes()), LoadError::Syntax { .. }));
+        match read_error(format!("{header}3 0 1 x\n").as_bytes()) {
+            LoadError::Syntax { location, .. } => assert!(matches!(location.position, Position::Text { line: 13, column: 7 })),
+            err => panic!("unexpected error: {err}"),
+        }
+        assert!(matches!(read_error(b"ply\nformat binary_middle_endian 1.0\nend_header\n"), LoadError::Unsupported { .. }));
+        assert!(matches!(read_error(b"ply\nformat ascii 2.0\nend_header\n"), LoadError::Unsupported { .. }));
+        assert!(matches!(read_error(b"ply\nproperty float x\nend_header\n"), LoadError::Syntax { .. }));
+    }
+
+    #[test]
+    fn rejects_truncated_body() {
+        let data = binary_square("binary_little_endian", u32::to_le_bytes);
+        let error = read_error(&data[..data.len() - 1]);
+        assert!(matches!(error, LoadError::Syntax { location, .. } if matches!(location.position, Position::Binary { offset } if offset == data.len() - 1)));
+
+        let text = format!("ply\nformat ascii 1.0\n{SQUARE_HEADER}0 0 0 0 255 128 0\n");
+        assert!(matches!(read_error(text.as_bytes()), LoadError::Syntax { message, .. } if message.contains("end of file")));
+    }
+
+    #[test]
+    fn rejects_counts_out_of_file() {
+        // Binary element count is checked before any allocation
+        let data = b"ply\nformat binary_little_endian 1.0\nelement vertex 4000000000000\n\
+            property float x\nproperty float y\nproperty float z\nend_header\n";
+        assert!(matches!(read_error(data), LoadError::Syntax { message, .. } if message.contains("don't fit")));
+
+        // Size of these elements overflows usize
+        let data = b"ply\nformat binary_big_endian 1.0\nelement vertex 4611686018427387904\n\
+            property double x\nproperty double y\nproperty double z\nend_header\n";
+        assert!(matches!(read_error(data), LoadError::Syntax { message, .. } if message.contains("don't fit")));
+
+        // Text one fails on the first missing value
+        let text = b"ply\nformat ascii 1.0\nelement vertex 4000000000000\n\
+            property float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n";
+        assert!(matches!(read_error(text), LoadError::Syntax { message, .. } if message.contains("end of file")));
+    }
+
+    #[test]
+    fn computes_vertex_normals_without_normal_properties() {
+        let header = "ply\nformat ascii 1.0\nelement vertex 8\n\
+            property float x\nproperty float y\nproperty float z\n";
+        let vertices = "-1 -1 -1\n1 -1 -1\n1 1 -1\n-1 1 -1\n-1 -1 1\n1 -1 1\n1 1 1\n-1 1 1\n";
+        let faces = "element face 6\nproperty list uchar int vertex_indices\nend_header\n";
+        let face_data = "4 0 3 2 1\n4 4 5 6 7\n4 0 1 5 4\n4 3 7 6 2\n4 0 4 7 3\n4 1 2 6 5\n";
+
+        // Hard edges split vertices, every corner gets normal of its side
+        let primitive = read_ply(format!("{header}{faces}{vertices}{face_data}").as_bytes()).unwrap();
+        assert_eq!(primitive.get_positions().len(), 24);
+        for face in primitive.faces() {
+            let normal = primitive.get_normals()[face.normal as usize];
+            assert!(face.vertices.iter().all(|vertex| (primitive.get_vertex_normals()[*vertex as usize] - normal).length2() < 1e-10));
+        }
+
+        // Given vertex normals are kept as is
+        let header = format!("{header}property float nx\nproperty float ny\nproperty float nz\n");
+        let vertices = vertices.lines().map(|line| format!("{line} 0 1 0\n")).collect::<String>();
+        let primitive = read_ply(format!("{header}{faces}{vertices}{face_data}").as_bytes()).unwrap();
+        assert_eq!(primitive.get_positions().len(), 8);
+        assert!(primitive.get_vertex_normals().iter().all(|normal| normal.y > 0.999));
+    }
+}
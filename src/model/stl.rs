@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::math::*;
+use crate::mesh::{self, polygon_normal};
+use crate::render;
+use super::error::*;
+use super::obj::parse_float;
+use super::text::{Token, Tokenizer};
+
+/// STL file format
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    /// Text format
+    Ascii,
+    /// Little-endian binary format, much more compact than text one
+    Binary,
+}
+
+/// Binary STL header, must not start with `solid` to be distinguishable from text STL
+const BINARY_HEADER: &[u8; 80] = b"s3d binary STL                                                                  ";
+
+/// Binary STL header and triangle count size
+const BINARY_HEADER_SIZE: usize = 84;
+
+/// Binary STL triangle record size
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// STL file loading function
+/// * `path` - path to file
+/// * Returns primitive with all file triangles
+pub fn load_stl(path: impl AsRef<Path>) -> Result<render::Primitive, LoadError> {
+    load_file(path.as_ref(), read_stl)
+}
+
+/// STL reading function.
+///
+/// Source is read as binary STL if its size matches triangle count in binary header, or if it doesn't start with `solid` keyword,
+/// and as text STL otherwise. Text STL may contain several solids, keywords are case-insensitive
+/// and facet loops may have more than 3 vertices.
+///
+/// Vertices with bitwise equal positions are welded, so primitive faces share positions. Every face gets
+/// stored facet normal, facets with zero or non-finite normals get geometric one, computed from vertex positions.
+/// Truncated sources, malformed text and non-finite positions are errors.
+/// * `reader` - STL source
+/// * Returns primitive, or error with line and column for text source, or byte offset for binary one
+pub fn read_stl(reader: impl Read) -> Result<render::Primitive, LoadError> {
+    let data = read_bytes(reader)?;
+
+    let binary_size = data
+        .get(80..BINARY_HEADER_SIZE)
+        .map(|count| BINARY_HEADER_SIZE as u64 + BINARY_TRIANGLE_SIZE as u64 * u32::from_le_bytes(count.try_into().unwrap()) as u64);
+
+    let is_text = data.trim_ascii_start().get(..5).is_some_and(|start| start.eq_ignore_ascii_case(b"solid"));
+    let mut mesh = if is_text && binary_size != Some(data.len() as u64) {
+        read_text_stl(&data)?
+    } else {
+        read_binary_stl(&data)?
+    };
+
+    let normals = std::mem::take(&mut mesh.normals);
+    let faces = std::mem::take(&mut mesh.faces);
+
+    render::Primitive::new(mesh.welder.positions, normals, faces, 0x00FF00)
+        .map_err(|err| LoadError::syntax(Position::Unknown, err.to_string()))
+}
+
+/// Fast position bit pattern hasher, based on FxHash
+#[derive(Default)]
+struct PositionHasher(u64);
+
+impl std::hash::Hasher for PositionHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u64(*byte as u64);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x517C_C1B7_2722_0A95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Position welder
+#[derive(Default)]
+struct Welder {
+    positions: Vec<Vec3f>,
+    /// Position index by position bit pattern
+    indices: HashMap<(u64, u32), u32, std::hash::BuildHasherDefault<PositionHasher>>,
+}
+
+impl Welder {
+    /// Position adding function
+    /// * `position` - finite position
+    /// * Returns index of position, equal to added one
+    fn add(&mut self, position: Vec3f) -> u32 {
+        // Zeros of both signs are welded
+        let bits = |value: f32| if value == 0.0 { 0 } else { value.to_bits() };
+        let key = (((bits(position.x) as u64) << 32) | bits(position.y) as u64, bits(position.z));
+
+        *self.indices.entry(key).or_insert_with(|| {
+            self.positions.push(position);
+            self.positions.len() as u32 - 1
+        })
+    }
+}
+
+/// Mesh, that is being read
+#[derive(Default)]
+struct MeshData {
+    welder: Welder,
+    normals: Vec<Vec3f>,
+    faces: render::Faces,
+}
+
+impl MeshData {
+    /// Face adding function
+    /// * `normal` - stored face normal
+    /// * `vertices` - face vertex positions
+    /// * `position` - face position in source, used in error messages
+    fn add_face(&mut self, normal: Vec3f, vertices: &[Vec3f], position: Position) -> Result<(), LoadError> {
+        if vertices.iter().any(|vertex| !(vertex.x.is_finite() && vertex.y.is_finite() && vertex.z.is_finite())) {
+            return Err(LoadError::syntax(position, "facet has non-finite vertex position"));
+        }
+
+        let is_valid = normal.x.is_finite() && normal.y.is_finite() && normal.z.is_finite() && normal.length2() > 0.0;
+        let normal = if is_valid {
+            normal.normalized()
+        } else {
+            polygon_normal(vertices.len(), |index| vertices[index])
+        };
+
+        self.faces.push(self.normals.len() as u32, vertices.iter().map(|vertex| self.welder.add(*vertex)));
+        self.normals.push(normal);
+
+        Ok(())
+    }
+}
+
+/// Binary STL parsing function
+/// * `data` - whole source
+fn read_binary_stl(data: &[u8]) -> Result<MeshData, LoadError> {
+    if data.len() < BINARY_HEADER_SIZE {
+        return Err(LoadError::syntax(Position::Binary { offset: data.len() }, "binary STL header is truncated"));
+    }
+
+    let triangle_count = u32::from_le_bytes(data[80..BINARY_HEADER_SIZE].try_into().unwrap()) as usize;
+    let available_count = (data.len() - BINARY_HEADER_SIZE) / BINARY_TRIANGLE_SIZE;
+    if available_count < triangle_count {
+        return Err(LoadError::syntax(
+            Position::Binary { offset: data.len() },
+            format!("binary STL is truncated, header declares {triangle_count} triangles, but file contains {available_count}"),
+        ));
+    }
+
+    let mut mesh = MeshData::default();
+    mesh.welder.positions.reserve(triangle_count / 2 + 3);
+    mesh.welder.indices.reserve(triangle_count / 2 + 3);
+    mesh.normals.reserve(triangle_count);
+    mesh.faces = render::Faces::with_capacity(triangle_count, triangle_count * 3);
+
+    let vector = |bytes: &[u8]| Vec3f::new(
+        f32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+        f32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+        f32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+    );
+
+    let triangles = data[BINARY_HEADER_SIZE..].chunks_exact(BINARY_TRIANGLE_SIZE).take(triangle_count);
+    for (index, triangle) in triangles.enumerate() {
+        let vertices = [vector(&triangle[12..24]), vector(&triangle[24..36]), vector(&triangle[36..48])];
+        let position = Position::Binary { offset: BINARY_HEADER_SIZE + index * BINARY_TRIANGLE_SIZE };
+
+        mesh.add_face(vector(&triangle[0..12]), &vertices, position)?;
+    }
+
+    Ok(mesh)
+}
+
+/// Keyword reading function
+/// * `tokenizer` - text STL tokenizer
+/// * `keyword` - expected lowercase keyword
+/// * Returns next token, or error if it isn't keyword
+fn expect<'a>(tokenizer: &mut Tokenizer<'a>, keyword: &str) -> Result<Token<'a>, LoadError> {
+    match tokenizer.next() {
+        Some(token) if token.text.eq_ignore_ascii_case(keyword.as_bytes()) => Ok(token),
+        Some(token) => Err(LoadError::syntax(token.position, format!("expected '{keyword}', got '{}'", token.display()))),
+        None => Err(LoadError::syntax(tokenizer.position(), format!("expected '{keyword}', got end of file"))),
+    }
+}
+
+/// Vector reading function
+/// * `tokenizer` - text STL tokenizer
+fn read_vector(tokenizer: &mut Tokenizer) -> Result<Vec3f, LoadError> {
+    let mut number = || match tokenizer.next() {
+        Some(token) => parse_float(token.text).ok_or_else(|| LoadError::syntax(token.position, format!("expected number, got '{}'", token.display()))),
+        None => Err(LoadError::syntax(tokenizer.position(), "expected number, got end of file")),
+    };
+
+    Ok(Vec3f::new(number()?, number()?, number()?))
+}
+
+/// Text STL parsing function
+/// * `data` - whole source, that starts with `solid`
+fn read_text_stl(data: &[u8]) -> Result<MeshData, LoadError> {
+    let mut tokenizer = Tokenizer::new(data);
+    let mut mesh = MeshData::default();
+    let mut vertices = Vec::new();
+
+    // Source may contain several solids
+    while let Some(token) = tokenizer.next() {
+        if !token.text.eq_ignore_ascii_case(b"solid") {
+            return Err(LoadError::syntax(token.position, format!("expected 'solid', got '{}'", token.display())));
+        }
+        tokenizer.skip_line();
+
+        loop {
+            let token = tokenizer.next().ok_or_else(|| LoadError::syntax(tokenizer.position(), "expected 'facet' or 'endsolid', got end of file"))?;
+
+            if token.text.eq_ignore_ascii_case(b"endsolid") {
+                tokenizer.skip_line();
+                break;
+            }
+            if !token.text.eq_ignore_ascii_case(b"facet") {
+                return Err(LoadError::syntax(token.position, format!("expected 'facet' or 'endsolid', got '{}'", token.display())));
+            }
+
+            expect(&mut tokenizer, "normal")?;
+            let normal = read_vector(&mut tokenizer)?;
+            expect(&mut tokenizer, "outer")?;
+            expect(&mut tokenizer, "loop")?;
+
+            vertices.clear();
+            let end = loop {
+                match tokenizer.next() {
+                    Some(token) if token.text.eq_ignore_ascii_case(b"vertex") => vertices.push(read_vector(&mut tokenizer)?),
+                    Some(token) if token.text.eq_ignore_ascii_case(b"endloop") => break token,
+                    Some(token) => return Err(LoadError::syntax(token.position, format!("expected 'vertex' or 'endloop', got '{}'", token.display()))),
+                    None => return Err(LoadError::syntax(tokenizer.position(), "expected 'vertex' or 'endloop', got end of file")),
+                }
+            };
+            if vertices.len() < 3 {
+                return Err(LoadError::syntax(end.position, format!("facet must have at least 3 vertices, got {}", vertices.len())));
+            }
+            expect(&mut tokenizer, "endfacet")?;
+
+            mesh.add_face(normal, &vertices, token.position)?;
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// STL file saving function
+/// * `path` - path to file
+/// * `primitive` - primitive to save
+/// * `format` - file format
+pub fn save_stl(path: impl AsRef<Path>, primitive: &render::Primitive, format: StlFormat) -> std::io::Result<()> {
+    write_stl(std::fs::File::create(path)?, primitive, format)
+}
+
+/// STL writing function.
+///
+/// Faces with more than 3 vertices are split into triangles by [`mesh::triangulate`], so concave faces are written exactly too.
+/// Every triangle gets normal of its face. Line strips and points can't be represented in STL and are skipped.
+/// * `writer` - destination
+/// * `primitive` - primitive to write
+/// * `format` - file format
+pub fn write_stl(writer: impl Write, primitive: &render::Primitive, format: StlFormat) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(writer);
+
+    let triangulated;
+    let primitive = if primitive.faces().any(|face| face.vertices.len() > 3) {
+        triangulated = mesh::triangulate(primitive).0;
+        &triangulated
+    } else {
+        primitive
+    };
+
+    match format {
+        StlFormat::Ascii => {
+            writeln!(writer, "solid")?;
+            for (normal, triangle) in triangles(primitive) {
+                writeln!(writer, "  facet normal {:e} {:e} {:e}", normal.x, normal.y, normal.z)?;
+                writeln!(writer, "    outer loop")?;
+                for vertex in triangle {
+                    writeln!(writer, "      vertex {:e} {:e} {:e}", vertex.x, vertex.y, vertex.z)?;
+                }
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+            writeln!(writer, "endsolid")?;
+        }
+        StlFormat::Binary => {
+            let triangle_count = u32::try_from(triangles(primitive).count())
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "too many triangles for binary STL"))?;
+
+            writer.write_all(BINARY_HEADER)?;
+            writer.write_all(&triangle_count.to_le_bytes())?;
+
+            for (normal, triangle) in triangles(primitive) {
+                for vector in std::iter::once(normal).chain(triangle) {
+                    writer.write_all(&vector.x.to_le_bytes())?;
+                    writer.write_all(&vector.y.to_le_bytes())?;
+                    writer.write_all(&vector.z.to_le_bytes())?;
+                }
+                // Attribute byte count
+                writer.write_all(&0u16.to_le_bytes())?;
+            }
+        }
+    }
+
+    writer.flush()
+}
+
+/// Triangle getting function
+/// * `primitive` - primitive with triangle faces only
+/// * Returns iterator over face normal and positions of every triangle
+fn triangles(primitive: &render::Primitive) -> impl Iterator<Item = (Vec3f, [Vec3f; 3])> + '_ {
+    let positions = primitive.get_positions();
+
+    primitive.faces().map(move |face| {
+        let normal = primitive.get_normals()[face.normal as usize];
+        (normal, [0, 1, 2].map(|index| positions[face.vertices[index] as usize]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Binary STL building function
+    /// * `header` - header start, padded by spaces
+    /// * `triangle_count` - declared triangle count
+    /// * `triangles` - normal and vertices of every triangle
+    fn binary_stl(header: &[u8], triangle_count: u32, triangles: &[[[f32; 3]; 4]]) -> Vec<u8> {
+        let mut data = header.to_vec();
+        data.resize(80, b' ');
+        data.extend(triangle_count.to_le_bytes());
+        for triangle in triangles {
+            data.extend(triangle.iter().flatten().flat_map(|value| value.to_le_bytes()));
+            data.extend([0, 0]);
+        }
+        data
+    }
+
+    /// Two triangles of unit square, second one has negative zeros and no normal
+    const SQUARE: [[[f32; 3]; 4]; 2] = [
+        [[0.0, 0.0, 2.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+        [[0.0, 0.0, 0.0], [-0.0, 0.0, 0.0], [1.0, 1.0, -0.0], [0.0, 1.0, 0.0]],
+    ];
+
+    /// Reading error getting function
+    fn read_error(data: &[u8]) -> LoadError {
+        read_stl(data).err().expect("invalid STL is accepted")
+    }
+
+    #[test]
+    fn reads_binary_with_solid_header() {
+        // Header starts with `solid`, but file size matches triangle count
+        for header in [&b"solid square"[..], b"binary square"] {
+            let primitive = read_stl(binary_stl(header, 2, &SQUARE).as_slice()).unwrap();
+
+            assert_eq!(primitive.get_faces().len(), 2);
+            assert!(primitive.get_normals().iter().all(|normal| (*normal - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn reads_text_solids() {
+        let text = b"  SOLID first\n\
+            facet normal 0 0 1\n outer loop\n vertex 0 0 0\n vertex 1 0 0\n vertex 1 1 0\n vertex 0 1 0\n endloop\n endfacet\n\
+            endsolid first\n\
+            solid second\n\
+            FACET NORMAL 0 0 0 OUTER LOOP VERTEX 0 0 0 VERTEX 0 -1 0 VERTEX 1 0 0 ENDLOOP ENDFACET\n\
+            endsolid\n";
+        let primitive = read_stl(&text[..]).unwrap();
+
+        let faces = primitive.get_faces().iter().map(|face| face.vertices.to_vec()).collect::<Vec<_>>();
+        assert_eq!(faces, vec![vec![0, 1, 2, 3], vec![0, 4, 1]]);
+        // Zero normal is replaced by geometric one
+        assert!((primitive.get_normals()[1] - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12);
+
+        let err = read_error(b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\n");
+        assert!(matches!(err, LoadError::Syntax { message, .. } if message.contains("at least 3 vertices")));
+        let err = read_error(b"solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nendfacet\n");
+        assert!(matches!(err, LoadError::Syntax { .. }));
+    }
+
+    #[test]
+    fn welds_bitwise_equal_positions() {
+        let primitive = read_stl(binary_stl(b"", 2, &SQUARE).as_slice()).unwrap();
+
+        // Shared corners are welded, zeros of both signs too
+        assert_eq!(primitive.get_positions().len(), 4);
+        let faces = primitive.get_faces().iter().map(|face| face.vertices.to_vec()).collect::<Vec<_>>();
+        assert_eq!(faces, vec![vec![0, 1, 2], vec![0, 2, 3]]);
+
+        // Nearly equal positions stay separate
+        let mut triangles = SQUARE;
+        triangles[1][1][0] = f32::from_bits(1);
+        let primitive = read_stl(binary_stl(b"", 2, &triangles).as_slice()).unwrap();
+        assert_eq!(primitive.get_positions().len(), 5);
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let data = binary_stl(b"", 3, &SQUARE);
+        match read_error(&data) {
+            LoadError::Syntax { location, message } => {
+                assert!(message.contains("declares 3 triangles"));
+                assert!(matches!(location.position, Position::Binary { offset } if offset == data.len()));
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+
+        let data = binary_stl(b"", 2, &SQUARE);
+        assert!(matches!(read_error(&data[..data.len() - 1]), LoadError::Syntax { message, .. } if message.contains("declares 2 triangles")));
+        assert!(matches!(read_error(&data[..83]), LoadError::Syntax { message, .. } if message.contains("header is truncated")));
+
+        // Count, that overflows file size, is checked before allocation
+        assert!(matches!(read_error(&binary_stl(b"", u32::MAX, &SQUARE)), LoadError::Syntax { .. }));
+
+        let mut triangles = SQUARE;
+        triangles[0][2][1] = f32::NAN;
+        assert!(matches!(read_error(&binary_stl(b"", 2, &triangles)), LoadError::Syntax { message, .. } if message.contains("non-finite")));
+    }
+
+    /// Face signed area in XY plane getting function
+    fn signed_area(primitive: &render::Primitive, vertices: &[u32]) -> f32 {
+        let positions = primitive.get_positions();
+        (0..vertices.len()).map(|index| {
+            let (current, next) = (positions[vertices[index] as usize], positions[vertices[(index + 1) % vertices.len()] as usize]);
+            current.x * next.y - next.x * current.y
+        }).sum::<f32>() / 2.0
+    }
+
+    #[test]
+    fn write_round_trip_splits_concave_faces() {
+        // L-shaped hexagon, triangle fan around its first vertex would overlap itself
+        let positions = [(2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 0.0), (2.0, 0.0)]
+            .map(|(x, y)| Vec3f::new(x, y, 0.0))
+            .to_vec();
+        let mut faces = render::Faces::new();
+        faces.push(0, 0..6);
+        let primitive = render::Primitive::new(positions, vec![Vec3f::new(0.0, 0.0, 1.0)], faces, 0x00FF00).unwrap();
+
+        for format in [StlFormat::Ascii, StlFormat::Binary] {
+            let mut data = Vec::new();
+            write_stl(&mut data, &primitive, format).unwrap();
+            let read = read_stl(data.as_slice()).unwrap();
+
+            assert_eq!(read.get_positions().len(), 6, "{format:?}");
+            assert_eq!(read.get_faces().len(), 4, "{format:?}");
+            for face in read.faces() {
+                assert!(signed_area(&read, face.vertices) > 0.0, "{format:?}");
+                assert!((read.get_normals()[face.normal as usize] - Vec3f::new(0.0, 0.0, 1.0)).length2() < 1e-12, "{format:?}");
+            }
+            assert_eq!(read.faces().map(|face| signed_area(&read, face.vertices)).sum::<f32>(), 3.0, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn write_round_trip_keeps_triangles() {
+        let primitive = mesh::triangulate(&mesh::icosphere(1.0, 1)).0;
+
+        for format in [StlFormat::Ascii, StlFormat::Binary] {
+            let mut data = Vec::new();
+            write_stl(&mut data, &primitive, format).unwrap();
+            let read = read_stl(data.as_slice()).unwrap();
+
+            assert_eq!(read.get_faces().len(), primitive.get_faces().len(), "{format:?}");
+            for (read_face, face) in read.faces().zip(primitive.faces()) {
+                for (read_vertex, vertex) in read_face.vertices.iter().zip(face.vertices) {
+                    let (read_position, position) = (read.get_positions()[*read_vertex as usize], primitive.get_positions()[*vertex as usize]);
+                    assert!((read_position - position).length2() < 1e-12, "{format:?}");
+                }
+                let (read_normal, normal) = (read.get_normals()[read_face.normal as usize], primitive.get_normals()[face.normal as usize]);
+                assert!((read_normal - normal).length2() < 1e-12, "{format:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use super::error::*;
+
+/// Text source token
+pub(super) struct Token<'a> {
+    pub text: &'a [u8],
+    pub position: Position,
+}
+
+impl<'a> Token<'a> {
+    /// Token text for error messages getting function
+    pub fn display(&self) -> std::borrow::Cow<'a, str> {
+        String::from_utf8_lossy(self.text)
+    }
+}
+
+/// Whitespace separated text source splitter
+pub(super) struct Tokenizer<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// 1-based current line number
+    line: usize,
+    /// Offset of current line start
+    line_start: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Tokenizer create function
+    /// * `data` - source to split
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0, line: 1, line_start: 0 }
+    }
+
+    /// Current offset in source getting function
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Current position getting function
+    pub fn position(&self) -> Position {
+        Position::Text { line: self.line, column: self.offset - self.line_start + 1 }
+    }
+
+    /// Next token reading function
+    /// * Returns next token, None at the end of source
+    pub fn next(&mut self) -> Option<Token<'a>> {
+        while self.offset < self.data.len() && self.data[self.offset].is_ascii_whitespace() {
+            if self.data[self.offset] == b'\n' {
+                self.line += 1;
+                self.line_start = self.offset + 1;
+            }
+            self.offset += 1;
+        }
+
+        self.token()
+    }
+
+    /// Next token of current line reading function
+    /// * Returns next token, None at the end of line
+    pub fn next_in_line(&mut self) -> Option<Token<'a>> {
+        while self.offset < self.data.len() && self.data[self.offset] != b'\n' && self.data[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+
+        match self.data.get(self.offset) {
+            Some(b'\n') => None,
+            _ => self.token(),
+        }
+    }
+
+    /// Token, that starts at current offset, reading function
+    fn token(&mut self) -> Option<Token<'a>> {
+        if self.offset == self.data.len() {
+            return None;
+        }
+
+        let position = self.position();
+        let start = self.offset;
+        while self.offset < self.data.len() && !self.data[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+
+        Some(Token { text: &self.data[start..self.offset], position })
+    }
+
+    /// Rest of line skipping function
+    /// * Returns skipped text without line end
+    pub fn skip_line(&mut self) -> &'a [u8] {
+        let start = self.offset;
+        while self.offset < self.data.len() && self.data[self.offset] != b'\n' {
+            self.offset += 1;
+        }
+
+        &self.data[start..self.offset]
+    }
+}
@@ -0,0 +1,321 @@
+use super::PrimitiveError;
+
+/// Polygon face list.
+///
+/// Faces are stored as offset table over single vertex index stream plus face normal index stream, so face `i`
+/// has vertices `vertices[offsets[i]..offsets[i + 1]]` and normal `normals[i]`. Offset table always starts with zero
+/// and ends with vertex index count, so face layout can't be broken by construction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Faces {
+    /// Start of every face in vertices, followed by vertex index count
+    offsets: Vec<u32>,
+    /// Position indices of all faces
+    vertices: Vec<u32>,
+    /// Normal index per face
+    normals: Vec<u32>,
+}
+
+/// Single primitive face reference
+#[derive(Copy, Clone)]
+pub struct Face<'a> {
+    /// Index of face in primitive faces
+    pub index: usize,
+    /// Index of face normal
+    pub normal: u32,
+    /// Indices of face vertices
+    pub vertices: &'a [u32],
+}
+
+/// Faces iterator
+#[derive(Clone)]
+pub struct FaceIter<'a> {
+    faces: &'a Faces,
+    index: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for FaceIter<'a> {
+    type Item = Face<'a>;
+
+    fn next(&mut self) -> Option<Face<'a>> {
+        if self.index >= self.end {
+            return None;
+        }
+
+        let face = self.faces.face_at(self.index);
+        self.index += 1;
+
+        Some(face)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end.saturating_sub(self.index);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for FaceIter<'_> {}
+
+impl Default for Faces {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Faces {
+    /// Empty face list create function
+    pub fn new() -> Self {
+        Self { offsets: vec![0], vertices: Vec::new(), normals: Vec::new() }
+    }
+
+    /// Empty face list with preallocated storage create function
+    /// * `face_count` - expected number of faces
+    /// * `vertex_count` - expected total number of face vertices
+    pub fn with_capacity(face_count: usize, vertex_count: usize) -> Self {
+        let mut offsets = Vec::with_capacity(face_count + 1);
+        offsets.push(0);
+
+        Self { offsets, vertices: Vec::with_capacity(vertex_count), normals: Vec::with_capacity(face_count) }
+    }
+
+    /// Face list from raw streams create function
+    /// * `offsets` - face starts in vertices followed by vertex count, starting with zero and non-decreasing
+    /// * `vertices` - position indices of all faces
+    /// * `normals` - normal index per face
+    /// * Returns face list, or error if streams don't match each other
+    pub fn from_streams(offsets: Vec<u32>, vertices: Vec<u32>, normals: Vec<u32>) -> Result<Self, PrimitiveError> {
+        if offsets.len() != normals.len() + 1 {
+            return Err(PrimitiveError::FaceNormalCountMismatch { count: normals.len(), face_count: offsets.len().saturating_sub(1) });
+        }
+
+        if offsets[0] != 0 {
+            return Err(PrimitiveError::InvalidFaceOffset { entry: 0 });
+        }
+        if let Some(entry) = offsets.windows(2).position(|pair| pair[1] < pair[0] || pair[1] as usize > vertices.len()) {
+            return Err(PrimitiveError::InvalidFaceOffset { entry: entry + 1 });
+        }
+        if offsets[offsets.len() - 1] as usize != vertices.len() {
+            return Err(PrimitiveError::InvalidFaceOffset { entry: offsets.len() - 1 });
+        }
+
+        Ok(Self { offsets, vertices, normals })
+    }
+
+    /// Face adding function
+    /// * `normal` - index of face normal
+    /// * `vertices` - position indices of face vertices
+    /// * Returns index of added face
+    pub fn push(&mut self, normal: u32, vertices: impl IntoIterator<Item = u32>) -> usize {
+        self.vertices.extend(vertices);
+        self.offsets.push(self.vertices.len() as u32);
+        self.normals.push(normal);
+
+        self.normals.len() - 1
+    }
+
+    /// Face list appending function
+    /// * `faces` - faces to append
+    /// * `vertex_base` - number to add to every appended vertex index
+    /// * `normal_base` - number to add to every appended normal index
+    pub fn append(&mut self, faces: &Faces, vertex_base: u32, normal_base: u32) {
+        for face in faces.iter() {
+            self.push(face.normal + normal_base, face.vertices.iter().map(|vertex| vertex + vertex_base));
+        }
+    }
+
+    /// Face count getting function
+    pub fn len(&self) -> usize {
+        self.normals.len()
+    }
+
+    /// Face list emptiness checking function
+    pub fn is_empty(&self) -> bool {
+        self.normals.is_empty()
+    }
+
+    /// Face getting function
+    /// * `index` - face index
+    /// * Returns face, if index is in range
+    pub fn get(&self, index: usize) -> Option<Face<'_>> {
+        (index < self.len()).then(|| self.face_at(index))
+    }
+
+    /// Face getting function
+    /// * `index` - face index, panics if it's out of range
+    pub(super) fn face_at(&self, index: usize) -> Face<'_> {
+        Face {
+            index,
+            normal: self.normals[index],
+            vertices: &self.vertices[self.offsets[index] as usize..self.offsets[index + 1] as usize],
+        }
+    }
+
+    /// Faces iterating function
+    pub fn iter(&self) -> FaceIter<'_> {
+        self.iter_range(0..self.len())
+    }
+
+    /// Face range iterating function
+    /// * `range` - range of face indices, clamped to face count
+    /// * Returns iterator over faces in range
+    pub fn iter_range(&self, range: std::ops::Range<usize>) -> FaceIter<'_> {
+        FaceIter { faces: self, index: range.start, end: range.end.min(self.len()) }
+    }
+
+    /// Face list in given face order building function
+    /// * `order` - indices of faces to take
+    /// * Returns new face list with faces in order given
+    pub fn reordered(&self, order: impl IntoIterator<Item = usize>) -> Self {
+        let mut faces = Self::with_capacity(self.len(), self.vertices.len());
+        for index in order {
+            let face = self.face_at(index);
+            faces.push(face.normal, face.vertices.iter().copied());
+        }
+
+        faces
+    }
+
+    /// Face offsets getting function
+    /// * Returns start of every face in vertices, followed by vertex index count
+    pub fn get_offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Face vertices getting function
+    /// * Returns position indices of all faces, face after face
+    pub fn get_vertices(&self) -> &[u32] {
+        &self.vertices
+    }
+
+    /// Mutable face vertices getting function
+    /// * Returns position indices of all faces, that may be replaced, while face layout stays unchanged
+    pub fn get_vertices_mut(&mut self) -> &mut [u32] {
+        &mut self.vertices
+    }
+
+    /// Face normal indices getting function
+    /// * Returns normal index per face
+    pub fn get_normals(&self) -> &[u32] {
+        &self.normals
+    }
+}
+
+impl<V: IntoIterator<Item = u32>> FromIterator<(u32, V)> for Faces {
+    /// Face list from normal index and vertex indices of every face building function
+    fn from_iter<I: IntoIterator<Item = (u32, V)>>(faces: I) -> Self {
+        let mut result = Self::new();
+        for (normal, vertices) in faces {
+            result.push(normal, vertices);
+        }
+
+        result
+    }
+}
+
+impl<'a> IntoIterator for &'a Faces {
+    type Item = Face<'a>;
+    type IntoIter = FaceIter<'a>;
+
+    fn into_iter(self) -> FaceIter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Triangle, empty face and quad list building function
+    fn faces() -> Faces {
+        let mut faces = Faces::new();
+        assert_eq!(faces.push(0, [0, 1, 2]), 0);
+        assert_eq!(faces.push(1, []), 1);
+        assert_eq!(faces.push(2, [3, 4, 5, 6]), 2);
+        faces
+    }
+
+    /// Face normal and vertices collecting function
+    fn collect<'a>(faces: impl Iterator<Item = Face<'a>>) -> Vec<(usize, u32, Vec<u32>)> {
+        faces.map(|face| (face.index, face.normal, face.vertices.to_vec())).collect()
+    }
+
+    #[test]
+    fn pushes_and_iterates_faces() {
+        let faces = faces();
+
+        assert_eq!(faces.len(), 3);
+        assert!(!faces.is_empty());
+        assert_eq!(faces.get_offsets(), [0, 3, 3, 7]);
+        assert_eq!(faces.get_normals(), [0, 1, 2]);
+        assert_eq!(collect(faces.iter()), vec![(0, 0, vec![0, 1, 2]), (1, 1, vec![]), (2, 2, vec![3, 4, 5, 6])]);
+        assert_eq!(faces.iter().len(), 3);
+        assert_eq!((&faces).into_iter().count(), 3);
+
+        assert!(faces.get(1).unwrap().vertices.is_empty());
+        assert!(faces.get(3).is_none());
+    }
+
+    #[test]
+    fn iterates_face_ranges() {
+        let faces = faces();
+
+        assert_eq!(collect(faces.iter_range(1..3)), collect(faces.iter().skip(1)));
+        assert_eq!(faces.iter_range(1..2).len(), 1);
+
+        // Ranges are clamped to face count
+        assert_eq!(collect(faces.iter_range(2..10)), vec![(2, 2, vec![3, 4, 5, 6])]);
+        assert_eq!(faces.iter_range(5..10).count(), 0);
+    }
+
+    #[test]
+    fn builds_faces_with_capacity() {
+        let mut faces = Faces::with_capacity(3, 7);
+
+        assert!(faces.is_empty());
+        assert_eq!(faces.get_offsets(), [0]);
+        assert_eq!(faces.iter().count(), 0);
+        assert_eq!(faces, Faces::default());
+
+        faces.push(0, [0, 1, 2]);
+        faces.push(1, []);
+        faces.push(2, [3, 4, 5, 6]);
+        assert_eq!(faces, self::faces());
+        assert_eq!(faces, [(0, vec![0, 1, 2]), (1, vec![]), (2, vec![3, 4, 5, 6])].into_iter().collect());
+    }
+
+    #[test]
+    fn appends_and_reorders_faces() {
+        let mut faces = faces();
+        faces.append(&self::faces(), 10, 3);
+
+        assert_eq!(faces.len(), 6);
+        assert_eq!(collect(faces.iter_range(3..6)), vec![(3, 3, vec![10, 11, 12]), (4, 4, vec![]), (5, 5, vec![13, 14, 15, 16])]);
+
+        let reordered = self::faces().reordered([2, 0, 1]);
+        assert_eq!(collect(reordered.iter()), vec![(0, 2, vec![3, 4, 5, 6]), (1, 0, vec![0, 1, 2]), (2, 1, vec![])]);
+    }
+
+    #[test]
+    fn creates_faces_from_streams() {
+        let faces = Faces::from_streams(vec![0, 3, 3, 7], vec![0, 1, 2, 3, 4, 5, 6], vec![0, 1, 2]).unwrap();
+        assert_eq!(faces, self::faces());
+
+        let empty = Faces::from_streams(vec![0], Vec::new(), Vec::new()).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_streams() {
+        let vertices = vec![0, 1, 2, 3];
+
+        assert_eq!(
+            Faces::from_streams(vec![0, 3, 4], vertices.clone(), vec![0]),
+            Err(PrimitiveError::FaceNormalCountMismatch { count: 1, face_count: 2 })
+        );
+        assert_eq!(Faces::from_streams(Vec::new(), Vec::new(), Vec::new()), Err(PrimitiveError::FaceNormalCountMismatch { count: 0, face_count: 0 }));
+        assert_eq!(Faces::from_streams(vec![1, 4], vertices.clone(), vec![0]), Err(PrimitiveError::InvalidFaceOffset { entry: 0 }));
+        assert_eq!(Faces::from_streams(vec![0, 3, 2, 4], vertices.clone(), vec![0; 3]), Err(PrimitiveError::InvalidFaceOffset { entry: 2 }));
+        assert_eq!(Faces::from_streams(vec![0, 5, 4], vertices.clone(), vec![0; 2]), Err(PrimitiveError::InvalidFaceOffset { entry: 1 }));
+        assert_eq!(Faces::from_streams(vec![0, 3], vertices, vec![0]), Err(PrimitiveError::InvalidFaceOffset { entry: 1 }));
+    }
+}
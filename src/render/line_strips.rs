@@ -0,0 +1,234 @@
+use super::PrimitiveError;
+
+/// Line strip list.
+///
+/// Line strips are stored as offset table over single vertex index stream, so strip `i` has vertices
+/// `vertices[offsets[i]..offsets[i + 1]]`. Offset table always starts with zero and ends with vertex index count,
+/// same as face one, so strip layout can't be broken by construction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineStrips {
+    /// Start of every strip in vertices, followed by vertex index count
+    offsets: Vec<u32>,
+    /// Position indices of all strips
+    vertices: Vec<u32>,
+}
+
+/// Line strips iterator
+#[derive(Clone)]
+pub struct LineStripIter<'a> {
+    strips: &'a LineStrips,
+    index: usize,
+}
+
+impl<'a> Iterator for LineStripIter<'a> {
+    type Item = &'a [u32];
+
+    fn next(&mut self) -> Option<&'a [u32]> {
+        let strip = self.strips.get(self.index)?;
+        self.index += 1;
+
+        Some(strip)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.strips.len().saturating_sub(self.index);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for LineStripIter<'_> {}
+
+impl Default for LineStrips {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineStrips {
+    /// Empty line strip list create function
+    pub fn new() -> Self {
+        Self { offsets: vec![0], vertices: Vec::new() }
+    }
+
+    /// Empty line strip list with preallocated storage create function
+    /// * `strip_count` - expected number of strips
+    /// * `vertex_count` - expected total number of strip vertices
+    pub fn with_capacity(strip_count: usize, vertex_count: usize) -> Self {
+        let mut offsets = Vec::with_capacity(strip_count + 1);
+        offsets.push(0);
+
+        Self { offsets, vertices: Vec::with_capacity(vertex_count) }
+    }
+
+    /// Line strip list from raw streams create function
+    /// * `offsets` - strip starts in vertices followed by vertex count, starting with zero and non-decreasing
+    /// * `vertices` - position indices of all strips
+    /// * Returns line strip list, or error if offsets don't match vertices
+    pub fn from_streams(offsets: Vec<u32>, vertices: Vec<u32>) -> Result<Self, PrimitiveError> {
+        if offsets.first() != Some(&0) {
+            return Err(PrimitiveError::InvalidLineStripOffset { entry: 0 });
+        }
+        if let Some(entry) = offsets.windows(2).position(|pair| pair[1] < pair[0] || pair[1] as usize > vertices.len()) {
+            return Err(PrimitiveError::InvalidLineStripOffset { entry: entry + 1 });
+        }
+        if offsets[offsets.len() - 1] as usize != vertices.len() {
+            return Err(PrimitiveError::InvalidLineStripOffset { entry: offsets.len() - 1 });
+        }
+
+        Ok(Self { offsets, vertices })
+    }
+
+    /// Line strip adding function
+    /// * `vertices` - position indices of strip vertices
+    /// * Returns index of added strip
+    pub fn push(&mut self, vertices: impl IntoIterator<Item = u32>) -> usize {
+        self.vertices.extend(vertices);
+        self.offsets.push(self.vertices.len() as u32);
+
+        self.offsets.len() - 2
+    }
+
+    /// Line strip list appending function
+    /// * `strips` - strips to append
+    /// * `vertex_base` - number to add to every appended vertex index
+    pub fn append(&mut self, strips: &LineStrips, vertex_base: u32) {
+        for strip in strips {
+            self.push(strip.iter().map(|vertex| vertex + vertex_base));
+        }
+    }
+
+    /// Line strip count getting function
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Line strip list emptiness checking function
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Line strip getting function
+    /// * `index` - strip index
+    /// * Returns strip vertex indices, if index is in range
+    pub fn get(&self, index: usize) -> Option<&[u32]> {
+        let (start, end) = (*self.offsets.get(index)?, *self.offsets.get(index + 1)?);
+        Some(&self.vertices[start as usize..end as usize])
+    }
+
+    /// Line strips iterating function
+    pub fn iter(&self) -> LineStripIter<'_> {
+        LineStripIter { strips: self, index: 0 }
+    }
+
+    /// Line strip offsets getting function
+    /// * Returns start of every strip in vertices, followed by vertex index count
+    pub fn get_offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Line strip vertices getting function
+    /// * Returns position indices of all strips, strip after strip
+    pub fn get_vertices(&self) -> &[u32] {
+        &self.vertices
+    }
+
+    /// Mutable line strip vertices getting function
+    /// * Returns position indices of all strips, that may be replaced, while strip layout stays unchanged
+    pub fn get_vertices_mut(&mut self) -> &mut [u32] {
+        &mut self.vertices
+    }
+}
+
+impl<V: IntoIterator<Item = u32>> FromIterator<V> for LineStrips {
+    /// Line strip list from vertex indices of every strip building function
+    fn from_iter<I: IntoIterator<Item = V>>(strips: I) -> Self {
+        let mut result = Self::new();
+        for vertices in strips {
+            result.push(vertices);
+        }
+
+        result
+    }
+}
+
+impl<'a> IntoIterator for &'a LineStrips {
+    type Item = &'a [u32];
+    type IntoIter = LineStripIter<'a>;
+
+    fn into_iter(self) -> LineStripIter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two strips around empty one building function
+    fn strips() -> LineStrips {
+        let mut strips = LineStrips::new();
+        assert_eq!(strips.push([0, 1]), 0);
+        assert_eq!(strips.push([]), 1);
+        assert_eq!(strips.push([2, 3, 4]), 2);
+        strips
+    }
+
+    #[test]
+    fn pushes_and_iterates_strips() {
+        let strips = strips();
+
+        assert_eq!(strips.len(), 3);
+        assert!(!strips.is_empty());
+        assert_eq!(strips.get_offsets(), [0, 2, 2, 5]);
+        assert_eq!(strips.iter().collect::<Vec<_>>(), vec![&[0, 1][..], &[], &[2, 3, 4]]);
+        assert_eq!(strips.iter().len(), 3);
+        assert_eq!(strips.iter().skip(2).len(), 1);
+        assert_eq!((&strips).into_iter().count(), 3);
+
+        assert_eq!(strips.get(1), Some(&[][..]));
+        assert_eq!(strips.get(3), None);
+    }
+
+    #[test]
+    fn builds_strips_with_capacity() {
+        let mut strips = LineStrips::with_capacity(3, 5);
+
+        assert!(strips.is_empty());
+        assert_eq!(strips.get_offsets(), [0]);
+        assert_eq!(strips.iter().count(), 0);
+        assert_eq!(strips, LineStrips::default());
+
+        strips.push([0, 1]);
+        strips.push([]);
+        strips.push([2, 3, 4]);
+        assert_eq!(strips, self::strips());
+        assert_eq!(strips, [vec![0, 1], vec![], vec![2, 3, 4]].into_iter().collect());
+    }
+
+    #[test]
+    fn appends_strips() {
+        let mut strips = strips();
+        strips.append(&self::strips(), 10);
+
+        assert_eq!(strips.len(), 6);
+        assert_eq!(strips.iter().skip(3).collect::<Vec<_>>(), vec![&[10, 11][..], &[], &[12, 13, 14]]);
+
+        // Vertex indices are replaced in place
+        strips.get_vertices_mut().iter_mut().for_each(|vertex| *vertex += 1);
+        assert_eq!(strips.get(0), Some(&[1, 2][..]));
+        assert_eq!(strips.get_offsets(), [0, 2, 2, 5, 7, 7, 10]);
+    }
+
+    #[test]
+    fn creates_strips_from_streams() {
+        assert_eq!(LineStrips::from_streams(vec![0, 2, 2, 5], vec![0, 1, 2, 3, 4]), Ok(strips()));
+        assert_eq!(LineStrips::from_streams(vec![0], Vec::new()), Ok(LineStrips::new()));
+
+        let vertices = vec![0, 1, 2, 3];
+        assert_eq!(LineStrips::from_streams(Vec::new(), Vec::new()), Err(PrimitiveError::InvalidLineStripOffset { entry: 0 }));
+        assert_eq!(LineStrips::from_streams(vec![1, 4], vertices.clone()), Err(PrimitiveError::InvalidLineStripOffset { entry: 0 }));
+        assert_eq!(LineStrips::from_streams(vec![0, 3, 2, 4], vertices.clone()), Err(PrimitiveError::InvalidLineStripOffset { entry: 2 }));
+        assert_eq!(LineStrips::from_streams(vec![0, 5, 4], vertices.clone()), Err(PrimitiveError::InvalidLineStripOffset { entry: 1 }));
+        assert_eq!(LineStrips::from_streams(vec![0, 3], vertices), Err(PrimitiveError::InvalidLineStripOffset { entry: 1 }));
+    }
+}
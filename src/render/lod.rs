@@ -0,0 +1,51 @@
+use super::Primitive;
+
+/// Single level of detail
+pub struct LodLevel {
+    pub primitive: Primitive,
+    /// Maximal projected size of primitive bounding sphere diameter in pixels, level is used at
+    pub max_screen_size: f32,
+}
+
+/// Level of detail chain.
+///
+/// Levels go from the finest to the coarsest one with decreasing maximal screen size, the first level is used at any size.
+/// All levels are expected to have the same bounds, bounding sphere of the first level is used to select level.
+pub struct LodChain {
+    levels: Vec<LodLevel>,
+}
+
+impl LodChain {
+    /// Chain create function
+    /// * `primitive` - the finest level of detail
+    /// * Returns chain with single level
+    pub fn new(primitive: Primitive) -> Self {
+        Self { levels: vec![LodLevel { primitive, max_screen_size: f32::INFINITY }] }
+    }
+
+    /// Coarser level adding function
+    /// * `primitive` - level geometry
+    /// * `max_screen_size` - maximal screen size of level in pixels, clamped to screen size of previous level
+    pub fn push(&mut self, primitive: Primitive, max_screen_size: f32) {
+        let previous = self.levels.last().map_or(f32::INFINITY, |level| level.max_screen_size);
+        self.levels.push(LodLevel { primitive, max_screen_size: max_screen_size.min(previous) });
+    }
+
+    /// Levels getting function
+    /// * Returns levels from the finest to the coarsest one
+    pub fn get_levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    /// The finest level getting function
+    pub fn get_primitive(&self) -> &Primitive {
+        &self.levels[0].primitive
+    }
+
+    /// Level selecting function
+    /// * `screen_size` - projected bounding sphere diameter in pixels
+    /// * Returns index of the coarsest level, that may be used at screen size
+    pub fn select(&self, screen_size: f32) -> usize {
+        self.levels.iter().rposition(|level| screen_size <= level.max_screen_size).unwrap_or(0)
+    }
+}
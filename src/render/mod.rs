@@ -1,11 +1,14 @@
 pub use crate::math::*;
 
-pub struct Primitive {
-    pub positions: Vec<Vec3f>,
-    pub normals: Vec<Vec3f>,
-    pub indices: Vec<u32>,
-    pub color: u32,
-}
+mod faces;
+mod line_strips;
+mod lod;
+mod primitive;
+
+pub use faces::*;
+pub use line_strips::*;
+pub use lod::*;
+pub use primitive::*;
 
 #[derive(Copy, Clone)]
 pub struct CameraLocation {
@@ -85,6 +88,16 @@ impl Camera {
         &self.projection
     }
 
+    pub fn get_view_projection_matrix(&self) -> &Mat4x4f {
+        &self.view_projection_matrix
+    }
+
+    /// View frustum getting function
+    /// * Returns frustum, extracted from view-projection matrix
+    pub fn get_frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.view_projection_matrix)
+    }
+
     pub fn set_projection(&mut self, near: f32, far: f32, size: Vec2f) {
         self.projection.near = near;
         self.projection.far = far;
@@ -117,12 +130,172 @@ impl Camera {
     }
 }
 
+/// Vertex is closer than camera near plane (or behind camera)
+const CLIP_NEAR: u8 = 0x01;
+/// Vertex is further than camera far plane
+const CLIP_FAR: u8 = 0x02;
+/// Vertex is projected left to surface
+const CLIP_LEFT: u8 = 0x04;
+/// Vertex is projected right to surface
+const CLIP_RIGHT: u8 = 0x08;
+/// Vertex is projected above surface
+const CLIP_TOP: u8 = 0x10;
+/// Vertex is projected below surface
+const CLIP_BOTTOM: u8 = 0x20;
+/// Vertex is not projected yet
+const CLIP_PENDING: u8 = 0x80;
+
+/// Post-transform vertex, shared by all faces referencing it
+#[derive(Copy, Clone)]
+struct ProjectedVertex {
+    /// Surface X coordinate, valid only if clip is zero
+    x: usize,
+    /// Surface Y coordinate, valid only if clip is zero
+    y: usize,
+    /// Set of CLIP_* flags, zero if vertex is visible
+    clip: u8,
+}
+
+/// Primitive space to surface transformation
+#[derive(Copy, Clone)]
+struct ViewTransform {
+    /// Camera axes in primitive space
+    right: Vec3f,
+    up: Vec3f,
+    direction: Vec3f,
+    /// Camera location projections on axes
+    location_right: f32,
+    location_up: f32,
+    location_direction: f32,
+
+    near: f32,
+    far: f32,
+
+    x_mul: f32,
+    x_add: f32,
+    y_mul: f32,
+    y_add: f32,
+
+    surface_width: f32,
+    surface_height: f32,
+}
+
+impl ViewTransform {
+    /// Camera space coordinates getting function
+    /// * `pt` - point in primitive space
+    /// * Returns point coordinates along camera right, up and direction axes
+    #[inline(always)]
+    fn camera_point(&self, pt: Vec3f) -> Vec3f {
+        Vec3f::new(
+            pt.x * self.right.x     + pt.y * self.right.y     + pt.z * self.right.z     - self.location_right,
+            pt.x * self.up.x        + pt.y * self.up.y        + pt.z * self.up.z        - self.location_up,
+            pt.x * self.direction.x + pt.y * self.direction.y + pt.z * self.direction.z - self.location_direction,
+        )
+    }
+
+    /// Camera space point to surface projection function
+    /// * `pt` - camera space point in front of near plane
+    /// * Returns surface coordinates
+    #[inline(always)]
+    fn surface_point(&self, pt: Vec3f) -> Vec2f {
+        let z = 1.0 / pt.z;
+        Vec2f::new(pt.x * z * self.x_mul + self.x_add, pt.y * z * self.y_mul + self.y_add)
+    }
+
+    /// Point projection function
+    #[inline(always)]
+    fn project(&self, pt: Vec3f) -> ProjectedVertex {
+        let depth = pt.x * self.direction.x + pt.y * self.direction.y + pt.z * self.direction.z - self.location_direction;
+
+        if depth <= self.near {
+            return ProjectedVertex { x: 0, y: 0, clip: CLIP_NEAR };
+        }
+
+        let z = 1.0 / depth;
+        let px = (pt.x * self.right.x + pt.y * self.right.y + pt.z * self.right.z - self.location_right) * z * self.x_mul + self.x_add;
+        let py = (pt.x * self.up.x    + pt.y * self.up.y    + pt.z * self.up.z    - self.location_up   ) * z * self.y_mul + self.y_add;
+
+        let clip =
+            if depth >= self.far          { CLIP_FAR    } else { 0 } |
+            if px < 0.0                   { CLIP_LEFT   } else { 0 } |
+            if px >= self.surface_width   { CLIP_RIGHT  } else { 0 } |
+            if py < 0.0                   { CLIP_TOP    } else { 0 } |
+            if py >= self.surface_height  { CLIP_BOTTOM } else { 0 };
+
+        // Float to integer casts saturate, so even NaN coordinates can't point outside of surface
+        ProjectedVertex { x: px as usize, y: py as usize, clip }
+    }
+}
+
+/// Surface color channel shift, channels are stored in RGBX order
+const CHANNEL_SHIFTS: [u32; 3] = [24, 16, 8];
+
+/// Lit surface color getting function
+/// * `color` - surface color
+/// * `light` - light divisor, at least 1
+/// * Returns color with every channel divided by light
+fn shade_color(color: u32, light: u8) -> u32 {
+    u32::from_ne_bytes(color.to_ne_bytes().map(|channel| channel / light))
+}
+
+/// Linear surface color interpolation function
+/// * `start`, `end` - surface colors
+/// * `t` - interpolation parameter in [0, 1] range
+fn lerp_color(start: u32, end: u32, t: f32) -> u32 {
+    CHANNEL_SHIFTS.iter().fold(0, |color, shift| {
+        let (start, end) = (((start >> shift) & 0xFF) as f32, ((end >> shift) & 0xFF) as f32);
+        color | (((start + (end - start) * t) as u32).min(0xFF) << shift)
+    })
+}
+
+/// Surface color interpolator, that yields colors of consecutive line pixels
+struct ColorGradient {
+    /// Current channel values in 16.16 fixed point format
+    channels: [i32; 3],
+    /// Channel increments in 16.16 fixed point format
+    steps: [i32; 3],
+}
+
+impl ColorGradient {
+    /// Interpolator create function
+    /// * `start`, `end` - colors of first and last pixel
+    /// * `step_count` - number of pixels between first and last one
+    fn new(start: u32, end: u32, step_count: usize) -> Self {
+        let channel = |color: u32, index: usize| (((color >> CHANNEL_SHIFTS[index]) & 0xFF) as i32) << 16;
+        let step_count = step_count.clamp(1, i32::MAX as usize) as i32;
+
+        Self {
+            channels: [0, 1, 2].map(|index| channel(start, index)),
+            steps: [0, 1, 2].map(|index| (channel(end, index) - channel(start, index)) / step_count),
+        }
+    }
+
+    /// Current color getting function, moves interpolator to the next pixel
+    #[inline(always)]
+    fn next(&mut self) -> u32 {
+        let mut color = 0;
+
+        for ((channel, step), shift) in self.channels.iter_mut().zip(&self.steps).zip(&CHANNEL_SHIFTS) {
+            color |= ((*channel >> 16) as u32) << shift;
+            *channel += step;
+        }
+
+        color
+    }
+}
+
 pub struct Render {
     camera: Camera,
+
+    /// Post-transform vertex buffer, kept between draws to avoid reallocation
+    projected_vertices: Vec<ProjectedVertex>,
+    /// Size of displayed points in pixels
+    point_size: usize,
 }
 
 pub struct RenderContext<'a> {
     render: &'a mut Render,
+    frustum: Frustum,
     surface_width: usize,
     surface_height: usize,
     surface_data: *mut u32,
@@ -131,6 +304,27 @@ pub struct RenderContext<'a> {
 impl<'a> RenderContext<'a> {
     /// Line displaying function
     unsafe fn draw_line_unchecked(&self, x1: usize, y1: usize, x2: usize, y2: usize, color: u32) {
+        self.draw_line_shaded_unchecked(x1, y1, x2, y2, || color);
+    }
+
+    /// Line with linearly interpolated color displaying function
+    /// * `x1`, `y1`, `x2`, `y2` - line ends on surface
+    /// * `color1`, `color2` - surface colors of line ends
+    unsafe fn draw_line_gradient_unchecked(&self, x1: usize, y1: usize, x2: usize, y2: usize, color1: u32, color2: u32) {
+        if color1 == color2 {
+            self.draw_line_unchecked(x1, y1, x2, y2, color1);
+            return;
+        }
+
+        let mut gradient = ColorGradient::new(color1, color2, x1.abs_diff(x2).max(y1.abs_diff(y2)));
+        self.draw_line_shaded_unchecked(x1, y1, x2, y2, || gradient.next());
+    }
+
+    /// Line with per-pixel color displaying function
+    /// * `x1`, `y1`, `x2`, `y2` - line ends on surface
+    /// * `color` - pixel color getter, called for every pixel from first line end to second one
+    #[inline(always)]
+    unsafe fn draw_line_shaded_unchecked(&self, x1: usize, y1: usize, x2: usize, y2: usize, mut color: impl FnMut() -> u32) {
         let (mut dy, sy): (usize, usize) = if y2 < y1 {
             (y1 - y2, self.surface_width.wrapping_neg())
         } else {
@@ -143,7 +337,7 @@ impl<'a> RenderContext<'a> {
         };
 
         let mut pptr = self.surface_data.wrapping_add(y1 * self.surface_width + x1);
-        pptr.write(color);
+        pptr.write(color());
 
         if dx >= dy {
             let ie = 2 * dy;
@@ -152,7 +346,7 @@ impl<'a> RenderContext<'a> {
 
             while dx != 0 {
                 pptr = pptr.wrapping_add(sx);
-                pptr.write(color);
+                pptr.write(color());
                 dx -= 1;
                 if f < std::mem::transmute(isize::MIN) {
                     pptr = pptr.wrapping_add(sy);
@@ -168,7 +362,7 @@ impl<'a> RenderContext<'a> {
 
             while dy != 0 {
                 pptr = pptr.wrapping_add(sy);
-                pptr.write(color);
+                pptr.write(color());
                 dy -= 1;
 
                 if f < std::mem::transmute(isize::MIN) {
@@ -199,102 +393,380 @@ impl<'a> RenderContext<'a> {
         self.set_pixel_unchecked(polygon.get_unchecked(bottom_index).x, polygon.get_unchecked(bottom_index).y, 0xFF000000);
     }
 
+    /// Polygon border with vertex colors displaying function
+    /// * `polygon` - polygon vertices on surface
+    /// * `colors` - surface colors of polygon vertices
+    unsafe fn draw_polygon_border_gradient_unchecked(&self, polygon: &[Vec2<usize>], colors: &[u32]) {
+        let mut previous = polygon.len() - 1;
+
+        for current in 0..polygon.len() {
+            let (start, end) = (polygon.get_unchecked(previous), polygon.get_unchecked(current));
+            self.draw_line_gradient_unchecked(start.x, start.y, end.x, end.y, *colors.get_unchecked(previous), *colors.get_unchecked(current));
+            previous = current;
+        }
+    }
+
     unsafe fn draw_polygon_unchecked(&self, polygon: &[Vec2<usize>], bottom_index: usize, color: u32) {
         // Do some scanline
         todo!();
     }
 
+    /// View frustum getting function
+    /// * Returns frustum of camera, that is used for drawing
+    pub fn get_frustum(&self) -> &Frustum {
+        &self.frustum
+    }
+
+    /// Primitive against frustum testing function
+    fn test_primitive(frustum: &Frustum, primitive: &Primitive) -> Containment {
+        let containment = frustum.test_sphere(primitive.get_bounding_sphere());
+
+        if containment == Containment::Intersects {
+            frustum.test_aabb(primitive.get_bounds())
+        } else {
+            containment
+        }
+    }
+
+    /// View frustum in primitive space getting function
+    /// * `transform` - primitive to world transformation
+    fn get_local_frustum(&self, transform: &Mat4x4f) -> Frustum {
+        Frustum::from_matrix(&(*transform * *self.render.camera.get_view_projection_matrix()))
+    }
+
     pub fn draw(&mut self, primitive: &Primitive) {
-        unsafe {
-            let cam_loc = *self.render.camera.get_location();
+        let frustum = self.frustum;
+        self.draw_clusters(primitive, &frustum, &Mat4x4f::identity());
+    }
 
-            let proj = *self.render.camera.get_projection();
-            let proj_inv_near = 1.0 / proj.near;
-            let proj_inv_far = 1.0 / proj.far;
+    /// Transformed primitive displaying function
+    /// * `primitive` - primitive to display
+    /// * `transform` - affine primitive to world transformation
+    pub fn draw_transformed(&mut self, primitive: &Primitive, transform: &Mat4x4f) {
+        let frustum = self.get_local_frustum(transform);
+        self.draw_clusters(primitive, &frustum, transform);
+    }
 
-            let proj_ext_min = usize::min(self.render.camera.extent.x, self.render.camera.extent.y) as f32;
-            let proj_x_x = 2.0 * proj.near / proj.size.x * self.render.camera.extent.y as f32 / proj_ext_min;
-            let proj_y_y = -2.0 * proj.near / proj.size.y * self.render.camera.extent.x as f32 / proj_ext_min;
+    /// Projected sphere size getting function
+    /// * `sphere` - sphere in primitive space
+    /// * `transform` - affine primitive to world transformation
+    /// * Returns projected sphere diameter in pixels, infinity if camera is inside sphere
+    pub fn get_screen_size(&self, sphere: &Sphere, transform: &Mat4x4f) -> f32 {
+        let camera = &self.render.camera;
+
+        let center = transform.transform_point(sphere.center);
+        let scale = [Vec3f::new(1.0, 0.0, 0.0), Vec3f::new(0.0, 1.0, 0.0), Vec3f::new(0.0, 0.0, 1.0)]
+            .map(|axis| transform.transform_vector(axis).length())
+            .into_iter()
+            .fold(0.0, f32::max);
+        let radius = sphere.radius * scale;
+
+        let distance = (center - camera.location.location).length();
+        if distance <= radius {
+            return f32::INFINITY;
+        }
 
-            let cam_right = cam_loc.right;
-            let cam_up = cam_loc.up;
-            let cam_dir = cam_loc.direction;
+        // Vertical scale is the one, points are drawn with, so size is measured in surface rows
+        2.0 * radius / distance * self.get_projection_scale().y.abs()
+    }
 
-            let cam_loc_right = cam_loc.location ^ cam_right;
-            let cam_loc_up = cam_loc.location ^ cam_up;
-            let cam_loc_dir = cam_loc.location ^ cam_dir;
+    /// Level of detail displaying function
+    /// * `chain` - level of detail chain
+    /// * `transform` - affine primitive to world transformation
+    /// * Returns index of displayed level, selected by projected size of the finest level bounding sphere
+    pub fn draw_lod(&mut self, chain: &LodChain, transform: &Mat4x4f) -> usize {
+        let level = chain.select(self.get_screen_size(chain.get_primitive().get_bounding_sphere(), transform));
+        self.draw_transformed(&chain.get_levels()[level].primitive, transform);
 
-            let proj_x_add = self.surface_width as f32 / 2.0;
-            let proj_x_mul = proj_x_add * proj_x_x;
+        level
+    }
 
-            let proj_y_add = self.surface_height as f32 / 2.0;
-            let proj_y_mul = proj_y_add * proj_y_y;
+    /// Primitive clusters, visible in frustum, displaying function
+    /// * `primitive` - primitive to display
+    /// * `frustum` - view frustum in primitive space
+    /// * `transform` - primitive to world transformation
+    fn draw_clusters(&mut self, primitive: &Primitive, frustum: &Frustum, transform: &Mat4x4f) {
+        let containment = Self::test_primitive(frustum, primitive);
+        if containment == Containment::Outside {
+            return;
+        }
 
-            let color = primitive.color << 8;
-            let positions = primitive.positions.as_ptr();
-            let normals = primitive.normals.as_ptr();
+        let cluster_faces = primitive.get_cluster_faces();
+        let faces = primitive.get_clusters()
+            .iter()
+            .filter(|cluster| containment == Containment::Inside || frustum.test_aabb(&cluster.bounds) != Containment::Outside)
+            .flat_map(|cluster| cluster_faces[cluster.faces.clone()].iter().map(|face| *face as usize));
 
-            let mut index = primitive.indices.as_ptr();
-            let index_end = index.add(primitive.indices.len());
+        self.draw_face_list(primitive, transform, faces);
 
-            // Projected face data
-            let mut face_polygon = Vec::<Vec2<usize>>::with_capacity(10);
+        if !primitive.get_line_strips().is_empty() || !primitive.get_points().is_empty() {
+            self.draw_elements(primitive, transform);
+        }
+    }
 
-            // Walk through faces, build 'em, then render.
-            while index < index_end {
-                // next begin
-                let face_end = index.add(*index as usize + 2);
-                let normal = *normals.add(*index.add(1) as usize + 1);
+    /// Primitive line strips and points displaying function
+    /// * `primitive` - primitive to display elements of
+    /// * `transform` - affine primitive to world transformation
+    fn draw_elements(&mut self, primitive: &Primitive, transform: &Mat4x4f) {
+        if self.surface_width == 0 || self.surface_height == 0 {
+            return;
+        }
+
+        let view = self.get_view_transform(transform);
+        let positions = primitive.get_positions();
+        let color = |index: u32| match primitive.get_colors() {
+            [] => primitive.color << 8,
+            colors => colors[index as usize] << 8,
+        };
+
+        for strip in primitive.line_strips() {
+            for segment in strip.windows(2) {
+                let [start, end] = [segment[0], segment[1]];
+                self.draw_segment(&view, positions[start as usize], positions[end as usize], color(start), color(end));
+            }
+        }
+
+        // Points are squares, clipped by surface
+        let size = self.render.point_size;
+        for index in primitive.get_points() {
+            let vertex = view.project(positions[*index as usize]);
+            if vertex.clip != 0 {
+                continue;
+            }
+
+            let half = (size - 1) / 2;
+            let x_range = vertex.x.saturating_sub(half)..(vertex.x + size - half).min(self.surface_width);
+            let y_range = vertex.y.saturating_sub(half)..(vertex.y + size - half).min(self.surface_height);
+
+            for y in y_range {
+                for x in x_range.clone() {
+                    unsafe {
+                        self.set_pixel_unchecked(x, y, color(*index));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Line segment clipping and displaying function
+    /// * `view` - primitive to surface transformation
+    /// * `start`, `end` - segment ends in primitive space
+    /// * `start_color`, `end_color` - surface colors of segment ends
+    fn draw_segment(&self, view: &ViewTransform, start: Vec3f, end: Vec3f, start_color: u32, end_color: u32) {
+        let mut start = view.camera_point(start);
+        let mut end = view.camera_point(end);
+        // Parameters of clipped ends on source segment
+        let mut t_start_camera = 0.0f32;
+        let mut t_end_camera = 1.0f32;
+
+        // Clip by near and far planes in camera space, so both ends can be projected
+        for (depth, sign) in [(view.near, 1.0), (view.far, -1.0)] {
+            let start_distance = (start.z - depth) * sign;
+            let end_distance = (end.z - depth) * sign;
+
+            if start_distance < 0.0 && end_distance < 0.0 {
+                return;
+            }
+            if start_distance < 0.0 {
+                let t = start_distance / (start_distance - end_distance);
+                start = start + (end - start) * t;
+                t_start_camera += (t_end_camera - t_start_camera) * t;
+            } else if end_distance < 0.0 {
+                let t = end_distance / (end_distance - start_distance);
+                end = end + (start - end) * t;
+                t_end_camera -= (t_end_camera - t_start_camera) * t;
+            }
+        }
+
+        // Clip by surface borders (Liang-Barsky)
+        let start = view.surface_point(start);
+        let delta = view.surface_point(end) - start;
+        let max_x = (self.surface_width - 1) as f32;
+        let max_y = (self.surface_height - 1) as f32;
+
+        let mut t_start = 0.0f32;
+        let mut t_end = 1.0f32;
+        for (p, q) in [(-delta.x, start.x), (delta.x, max_x - start.x), (-delta.y, start.y), (delta.y, max_y - start.y)] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return;
+                }
+            } else if p < 0.0 {
+                t_start = t_start.max(q / p);
+            } else {
+                t_end = t_end.min(q / p);
+            }
+        }
+
+        if t_start > t_end {
+            return;
+        }
+
+        let clipped_start = start + delta * t_start;
+        let clipped_end = start + delta * t_end;
+        // Casts saturate, so even NaN coordinates stay on surface
+        let to_x = |x: f32| (x as usize).min(self.surface_width - 1);
+        let to_y = |y: f32| (y as usize).min(self.surface_height - 1);
+
+        // Colors are interpolated linearly on surface, same as along face edges
+        let color = |t: f32| lerp_color(start_color, end_color, t_start_camera + (t_end_camera - t_start_camera) * t);
+
+        unsafe {
+            self.draw_line_gradient_unchecked(
+                to_x(clipped_start.x),
+                to_y(clipped_start.y),
+                to_x(clipped_end.x),
+                to_y(clipped_end.y),
+                color(t_start),
+                color(t_end),
+            );
+        }
+    }
+
+    /// Primitive face subset displaying function
+    /// * `primitive` - primitive to display faces of
+    /// * `faces` - indices of faces to display, indices out of face count are ignored
+    pub fn draw_faces(&mut self, primitive: &Primitive, faces: &[u32]) {
+        if Self::test_primitive(&self.frustum, primitive) == Containment::Outside {
+            return;
+        }
+
+        self.draw_face_list(primitive, &Mat4x4f::identity(), faces.iter().map(|face| *face as usize));
+    }
+
+    /// Projection scale getting function
+    /// * Returns surface pixels per unit of camera space X and Y coordinate divided by depth, Y one is negative
+    fn get_projection_scale(&self) -> Vec2f {
+        let camera = &self.render.camera;
+        let projection = camera.get_projection();
+
+        let extent_min = usize::min(camera.extent.x, camera.extent.y) as f32;
+        let x_x = 2.0 * projection.near / projection.size.x * camera.extent.y as f32 / extent_min;
+        let y_y = -2.0 * projection.near / projection.size.y * camera.extent.x as f32 / extent_min;
+
+        Vec2f::new(self.surface_width as f32 / 2.0 * x_x, self.surface_height as f32 / 2.0 * y_y)
+    }
+
+    /// Primitive to surface transformation getting function
+    /// * `transform` - affine primitive to world transformation
+    fn get_view_transform(&self, transform: &Mat4x4f) -> ViewTransform {
+        let camera = &self.render.camera;
+        let location = camera.get_location();
+        let projection = camera.get_projection();
+        let scale = self.get_projection_scale();
+
+        // Camera axes are moved to primitive space, so (p * M) ^ axis = p ^ (M axis) + (translation ^ axis)
+        let m = &transform.data;
+        let to_local = |v: Vec3f| Vec3f::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        );
+        let relative_location = location.location - Vec3f::new(m[3][0], m[3][1], m[3][2]);
+
+        let x_add = self.surface_width as f32 / 2.0;
+        let y_add = self.surface_height as f32 / 2.0;
+
+        ViewTransform {
+            right: to_local(location.right),
+            up: to_local(location.up),
+            direction: to_local(location.direction),
+            location_right: relative_location ^ location.right,
+            location_up: relative_location ^ location.up,
+            location_direction: relative_location ^ location.direction,
+
+            near: projection.near,
+            far: projection.far,
+
+            x_mul: scale.x,
+            x_add,
+            y_mul: scale.y,
+            y_add,
+
+            surface_width: self.surface_width as f32,
+            surface_height: self.surface_height as f32,
+        }
+    }
+
+    /// Primitive faces displaying function
+    /// * `primitive` - primitive to display faces of
+    /// * `transform` - affine primitive to world transformation
+    /// * `faces` - indices of faces to display, indices out of face count are ignored
+    fn draw_face_list(&mut self, primitive: &Primitive, transform: &Mat4x4f, faces: impl Iterator<Item = usize>) {
+        let view = self.get_view_transform(transform);
+        let project = |pt: Vec3f| view.project(pt);
+
+        // Vertices are projected on first use, so vertices of culled clusters are never projected
+        let mut projected = std::mem::take(&mut self.render.projected_vertices);
+        projected.clear();
+        projected.resize(primitive.get_positions().len(), ProjectedVertex { x: 0, y: 0, clip: CLIP_PENDING });
+
+        let color = primitive.color << 8;
+        let colors = primitive.get_colors();
+        let positions = primitive.get_positions();
+        let normals = primitive.get_normals();
+
+        // Projected face data
+        let mut face_polygon = Vec::<Vec2<usize>>::with_capacity(10);
+        let mut face_colors = Vec::<u32>::with_capacity(10);
+
+        for face in faces {
+            // Face indices may come from outside, so invalid ones are skipped
+            if let Some(face) = primitive.get_faces().get(face) {
+                // Normal indices are valid by primitive invariants
+                debug_assert!((face.normal as usize) < normals.len(), "face {} normal is out of primitive normals", face.index);
+                let normal = transform.transform_vector(unsafe { *normals.get_unchecked(face.normal as usize) });
                 let light = (1.0 / (normal.x + normal.y + normal.z).clamp(0.1, 1.0)) as u8;
-                let face_color: [u8; 4] = std::mem::transmute(color);
-                let face_color: u32 = std::mem::transmute([
-                    face_color[0] / light,
-                    face_color[1] / light,
-                    face_color[2] / light,
-                    face_color[3] / light,
-                ]);
+                let face_color = shade_color(color, light);
 
                 // Iterate through vertices
                 'face_rendering: {
-                    index = index.add(2);
-
                     // detect projected polygon bottom
                     let mut bottom_y = usize::MAX;
                     let mut bottom_index = 0usize;
-                    let mut i = 0usize;
 
                     // Build face polygon
-                    while index < face_end {
-                        let pt = *positions.add(*index as usize);
+                    for (i, index) in face.vertices.iter().enumerate() {
+                        // Face indices are valid by primitive invariants
+                        let vertex = unsafe { projected.get_unchecked_mut(*index as usize) };
 
-                        let z = 1.0 / (pt.x * cam_dir.x   + pt.y * cam_dir.y   + pt.z * cam_dir.z   - cam_loc_dir);
-                        let px = ((pt.x * cam_right.x + pt.y * cam_right.y + pt.z * cam_right.z - cam_loc_right) * z * proj_x_mul + proj_x_add).to_int_unchecked::<usize>();
-                        let py = ((pt.x * cam_up.x    + pt.y * cam_up.y    + pt.z * cam_up.z    - cam_loc_up   ) * z * proj_y_mul + proj_y_add).to_int_unchecked::<usize>();
+                        if vertex.clip == CLIP_PENDING {
+                            *vertex = project(unsafe { *positions.get_unchecked(*index as usize) });
+                        }
+                        let vertex = *vertex;
 
                         // face clipping
-                        if px >= self.surface_width || py >= self.surface_height || z >= proj_inv_near || z <= proj_inv_far {
+                        if vertex.clip != 0 {
                             break 'face_rendering;
                         }
 
-                        face_polygon.push(Vec2::<usize> { x: px, y: py });
+                        face_polygon.push(Vec2::<usize> { x: vertex.x, y: vertex.y });
+                        if !colors.is_empty() {
+                            face_colors.push(shade_color(unsafe { *colors.get_unchecked(*index as usize) } << 8, light));
+                        }
 
-                        if py < bottom_y {
-                            bottom_y = py;
+                        if vertex.y < bottom_y {
+                            bottom_y = vertex.y;
                             bottom_index = i;
                         }
-
-                        i += 1;
-                        index = index.add(1);
                     }
 
                     // Perform rendering
-                    self.draw_polygon_border_unchecked(&face_polygon, bottom_index, face_color);
+                    unsafe {
+                        if colors.is_empty() {
+                            self.draw_polygon_border_unchecked(&face_polygon, bottom_index, face_color);
+                        } else {
+                            self.draw_polygon_border_gradient_unchecked(&face_polygon, &face_colors);
+                        }
+                    }
                 }
 
                 face_polygon.clear();
-                index = face_end;
+                face_colors.clear();
             }
         }
+
+        self.render.projected_vertices = projected;
     }
 
     pub fn finish(self) {
@@ -306,9 +778,22 @@ impl Render {
     pub fn new() -> Self {
         Self {
             camera: Camera::new(),
+            projected_vertices: Vec::new(),
+            point_size: 1,
         }
     }
 
+    /// Point size setting function
+    /// * `size` - side of point square in pixels, at least 1
+    pub fn set_point_size(&mut self, size: usize) {
+        self.point_size = size.max(1);
+    }
+
+    /// Point size getting function
+    pub fn get_point_size(&self) -> usize {
+        self.point_size
+    }
+
     pub fn get_camera_mut(&mut self) -> &mut Camera {
         &mut self.camera
     }
@@ -323,10 +808,62 @@ impl Render {
 
         self.camera.resize(surface.get_extent());
         RenderContext {
+            frustum: self.camera.get_frustum(),
             render: self,
             surface_width: surface.get_extent().x,
             surface_height: surface.get_extent().y,
             surface_data: surface.get_data_mut().as_mut_ptr(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Surface {
+        data: Vec<u32>,
+        extent: Vec2<usize>,
+    }
+
+    impl<'a> crate::window::Surface<'a> for Surface {
+        fn get_data(&self) -> &[u32] {
+            self.data.as_slice()
+        }
+
+        fn get_data_mut(&mut self) -> &mut [u32] {
+            self.data.as_mut_slice()
+        }
+
+        fn get_extent(&self) -> Vec2<usize> {
+            self.extent
+        }
+    }
+
+    #[test]
+    fn screen_size_matches_drawn_sphere() {
+        let sphere = crate::mesh::shapes::icosphere(1.0, 4);
+
+        for (width, height) in [(800, 600), (600, 800), (500, 500)] {
+            let mut render = Render::new();
+            let mut surface = Surface { data: vec![0; width * height], extent: Vec2::<usize>::new(width, height) };
+            render.get_camera_mut().set(&Vec3f::new(0.0, 0.0, 8.0), &Vec3f::new(0.0, 0.0, 0.0), &Vec3f::new(0.0, 1.0, 0.0));
+
+            let mut context = render.start(&mut surface);
+            let screen_size = context.get_screen_size(sphere.get_bounding_sphere(), &Mat4x4f::identity());
+            context.draw(&sphere);
+            context.finish();
+
+            let rows = surface.data
+                .chunks(width)
+                .enumerate()
+                .filter(|(_, row)| row.iter().any(|pixel| *pixel != 0))
+                .map(|(y, _)| y)
+                .collect::<Vec<_>>();
+            let drawn_size = (rows[rows.len() - 1] - rows[0] + 1) as f32;
+
+            // Silhouette of sphere is a bit larger than its projected diameter, tessellation makes it a bit smaller
+            assert!((drawn_size - screen_size).abs() <= screen_size * 0.02 + 2.0, "{width}x{height}: drawn {drawn_size}, computed {screen_size}");
+        }
+    }
+}
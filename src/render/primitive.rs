@@ -0,0 +1,737 @@
+use crate::math::*;
+use super::faces::*;
+use super::line_strips::*;
+
+/// Maximal number of faces in single primitive cluster
+const CLUSTER_FACE_COUNT: usize = 256;
+
+/// Spatially close group of primitive faces, culled as a whole
+#[derive(Clone)]
+pub struct Cluster {
+    /// Bounding box of cluster faces
+    pub bounds: Aabb,
+    /// Range of primitive cluster face list, that holds indices of cluster faces, see [`Primitive::get_cluster_faces`]
+    pub faces: std::ops::Range<usize>,
+}
+
+/// Primitive data validation error
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PrimitiveError {
+    /// Face offset table doesn't start with zero, decreases or doesn't end at face vertex count
+    InvalidFaceOffset { entry: usize },
+    /// Face normal index count doesn't match face count
+    FaceNormalCountMismatch { count: usize, face_count: usize },
+    /// Face has no vertices
+    EmptyFace { face: usize },
+    /// Face references position, that doesn't exist
+    PositionIndexOutOfRange { face: usize, index: u32, count: usize },
+    /// Face references normal, that doesn't exist
+    NormalIndexOutOfRange { face: usize, index: u32, count: usize },
+    /// Position has NaN or infinite coordinate
+    NonFinitePosition { index: usize },
+    /// Texture coordinate count neither matches position count nor is zero
+    TexCoordCountMismatch { count: usize, position_count: usize },
+    /// Vertex color count neither matches position count nor is zero
+    ColorCountMismatch { count: usize, position_count: usize },
+    /// Vertex normal count neither matches position count nor is zero
+    VertexNormalCountMismatch { count: usize, position_count: usize },
+    /// Line strip offset table doesn't start with zero, decreases or doesn't end at line strip vertex count
+    InvalidLineStripOffset { entry: usize },
+    /// Line strip has less than 2 vertices
+    ShortLineStrip { strip: usize },
+    /// Line strip references position, that doesn't exist
+    LinePositionIndexOutOfRange { strip: usize, index: u32, count: usize },
+    /// Point at offset references position, that doesn't exist
+    PointPositionIndexOutOfRange { offset: usize, index: u32, count: usize },
+    /// Cluster at index is empty, doesn't continue previous one, or clusters don't cover all faces
+    InvalidCluster { index: usize },
+    /// Cluster face list entry at offset references face, that doesn't exist or is already listed,
+    /// or list ends at offset before covering all faces
+    InvalidClusterFace { offset: usize },
+}
+
+impl std::fmt::Display for PrimitiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidFaceOffset { entry } => write!(f, "face offset table entry {entry} is out of face vertex range"),
+            Self::FaceNormalCountMismatch { count, face_count } => write!(f, "{count} face normal indices given for {face_count} faces"),
+            Self::EmptyFace { face } => write!(f, "face {face} has no vertices"),
+            Self::PositionIndexOutOfRange { face, index, count } => write!(f, "face {face} references position {index}, but there are {count} positions"),
+            Self::NormalIndexOutOfRange { face, index, count } => write!(f, "face {face} references normal {index}, but there are {count} normals"),
+            Self::NonFinitePosition { index } => write!(f, "position {index} has non-finite coordinate"),
+            Self::TexCoordCountMismatch { count, position_count } => write!(f, "{count} texture coordinates given for {position_count} positions"),
+            Self::ColorCountMismatch { count, position_count } => write!(f, "{count} vertex colors given for {position_count} positions"),
+            Self::VertexNormalCountMismatch { count, position_count } => write!(f, "{count} vertex normals given for {position_count} positions"),
+            Self::InvalidLineStripOffset { entry } => write!(f, "line strip offset table entry {entry} is out of line strip vertex range"),
+            Self::ShortLineStrip { strip } => write!(f, "line strip {strip} has less than 2 vertices"),
+            Self::LinePositionIndexOutOfRange { strip, index, count } => write!(f, "line strip {strip} references position {index}, but there are {count} positions"),
+            Self::PointPositionIndexOutOfRange { offset, index, count } => write!(f, "point {offset} references position {index}, but there are {count} positions"),
+            Self::InvalidCluster { index } => write!(f, "cluster {index} doesn't match face layout"),
+            Self::InvalidClusterFace { offset } => write!(f, "cluster face list entry {offset} doesn't match faces"),
+        }
+    }
+}
+
+impl std::error::Error for PrimitiveError {}
+
+/// Polygonal primitive.
+///
+/// Besides faces, primitive may contain line strips and points, that are displayed unlit in primitive color.
+/// If primitive has vertex colors, they are used instead of primitive color and interpolated along edges.
+///
+/// Data is validated on construction, so every face has at least one vertex, every line strip has at least two,
+/// every index references existing position and normal, and all positions are finite.
+pub struct Primitive {
+    pub color: u32,
+
+    positions: Vec<Vec3f>,
+    normals: Vec<Vec3f>,
+    faces: Faces,
+    /// Texture coordinates, one per position, empty if primitive isn't textured
+    tex_coords: Vec<Vec2f>,
+    /// Vertex colors in `color` format, one per position, empty if primitive has single color
+    colors: Vec<u32>,
+    /// Vertex normals, one per position, empty if primitive has face normals only
+    vertex_normals: Vec<Vec3f>,
+    line_strips: LineStrips,
+    /// Point vertex indices
+    points: Vec<u32>,
+
+    bounds: Aabb,
+    bounding_sphere: Sphere,
+    clusters: Vec<Cluster>,
+    /// Face indices, grouped by cluster
+    cluster_faces: Vec<u32>,
+}
+
+/// Primitive contents with face clusters, that allow to restore primitive without repartitioning faces
+pub(crate) struct PrimitiveParts {
+    pub color: u32,
+    pub positions: Vec<Vec3f>,
+    pub normals: Vec<Vec3f>,
+    pub faces: Faces,
+    pub tex_coords: Vec<Vec2f>,
+    pub colors: Vec<u32>,
+    pub vertex_normals: Vec<Vec3f>,
+    pub line_strips: LineStrips,
+    pub points: Vec<u32>,
+    pub clusters: Vec<Cluster>,
+    pub cluster_faces: Vec<u32>,
+}
+
+impl Primitive {
+    /// Primitive create function.
+    ///
+    /// Faces are split into spatial clusters of at most 256 faces for culling. Clusters reference faces through
+    /// separate face list, so primitive keeps face order and face indices of primitive match indices in `faces`.
+    /// * `positions` - vertex positions
+    /// * `normals` - face normals
+    /// * `faces` - faces, referencing positions and normals
+    /// * `color` - primitive color
+    /// * Returns primitive with bounding volumes computed, or error if data is invalid
+    pub fn new(positions: Vec<Vec3f>, normals: Vec<Vec3f>, faces: Faces, color: u32) -> Result<Self, PrimitiveError> {
+        Self::validate(&positions, &normals, &faces)?;
+
+        let mut primitive = Self {
+            color,
+            positions,
+            normals,
+            faces,
+            tex_coords: Vec::new(),
+            colors: Vec::new(),
+            vertex_normals: Vec::new(),
+            line_strips: LineStrips::new(),
+            points: Vec::new(),
+
+            bounds: Aabb::empty(),
+            bounding_sphere: Sphere { center: Vec3f::new(0.0, 0.0, 0.0), radius: 0.0 },
+            clusters: Vec::new(),
+            cluster_faces: Vec::new(),
+        };
+
+        primitive.update_bounds();
+
+        Ok(primitive)
+    }
+
+    /// Primitive from parts create function
+    /// * `parts` - primitive contents, clusters and cluster face list are ones, got by [`Primitive::get_clusters`]
+    ///   and [`Primitive::get_cluster_faces`] for the same faces
+    /// * Returns primitive with given clusters and bounding volumes computed, or error if any part is invalid
+    pub(crate) fn from_parts(parts: PrimitiveParts) -> Result<Self, PrimitiveError> {
+        Self::validate(&parts.positions, &parts.normals, &parts.faces)?;
+        Self::validate_clusters(parts.faces.len(), &parts.clusters, &parts.cluster_faces)?;
+        Self::validate_line_strips(parts.positions.len(), &parts.line_strips)?;
+        Self::validate_points(parts.positions.len(), &parts.points)?;
+
+        let mut primitive = Self {
+            color: parts.color,
+            positions: parts.positions,
+            normals: parts.normals,
+            faces: parts.faces,
+            tex_coords: Vec::new(),
+            colors: Vec::new(),
+            vertex_normals: Vec::new(),
+            line_strips: parts.line_strips,
+            points: parts.points,
+
+            bounds: Aabb::empty(),
+            bounding_sphere: Sphere { center: Vec3f::new(0.0, 0.0, 0.0), radius: 0.0 },
+            clusters: parts.clusters,
+            cluster_faces: parts.cluster_faces,
+        };
+
+        primitive.set_tex_coords(parts.tex_coords)?;
+        primitive.set_colors(parts.colors)?;
+        primitive.set_vertex_normals(parts.vertex_normals)?;
+        primitive.update_bounding_volumes();
+
+        Ok(primitive)
+    }
+
+    /// Primitive data validation function
+    /// * `positions` - vertex positions
+    /// * `normals` - face normals
+    /// * `faces` - faces
+    /// * Returns first found data error
+    fn validate(positions: &[Vec3f], normals: &[Vec3f], faces: &Faces) -> Result<(), PrimitiveError> {
+        if let Some(index) = positions.iter().position(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+            return Err(PrimitiveError::NonFinitePosition { index });
+        }
+
+        for face in faces {
+            if face.vertices.is_empty() {
+                return Err(PrimitiveError::EmptyFace { face: face.index });
+            }
+            if face.normal as usize >= normals.len() {
+                return Err(PrimitiveError::NormalIndexOutOfRange { face: face.index, index: face.normal, count: normals.len() });
+            }
+            if let Some(index) = face.vertices.iter().find(|index| **index as usize >= positions.len()) {
+                return Err(PrimitiveError::PositionIndexOutOfRange { face: face.index, index: *index, count: positions.len() });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clusters validation function
+    /// * `face_count` - number of primitive faces
+    /// * `clusters` - clusters to validate
+    /// * `cluster_faces` - cluster face list to validate
+    /// * Returns error if face list doesn't list every face once, or clusters don't split it into continuous non-empty ranges in order
+    fn validate_clusters(face_count: usize, clusters: &[Cluster], cluster_faces: &[u32]) -> Result<(), PrimitiveError> {
+        let mut is_listed = vec![false; face_count];
+        for (offset, face) in cluster_faces.iter().enumerate() {
+            match is_listed.get_mut(*face as usize) {
+                Some(is_listed) if !*is_listed => *is_listed = true,
+                _ => return Err(PrimitiveError::InvalidClusterFace { offset }),
+            }
+        }
+        if cluster_faces.len() != face_count {
+            return Err(PrimitiveError::InvalidClusterFace { offset: cluster_faces.len() });
+        }
+
+        let mut end = 0;
+
+        for (index, cluster) in clusters.iter().enumerate() {
+            if cluster.faces.start != end || cluster.faces.end <= end || cluster.faces.end > face_count {
+                return Err(PrimitiveError::InvalidCluster { index });
+            }
+            end = cluster.faces.end;
+        }
+
+        if end != face_count {
+            return Err(PrimitiveError::InvalidCluster { index: clusters.len() });
+        }
+
+        Ok(())
+    }
+
+    /// Line strips validation function
+    /// * `position_count` - number of primitive positions
+    /// * `line_strips` - line strips
+    /// * Returns first line strip error
+    fn validate_line_strips(position_count: usize, line_strips: &LineStrips) -> Result<(), PrimitiveError> {
+        for (strip, vertices) in line_strips.iter().enumerate() {
+            if vertices.len() < 2 {
+                return Err(PrimitiveError::ShortLineStrip { strip });
+            }
+            if let Some(index) = vertices.iter().find(|index| **index as usize >= position_count) {
+                return Err(PrimitiveError::LinePositionIndexOutOfRange { strip, index: *index, count: position_count });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Points validation function
+    /// * `position_count` - number of primitive positions
+    /// * `points` - point vertex indices
+    /// * Returns first point error
+    fn validate_points(position_count: usize, points: &[u32]) -> Result<(), PrimitiveError> {
+        match points.iter().position(|index| *index as usize >= position_count) {
+            Some(offset) => Err(PrimitiveError::PointPositionIndexOutOfRange { offset, index: points[offset], count: position_count }),
+            None => Ok(()),
+        }
+    }
+
+    /// Texture coordinates setting function
+    /// * `tex_coords` - texture coordinates, one per position, or none to make primitive untextured
+    /// * Returns error if texture coordinate count doesn't match position count
+    pub fn set_tex_coords(&mut self, tex_coords: Vec<Vec2f>) -> Result<(), PrimitiveError> {
+        if !tex_coords.is_empty() && tex_coords.len() != self.positions.len() {
+            return Err(PrimitiveError::TexCoordCountMismatch { count: tex_coords.len(), position_count: self.positions.len() });
+        }
+
+        self.tex_coords = tex_coords;
+        Ok(())
+    }
+
+    /// Vertex colors setting function
+    /// * `colors` - colors in `color` format, one per position, or none to make primitive single-colored
+    /// * Returns error if color count doesn't match position count
+    pub fn set_colors(&mut self, colors: Vec<u32>) -> Result<(), PrimitiveError> {
+        if !colors.is_empty() && colors.len() != self.positions.len() {
+            return Err(PrimitiveError::ColorCountMismatch { count: colors.len(), position_count: self.positions.len() });
+        }
+
+        self.colors = colors;
+        Ok(())
+    }
+
+    /// Vertex normals setting function
+    /// * `vertex_normals` - normals, one per position, or none to remove vertex normals
+    /// * Returns error if normal count doesn't match position count
+    pub fn set_vertex_normals(&mut self, vertex_normals: Vec<Vec3f>) -> Result<(), PrimitiveError> {
+        if !vertex_normals.is_empty() && vertex_normals.len() != self.positions.len() {
+            return Err(PrimitiveError::VertexNormalCountMismatch { count: vertex_normals.len(), position_count: self.positions.len() });
+        }
+
+        self.vertex_normals = vertex_normals;
+        Ok(())
+    }
+
+    /// Line strips setting function
+    /// * `line_strips` - line strips, referencing positions
+    /// * Returns error if any line strip is invalid
+    pub fn set_line_strips(&mut self, line_strips: LineStrips) -> Result<(), PrimitiveError> {
+        Self::validate_line_strips(self.positions.len(), &line_strips)?;
+
+        self.line_strips = line_strips;
+        self.update_bounding_volumes();
+        Ok(())
+    }
+
+    /// Points setting function
+    /// * `points` - point vertex indices
+    /// * Returns error if any point references missing position
+    pub fn set_points(&mut self, points: Vec<u32>) -> Result<(), PrimitiveError> {
+        Self::validate_points(self.positions.len(), &points)?;
+
+        self.points = points;
+        self.update_bounding_volumes();
+        Ok(())
+    }
+
+    /// Line strips iterating function
+    /// * Returns iterator over vertex indices of every line strip
+    pub fn line_strips(&self) -> LineStripIter<'_> {
+        self.line_strips.iter()
+    }
+
+    /// Line strips getting function
+    pub fn get_line_strips(&self) -> &LineStrips {
+        &self.line_strips
+    }
+
+    /// Points getting function
+    /// * Returns point vertex indices
+    pub fn get_points(&self) -> &[u32] {
+        &self.points
+    }
+
+    /// Positions getting function
+    pub fn get_positions(&self) -> &[Vec3f] {
+        &self.positions
+    }
+
+    /// Face normals getting function
+    pub fn get_normals(&self) -> &[Vec3f] {
+        &self.normals
+    }
+
+    /// Faces getting function
+    pub fn get_faces(&self) -> &Faces {
+        &self.faces
+    }
+
+    /// Texture coordinates getting function
+    /// * Returns texture coordinates, one per position, empty if primitive isn't textured
+    pub fn get_tex_coords(&self) -> &[Vec2f] {
+        &self.tex_coords
+    }
+
+    /// Vertex colors getting function
+    /// * Returns colors in `color` format, one per position, empty if primitive has single color
+    pub fn get_colors(&self) -> &[u32] {
+        &self.colors
+    }
+
+    /// Vertex normals getting function
+    /// * Returns normals, one per position, empty if primitive has face normals only
+    pub fn get_vertex_normals(&self) -> &[Vec3f] {
+        &self.vertex_normals
+    }
+
+    /// Bounding box getting function
+    /// * Returns bounding box of all face, line strip and point vertices
+    pub fn get_bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+
+    /// Bounding sphere getting function
+    /// * Returns bounding sphere of all face, line strip and point vertices
+    pub fn get_bounding_sphere(&self) -> &Sphere {
+        &self.bounding_sphere
+    }
+
+    /// Face clusters getting function
+    /// * Returns clusters, that together cover all primitive faces
+    pub fn get_clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// Cluster face list getting function
+    /// * Returns index of every primitive face, grouped by cluster, cluster faces are at [`Cluster::faces`] range of it
+    pub fn get_cluster_faces(&self) -> &[u32] {
+        &self.cluster_faces
+    }
+
+    /// Faces iterating function
+    /// * Returns iterator over all primitive faces
+    pub fn faces(&self) -> FaceIter<'_> {
+        self.faces.iter()
+    }
+
+    /// Face range iterating function
+    /// * `range` - range of face indices, clamped to face count
+    /// * Returns iterator over faces in range
+    pub fn faces_in(&self, range: std::ops::Range<usize>) -> FaceIter<'_> {
+        self.faces.iter_range(range)
+    }
+
+    /// Bounding volumes and clusters calculation function
+    fn update_bounds(&mut self) {
+        let positions = &self.positions;
+
+        // Face indices and centers to partition
+        let mut faces = self.faces()
+            .map(|face| {
+                let center = face.vertices.iter().fold(Vec3f::new(0.0, 0.0, 0.0), |sum, index| sum + positions[*index as usize]);
+                (face.index, center / face.vertices.len().max(1) as f32)
+            })
+            .collect::<Vec<_>>();
+
+        let mut partition = Vec::new();
+        Self::partition_faces(&mut faces, &mut partition);
+
+        // List faces in cluster order
+        self.cluster_faces.clear();
+        self.cluster_faces.reserve(self.faces.len());
+        self.clusters.clear();
+
+        for cluster_faces in partition {
+            let start = self.cluster_faces.len();
+            let mut bounds = Aabb::empty();
+
+            for (index, _) in cluster_faces {
+                for vertex in self.faces.face_at(index).vertices {
+                    bounds.add_point(&positions[*vertex as usize]);
+                }
+                self.cluster_faces.push(index as u32);
+            }
+
+            self.clusters.push(Cluster { bounds, faces: start..self.cluster_faces.len() });
+        }
+
+        self.update_bounding_volumes();
+    }
+
+    /// Bounding box and sphere calculation function, clusters must be up to date
+    fn update_bounding_volumes(&mut self) {
+        self.bounds = self.clusters
+            .iter()
+            .fold(Aabb::empty(), |bounds, cluster| bounds.union(&cluster.bounds))
+            .union(&Aabb::from_points(self.element_vertices()));
+
+        let center = self.bounds.center();
+        let radius2 = self.face_vertices().chain(self.element_vertices()).fold(0.0f32, |radius2, point| radius2.max((*point - center).length2()));
+        self.bounding_sphere = Sphere { center, radius: radius2.sqrt() };
+    }
+
+    /// Iterator over positions of vertices, referenced by line strips and points, getting function
+    fn element_vertices(&self) -> impl Iterator<Item = &Vec3f> {
+        self.line_strips()
+            .flat_map(|strip| strip.iter())
+            .chain(self.points.iter())
+            .map(|index| &self.positions[*index as usize])
+    }
+
+    /// Iterator over positions of vertices, referenced by faces, getting function
+    fn face_vertices(&self) -> impl Iterator<Item = &Vec3f> {
+        self.faces().flat_map(|face| face.vertices.iter().map(|index| &self.positions[*index as usize]))
+    }
+
+    /// Face recursive median split function
+    /// * `faces` - face indices and centers to split
+    /// * `partition` - resulting face groups
+    fn partition_faces(faces: &mut [(usize, Vec3f)], partition: &mut Vec<Vec<(usize, Vec3f)>>) {
+        if faces.len() <= CLUSTER_FACE_COUNT {
+            if !faces.is_empty() {
+                partition.push(faces.to_vec());
+            }
+            return;
+        }
+
+        // Split by longest axis of face center bounds
+        let size = Aabb::from_points(faces.iter().map(|(_, center)| center)).size();
+        let axis = |v: &Vec3f| if size.x >= size.y && size.x >= size.z {
+            v.x
+        } else if size.y >= size.z {
+            v.y
+        } else {
+            v.z
+        };
+
+        let middle = faces.len() / 2;
+        faces.select_nth_unstable_by(middle, |(_, lhs), (_, rhs)| axis(lhs).total_cmp(&axis(rhs)));
+
+        let (left, right) = faces.split_at_mut(middle);
+        Self::partition_faces(left, partition);
+        Self::partition_faces(right, partition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit square positions getting function
+    fn square() -> Vec<Vec3f> {
+        vec![
+            Vec3f::new(0.0, 0.0, 0.0),
+            Vec3f::new(1.0, 0.0, 0.0),
+            Vec3f::new(1.0, 1.0, 0.0),
+            Vec3f::new(0.0, 1.0, 0.0),
+        ]
+    }
+
+    /// Two triangle square primitive parts with single cluster getting function
+    fn square_parts() -> PrimitiveParts {
+        let mut faces = Faces::new();
+        faces.push(0, [0, 1, 2]);
+        faces.push(0, [0, 2, 3]);
+
+        PrimitiveParts {
+            color: 0xFFFFFF,
+            positions: square(),
+            normals: vec![Vec3f::new(0.0, 0.0, 1.0)],
+            faces,
+            tex_coords: Vec::new(),
+            colors: Vec::new(),
+            vertex_normals: Vec::new(),
+            line_strips: LineStrips::new(),
+            points: Vec::new(),
+            clusters: vec![Cluster { bounds: Aabb::empty(), faces: 0..2 }],
+            cluster_faces: vec![0, 1],
+        }
+    }
+
+    /// Primitive from parts error getting function
+    fn parts_error(parts: PrimitiveParts) -> PrimitiveError {
+        Primitive::from_parts(parts).err().expect("invalid parts are accepted")
+    }
+
+    /// Square primitive create function
+    fn square_primitive() -> Primitive {
+        Primitive::from_parts(square_parts()).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    #[test]
+    fn accepts_valid_parts() {
+        let primitive = square_primitive();
+
+        assert_eq!(primitive.get_faces().len(), 2);
+        assert_eq!(primitive.get_cluster_faces(), &[0, 1]);
+        assert_eq!(primitive.get_clusters().len(), 1);
+    }
+
+    #[test]
+    fn rejects_non_finite_positions() {
+        let mut positions = square();
+        positions[2].y = f32::NAN;
+        positions[3].x = f32::INFINITY;
+
+        let mut faces = Faces::new();
+        faces.push(0, [0, 1, 2]);
+
+        let error = Primitive::new(positions, vec![Vec3f::new(0.0, 0.0, 1.0)], faces, 0).err();
+        assert_eq!(error, Some(PrimitiveError::NonFinitePosition { index: 2 }));
+    }
+
+    #[test]
+    fn rejects_empty_faces() {
+        let mut faces = Faces::new();
+        faces.push(0, [0, 1, 2]);
+        faces.push(0, []);
+
+        let error = Primitive::new(square(), vec![Vec3f::new(0.0, 0.0, 1.0)], faces, 0).err();
+        assert_eq!(error, Some(PrimitiveError::EmptyFace { face: 1 }));
+    }
+
+    #[test]
+    fn rejects_normal_indices_out_of_range() {
+        let mut faces = Faces::new();
+        faces.push(0, [0, 1, 2]);
+        faces.push(1, [0, 2, 3]);
+
+        let error = Primitive::new(square(), vec![Vec3f::new(0.0, 0.0, 1.0)], faces, 0).err();
+        assert_eq!(error, Some(PrimitiveError::NormalIndexOutOfRange { face: 1, index: 1, count: 1 }));
+    }
+
+    #[test]
+    fn rejects_position_indices_out_of_range() {
+        let mut faces = Faces::new();
+        faces.push(0, [0, 4, 2]);
+
+        let error = Primitive::new(square(), vec![Vec3f::new(0.0, 0.0, 1.0)], faces, 0).err();
+        assert_eq!(error, Some(PrimitiveError::PositionIndexOutOfRange { face: 0, index: 4, count: 4 }));
+    }
+
+    #[test]
+    fn rejects_cluster_faces_out_of_range() {
+        let mut parts = square_parts();
+        parts.cluster_faces = vec![0, 2];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidClusterFace { offset: 1 });
+    }
+
+    #[test]
+    fn rejects_repeated_cluster_faces() {
+        let mut parts = square_parts();
+        parts.cluster_faces = vec![1, 1];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidClusterFace { offset: 1 });
+    }
+
+    #[test]
+    fn rejects_incomplete_cluster_face_list() {
+        let mut parts = square_parts();
+        parts.cluster_faces = vec![1];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidClusterFace { offset: 1 });
+    }
+
+    #[test]
+    fn rejects_empty_clusters() {
+        let mut parts = square_parts();
+        parts.clusters = vec![
+            Cluster { bounds: Aabb::empty(), faces: 0..0 },
+            Cluster { bounds: Aabb::empty(), faces: 0..2 },
+        ];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidCluster { index: 0 });
+    }
+
+    #[test]
+    fn rejects_discontinuous_clusters() {
+        let mut parts = square_parts();
+        parts.clusters = vec![
+            Cluster { bounds: Aabb::empty(), faces: 0..1 },
+            Cluster { bounds: Aabb::empty(), faces: 0..2 },
+        ];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidCluster { index: 1 });
+    }
+
+    #[test]
+    fn rejects_clusters_out_of_face_list() {
+        let mut parts = square_parts();
+        parts.clusters = vec![Cluster { bounds: Aabb::empty(), faces: 0..3 }];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidCluster { index: 0 });
+    }
+
+    #[test]
+    fn rejects_clusters_not_covering_faces() {
+        let mut parts = square_parts();
+        parts.clusters = vec![Cluster { bounds: Aabb::empty(), faces: 0..1 }];
+
+        assert_eq!(parts_error(parts), PrimitiveError::InvalidCluster { index: 1 });
+    }
+
+    #[test]
+    fn rejects_short_line_strips() {
+        let mut line_strips = LineStrips::new();
+        line_strips.push([0, 1, 2]);
+        line_strips.push([3]);
+
+        let mut primitive = square_primitive();
+        assert_eq!(primitive.set_line_strips(line_strips).err(), Some(PrimitiveError::ShortLineStrip { strip: 1 }));
+        assert!(primitive.get_line_strips().is_empty());
+    }
+
+    #[test]
+    fn rejects_line_strip_indices_out_of_range() {
+        let mut line_strips = LineStrips::new();
+        line_strips.push([0, 1]);
+        line_strips.push([2, 5, 3]);
+
+        let mut primitive = square_primitive();
+        assert_eq!(
+            primitive.set_line_strips(line_strips).err(),
+            Some(PrimitiveError::LinePositionIndexOutOfRange { strip: 1, index: 5, count: 4 })
+        );
+
+        let mut parts = square_parts();
+        parts.line_strips.push([0, 7]);
+        assert_eq!(parts_error(parts), PrimitiveError::LinePositionIndexOutOfRange { strip: 0, index: 7, count: 4 });
+    }
+
+    #[test]
+    fn rejects_point_indices_out_of_range() {
+        let mut primitive = square_primitive();
+        assert_eq!(
+            primitive.set_points(vec![0, 3, 4]).err(),
+            Some(PrimitiveError::PointPositionIndexOutOfRange { offset: 2, index: 4, count: 4 })
+        );
+        assert!(primitive.get_points().is_empty());
+
+        let mut parts = square_parts();
+        parts.points = vec![9];
+        assert_eq!(parts_error(parts), PrimitiveError::PointPositionIndexOutOfRange { offset: 0, index: 9, count: 4 });
+    }
+
+    #[test]
+    fn rejects_vertex_attribute_count_mismatches() {
+        let mut primitive = square_primitive();
+
+        assert_eq!(
+            primitive.set_tex_coords(vec![Vec2f::new(0.0, 0.0); 3]).err(),
+            Some(PrimitiveError::TexCoordCountMismatch { count: 3, position_count: 4 })
+        );
+        assert_eq!(
+            primitive.set_colors(vec![0; 5]).err(),
+            Some(PrimitiveError::ColorCountMismatch { count: 5, position_count: 4 })
+        );
+        assert_eq!(
+            primitive.set_vertex_normals(vec![Vec3f::new(0.0, 0.0, 1.0); 1]).err(),
+            Some(PrimitiveError::VertexNormalCountMismatch { count: 1, position_count: 4 })
+        );
+
+        // Empty attributes are always accepted
+        assert!(primitive.set_tex_coords(Vec::new()).is_ok());
+        assert!(primitive.set_colors(Vec::new()).is_ok());
+        assert!(primitive.set_vertex_normals(Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,683 @@
+use crate::math::*;
+use crate::render::Primitive;
+
+/// Number of bins, used to estimate surface area heuristic on build
+const BIN_COUNT: usize = 12;
+
+/// Maximal number of faces in leaf node
+const LEAF_FACE_COUNT: usize = 4;
+
+/// Reference to single face of some primitive
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FaceRef {
+    /// Index of primitive in BVH primitive list
+    pub primitive: u32,
+    /// Index of face in primitive faces
+    pub face: u32,
+}
+
+/// Ray, represented by origin and direction
+#[derive(Copy, Clone)]
+pub struct Ray {
+    pub origin: Vec3f,
+    pub direction: Vec3f,
+}
+
+/// Ray query result
+#[derive(Copy, Clone)]
+pub struct RayHit {
+    /// Face, hit by ray
+    pub face: FaceRef,
+    /// Ray parameter of hit point, measured in direction lengths
+    pub distance: f32,
+    /// Hit point itself
+    pub point: Vec3f,
+}
+
+/// Nearest point query result
+#[derive(Copy, Clone)]
+pub struct NearestPoint {
+    /// Face point belongs to
+    pub face: FaceRef,
+    /// Distance from query point
+    pub distance: f32,
+    /// Nearest point itself
+    pub point: Vec3f,
+}
+
+/// BVH node
+#[derive(Copy, Clone)]
+struct Node {
+    /// Bounding box of all node faces
+    bounds: Aabb,
+    /// Index of first face for leaf nodes, index of second child for inner ones (first child directly follows parent)
+    start: u32,
+    /// Face count for leaf nodes, zero for inner ones
+    count: u32,
+}
+
+/// Bounding volume hierarchy over faces of a set of primitives, built by surface area heuristic
+pub struct Bvh<'a> {
+    primitives: Vec<&'a Primitive>,
+    nodes: Vec<Node>,
+    faces: Vec<FaceRef>,
+}
+
+/// Face data, used during build only
+#[derive(Copy, Clone)]
+struct BuildFace {
+    face: FaceRef,
+    bounds: Aabb,
+    center: Vec3f,
+}
+
+impl<'a> Bvh<'a> {
+    /// BVH build function
+    /// * `primitives` - primitives to build hierarchy over
+    /// * Returns hierarchy over faces of all primitives
+    pub fn new(primitives: &[&'a Primitive]) -> Self {
+        let mut build_faces = Vec::new();
+
+        for (primitive_index, primitive) in primitives.iter().enumerate() {
+            for face in primitive.faces() {
+                let bounds = Aabb::from_points(face.vertices.iter().map(|index| &primitive.get_positions()[*index as usize]));
+
+                build_faces.push(BuildFace {
+                    face: FaceRef { primitive: primitive_index as u32, face: face.index as u32 },
+                    bounds,
+                    center: bounds.center(),
+                });
+            }
+        }
+
+        let mut bvh = Self {
+            primitives: primitives.to_vec(),
+            nodes: Vec::with_capacity(build_faces.len() * 2),
+            faces: Vec::with_capacity(build_faces.len()),
+        };
+
+        if !build_faces.is_empty() {
+            bvh.build_node(&mut build_faces);
+        }
+
+        bvh
+    }
+
+    /// Primitives getting function
+    /// * Returns primitives, face references point to
+    pub fn get_primitives(&self) -> &[&'a Primitive] {
+        &self.primitives
+    }
+
+    /// Bounding box getting function
+    /// * Returns bounding box of all faces
+    pub fn get_bounds(&self) -> Aabb {
+        self.nodes.first().map(|node| node.bounds).unwrap_or(Aabb::empty())
+    }
+
+    /// Node recursive build function
+    /// * `faces` - faces to build node of
+    fn build_node(&mut self, faces: &mut [BuildFace]) {
+        let bounds = faces.iter().fold(Aabb::empty(), |bounds, face| bounds.union(&face.bounds));
+        let node_index = self.nodes.len();
+
+        self.nodes.push(Node { bounds, start: 0, count: 0 });
+
+        let split = if faces.len() > LEAF_FACE_COUNT {
+            Self::find_split(faces, &bounds)
+        } else {
+            None
+        };
+
+        let Some((axis, position)) = split else {
+            self.nodes[node_index].start = self.faces.len() as u32;
+            self.nodes[node_index].count = faces.len() as u32;
+            self.faces.extend(faces.iter().map(|face| face.face));
+            return;
+        };
+
+        // Partition faces by split plane
+        let mut middle = 0;
+        for i in 0..faces.len() {
+            if axis_value(&faces[i].center, axis) < position {
+                faces.swap(i, middle);
+                middle += 1;
+            }
+        }
+
+        // All centers are on one side, so split in half
+        if middle == 0 || middle == faces.len() {
+            middle = faces.len() / 2;
+            faces.select_nth_unstable_by(middle, |lhs, rhs| axis_value(&lhs.center, axis).total_cmp(&axis_value(&rhs.center, axis)));
+        }
+
+        let (left, right) = faces.split_at_mut(middle);
+        self.build_node(left);
+        self.nodes[node_index].start = self.nodes.len() as u32;
+        self.build_node(right);
+    }
+
+    /// Best split plane by surface area heuristic finding function
+    /// * `faces` - faces to split
+    /// * `bounds` - bounding box of faces
+    /// * Returns split axis and coordinate, None if making leaf is cheaper
+    fn find_split(faces: &[BuildFace], bounds: &Aabb) -> Option<(usize, f32)> {
+        let center_bounds = Aabb::from_points(faces.iter().map(|face| &face.center));
+        let leaf_cost = faces.len() as f32;
+        let mut best: Option<(usize, f32, f32)> = None;
+
+        for axis in 0..3 {
+            let min = axis_value(&center_bounds.min, axis);
+            let max = axis_value(&center_bounds.max, axis);
+
+            if max - min <= f32::EPSILON * max.abs().max(1.0) {
+                continue;
+            }
+
+            let mut bins = [(Aabb::empty(), 0usize); BIN_COUNT];
+            let scale = BIN_COUNT as f32 / (max - min);
+
+            for face in faces {
+                let bin = (((axis_value(&face.center, axis) - min) * scale) as usize).min(BIN_COUNT - 1);
+                bins[bin].0 = bins[bin].0.union(&face.bounds);
+                bins[bin].1 += 1;
+            }
+
+            // Sweep from the right to accumulate right side areas
+            let mut right_costs = [0.0f32; BIN_COUNT];
+            let mut right = (Aabb::empty(), 0usize);
+            for bin in (1..BIN_COUNT).rev() {
+                right = (right.0.union(&bins[bin].0), right.1 + bins[bin].1);
+                right_costs[bin] = surface_area(&right.0) * right.1 as f32;
+            }
+
+            let mut left = (Aabb::empty(), 0usize);
+            for bin in 0..BIN_COUNT - 1 {
+                left = (left.0.union(&bins[bin].0), left.1 + bins[bin].1);
+
+                if left.1 == 0 || left.1 == faces.len() {
+                    continue;
+                }
+
+                let cost = (surface_area(&left.0) * left.1 as f32 + right_costs[bin + 1]) / surface_area(bounds).max(f32::MIN_POSITIVE);
+
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, min + (bin + 1) as f32 / scale, cost));
+                }
+            }
+        }
+
+        best
+            .filter(|(_, _, cost)| *cost < leaf_cost)
+            .map(|(axis, position, _)| (axis, position))
+    }
+
+    /// Face vertex indices getting function
+    /// * `face` - face to get vertices of
+    /// * Returns primitive the face belongs to and face vertex indices
+    pub fn get_face(&self, face: FaceRef) -> (&'a Primitive, &'a [u32]) {
+        let primitive = self.primitives[face.primitive as usize];
+
+        (primitive, primitive.get_faces().get(face.face as usize).expect("BVH references existing faces").vertices)
+    }
+
+    /// Frustum query function
+    /// * `frustum` - frustum to find faces in
+    /// * `result` - vector to push faces, whose bounding boxes intersect frustum, to
+    pub fn query_frustum(&self, frustum: &Frustum, result: &mut Vec<FaceRef>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![(0usize, false)];
+
+        while let Some((node_index, inside)) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            let containment = if inside {
+                Containment::Inside
+            } else {
+                frustum.test_aabb(&node.bounds)
+            };
+
+            match containment {
+                Containment::Outside => continue,
+                Containment::Inside if node.count == 0 => {
+                    // Whole subtree is inside, so push all its faces at once
+                    let (first, last) = (self.first_face(node_index), self.last_face(node_index));
+                    result.extend_from_slice(&self.faces[first..last]);
+                },
+                _ => if node.count != 0 {
+                    result.extend_from_slice(&self.faces[node.start as usize..(node.start + node.count) as usize]);
+                } else {
+                    stack.push((node_index + 1, false));
+                    stack.push((node.start as usize, false));
+                },
+            }
+        }
+    }
+
+    /// Subtree first face index getting function
+    fn first_face(&self, mut node_index: usize) -> usize {
+        while self.nodes[node_index].count == 0 {
+            node_index += 1;
+        }
+        self.nodes[node_index].start as usize
+    }
+
+    /// Subtree end face index getting function
+    fn last_face(&self, mut node_index: usize) -> usize {
+        while self.nodes[node_index].count == 0 {
+            node_index = self.nodes[node_index].start as usize;
+        }
+        (self.nodes[node_index].start + self.nodes[node_index].count) as usize
+    }
+
+    /// Ray query function
+    /// * `ray` - ray to trace
+    /// * `max_distance` - maximal ray parameter to consider
+    /// * Returns closest hit of ray with any face, if any
+    pub fn intersect_ray(&self, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_direction = Vec3f::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut best: Option<RayHit> = None;
+        let mut best_distance = max_distance;
+        let mut stack = vec![0usize];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            if ray_aabb_distance(ray, &inv_direction, &node.bounds).is_none_or(|distance| distance > best_distance) {
+                continue;
+            }
+
+            if node.count == 0 {
+                let (near, far) = (node_index + 1, node.start as usize);
+                let near_distance = ray_aabb_distance(ray, &inv_direction, &self.nodes[near].bounds).unwrap_or(f32::MAX);
+                let far_distance = ray_aabb_distance(ray, &inv_direction, &self.nodes[far].bounds).unwrap_or(f32::MAX);
+
+                // Visit closer child first
+                if near_distance <= far_distance {
+                    stack.push(far);
+                    stack.push(near);
+                } else {
+                    stack.push(near);
+                    stack.push(far);
+                }
+                continue;
+            }
+
+            for face in &self.faces[node.start as usize..(node.start + node.count) as usize] {
+                let (primitive, vertices) = self.get_face(*face);
+
+                for (v0, v1, v2) in fan(primitive, vertices) {
+                    if let Some(distance) = ray_triangle_distance(ray, v0, v1, v2) {
+                        if distance <= best_distance {
+                            best_distance = distance;
+                            best = Some(RayHit { face: *face, distance, point: ray.origin + ray.direction * distance });
+                        }
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Nearest point query function
+    /// * `point` - point to find nearest face point to
+    /// * `max_distance` - maximal distance to search in
+    /// * Returns nearest point on any face, if any is closer than max_distance
+    pub fn nearest_point(&self, point: Vec3f, max_distance: f32) -> Option<NearestPoint> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<NearestPoint> = None;
+        let mut best_distance2 = max_distance * max_distance;
+        let mut stack = vec![0usize];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            if aabb_distance2(&node.bounds, &point) > best_distance2 {
+                continue;
+            }
+
+            if node.count == 0 {
+                let (near, far) = (node_index + 1, node.start as usize);
+
+                if aabb_distance2(&self.nodes[near].bounds, &point) <= aabb_distance2(&self.nodes[far].bounds, &point) {
+                    stack.push(far);
+                    stack.push(near);
+                } else {
+                    stack.push(near);
+                    stack.push(far);
+                }
+                continue;
+            }
+
+            for face in &self.faces[node.start as usize..(node.start + node.count) as usize] {
+                let (primitive, vertices) = self.get_face(*face);
+
+                for (v0, v1, v2) in fan(primitive, vertices) {
+                    let nearest = triangle_nearest_point(&point, v0, v1, v2);
+                    let distance2 = (nearest - point).length2();
+
+                    if distance2 <= best_distance2 {
+                        best_distance2 = distance2;
+                        best = Some(NearestPoint { face: *face, distance: distance2.sqrt(), point: nearest });
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Vector component by axis index getting function
+fn axis_value(v: &Vec3f, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+/// Box surface area getting function
+fn surface_area(aabb: &Aabb) -> f32 {
+    if aabb.is_empty() {
+        return 0.0;
+    }
+
+    let size = aabb.size();
+    2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+}
+
+/// Face triangle fan getting function
+/// * `primitive` - primitive face belongs to
+/// * `vertices` - face vertex indices
+/// * Returns iterator over face triangles
+fn fan<'a>(primitive: &'a Primitive, vertices: &'a [u32]) -> impl Iterator<Item = (&'a Vec3f, &'a Vec3f, &'a Vec3f)> {
+    let position = move |index: u32| &primitive.get_positions()[index as usize];
+
+    vertices
+        .windows(2)
+        .skip(1)
+        .map(move |edge| (position(vertices[0]), position(edge[0]), position(edge[1])))
+}
+
+/// Ray to box entry distance getting function
+/// * Returns ray parameter of box entry point (zero if origin is inside box), None if ray misses box
+fn ray_aabb_distance(ray: &Ray, inv_direction: &Vec3f, aabb: &Aabb) -> Option<f32> {
+    let t0 = (aabb.min - ray.origin) * *inv_direction;
+    let t1 = (aabb.max - ray.origin) * *inv_direction;
+
+    let near = t0.x.min(t1.x).max(t0.y.min(t1.y)).max(t0.z.min(t1.z)).max(0.0);
+    let far = t0.x.max(t1.x).min(t0.y.max(t1.y)).min(t0.z.max(t1.z));
+
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+/// Ray to triangle intersection (Moller-Trumbore) function
+/// * Returns ray parameter of intersection point, None if there is no intersection in front of ray origin
+fn ray_triangle_distance(ray: &Ray, v0: &Vec3f, v1: &Vec3f, v2: &Vec3f) -> Option<f32> {
+    let edge1 = *v1 - *v0;
+    let edge2 = *v2 - *v0;
+    let p = ray.direction % edge2;
+    let determinant = edge1 ^ p;
+
+    if determinant.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let inv_determinant = 1.0 / determinant;
+    let s = ray.origin - *v0;
+    let u = (s ^ p) * inv_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s % edge1;
+    let v = (ray.direction ^ q) * inv_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = (edge2 ^ q) * inv_determinant;
+    if distance >= 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Squared distance from point to box getting function
+fn aabb_distance2(aabb: &Aabb, point: &Vec3f) -> f32 {
+    let delta = Vec3f::new(
+        (aabb.min.x - point.x).max(0.0).max(point.x - aabb.max.x),
+        (aabb.min.y - point.y).max(0.0).max(point.y - aabb.max.y),
+        (aabb.min.z - point.z).max(0.0).max(point.z - aabb.max.z),
+    );
+
+    delta.length2()
+}
+
+/// Nearest to point triangle point getting function (by Ericson, 'Real-Time Collision Detection', 5.1.5)
+fn triangle_nearest_point(point: &Vec3f, a: &Vec3f, b: &Vec3f, c: &Vec3f) -> Vec3f {
+    let ab = *b - *a;
+    let ac = *c - *a;
+    let ap = *point - *a;
+
+    let d1 = ab ^ ap;
+    let d2 = ac ^ ap;
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+
+    let bp = *point - *b;
+    let d3 = ab ^ bp;
+    let d4 = ac ^ bp;
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return *a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = *point - *c;
+    let d5 = ab ^ cp;
+    let d6 = ac ^ cp;
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return *a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return *b + (*c - *b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    *a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Faces;
+
+    /// Deterministic xorshift random number generator
+    struct Random(u32);
+
+    impl Random {
+        /// Next number in [0, 1) range getting function
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 >> 8) as f32 / (1 << 24) as f32
+        }
+
+        /// Random point in [-size, size] cube getting function
+        fn point(&mut self, size: f32) -> Vec3f {
+            Vec3f::new(self.next() * 2.0 - 1.0, self.next() * 2.0 - 1.0, self.next() * 2.0 - 1.0) * size
+        }
+    }
+
+    /// Random small triangles in [-10, 10] cube building function
+    fn random_triangles(random: &mut Random, count: usize) -> Primitive {
+        let mut positions = Vec::with_capacity(count * 3);
+        for _ in 0..count {
+            let center = random.point(10.0);
+            positions.extend([center + random.point(1.0), center + random.point(1.0), center + random.point(1.0)]);
+        }
+        let faces = (0..count as u32).map(|face| (0, [face * 3, face * 3 + 1, face * 3 + 2])).collect::<Faces>();
+
+        Primitive::new(positions, vec![Vec3f::new(0.0, 1.0, 0.0)], faces, 0xFFFFFF).unwrap()
+    }
+
+    /// Every triangle of primitive iterating function
+    fn triangles(primitive: &Primitive) -> impl Iterator<Item = (u32, (&Vec3f, &Vec3f, &Vec3f))> {
+        primitive.faces().flat_map(move |face| fan(primitive, face.vertices).map(move |triangle| (face.index as u32, triangle)))
+    }
+
+    #[test]
+    fn build_references_every_face_once() {
+        let primitive = random_triangles(&mut Random(7), 500);
+        let bvh = Bvh::new(&[&primitive]);
+
+        let mut counts = vec![0; primitive.get_faces().len()];
+        for face in &bvh.faces {
+            counts[face.face as usize] += 1;
+        }
+        assert!(counts.iter().all(|count| *count == 1));
+
+        // Leaves are small and every leaf box contains its faces
+        for node in bvh.nodes.iter().filter(|node| node.count != 0) {
+            assert!(node.count as usize <= LEAF_FACE_COUNT * 2);
+
+            for face in &bvh.faces[node.start as usize..(node.start + node.count) as usize] {
+                for vertex in bvh.get_face(*face).1 {
+                    let position = primitive.get_positions()[*vertex as usize];
+                    assert!(node.bounds.min.x <= position.x && position.x <= node.bounds.max.x);
+                    assert!(node.bounds.min.y <= position.y && position.y <= node.bounds.max.y);
+                    assert!(node.bounds.min.z <= position.z && position.z <= node.bounds.max.z);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ray_triangle_hits_inside_and_misses_outside() {
+        let (v0, v1, v2) = (Vec3f::new(0.0, 0.0, 0.0), Vec3f::new(1.0, 0.0, 0.0), Vec3f::new(0.0, 1.0, 0.0));
+        let ray = |x: f32, y: f32| Ray { origin: Vec3f::new(x, y, 2.0), direction: Vec3f::new(0.0, 0.0, -1.0) };
+
+        assert_eq!(ray_triangle_distance(&ray(0.25, 0.25), &v0, &v1, &v2), Some(2.0));
+        assert_eq!(ray_triangle_distance(&ray(0.75, 0.75), &v0, &v1, &v2), None);
+        assert_eq!(ray_triangle_distance(&ray(-0.1, 0.5), &v0, &v1, &v2), None);
+
+        // Triangle behind origin
+        let backward = Ray { origin: Vec3f::new(0.25, 0.25, 2.0), direction: Vec3f::new(0.0, 0.0, 1.0) };
+        assert_eq!(ray_triangle_distance(&backward, &v0, &v1, &v2), None);
+    }
+
+    #[test]
+    fn ray_query_matches_brute_force() {
+        let mut random = Random(11);
+        let primitive = random_triangles(&mut random, 500);
+        let bvh = Bvh::new(&[&primitive]);
+
+        let mut hit_count = 0;
+        for _ in 0..500 {
+            let ray = Ray { origin: random.point(15.0), direction: random.point(1.0) };
+
+            let expected = triangles(&primitive)
+                .filter_map(|(_, (v0, v1, v2))| ray_triangle_distance(&ray, v0, v1, v2))
+                .fold(None, |best: Option<f32>, distance| Some(best.map_or(distance, |best| best.min(distance))));
+            let hit = bvh.intersect_ray(&ray, f32::MAX);
+
+            assert_eq!(hit.map(|hit| hit.distance), expected);
+            if let Some(hit) = hit {
+                let vertices = bvh.get_face(hit.face).1;
+                let positions = primitive.get_positions();
+                let (v0, v1, v2) = (positions[vertices[0] as usize], positions[vertices[1] as usize], positions[vertices[2] as usize]);
+
+                assert_eq!(ray_triangle_distance(&ray, &v0, &v1, &v2), Some(hit.distance));
+                hit_count += 1;
+            }
+        }
+        assert!(hit_count > 0);
+    }
+
+    #[test]
+    fn ray_query_respects_max_distance() {
+        let mut random = Random(13);
+        let primitive = random_triangles(&mut random, 300);
+        let bvh = Bvh::new(&[&primitive]);
+
+        for _ in 0..200 {
+            let ray = Ray { origin: random.point(15.0), direction: random.point(1.0) };
+
+            if let Some(hit) = bvh.intersect_ray(&ray, f32::MAX) {
+                assert!(bvh.intersect_ray(&ray, hit.distance * 0.99).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_nearest_point_regions() {
+        let (a, b, c) = (Vec3f::new(0.0, 0.0, 0.0), Vec3f::new(2.0, 0.0, 0.0), Vec3f::new(0.0, 2.0, 0.0));
+        let nearest = |x: f32, y: f32, z: f32| triangle_nearest_point(&Vec3f::new(x, y, z), &a, &b, &c);
+        let is_near = |point: Vec3f, x: f32, y: f32, z: f32| (point - Vec3f::new(x, y, z)).length() < 1e-6;
+
+        assert!(is_near(nearest(-1.0, -1.0, 1.0), 0.0, 0.0, 0.0));
+        assert!(is_near(nearest(3.0, -1.0, 0.0), 2.0, 0.0, 0.0));
+        assert!(is_near(nearest(1.0, -1.0, 5.0), 1.0, 0.0, 0.0));
+        assert!(is_near(nearest(2.0, 2.0, 0.0), 1.0, 1.0, 0.0));
+        assert!(is_near(nearest(0.5, 0.5, -3.0), 0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn nearest_point_query_matches_brute_force() {
+        let mut random = Random(17);
+        let primitive = random_triangles(&mut random, 500);
+        let bvh = Bvh::new(&[&primitive]);
+
+        for _ in 0..500 {
+            let point = random.point(15.0);
+
+            let expected = triangles(&primitive)
+                .map(|(_, (v0, v1, v2))| (triangle_nearest_point(&point, v0, v1, v2) - point).length())
+                .fold(f32::MAX, f32::min);
+            let nearest = bvh.nearest_point(point, f32::MAX).unwrap();
+
+            assert_eq!(nearest.distance, expected);
+            assert!(((nearest.point - point).length() - nearest.distance).abs() < 1e-4);
+
+            // Nearest point isn't farther than any sampled triangle point
+            for (_, (v0, v1, v2)) in triangles(&primitive) {
+                for (u, v) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.25, 0.25), (0.5, 0.5), (0.1, 0.7)] {
+                    let sample = *v0 + (*v1 - *v0) * u + (*v2 - *v0) * v;
+                    assert!(nearest.distance <= (sample - point).length() + 1e-4);
+                }
+            }
+        }
+
+        assert!(bvh.nearest_point(Vec3f::new(100.0, 100.0, 100.0), 1.0).is_none());
+    }
+}